@@ -18,8 +18,8 @@ use crate::config::TorConfig;
 use crate::keychain::Keychain;
 use crate::libwallet::api_impl::foreign;
 use crate::libwallet::{
-	BlockFees, CbData, Error, NodeClient, NodeVersionInfo, Slate, VersionInfo, WalletInst,
-	WalletLCProvider,
+	BlockFees, CbData, Error, NodeClient, NodeVersionInfo, Payout, Slate, SlateTransport,
+	VersionInfo, WalletInst, WalletLCProvider,
 };
 use crate::try_slatepack_sync_workflow;
 use crate::util::secp::key::SecretKey;
@@ -77,6 +77,8 @@ where
 	/// Optional TOR configuration, holding address of sender and
 	/// data directory
 	tor_config: Mutex<Option<TorConfig>>,
+	/// How the slates received through this instance are exchanged, if known
+	slate_transport: Mutex<Option<SlateTransport>>,
 }
 
 impl<'a, L, C, K> Foreign<'a, L, C, K>
@@ -182,6 +184,7 @@ where
 			middleware,
 			keychain_mask,
 			tor_config: Mutex::new(None),
+			slate_transport: Mutex::new(None),
 		}
 	}
 
@@ -198,6 +201,19 @@ where
 		*lock = tor_config;
 	}
 
+	/// Set how slates are exchanged with this instance of the ForeignAPI, recorded
+	/// in the transaction log by `receive_tx`
+	///
+	/// # Arguments
+	/// * `transport` - The optional [SlateTransport](#) in use
+	/// # Returns
+	/// * Nothing
+
+	pub fn set_slate_transport(&self, transport: Option<SlateTransport>) {
+		let mut lock = self.slate_transport.lock();
+		*lock = transport;
+	}
+
 	/// Return the version capabilities of the running ForeignApi Node
 	/// # Arguments
 	/// None
@@ -368,6 +384,7 @@ where
 			(&self.keychain_mask).as_ref(),
 			slate,
 			dest_acct_name,
+			*self.slate_transport.lock(),
 			self.doctest_mode,
 		)?;
 		match r_addr {
@@ -390,6 +407,75 @@ where
 		}
 	}
 
+	/// Receives a batch payout, as [`receive_tx`](struct.Foreign.html#method.receive_tx)
+	/// does for a regular transaction, but creating one output for each of the given payouts
+	/// rather than a single output for the full amount. This lets a single wallet collect the
+	/// payouts of a batch into separate outputs, e.g. for outputs it holds on behalf of others.
+	///
+	/// The payouts are recorded in the wallet's corresponding
+	/// [Transaction Log Entry](../grin_wallet_libwallet/types/struct.TxLogEntry.html).
+	///
+	/// # Arguments
+	/// * `slate` - The transaction [`Slate`](../grin_wallet_libwallet/slate/struct.Slate.html),
+	/// as created by the sender with the same payouts.
+	/// * `dest_acct_name` - The name of the account into which the slate should be received. If
+	/// `None`, the default account is used.
+	/// * `payouts` - The payouts, which must add up to the amount of the slate.
+	///
+	/// # Returns
+	/// * a result containing:
+	/// * `Ok`([`slate`](../grin_wallet_libwallet/slate/struct.Slate.html)`)` if successful,
+	/// containing the new slate updated with the recipient's outputs and public signing information.
+	/// * or [`libwallet::Error`](../grin_wallet_libwallet/struct.Error.html) if an error is encountered.
+	///
+	/// # Example
+	/// Set up as in [new](struct.Foreign.html#method.new) method above.
+	/// ```
+	/// # grin_wallet_api::doctest_helper_setup_doc_env_foreign!(wallet, wallet_config);
+	///
+	/// let mut api_foreign = Foreign::new(wallet.clone(), None, None, false);
+	/// # let slate = Slate::blank(2, false);
+	///
+	/// // . . .
+	/// // Obtain a sent batch payout slate somehow
+	/// let payouts = vec![
+	///     libwallet::Payout { amount: 1_000_000_000, recipient: Some("miner1".to_owned()) },
+	///     libwallet::Payout { amount: 2_000_000_000, recipient: Some("miner2".to_owned()) },
+	/// ];
+	/// let result = api_foreign.receive_payouts_tx(&slate, None, &payouts);
+	///
+	/// if let Ok(slate) = result {
+	///     // Send back to the sender somehow
+	///     // ...
+	/// }
+	/// ```
+
+	pub fn receive_payouts_tx(
+		&self,
+		slate: &Slate,
+		dest_acct_name: Option<&str>,
+		payouts: &[Payout],
+	) -> Result<Slate, Error> {
+		let mut w_lock = self.wallet_inst.lock();
+		let w = w_lock.lc_provider()?.wallet_inst()?;
+		if let Some(m) = self.middleware.as_ref() {
+			m(
+				ForeignCheckMiddlewareFn::ReceiveTx,
+				w.w2n_client().get_version_info(),
+				Some(slate),
+			)?;
+		}
+		foreign::receive_payouts_tx(
+			&mut **w,
+			(&self.keychain_mask).as_ref(),
+			slate,
+			dest_acct_name,
+			payouts,
+			*self.slate_transport.lock(),
+			self.doctest_mode,
+		)
+	}
+
 	/// Finalizes a (standard or invoice) transaction initiated by this wallet's Owner api.
 	/// This step assumes the paying party has completed round 1 and 2 of slate
 	/// creation, and added their partial signatures. This wallet will verify
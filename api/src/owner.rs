@@ -26,16 +26,21 @@ use crate::keychain::{Identifier, Keychain};
 use crate::libwallet::api_impl::owner_updater::{start_updater_log_thread, StatusMessage};
 use crate::libwallet::api_impl::{owner, owner_updater};
 use crate::libwallet::{
-	AcctPathMapping, Error, ErrorKind, InitTxArgs, IssueInvoiceTxArgs, NodeClient,
-	NodeHeightResult, OutputCommitMapping, PaymentProof, Slate, Slatepack, SlatepackAddress,
-	TxLogEntry, WalletInfo, WalletInst, WalletLCProvider,
+	AcctPathMapping, Error, ErrorKind, FeeEstimate, InitTxArgs, IssueInvoiceTxArgs, NodeClient,
+	NodeHeightResult, OutputCommitMapping, PaymentProof, Slate, SlateTransport, Slatepack,
+	SlatepackAddress, TxExportArgs, TxLogEntry, TxNoteFilter, TxPreview, WalletInfo, WalletInst,
+	WalletLCProvider,
+};
+use crate::libwallet::{
+	ConsolidateArgs, IssueTokenArgs, SlateActivity, SplitOutputsArgs, TokenOutputCommitMapping,
+	TokenTxLogEntry, WalletTokenInfo,
 };
-use crate::libwallet::{IssueTokenArgs, TokenOutputCommitMapping, TokenTxLogEntry};
 use crate::util::logger::LoggingConfig;
 use crate::util::secp::key::SecretKey;
 use crate::util::{from_hex, static_secp_instance, Mutex, ZeroingString};
 use grin_wallet_util::OnionV3Address;
 use std::convert::TryFrom;
+use std::io::Write;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{channel, Sender};
 use std::sync::Arc;
@@ -74,6 +79,9 @@ where
 	updater: Arc<Mutex<owner_updater::Updater<'static, L, C, K>>>,
 	/// Stop state for update thread
 	pub updater_running: Arc<AtomicBool>,
+	/// Raised to cancel a running scan, which stops at the next batch of
+	/// outputs and is resumed by the next scan
+	pub scan_cancel: Arc<AtomicBool>,
 	/// Sender for update messages
 	status_tx: Mutex<Option<Sender<StatusMessage>>>,
 	/// Holds all update and status messages returned by the
@@ -82,6 +90,8 @@ where
 	/// Optional TOR configuration, holding address of sender and
 	/// data directory
 	tor_config: Mutex<Option<TorConfig>>,
+	/// How the slates locked through this instance are exchanged, if known
+	slate_transport: Mutex<Option<SlateTransport>>,
 }
 
 impl<L, C, K> Owner<L, C, K>
@@ -198,9 +208,11 @@ where
 			shared_key: Arc::new(Mutex::new(None)),
 			updater,
 			updater_running,
+			scan_cancel: Arc::new(AtomicBool::new(false)),
 			status_tx: Mutex::new(Some(tx)),
 			updater_messages,
 			tor_config: Mutex::new(None),
+			slate_transport: Mutex::new(None),
 		}
 	}
 
@@ -217,6 +229,19 @@ where
 		*lock = tor_config;
 	}
 
+	/// Set how slates are exchanged by the caller of this instance of the OwnerAPI,
+	/// recorded in the transaction log by `tx_lock_outputs`
+	///
+	/// # Arguments
+	/// * `transport` - The optional [SlateTransport](#) in use
+	/// # Returns
+	/// * Nothing
+
+	pub fn set_slate_transport(&self, transport: Option<SlateTransport>) {
+		let mut lock = self.slate_transport.lock();
+		*lock = transport;
+	}
+
 	/// Returns a list of accounts stored in the wallet (i.e. mappings between
 	/// user-specified labels and BIP32 derivation paths.
 	/// # Arguments
@@ -359,6 +384,52 @@ where
 		owner::set_active_account(&mut **w, label)
 	}
 
+	/// Sets whether change outputs of an account are derived on a dedicated
+	/// change branch, rather than alongside the outputs it receives.
+	///
+	/// # Arguments
+	/// * `keychain_mask` - Wallet secret mask to XOR against the stored wallet seed before using, if
+	/// being used.
+	/// * `label` - The human readable label for the account.
+	/// * `enabled` - Whether change is derived on the account's change branch.
+	///
+	/// # Returns
+	/// * Result Containing:
+	/// * `Ok(())` if the setting was saved
+	/// * or [`libwallet::Error`](../grin_wallet_libwallet/struct.Error.html) if an error is encountered.
+	///
+	/// # Remarks
+	///
+	/// * The change branch of the account at `m/k/0` is `m/k/1`, so change keys
+	/// are derived at `m/k/1/0`, `m/k/1/1` etc... Accounts at other paths have
+	/// no change branch.
+	/// * Keys already derived are unaffected, and scanning the chain restores
+	/// outputs from both the account path and its change branch.
+	/// * New accounts created with
+	/// [`create_account_path`](struct.Owner.html#method.create_account_path) take
+	/// the setting of the 'default' account.
+	///
+	/// # Example
+	/// Set up as in [`new`](struct.Owner.html#method.new) method above.
+	/// ```
+	/// # grin_wallet_api::doctest_helper_setup_doc_env!(wallet, wallet_config);
+	///
+	/// let api_owner = Owner::new(wallet.clone(), None);
+	///
+	/// let result = api_owner.set_account_change_branch(None, "default", true);
+	/// ```
+
+	pub fn set_account_change_branch(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		label: &str,
+		enabled: bool,
+	) -> Result<(), Error> {
+		let mut w_lock = self.wallet_inst.lock();
+		let w = w_lock.lc_provider()?.wallet_inst()?;
+		owner::set_account_change_branch(&mut **w, keychain_mask, label, enabled)
+	}
+
 	/// Returns a list of outputs from the active account in the wallet.
 	///
 	/// # Arguments
@@ -516,6 +587,10 @@ where
 	/// the transaction log entry of id `i`.
 	/// * `tx_slate_id` - If `Some(uuid)`, only return transactions associated with
 	/// the given [`Slate`](../grin_wallet_libwallet/slate/struct.Slate.html) uuid.
+	/// * `note_filter` - If `Some(filter)`, only return transactions whose note, as kept via
+	/// [`set_tx_note`](struct.Owner.html#method.set_tx_note), contains the filter's `note`, and
+	/// whose labels include its `label`. See
+	/// [`TxNoteFilter`](../grin_wallet_libwallet/api_impl/types/struct.TxNoteFilter.html).
 	///
 	/// # Returns
 	/// * `(bool, Vec<TxLogEntry)` - A tuple:
@@ -534,9 +609,10 @@ where
 	/// let update_from_node = true;
 	/// let tx_id = None;
 	/// let tx_slate_id = None;
+	/// let note_filter = None;
 	///
 	/// // Return all TxLogEntries
-	/// let result = api_owner.retrieve_txs(None, update_from_node, tx_id, tx_slate_id);
+	/// let result = api_owner.retrieve_txs(None, update_from_node, tx_id, tx_slate_id, note_filter);
 	///
 	/// if let Ok((was_updated, tx_log_entries)) = result {
 	///     //...
@@ -549,6 +625,7 @@ where
 		refresh_from_node: bool,
 		tx_id: Option<u32>,
 		tx_slate_id: Option<Uuid>,
+		note_filter: Option<TxNoteFilter>,
 	) -> Result<(bool, Vec<TxLogEntry>), Error> {
 		let tx = {
 			let t = self.status_tx.lock();
@@ -565,6 +642,7 @@ where
 			refresh_from_node,
 			tx_id,
 			tx_slate_id,
+			note_filter.as_ref(),
 		)?;
 		if self.doctest_mode {
 			res.1 = res
@@ -595,6 +673,10 @@ where
 	/// the transaction log entry of id `i`.
 	/// * `tx_slate_id` - If `Some(uuid)`, only return transactions associated with
 	/// the given [`Slate`](../grin_wallet_libwallet/slate/struct.Slate.html) uuid.
+	/// * `note_filter` - If `Some(filter)`, only return transactions whose note, as kept via
+	/// [`set_tx_note`](struct.Owner.html#method.set_tx_note), contains the filter's `note`, and
+	/// whose labels include its `label`. See
+	/// [`TxNoteFilter`](../grin_wallet_libwallet/api_impl/types/struct.TxNoteFilter.html).
 	///
 	/// # Returns
 	/// * `(bool, Vec<TokenTxLogEntry)` - A tuple:
@@ -609,13 +691,20 @@ where
 	/// ```
 	/// # grin_wallet_api::doctest_helper_setup_doc_env!(wallet, wallet_config);
 	///
+	/// use grin_wallet_libwallet::TxNoteFilter;
+	///
 	/// let api_owner = Owner::new(wallet.clone(), None);
 	/// let update_from_node = true;
 	/// let tx_id = None;
 	/// let tx_slate_id = None;
+	/// let note_filter = Some(TxNoteFilter {
+	///     label: Some("payroll".to_owned()),
+	///     ..Default::default()
+	/// });
 	///
-	/// // Return all TokenTxLogEntry
-	/// let result = api_owner.retrieve_token_txs(None, update_from_node, tx_id, tx_slate_id);
+	/// // Return the TokenTxLogEntries labelled "payroll"
+	/// let result =
+	///     api_owner.retrieve_token_txs(None, update_from_node, tx_id, tx_slate_id, note_filter);
 	///
 	/// if let Ok((was_updated, tx_log_entries)) = result {
 	///     //...
@@ -628,6 +717,7 @@ where
 		refresh_from_node: bool,
 		tx_id: Option<u32>,
 		tx_slate_id: Option<Uuid>,
+		note_filter: Option<TxNoteFilter>,
 	) -> Result<(bool, Vec<TokenTxLogEntry>), Error> {
 		let tx = {
 			let t = self.status_tx.lock();
@@ -644,6 +734,7 @@ where
 			refresh_from_node,
 			tx_id,
 			tx_slate_id,
+			note_filter.as_ref(),
 		)?;
 		if self.doctest_mode {
 			res.1 = res
@@ -659,6 +750,123 @@ where
 		Ok(res)
 	}
 
+	/// Returns what each slate of the active account moved of both vcash and tokens, merging its
+	/// [Transaction Log Entries](../grin_wallet_libwallet/types/struct.TxLogEntry.html) and
+	/// [Token Transaction Log Entries](../grin_wallet_libwallet/types/struct.TokenTxLogEntry.html).
+	/// A token transaction is only logged as a token transaction log entry, which also holds the
+	/// vcash it spent on the fee and the vcash change, so the base transaction log alone doesn't
+	/// add up to the vcash balance. The credits less the debits of all records returned do, for
+	/// each asset.
+	///
+	/// # Arguments
+	/// * `keychain_mask` - Wallet secret mask to XOR against the stored wallet seed before using, if
+	/// being used.
+	/// * `refresh_from_node` - As in [`retrieve_txs`](struct.Owner.html#method.retrieve_txs).
+	/// * `tx_slate_id` - If `Some(uuid)`, only return the record of the given
+	/// [`Slate`](../grin_wallet_libwallet/slate/struct.Slate.html) uuid.
+	///
+	/// # Returns
+	/// * `(bool, Vec<SlateActivity>)` - Whether the data was successfully refreshed from the node,
+	/// and a [`SlateActivity`](../grin_wallet_libwallet/api_impl/types/struct.SlateActivity.html)
+	/// per slate, ordered by creation time. Entries not built from a slate, such as coinbase
+	/// rewards, each make a record of their own.
+	///
+	/// # Example
+	/// Set up as in [`new`](struct.Owner.html#method.new) method above.
+	/// ```
+	/// # grin_wallet_api::doctest_helper_setup_doc_env!(wallet, wallet_config);
+	///
+	/// let api_owner = Owner::new(wallet.clone(), None);
+	///
+	/// let result = api_owner.retrieve_slate_activity(None, true, None);
+	///
+	/// if let Ok((was_updated, activity)) = result {
+	///     //...
+	/// }
+	/// ```
+
+	pub fn retrieve_slate_activity(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		refresh_from_node: bool,
+		tx_slate_id: Option<Uuid>,
+	) -> Result<(bool, Vec<SlateActivity>), Error> {
+		let tx = {
+			let t = self.status_tx.lock();
+			t.clone()
+		};
+		let refresh_from_node = match self.updater_running.load(Ordering::Relaxed) {
+			true => false,
+			false => refresh_from_node,
+		};
+		owner::retrieve_slate_activity(
+			self.wallet_inst.clone(),
+			keychain_mask,
+			&tx,
+			refresh_from_node,
+			tx_slate_id,
+		)
+	}
+
+	/// Exports the transaction history of the active account, for use in a spreadsheet or by
+	/// accounting software. [Transaction Log Entries](../grin_wallet_libwallet/types/struct.TxLogEntry.html)
+	/// and [Token Transaction Log Entries](../grin_wallet_libwallet/types/struct.TokenTxLogEntry.html)
+	/// are merged into a single timeline, ordered by creation time, with a row for each asset a
+	/// transaction moves.
+	///
+	/// # Arguments
+	/// * `keychain_mask` - Wallet secret mask to XOR against the stored wallet seed before using, if
+	/// being used.
+	/// * `refresh_from_node` - As in [`retrieve_txs`](struct.Owner.html#method.retrieve_txs).
+	/// * `args` - A [`TxExportArgs`](../grin_wallet_libwallet/api_impl/types/struct.TxExportArgs.html)
+	/// selecting the format, columns and date range to export.
+	/// * `writer` - Where the history is written to.
+	///
+	/// # Returns
+	/// * `(bool, usize)` - Whether the data was successfully refreshed from the node, and the
+	/// number of rows written.
+	///
+	/// # Example
+	/// Set up as in [`new`](struct.Owner.html#method.new) method above.
+	/// ```
+	/// # grin_wallet_api::doctest_helper_setup_doc_env!(wallet, wallet_config);
+	/// use grin_wallet_libwallet::{TxExportArgs, TxExportFormat};
+	///
+	/// let api_owner = Owner::new(wallet.clone(), None);
+	/// let args = TxExportArgs {
+	///     format: TxExportFormat::Csv,
+	///     ..Default::default()
+	/// };
+	///
+	/// let mut csv = vec![];
+	/// let result = api_owner.export_txs(None, true, &args, &mut csv);
+	/// ```
+
+	pub fn export_txs(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		refresh_from_node: bool,
+		args: &TxExportArgs,
+		writer: &mut dyn Write,
+	) -> Result<(bool, usize), Error> {
+		let tx = {
+			let t = self.status_tx.lock();
+			t.clone()
+		};
+		let refresh_from_node = match self.updater_running.load(Ordering::Relaxed) {
+			true => false,
+			false => refresh_from_node,
+		};
+		owner::export_txs(
+			self.wallet_inst.clone(),
+			keychain_mask,
+			&tx,
+			refresh_from_node,
+			args,
+			writer,
+		)
+	}
+
 	/// Returns summary information from the active account in the wallet.
 	///
 	/// # Arguments
@@ -721,6 +929,71 @@ where
 		)
 	}
 
+	/// Returns summary information about the tokens held by the active account
+	/// in the wallet, broken down by token type.
+	///
+	/// # Arguments
+	/// * `keychain_mask` - Wallet secret mask to XOR against the stored wallet seed before using, if
+	/// being used.
+	/// * `refresh_from_node` - If true, the wallet will attempt to contact
+	/// a node (via the [`NodeClient`](../grin_wallet_libwallet/types/trait.NodeClient.html)
+	/// provided during wallet instantiation). If `false`, the results will
+	/// contain output information that may be out-of-date (from the last time
+	/// the wallet's output set was refreshed against the node).
+	/// Note this setting is ignored if the updater process is running via a call to
+	/// [`start_updater`](struct.Owner.html#method.start_updater)
+	/// * `minimum_confirmations` - The minimum number of confirmations a token output
+	/// should have before it's included in the 'amount_currently_spendable' total
+	///
+	/// # Returns
+	/// * (`bool`, `Vec<WalletTokenInfo>`) - A tuple:
+	/// * The first `bool` element indicates whether the data was successfully
+	/// refreshed from the node (note this may be false even if the `refresh_from_node`
+	/// argument was set to `true`.
+	/// * The second element contains a
+	/// [`WalletTokenInfo`](../grin_wallet_libwallet/types/struct.WalletTokenInfo.html)
+	/// for each token type held, ordered by token type
+	///
+	/// # Example
+	/// Set up as in [`new`](struct.Owner.html#method.new) method above.
+	/// ```
+	/// # grin_wallet_api::doctest_helper_setup_doc_env!(wallet, wallet_config);
+	///
+	/// let mut api_owner = Owner::new(wallet.clone(), None);
+	/// let update_from_node = true;
+	/// let minimum_confirmations=10;
+	///
+	/// // Return token summary info for active account
+	/// let result = api_owner.retrieve_token_summary_info(None, update_from_node, minimum_confirmations);
+	///
+	/// if let Ok((was_updated, token_infos)) = result {
+	///     //...
+	/// }
+	/// ```
+
+	pub fn retrieve_token_summary_info(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		refresh_from_node: bool,
+		minimum_confirmations: u64,
+	) -> Result<(bool, Vec<WalletTokenInfo>), Error> {
+		let tx = {
+			let t = self.status_tx.lock();
+			t.clone()
+		};
+		let refresh_from_node = match self.updater_running.load(Ordering::Relaxed) {
+			true => false,
+			false => refresh_from_node,
+		};
+		owner::retrieve_token_summary_info(
+			self.wallet_inst.clone(),
+			keychain_mask,
+			&tx,
+			refresh_from_node,
+			minimum_confirmations,
+		)
+	}
+
 	/// Initiates a new transaction as the token issuer, creating a new
 	/// [`Slate`](../grin_wallet_libwallet/slate/struct.Slate.html) object containing
 	/// the issuer's inputs, change outputs, issue token outputs and public signature data.
@@ -752,6 +1025,111 @@ where
 		res
 	}
 
+	/// Builds a transaction spending many small outputs back into a single output
+	/// of the wallet's own, so later sends need fewer inputs. Up to `max_outputs`
+	/// of the smallest eligible outputs are spent, less the fee. The inputs are
+	/// locked and the transaction logged as usual, so the returned
+	/// [`Slate`](../grin_wallet_libwallet/slate/struct.Slate.html) only needs to
+	/// be posted with [`post_tx`](struct.Owner.html#method.post_tx).
+	///
+	/// # Arguments
+	/// * `keychain_mask` - Wallet secret mask to XOR against the stored wallet seed before using, if
+	/// being used.
+	/// * `args` - [`ConsolidateArgs`](../grin_wallet_libwallet/types/struct.ConsolidateArgs.html),
+	/// consolidation arguments. See struct documentation for further detail.
+	///
+	/// # Returns
+	/// * a result containing:
+	/// * The finalized transaction [Slate](../grin_wallet_libwallet/slate/struct.Slate.html).
+	/// * or [`libwallet::Error`](../grin_wallet_libwallet/struct.Error.html) if an error is encountered,
+	/// including when fewer than `min_outputs` outputs qualify.
+	///
+	/// # Example
+	/// Set up as in [`new`](struct.Owner.html#method.new) method above.
+	/// ```
+	/// # grin_wallet_api::doctest_helper_setup_doc_env!(wallet, wallet_config);
+	/// use grin_wallet_libwallet::ConsolidateArgs;
+	///
+	/// let mut api_owner = Owner::new(wallet.clone(), None);
+	/// let args = ConsolidateArgs {
+	/// 	minimum_confirmations: 2,
+	/// 	below_value: Some(1_000_000_000),
+	/// 	..Default::default()
+	/// };
+	/// let result = api_owner.init_consolidate_tx(None, args);
+	///
+	/// if let Ok(slate) = result {
+	/// 	// post the transaction
+	/// 	let res = api_owner.post_tx(None, &slate, true);
+	/// }
+	/// ```
+	pub fn init_consolidate_tx(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		args: ConsolidateArgs,
+	) -> Result<Slate, Error> {
+		let mut w_lock = self.wallet_inst.lock();
+		let w = w_lock.lc_provider()?.wallet_inst()?;
+		owner::init_consolidate_tx(&mut **w, keychain_mask, args, self.doctest_mode)
+	}
+
+	/// Builds a transaction splitting the wallet's largest outputs into several
+	/// of its own, so it holds enough outputs to make payments in parallel.
+	/// As many of the largest eligible outputs as needed to cover `amount` are
+	/// spent whole, and what's left after the fee split into `num_outputs`
+	/// outputs of roughly equal value, or into the given `denominations`.
+	/// Token outputs can be split too, with the fee paid from base outputs.
+	/// The inputs are locked and the transaction logged as usual, so the
+	/// returned [`Slate`](../grin_wallet_libwallet/slate/struct.Slate.html) only
+	/// needs to be posted with [`post_tx`](struct.Owner.html#method.post_tx).
+	///
+	/// # Arguments
+	/// * `keychain_mask` - Wallet secret mask to XOR against the stored wallet seed before using, if
+	/// being used.
+	/// * `args` - [`SplitOutputsArgs`](../grin_wallet_libwallet/types/struct.SplitOutputsArgs.html),
+	/// split arguments. See struct documentation for further detail.
+	///
+	/// # Returns
+	/// * a result containing:
+	/// * The finalized transaction [Slate](../grin_wallet_libwallet/slate/struct.Slate.html).
+	/// * or [`libwallet::Error`](../grin_wallet_libwallet/struct.Error.html) if an error is encountered.
+	///
+	/// # Example
+	/// Set up as in [`new`](struct.Owner.html#method.new) method above.
+	/// ```
+	/// # grin_wallet_api::doctest_helper_setup_doc_env!(wallet, wallet_config);
+	/// use grin_wallet_libwallet::SplitOutputsArgs;
+	///
+	/// let mut api_owner = Owner::new(wallet.clone(), None);
+	/// let args = SplitOutputsArgs {
+	/// 	minimum_confirmations: 2,
+	/// 	num_outputs: 4,
+	/// 	..Default::default()
+	/// };
+	/// let result = api_owner.init_split_outputs_tx(None, args);
+	///
+	/// if let Ok(slate) = result {
+	/// 	// post the transaction
+	/// 	let res = api_owner.post_tx(None, &slate, true);
+	/// }
+	/// ```
+	pub fn init_split_outputs_tx(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		args: SplitOutputsArgs,
+	) -> Result<Slate, Error> {
+		let mut w_lock = self.wallet_inst.lock();
+		let w = w_lock.lc_provider()?.wallet_inst()?;
+		if args.token_type.is_some() {
+			let client = w.w2n_client();
+			let current_height = client.get_chain_tip()?.0;
+			if current_height < global::support_token_height() {
+				return Err(ErrorKind::UnreachTokenSupportHeight.into());
+			}
+		}
+		owner::init_split_outputs_tx(&mut **w, keychain_mask, args, self.doctest_mode)
+	}
+
 	/// Initiates a new transaction as the sender, creating a new
 	/// [`Slate`](../grin_wallet_libwallet/slate/struct.Slate.html) object containing
 	/// the sender's inputs, change outputs, and public signature data. This slate can
@@ -874,7 +1252,17 @@ where
 				match res {
 					Ok(Some(s)) => {
 						if sa.post_tx {
-							self.tx_lock_outputs(keychain_mask, &s)?;
+							// the slate went over http or TOR, whatever the caller set
+							{
+								let mut w_lock = self.wallet_inst.lock();
+								let w = w_lock.lc_provider()?.wallet_inst()?;
+								owner::tx_lock_outputs(
+									&mut **w,
+									keychain_mask,
+									&s,
+									Some(SlateTransport::Http),
+								)?;
+							}
 							let ret_slate = self.finalize_tx(keychain_mask, &s)?;
 							let result = self.post_tx(keychain_mask, &ret_slate, sa.fluff);
 							match result {
@@ -899,6 +1287,147 @@ where
 		}
 	}
 
+	/// Estimates the inputs, change and fee a call to
+	/// [`init_send_tx`](struct.Owner.html#method.init_send_tx) with the same arguments would use,
+	/// without building a slate, deriving keys or locking any outputs.
+	///
+	/// # Arguments
+	/// * `args` - [`InitTxArgs`](../grin_wallet_libwallet/types/struct.InitTxArgs.html),
+	/// transaction initialization arguments. The selection and fee fields are honoured, the
+	/// `send_args`, `estimate_only` and payment proof fields are ignored.
+	///
+	/// # Returns
+	/// * a result containing:
+	/// * A [`FeeEstimate`](../grin_wallet_libwallet/types/struct.FeeEstimate.html). If the wallet
+	/// cannot cover the amount, `sufficient_funds` is false and the estimate describes the largest
+	/// transaction the wallet could build.
+	/// * or [`libwallet::Error`](../grin_wallet_libwallet/struct.Error.html) if an error is encountered.
+	///
+	/// # Remarks
+	///
+	/// * Outputs are not refreshed from the node, so the estimate reflects the wallet state as of
+	/// the last update.
+	///
+	/// # Example
+	/// Set up as in [`new`](struct.Owner.html#method.new) method above.
+	/// ```
+	/// # grin_wallet_api::doctest_helper_setup_doc_env!(wallet, wallet_config);
+	///
+	/// let api_owner = Owner::new(wallet.clone(), None);
+	/// let args = InitTxArgs {
+	///     src_acct_name: None,
+	///     amount: 2_000_000_000,
+	///     minimum_confirmations: 2,
+	///     max_outputs: 500,
+	///     num_change_outputs: 1,
+	///     selection_strategy_is_use_all: false,
+	///     ..Default::default()
+	/// };
+	/// let result = api_owner.estimate_fee(args);
+	///
+	/// if let Ok(estimate) = result {
+	///     // Show the fee to the user
+	///     // ...
+	/// }
+	/// ```
+
+	pub fn estimate_fee(&self, args: InitTxArgs) -> Result<FeeEstimate, Error> {
+		let mut w_lock = self.wallet_inst.lock();
+		let w = w_lock.lc_provider()?.wallet_inst()?;
+		owner::estimate_fee(&mut **w, args)
+	}
+
+	/// Previews the exact transaction a call to
+	/// [`init_send_tx`](struct.Owner.html#method.init_send_tx) with the same arguments would
+	/// build: the commitments it would spend, its change outputs and fee, and the wallet balance
+	/// once it confirms. No slate is built, no keys are derived and no outputs are locked.
+	///
+	/// # Arguments
+	/// * `args` - [`InitTxArgs`](../grin_wallet_libwallet/types/struct.InitTxArgs.html),
+	/// transaction initialization arguments. The selection and fee fields are honoured, the
+	/// `send_args`, `estimate_only`, `late_lock` and payment proof fields are ignored.
+	///
+	/// # Returns
+	/// * a result containing:
+	/// * A [`TxPreview`](../grin_wallet_libwallet/types/struct.TxPreview.html).
+	/// * or [`libwallet::Error`](../grin_wallet_libwallet/struct.Error.html) if an error is
+	/// encountered, including the same `NotEnoughFunds` error the send would fail with.
+	///
+	/// # Remarks
+	///
+	/// * Outputs are not refreshed from the node, so the preview reflects the wallet state as of
+	/// the last update.
+	///
+	/// # Example
+	/// Set up as in [`new`](struct.Owner.html#method.new) method above.
+	/// ```
+	/// # grin_wallet_api::doctest_helper_setup_doc_env!(wallet, wallet_config);
+	///
+	/// let api_owner = Owner::new(wallet.clone(), None);
+	/// let args = InitTxArgs {
+	///     src_acct_name: None,
+	///     amount: 2_000_000_000,
+	///     minimum_confirmations: 2,
+	///     max_outputs: 500,
+	///     num_change_outputs: 1,
+	///     selection_strategy_is_use_all: false,
+	///     ..Default::default()
+	/// };
+	/// let result = api_owner.preview_tx(args);
+	///
+	/// if let Ok(preview) = result {
+	///     // Show the inputs, change and fee to the user
+	///     // ...
+	/// }
+	/// ```
+
+	pub fn preview_tx(&self, args: InitTxArgs) -> Result<TxPreview, Error> {
+		let mut w_lock = self.wallet_inst.lock();
+		let w = w_lock.lc_provider()?.wallet_inst()?;
+		owner::preview_tx(&mut **w, args)
+	}
+
+	/// Previews the transaction a call to
+	/// [`init_issue_token_tx`](struct.Owner.html#method.init_issue_token_tx) with the same
+	/// arguments would build, without building a slate, deriving keys or locking any outputs.
+	///
+	/// # Arguments
+	/// * `args` - [`IssueTokenArgs`](../grin_wallet_libwallet/types/struct.IssueTokenArgs.html),
+	/// token issuance arguments.
+	///
+	/// # Returns
+	/// * a result containing:
+	/// * A [`TxPreview`](../grin_wallet_libwallet/types/struct.TxPreview.html), whose
+	/// `token_balance_after` is the issued amount.
+	/// * or [`libwallet::Error`](../grin_wallet_libwallet/struct.Error.html) if an error is encountered.
+	///
+	/// # Example
+	/// Set up as in [`new`](struct.Owner.html#method.new) method above.
+	/// ```
+	/// # grin_wallet_api::doctest_helper_setup_doc_env!(wallet, wallet_config);
+	///
+	/// use grin_wallet_libwallet::IssueTokenArgs;
+	///
+	/// let api_owner = Owner::new(wallet.clone(), None);
+	/// let args = IssueTokenArgs {
+	///     acct_name: None,
+	///     amount: 1_000,
+	///     ..Default::default()
+	/// };
+	/// let result = api_owner.preview_issue_token_tx(args);
+	///
+	/// if let Ok(preview) = result {
+	///     // Show the fee to the user
+	///     // ...
+	/// }
+	/// ```
+
+	pub fn preview_issue_token_tx(&self, args: IssueTokenArgs) -> Result<TxPreview, Error> {
+		let mut w_lock = self.wallet_inst.lock();
+		let w = w_lock.lc_provider()?.wallet_inst()?;
+		owner::preview_issue_token_tx(&mut **w, args)
+	}
+
 	/// Issues a new invoice transaction slate, essentially a `request for payment`.
 	/// The slate created by this function will contain the amount, an output for the amount,
 	/// as well as round 1 of singature creation complete. The slate should then be send
@@ -1065,7 +1594,8 @@ where
 	/// * `participant_id` - The participant id, generally 0 for the party putting in funds, 1 for the
 	/// party receiving.
 	/// elements in the `input` vector of the `tx` field that are found in the wallet's currently
-	/// active account will be set to status `Locked`
+	/// active account will be set to status `Locked`. How the slate was exchanged is recorded
+	/// in the transaction log, if set with [`set_slate_transport`](struct.Owner.html#method.set_slate_transport).
 	///
 	/// # Returns
 	/// * Ok(()) if successful
@@ -1103,10 +1633,64 @@ where
 		&self,
 		keychain_mask: Option<&SecretKey>,
 		slate: &Slate,
+	) -> Result<(), Error> {
+		let transport = *self.slate_transport.lock();
+		let mut w_lock = self.wallet_inst.lock();
+		let w = w_lock.lc_provider()?.wallet_inst()?;
+		owner::tx_lock_outputs(&mut **w, keychain_mask, slate, transport)
+	}
+
+	/// Reserves the outputs of a transaction for a number of blocks instead of locking them,
+	/// for a slate that is handed to a signer before it is sent. Reserved outputs stay
+	/// `Unspent` but aren't selected by other transactions until the reservation expires,
+	/// the transaction is cancelled or its outputs are locked with
+	/// [`tx_lock_outputs`](struct.Owner.html#method.tx_lock_outputs).
+	///
+	/// # Arguments
+	/// * `keychain_mask` - Wallet secret mask to XOR against the stored wallet seed before using, if
+	/// being used.
+	/// * `slate` - The transaction [`Slate`](../grin_wallet_libwallet/slate/struct.Slate.html).
+	/// * `reserve_blocks` - How many blocks from the current height the reservation lasts.
+	///
+	/// # Returns
+	/// * Ok(()) if successful
+	/// * or [`libwallet::Error`](../grin_wallet_libwallet/struct.Error.html) if an error is encountered.
+	///
+	/// # Example
+	/// Set up as in [`new`](struct.Owner.html#method.new) method above.
+	/// ```
+	/// # grin_wallet_api::doctest_helper_setup_doc_env!(wallet, wallet_config);
+	///
+	/// let mut api_owner = Owner::new(wallet.clone(), None);
+	/// let args = InitTxArgs {
+	///     src_acct_name: None,
+	///     amount: 2_000_000_000,
+	///     minimum_confirmations: 10,
+	///     max_outputs: 500,
+	///     num_change_outputs: 1,
+	///     selection_strategy_is_use_all: false,
+	///     ..Default::default()
+	/// };
+	/// let result = api_owner.init_send_tx(
+	///     None,
+	///     args,
+	/// );
+	///
+	/// if let Ok(slate) = result {
+	///     // Keep the outputs for the signer for about an hour
+	///     api_owner.tx_reserve_outputs(None, &slate, 60);
+	/// }
+	/// ```
+
+	pub fn tx_reserve_outputs(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		slate: &Slate,
+		reserve_blocks: u64,
 	) -> Result<(), Error> {
 		let mut w_lock = self.wallet_inst.lock();
 		let w = w_lock.lc_provider()?.wallet_inst()?;
-		owner::tx_lock_outputs(&mut **w, keychain_mask, slate)
+		owner::tx_reserve_outputs(&mut **w, keychain_mask, slate, reserve_blocks)
 	}
 
 	/// Finalizes a transaction, after all parties
@@ -1248,7 +1832,8 @@ where
 	/// used in new transactions.
 	///
 	/// Transactions can be cancelled by transaction log id or slate id (call with either set to
-	/// Some, not both)
+	/// Some, not both). A send that was never locked has no transaction log entry, and can only
+	/// be cancelled by slate id, which releases the inputs reserved for it.
 	///
 	/// # Arguments
 	///
@@ -1314,6 +1899,87 @@ where
 		)
 	}
 
+	/// Freezes or unfreezes an output (or token output), identified by its commitment. Frozen
+	/// outputs are never picked by automatic coin selection, even when spending as many outputs
+	/// as possible, and are reported separately in the wallet summary. The flag is stored with
+	/// the output, so it survives restarts and scans.
+	///
+	/// # Arguments
+	///
+	/// * `keychain_mask` - Wallet secret mask to XOR against the stored wallet seed before using, if
+	/// being used.
+	/// * `commit` - The hex-encoded commitment of the output.
+	/// * `frozen` - Whether the output should be frozen or unfrozen.
+	///
+	/// # Returns
+	/// * `Ok(())` if successful
+	/// * or [`libwallet::Error`](../grin_wallet_libwallet/struct.Error.html) if an error is encountered,
+	/// including when no output with the given commitment exists.
+	///
+	/// # Example
+	/// Set up as in [`new`](struct.Owner.html#method.new) method above.
+	/// ```
+	/// # grin_wallet_api::doctest_helper_setup_doc_env!(wallet, wallet_config);
+	///
+	/// let api_owner = Owner::new(wallet.clone(), None);
+	/// let commit = "083fbf6c559dd0d0220a155afcdd99c1320b56681d4dbfb7d0cd4f92b28f79c60b";
+	/// let result = api_owner.set_output_frozen(None, commit, true);
+	/// ```
+
+	pub fn set_output_frozen(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		commit: &str,
+		frozen: bool,
+	) -> Result<(), Error> {
+		let mut w_lock = self.wallet_inst.lock();
+		let w = w_lock.lc_provider()?.wallet_inst()?;
+		owner::set_output_frozen(&mut **w, keychain_mask, commit, frozen)
+	}
+
+	/// Keeps a free-form note and labels with a transaction, such as "invoice #1234", replacing
+	/// any it had. They're only stored locally, survive scans and are returned with the
+	/// [`TxLogEntry`](../grin_wallet_libwallet/types/struct.TxLogEntry.html). Either the
+	/// Transaction Log ID or the Slate UUID must be supplied.
+	///
+	/// # Arguments
+	///
+	/// * `keychain_mask` - Wallet secret mask to XOR against the stored wallet seed before using, if
+	/// being used.
+	/// * `tx_id` - If present, the [`TxLogEntry`](../grin_wallet_libwallet/types/struct.TxLogEntry.html) id
+	/// for the transaction.
+	/// * `tx_slate_id` - If present, the Slate id of the transaction.
+	/// * `note` - The note to keep, up to `MAX_TX_NOTE_LEN` bytes, or `None` to remove it.
+	/// * `labels` - Up to `MAX_TX_LABELS` labels of at most `MAX_TX_LABEL_LEN` bytes each.
+	///
+	/// # Returns
+	/// * `Ok(())` if successful
+	/// * or [`libwallet::Error`](../grin_wallet_libwallet/struct.Error.html) if an error is encountered,
+	/// including when the transaction doesn't exist or the note or labels are invalid.
+	///
+	/// # Example
+	/// Set up as in [`new`](struct.Owner.html#method.new) method above.
+	/// ```
+	/// # grin_wallet_api::doctest_helper_setup_doc_env!(wallet, wallet_config);
+	///
+	/// let api_owner = Owner::new(wallet.clone(), None);
+	/// let note = Some("invoice #1234".to_owned());
+	/// let result = api_owner.set_tx_note(None, Some(1), None, note, vec!["payroll".to_owned()]);
+	/// ```
+
+	pub fn set_tx_note(
+		&self,
+		keychain_mask: Option<&SecretKey>,
+		tx_id: Option<u32>,
+		tx_slate_id: Option<Uuid>,
+		note: Option<String>,
+		labels: Vec<String>,
+	) -> Result<(), Error> {
+		let mut w_lock = self.wallet_inst.lock();
+		let w = w_lock.lc_provider()?.wallet_inst()?;
+		owner::set_tx_note(&mut **w, keychain_mask, tx_id, tx_slate_id, note, labels)
+	}
+
 	/// Retrieves the stored transaction associated with a TxLogEntry. Can be used even after the
 	/// transaction has completed. Either the Transaction Log ID or the Slate UUID must be supplied.
 	/// If both are supplied, the Transaction Log ID is preferred.
@@ -1343,9 +2009,10 @@ where
 	/// let update_from_node = true;
 	/// let tx_id = None;
 	/// let tx_slate_id = None;
+	/// let note_filter = None;
 	///
 	/// // Return all TxLogEntries
-	/// let result = api_owner.retrieve_txs(None, update_from_node, tx_id, tx_slate_id);
+	/// let result = api_owner.retrieve_txs(None, update_from_node, tx_id, tx_slate_id, note_filter);
 	///
 	/// if let Ok((was_updated, tx_log_entries)) = result {
 	///     let stored_tx = api_owner.get_stored_tx(None, Some(tx_log_entries[0].id), None).unwrap();
@@ -1385,7 +2052,8 @@ where
 	/// * `keychain_mask` - Wallet secret mask to XOR against the stored wallet seed before using, if
 	/// being used.
 	/// * `start_height` - If provided, the height of the first block from which to start scanning.
-	/// The scan will start from block 1 if this is not provided.
+	/// If not provided, a scan cancelled earlier is resumed from where it stopped, otherwise
+	/// the scan will start from block 1.
 	/// * `delete_unconfirmed` - if `false`, the scan process will be non-destructive, and
 	/// mostly limited to restoring missing outputs. It will leave unconfirmed transaction logs entries
 	/// and unconfirmed outputs intact. If `true`, the process will unlock all locked outputs,
@@ -1396,9 +2064,14 @@ where
 	/// will happen if this flag is set. Note that if transactions/outputs are removed that later
 	/// confirm on the chain, another call to this function will restore them.
 	///
+	/// Progress is sent as [`StatusMessage::ScanProgress`](../grin_wallet_libwallet/enum.StatusMessage.html)
+	/// messages. Raising [`scan_cancel`](struct.Owner.html#structfield.scan_cancel) stops the scan
+	/// at the next batch of outputs, storing where it got to for the next scan to resume from.
+	///
 	/// # Returns
 	/// * `Ok(())` if successful
-	/// * or [`libwallet::Error`](../grin_wallet_libwallet/struct.Error.html) if an error is encountered.
+	/// * or [`libwallet::Error`](../grin_wallet_libwallet/struct.Error.html) if an error is encountered,
+	/// `ScanCancelled` if the scan was cancelled.
 
 	/// # Example
 	/// Set up as in [`new`](struct.Owner.html#method.new) method above.
@@ -1428,11 +2101,13 @@ where
 			let t = self.status_tx.lock();
 			t.clone()
 		};
+		self.scan_cancel.store(false, Ordering::Relaxed);
 		owner::scan(
 			self.wallet_inst.clone(),
 			keychain_mask,
 			start_height,
 			delete_unconfirmed,
+			Some(&self.scan_cancel),
 			&tx,
 		)
 	}
@@ -2614,7 +3289,7 @@ pub fn try_slatepack_sync_workflow(
 		Err(e) => {
 			debug!("Send (HTTP): Cannot create HTTP Slate sender {:?}", e);
 			return Ok(None);
-		}
+			}
 	}
 }
 
@@ -19,9 +19,10 @@ use crate::config::{TorConfig, WalletConfig};
 use crate::core::global;
 use crate::keychain::{Identifier, Keychain};
 use crate::libwallet::{
-	AcctPathMapping, ErrorKind, InitTxArgs, IssueInvoiceTxArgs, NodeClient, NodeHeightResult,
-	OutputCommitMapping, PaymentProof, Slate, SlateVersion, Slatepack, SlatepackAddress,
-	StatusMessage, TxLogEntry, VersionedSlate, WalletInfo, WalletLCProvider,
+	AcctPathMapping, ConsolidateArgs, ErrorKind, FeeEstimate, InitTxArgs, IssueInvoiceTxArgs,
+	NodeClient, NodeHeightResult, OutputCommitMapping, PaymentProof, Slate, SlateVersion,
+	Slatepack, SlatepackAddress, SplitOutputsArgs, StatusMessage, TxExportArgs, TxLogEntry,
+	TxNoteFilter, TxPreview, VersionedSlate, WalletInfo, WalletLCProvider,
 };
 use crate::util::logger::LoggingConfig;
 use crate::util::secp::key::{PublicKey, SecretKey};
@@ -179,6 +180,7 @@ pub trait OwnerRpc {
 							"commit": "083fbf6c559dd0d0220a155afcdd99c1320b56681d4dbfb7d0cd4f92b28f79c60b",
 							"height": "1",
 							"is_coinbase": true,
+							"is_frozen": false,
 							"key_id": "0300000000000000000000000000000000",
 							"lock_height": "4",
 							"mmr_index": null,
@@ -195,6 +197,7 @@ pub trait OwnerRpc {
 							"commit": "095a9fd054e3f5d63302c8e1d44e14be686b363b542204207f8db5958ac69aede2",
 							"height": "2",
 							"is_coinbase": true,
+							"is_frozen": false,
 							"key_id": "0300000000000000000000000100000000",
 							"lock_height": "5",
 							"mmr_index": null,
@@ -236,7 +239,8 @@ pub trait OwnerRpc {
 				"token": "d202964900000000d302964900000000d402964900000000d502964900000000",
 				"refresh_from_node": true,
 				"tx_id": null,
-				"tx_slate_id": null
+				"tx_slate_id": null,
+				"note_filter": null
 			},
 			"id": 1
 		}
@@ -305,6 +309,7 @@ pub trait OwnerRpc {
 		refresh_from_node: bool,
 		tx_id: Option<u32>,
 		tx_slate_id: Option<Uuid>,
+		note_filter: Option<TxNoteFilter>,
 	) -> Result<(bool, Vec<TxLogEntry>), ErrorKind>;
 
 	/**
@@ -336,8 +341,10 @@ pub trait OwnerRpc {
 					"amount_awaiting_confirmation": "0",
 					"amount_awaiting_finalization": "0",
 					"amount_currently_spendable": "50000000000",
+					"amount_frozen": "0",
 					"amount_immature": "150000000000",
 					"amount_locked": "0",
+					"amount_reserved": "0",
 					"amount_reverted": "0",
 					"last_confirmed_height": "4",
 					"minimum_confirmations": "1",
@@ -359,6 +366,53 @@ pub trait OwnerRpc {
 		minimum_confirmations: u64,
 	) -> Result<(bool, WalletInfo), ErrorKind>;
 
+	/**
+	Networked version of [Owner::export_txs](struct.Owner.html#method.export_txs), returning
+	the exported history rather than writing it out.
+
+	```
+	# grin_wallet_api::doctest_helper_json_rpc_owner_assert_response!(
+	# r#"
+	{
+		"jsonrpc": "2.0",
+		"method": "export_txs",
+		"params": {
+			"token": "d202964900000000d302964900000000d402964900000000d502964900000000",
+			"refresh_from_node": true,
+			"args": {
+				"format": "Csv",
+				"columns": ["Id", "Type", "AmountCredited", "Confirmed"],
+				"from": null,
+				"to": null
+			}
+		},
+		"id": 1
+	}
+	# "#
+	# ,
+	# r#"
+	{
+		"id": 1,
+		"jsonrpc": "2.0",
+		"result": {
+			"Ok": [
+				true,
+				"id,type,amount_credited,confirmed\n0,ConfirmedCoinbase,50000000000,true\n1,ConfirmedCoinbase,50000000000,true\n"
+			]
+		}
+	}
+	# "#
+	# , 2, false, false, false, false);
+	```
+	*/
+
+	fn export_txs(
+		&self,
+		token: Token,
+		refresh_from_node: bool,
+		args: TxExportArgs,
+	) -> Result<(bool, String), ErrorKind>;
+
 	/**
 		Networked version of [Owner::init_send_tx](struct.Owner.html#method.init_send_tx).
 
@@ -419,6 +473,115 @@ pub trait OwnerRpc {
 
 	fn init_send_tx(&self, token: Token, args: InitTxArgs) -> Result<VersionedSlate, ErrorKind>;
 
+	/**
+		Networked version of [Owner::estimate_fee](struct.Owner.html#method.estimate_fee).
+
+	```
+		# grin_wallet_api::doctest_helper_json_rpc_owner_assert_response!(
+		# r#"
+		{
+			"jsonrpc": "2.0",
+			"method": "estimate_fee",
+			"params": {
+				"args": {
+					"src_acct_name": null,
+					"amount": "6000000000",
+					"minimum_confirmations": 2,
+					"max_outputs": 500,
+					"num_change_outputs": 1,
+					"selection_strategy_is_use_all": true,
+					"target_slate_version": null,
+					"payment_proof_recipient_address": null,
+					"ttl_blocks": null,
+					"send_args": null
+				}
+			},
+			"id": 1
+		}
+		# "#
+		# ,
+		# r#"
+		{
+			"id": 1,
+			"jsonrpc": "2.0",
+			"result": {
+				"Ok": {
+					"change": "43992000000",
+					"fee": "8000000",
+					"num_inputs": 1,
+					"sufficient_funds": true,
+					"token_change": "0",
+					"token_num_inputs": 0,
+					"token_total": "0",
+					"total": "50000000000"
+				}
+			}
+		}
+		# "#
+		# , 4, false, false, false, false);
+	```
+	*/
+
+	fn estimate_fee(&self, args: InitTxArgs) -> Result<FeeEstimate, ErrorKind>;
+
+	/**
+		Networked version of [Owner::preview_tx](struct.Owner.html#method.preview_tx).
+
+	```
+		# grin_wallet_api::doctest_helper_json_rpc_owner_assert_response!(
+		# r#"
+		{
+			"jsonrpc": "2.0",
+			"method": "preview_tx",
+			"params": {
+				"args": {
+					"src_acct_name": null,
+					"amount": "6000000000",
+					"minimum_confirmations": 2,
+					"max_outputs": 500,
+					"num_change_outputs": 1,
+					"selection_strategy_is_use_all": true,
+					"target_slate_version": null,
+					"payment_proof_recipient_address": null,
+					"ttl_blocks": null,
+					"send_args": null
+				}
+			},
+			"id": 1
+		}
+		# "#
+		# ,
+		# r#"
+		{
+			"id": 1,
+			"jsonrpc": "2.0",
+			"result": {
+				"Ok": {
+					"amount": "6000000000",
+					"balance_after": "193992000000",
+					"change": [43992000000],
+					"fee": "8000000",
+					"inputs": [
+						{
+							"commit": "083fbf6c559dd0d0220a155afcdd99c1320b56681d4dbfb7d0cd4f92b28f79c60b",
+							"height": "1",
+							"value": "50000000000"
+						}
+					],
+					"token_balance_after": null,
+					"token_change": [],
+					"token_fee": "0",
+					"token_inputs": []
+				}
+			}
+		}
+		# "#
+		# , 4, false, false, false, false);
+	```
+	*/
+
+	fn preview_tx(&self, args: InitTxArgs) -> Result<TxPreview, ErrorKind>;
+
 	/**
 		Networked version of [Owner::issue_invoice_tx](struct.Owner.html#method.issue_invoice_tx).
 
@@ -556,6 +719,100 @@ pub trait OwnerRpc {
 		args: InitTxArgs,
 	) -> Result<VersionedSlate, ErrorKind>;
 
+	/**
+	Networked version of [Owner::init_consolidate_tx](struct.Owner.html#method.init_consolidate_tx).
+	As below, no transaction is built when fewer outputs than `min_outputs` qualify.
+
+	```
+	# grin_wallet_api::doctest_helper_json_rpc_owner_assert_response!(
+	# r#"
+	{
+		"jsonrpc": "2.0",
+		"method": "init_consolidate_tx",
+		"params": {
+			"token": "d202964900000000d302964900000000d402964900000000d502964900000000",
+			"args": {
+				"acct_name": null,
+				"minimum_confirmations": "1",
+				"max_outputs": 500,
+				"min_outputs": 2,
+				"below_value": null,
+				"fee_base": null
+			}
+		},
+		"id": 1
+	}
+	# "#
+	# ,
+	# r#"
+	{
+		"id": 1,
+		"jsonrpc": "2.0",
+		"result": {
+			"Err": {
+				"Consolidation": "only 1 outputs qualify, at least 2 needed"
+			}
+		}
+	}
+	# "#
+	# , 4, false, false, false, false);
+	```
+	*/
+
+	fn init_consolidate_tx(
+		&self,
+		token: Token,
+		args: ConsolidateArgs,
+	) -> Result<VersionedSlate, ErrorKind>;
+
+	/**
+	Networked version of [Owner::init_split_outputs_tx](struct.Owner.html#method.init_split_outputs_tx).
+	As below, an output has to be split into at least two.
+
+	```
+	# grin_wallet_api::doctest_helper_json_rpc_owner_assert_response!(
+	# r#"
+	{
+		"jsonrpc": "2.0",
+		"method": "init_split_outputs_tx",
+		"params": {
+			"token": "d202964900000000d302964900000000d402964900000000d502964900000000",
+			"args": {
+				"acct_name": null,
+				"token_type": null,
+				"minimum_confirmations": "1",
+				"max_outputs": 500,
+				"num_outputs": 1,
+				"amount": null,
+				"denominations": null,
+				"fee_base": null
+			}
+		},
+		"id": 1
+	}
+	# "#
+	# ,
+	# r#"
+	{
+		"id": 1,
+		"jsonrpc": "2.0",
+		"result": {
+			"Err": {
+				"SplitOutputs": "can't split into 1 outputs, at least 2 needed"
+			}
+		}
+	}
+	# "#
+	# , 4, false, false, false, false);
+	```
+	*/
+
+	fn init_split_outputs_tx(
+		&self,
+		token: Token,
+		args: SplitOutputsArgs,
+	) -> Result<VersionedSlate, ErrorKind>;
+
 	/**
 	Networked version of [Owner::tx_lock_outputs](struct.Owner.html#method.tx_lock_outputs).
 
@@ -601,6 +858,57 @@ pub trait OwnerRpc {
 	 */
 	fn tx_lock_outputs(&self, token: Token, slate: VersionedSlate) -> Result<(), ErrorKind>;
 
+	/**
+	Networked version of [Owner::tx_reserve_outputs](struct.Owner.html#method.tx_reserve_outputs).
+
+	```
+	# grin_wallet_api::doctest_helper_json_rpc_owner_assert_response!(
+	# r#"
+	{
+		"jsonrpc": "2.0",
+		"method": "tx_reserve_outputs",
+		"id": 1,
+		"params": {
+			"token": "d202964900000000d302964900000000d402964900000000d502964900000000",
+			"slate": {
+				"ver": "4:3",
+				"id": "0436430c-2b02-624c-2032-570501212b00",
+				"sta": "S1",
+				"off": "d202964900000000d302964900000000d402964900000000d502964900000000",
+				"amt": "60000000000",
+				"fee": "7000000",
+				"sigs": [
+					{
+						"xs": "030152d2d72e2dba7c6086ad49a219d9ff0dfe0fd993dcaea22e058c210033ce93",
+						"nonce": "031b84c5567b126440995d3ed5aaba0565d71e1834604819ff9c17f5e9d5dd078f"
+					}
+				]
+			},
+			"reserve_blocks": 10
+		}
+	}
+	# "#
+	# ,
+	# r#"
+	{
+		"jsonrpc": "2.0",
+		"id": 1,
+		"result": {
+			"Ok": null
+		}
+	}
+	# "#
+	# , 5 ,true, false, false, false);
+
+	```
+	 */
+	fn tx_reserve_outputs(
+		&self,
+		token: Token,
+		slate: VersionedSlate,
+		reserve_blocks: u64,
+	) -> Result<(), ErrorKind>;
+
 	/**
 	Networked version of [Owner::finalize_tx](struct.Owner.html#method.finalize_tx).
 
@@ -848,6 +1156,84 @@ pub trait OwnerRpc {
 		slate_id: Option<Uuid>,
 	) -> Result<Option<VersionedSlate>, ErrorKind>;
 
+	/**
+	Networked version of [Owner::set_output_frozen](struct.Owner.html#method.set_output_frozen).
+
+	```
+	# grin_wallet_api::doctest_helper_json_rpc_owner_assert_response!(
+	# r#"
+	{
+		"jsonrpc": "2.0",
+		"method": "set_output_frozen",
+		"id": 1,
+		"params": {
+			"token": "d202964900000000d302964900000000d402964900000000d502964900000000",
+			"commit": "083fbf6c559dd0d0220a155afcdd99c1320b56681d4dbfb7d0cd4f92b28f79c60b",
+			"frozen": true
+		}
+	}
+	# "#
+	# ,
+	# r#"
+	{
+		"jsonrpc": "2.0",
+		"id": 1,
+		"result": {
+			"Ok": null
+		}
+	}
+	# "#
+	# , 2, false, false, false, false);
+	```
+	 */
+	fn set_output_frozen(
+		&self,
+		token: Token,
+		commit: String,
+		frozen: bool,
+	) -> Result<(), ErrorKind>;
+
+	/**
+	Networked version of [Owner::set_tx_note](struct.Owner.html#method.set_tx_note).
+
+	```
+	# grin_wallet_api::doctest_helper_json_rpc_owner_assert_response!(
+	# r#"
+	{
+		"jsonrpc": "2.0",
+		"method": "set_tx_note",
+		"id": 1,
+		"params": {
+			"token": "d202964900000000d302964900000000d402964900000000d502964900000000",
+			"tx_id": 0,
+			"tx_slate_id": null,
+			"note": "invoice #1234",
+			"labels": ["payroll"]
+		}
+	}
+	# "#
+	# ,
+	# r#"
+	{
+		"jsonrpc": "2.0",
+		"id": 1,
+		"result": {
+			"Ok": null
+		}
+	}
+	# "#
+	# , 2, false, false, false, false);
+	```
+	 */
+	fn set_tx_note(
+		&self,
+		token: Token,
+		tx_id: Option<u32>,
+		tx_slate_id: Option<Uuid>,
+		note: Option<String>,
+		labels: Vec<String>,
+	) -> Result<(), ErrorKind>;
+
 	/**
 	Networked version of [Owner::scan](struct.Owner.html#method.scan).
 
@@ -1059,6 +1445,7 @@ pub trait OwnerRpc {
 				"owner_api_include_foreign": false,
 				"data_file_dir": "/path/to/data/file/dir",
 				"no_commit_cache": null,
+				"change_key_branch": null,
 				"tls_certificate_file": null,
 				"tls_certificate_key": null,
 				"dark_background_color_scheme": null,
@@ -1776,6 +2163,7 @@ where
 		refresh_from_node: bool,
 		tx_id: Option<u32>,
 		tx_slate_id: Option<Uuid>,
+		note_filter: Option<TxNoteFilter>,
 	) -> Result<(bool, Vec<TxLogEntry>), ErrorKind> {
 		Owner::retrieve_txs(
 			self,
@@ -1783,6 +2171,7 @@ where
 			refresh_from_node,
 			tx_id,
 			tx_slate_id,
+			note_filter,
 		)
 		.map_err(|e| e.kind())
 	}
@@ -1802,6 +2191,26 @@ where
 		.map_err(|e| e.kind())
 	}
 
+	fn export_txs(
+		&self,
+		token: Token,
+		refresh_from_node: bool,
+		args: TxExportArgs,
+	) -> Result<(bool, String), ErrorKind> {
+		let mut out = Vec::new();
+		let (validated, _) = Owner::export_txs(
+			self,
+			(&token.keychain_mask).as_ref(),
+			refresh_from_node,
+			&args,
+			&mut out,
+		)
+		.map_err(|e| e.kind())?;
+		let exported =
+			String::from_utf8(out).map_err(|e| ErrorKind::GenericError(format!("{}", e)))?;
+		Ok((validated, exported))
+	}
+
 	fn init_send_tx(&self, token: Token, args: InitTxArgs) -> Result<VersionedSlate, ErrorKind> {
 		let slate = Owner::init_send_tx(self, (&token.keychain_mask).as_ref(), args)
 			.map_err(|e| e.kind())?;
@@ -1809,6 +2218,14 @@ where
 		Ok(VersionedSlate::into_version(slate, version).map_err(|e| e.kind())?)
 	}
 
+	fn estimate_fee(&self, args: InitTxArgs) -> Result<FeeEstimate, ErrorKind> {
+		Owner::estimate_fee(self, args).map_err(|e| e.kind())
+	}
+
+	fn preview_tx(&self, args: InitTxArgs) -> Result<TxPreview, ErrorKind> {
+		Owner::preview_tx(self, args).map_err(|e| e.kind())
+	}
+
 	fn issue_invoice_tx(
 		&self,
 		token: Token,
@@ -1852,6 +2269,28 @@ where
 		Ok(VersionedSlate::into_version(out_slate, version).map_err(|e| e.kind())?)
 	}
 
+	fn init_consolidate_tx(
+		&self,
+		token: Token,
+		args: ConsolidateArgs,
+	) -> Result<VersionedSlate, ErrorKind> {
+		let slate = Owner::init_consolidate_tx(self, (&token.keychain_mask).as_ref(), args)
+			.map_err(|e| e.kind())?;
+		let version = SlateVersion::V4;
+		Ok(VersionedSlate::into_version(slate, version).map_err(|e| e.kind())?)
+	}
+
+	fn init_split_outputs_tx(
+		&self,
+		token: Token,
+		args: SplitOutputsArgs,
+	) -> Result<VersionedSlate, ErrorKind> {
+		let slate = Owner::init_split_outputs_tx(self, (&token.keychain_mask).as_ref(), args)
+			.map_err(|e| e.kind())?;
+		let version = SlateVersion::V4;
+		Ok(VersionedSlate::into_version(slate, version).map_err(|e| e.kind())?)
+	}
+
 	fn tx_lock_outputs(&self, token: Token, in_slate: VersionedSlate) -> Result<(), ErrorKind> {
 		Owner::tx_lock_outputs(
 			self,
@@ -1861,6 +2300,21 @@ where
 		.map_err(|e| e.kind())
 	}
 
+	fn tx_reserve_outputs(
+		&self,
+		token: Token,
+		in_slate: VersionedSlate,
+		reserve_blocks: u64,
+	) -> Result<(), ErrorKind> {
+		Owner::tx_reserve_outputs(
+			self,
+			(&token.keychain_mask).as_ref(),
+			&Slate::from(in_slate),
+			reserve_blocks,
+		)
+		.map_err(|e| e.kind())
+	}
+
 	fn cancel_tx(
 		&self,
 		token: Token,
@@ -1895,6 +2349,35 @@ where
 		}
 	}
 
+	fn set_output_frozen(
+		&self,
+		token: Token,
+		commit: String,
+		frozen: bool,
+	) -> Result<(), ErrorKind> {
+		Owner::set_output_frozen(self, (&token.keychain_mask).as_ref(), &commit, frozen)
+			.map_err(|e| e.kind())
+	}
+
+	fn set_tx_note(
+		&self,
+		token: Token,
+		tx_id: Option<u32>,
+		tx_slate_id: Option<Uuid>,
+		note: Option<String>,
+		labels: Vec<String>,
+	) -> Result<(), ErrorKind> {
+		Owner::set_tx_note(
+			self,
+			(&token.keychain_mask).as_ref(),
+			tx_id,
+			tx_slate_id,
+			note,
+			labels,
+		)
+		.map_err(|e| e.kind())
+	}
+
 	fn post_tx(&self, token: Token, slate: VersionedSlate, fluff: bool) -> Result<(), ErrorKind> {
 		Owner::post_tx(
 			self,
@@ -2303,14 +2786,21 @@ pub fn run_doctest_owner(
 		{
 			let mut w_lock = wallet2.lock();
 			let w2 = w_lock.lc_provider().unwrap().wallet_inst().unwrap();
-			slate = api_impl::foreign::receive_tx(&mut **w2, (&mask2).as_ref(), &slate, None, true)
-				.unwrap();
+			slate = api_impl::foreign::receive_tx(
+				&mut **w2,
+				(&mask2).as_ref(),
+				&slate,
+				None,
+				None,
+				true,
+			)
+			.unwrap();
 			w2.close().unwrap();
 		}
 		// Spit out slate for input to finalize_tx
 		if lock_tx {
 			println!("LOCKING TX");
-			api_impl::owner::tx_lock_outputs(&mut **w, (&mask1).as_ref(), &slate).unwrap();
+			api_impl::owner::tx_lock_outputs(&mut **w, (&mask1).as_ref(), &slate, None).unwrap();
 		}
 		println!("RECEIPIENT SLATE");
 		println!("{}", serde_json::to_string_pretty(&slate).unwrap());
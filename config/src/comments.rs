@@ -102,6 +102,15 @@ fn comments() -> HashMap<String, String> {
 #If true, don't store calculated commits in the database
 #better privacy, but at a performance cost of having to
 #re-calculate commits every time they're used
+"
+		.to_string(),
+	);
+	retval.insert(
+		"change_key_branch".to_string(),
+		"
+#If true, a wallet created with this configuration derives change
+#outputs on a dedicated change branch of each account, so they can
+#be told apart from received outputs. Has no effect on existing wallets
 "
 		.to_string(),
 	);
@@ -47,6 +47,9 @@ pub struct WalletConfig {
 	/// If Some(true), don't cache commits alongside output data
 	/// speed improvement, but your commits are in the database
 	pub no_commit_cache: Option<bool>,
+	/// If Some(true), new wallets derive change outputs on a dedicated change
+	/// branch of each account, rather than alongside received outputs
+	pub change_key_branch: Option<bool>,
 	/// TLS certificate file
 	pub tls_certificate_file: Option<String>,
 	/// TLS certificate private key file
@@ -71,6 +74,7 @@ impl Default for WalletConfig {
 			owner_api_include_foreign: Some(false),
 			data_file_dir: ".".to_string(),
 			no_commit_cache: Some(false),
+			change_key_branch: Some(false),
 			tls_certificate_file: None,
 			tls_certificate_key: None,
 			dark_background_color_scheme: Some(true),
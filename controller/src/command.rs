@@ -24,8 +24,9 @@ use crate::impls::{HttpSlateSender, PathToSlate, PathToSlatepack, SlatePutter};
 use crate::keychain;
 use crate::libwallet::IssueTokenArgs;
 use crate::libwallet::{
-	self, InitTxArgs, IssueInvoiceTxArgs, NodeClient, PaymentProof, Slate, SlateVersion, Slatepack,
-	SlatepackAddress, Slatepacker, SlatepackerArgs, WalletLCProvider,
+	self, InitTxArgs, IssueInvoiceTxArgs, NodeClient, PaymentProof, SelectionStrategy, Slate,
+	SlateTransport, SlateVersion, Slatepack, SlatepackAddress, Slatepacker, SlatepackerArgs,
+	WalletLCProvider,
 };
 use crate::util::secp::key::SecretKey;
 use crate::util::{Mutex, ZeroingString};
@@ -95,6 +96,20 @@ where
 		test_mode,
 	)?;
 
+	if args.config.change_key_branch == Some(true) {
+		let mask = p.open_wallet(None, args.password.clone(), false, false)?;
+		{
+			let w = p.wallet_inst()?;
+			libwallet::api_impl::owner::set_account_change_branch(
+				&mut **w,
+				mask.as_ref(),
+				"default",
+				true,
+			)?;
+		}
+		p.close_wallet(None)?;
+	}
+
 	let m = p.get_mnemonic(None, args.password)?;
 	show_recovery_phrase(m);
 	Ok(())
@@ -263,6 +278,7 @@ where
 		let args = IssueTokenArgs {
 			acct_name: None,
 			amount: args.amount,
+			..Default::default()
 		};
 
 		let result = api.init_issue_token_tx(m, args);
@@ -297,6 +313,20 @@ where
 	Ok(())
 }
 
+/// Maps a selection strategy name given on the command line onto a strategy
+fn selection_strategy_from_arg(arg: &str) -> SelectionStrategy {
+	match arg {
+		"all" => SelectionStrategy::UseAll,
+		"largest" => SelectionStrategy::LargestFirst,
+		"fewest" => SelectionStrategy::BiggestFewest,
+		"oldest" => SelectionStrategy::OldestFirst,
+		"random" => SelectionStrategy::Random {
+			seed: rand::random(),
+		},
+		_ => SelectionStrategy::SmallestFirst,
+	}
+}
+
 /// Arguments for the send command
 #[derive(Clone)]
 pub struct SendArgs {
@@ -454,7 +484,7 @@ where
 	let mut slate = Slate::blank(2, false);
 	controller::owner_single_use(None, keychain_mask, Some(owner_api), |api, m| {
 		if args.estimate_selection_strategies {
-			let strategies = vec!["smallest", "all"]
+			let strategies = vec!["smallest", "largest", "fewest", "oldest", "all"]
 				.into_iter()
 				.map(|strategy| {
 					let init_args = InitTxArgs {
@@ -465,6 +495,7 @@ where
 						max_outputs: args.max_outputs as u32,
 						num_change_outputs: args.change_outputs as u32,
 						selection_strategy_is_use_all: strategy == "all",
+						selection_strategy: Some(selection_strategy_from_arg(strategy)),
 						estimate_only: Some(true),
 						..Default::default()
 					};
@@ -483,6 +514,7 @@ where
 				max_outputs: args.max_outputs as u32,
 				num_change_outputs: args.change_outputs as u32,
 				selection_strategy_is_use_all: args.selection_strategy == "all",
+				selection_strategy: Some(selection_strategy_from_arg(&args.selection_strategy)),
 				target_slate_version: args.target_slate_version,
 				payment_proof_recipient_address: args.payment_proof_address.clone(),
 				ttl_blocks: args.ttl_blocks,
@@ -522,6 +554,7 @@ where
 
 	match res {
 		Ok(Some(s)) => {
+			owner_api.set_slate_transport(Some(SlateTransport::Http));
 			controller::owner_single_use(None, keychain_mask, Some(owner_api), |api, m| {
 				api.tx_lock_outputs(m, &s)?;
 				let ret_slate = api.finalize_tx(m, &s)?;
@@ -598,6 +631,10 @@ where
 	};
 
 	if lock {
+		owner_api.set_slate_transport(Some(match is_pre_fork {
+			true => SlateTransport::File,
+			false => SlateTransport::Slatepack,
+		}));
 		controller::owner_single_use(None, keychain_mask, Some(owner_api), |api, m| {
 			api.tx_lock_outputs(m, &slate)?;
 			Ok(())
@@ -762,7 +799,13 @@ where
 		None => None,
 	};
 
+	// slate files predate slatepacks, which carry v4 slates
+	let transport = match slate.version_info.version < 4 {
+		true => SlateTransport::File,
+		false => SlateTransport::Slatepack,
+	};
 	controller::foreign_single_use(owner_api.wallet_inst.clone(), km, |api| {
+		api.set_slate_transport(Some(transport));
 		slate = api.receive_tx(&slate, Some(&g_args.account), None)?;
 		Ok(())
 	})?;
@@ -1076,7 +1119,7 @@ where
 
 	controller::owner_single_use(None, keychain_mask, Some(owner_api), |api, m| {
 		if args.estimate_selection_strategies {
-			let strategies = vec!["smallest", "all"]
+			let strategies = vec!["smallest", "largest", "fewest", "oldest", "all"]
 				.into_iter()
 				.map(|strategy| {
 					let init_args = InitTxArgs {
@@ -1086,6 +1129,7 @@ where
 						max_outputs: args.max_outputs as u32,
 						num_change_outputs: 1u32,
 						selection_strategy_is_use_all: strategy == "all",
+						selection_strategy: Some(selection_strategy_from_arg(strategy)),
 						estimate_only: Some(true),
 						..Default::default()
 					};
@@ -1103,6 +1147,7 @@ where
 				max_outputs: args.max_outputs as u32,
 				num_change_outputs: 1u32,
 				selection_strategy_is_use_all: args.selection_strategy == "all",
+				selection_strategy: Some(selection_strategy_from_arg(&args.selection_strategy)),
 				ttl_blocks: args.ttl_blocks,
 				send_args: None,
 				..Default::default()
@@ -1252,7 +1297,7 @@ where
 	let updater_running = owner_api.updater_running.load(Ordering::Relaxed);
 	controller::owner_single_use(None, keychain_mask, Some(owner_api), |api, m| {
 		let res = api.node_height(m)?;
-		let (validated, txs) = api.retrieve_txs(m, true, args.id, args.tx_slate_id)?;
+		let (validated, txs) = api.retrieve_txs(m, true, args.id, args.tx_slate_id, None)?;
 		let include_status = !args.id.is_some() && !args.tx_slate_id.is_some();
 		display::txs(
 			&g_args.account,
@@ -1264,7 +1309,7 @@ where
 		)?;
 
 		let (token_validated, token_txs) =
-			api.retrieve_token_txs(m, true, args.id, args.tx_slate_id)?;
+			api.retrieve_token_txs(m, true, args.id, args.tx_slate_id, None)?;
 		let include_status = !args.id.is_some() && !args.tx_slate_id.is_some();
 		display::token_txs(
 			&g_args.account,
@@ -1388,8 +1433,8 @@ where
 			}
 			Some(s) => s,
 		};
-		let (_, txs) = api.retrieve_txs(m, true, Some(args.id), None)?;
-		let (_, token_txs) = api.retrieve_token_txs(m, true, Some(args.id), None)?;
+		let (_, txs) = api.retrieve_txs(m, true, Some(args.id), None, None)?;
+		let (_, token_txs) = api.retrieve_token_txs(m, true, Some(args.id), None, None)?;
 		match args.dump_file {
 			None => {
 				if txs.len() > 0 && txs[0].confirmed {
@@ -1487,15 +1532,16 @@ where
 {
 	controller::owner_single_use(None, keychain_mask, Some(owner_api), |api, m| {
 		let tip_height = api.node_height(m)?.height;
+		// without a start height, a cancelled scan is picked up where it stopped
 		let start_height = match args.backwards_from_tip {
-			Some(b) => tip_height.saturating_sub(b),
-			None => match args.start_height {
-				Some(s) => s,
-				None => 1,
-			},
+			Some(b) => Some(tip_height.saturating_sub(b)),
+			None => args.start_height,
 		};
-		warn!("Starting output scan from height {} ...", start_height);
-		let result = api.scan(m, Some(start_height), args.delete_unconfirmed);
+		match start_height {
+			Some(h) => warn!("Starting output scan from height {} ...", h),
+			None => warn!("Starting output scan ..."),
+		}
+		let result = api.scan(m, start_height, args.delete_unconfirmed);
 		match result {
 			Ok(_) => {
 				warn!("Wallet check complete",);
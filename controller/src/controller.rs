@@ -18,8 +18,8 @@ use crate::api::{self, ApiServer, BasicAuthMiddleware, ResponseFuture, Router, T
 use crate::config::TorConfig;
 use crate::keychain::Keychain;
 use crate::libwallet::{
-	address, Error, ErrorKind, NodeClient, NodeVersionInfo, Slate, SlatepackAddress, WalletInst,
-	WalletLCProvider, GRIN_BLOCK_HEADER_VERSION,
+	address, Error, ErrorKind, NodeClient, NodeVersionInfo, Slate, SlateTransport,
+	SlatepackAddress, WalletInst, WalletLCProvider, GRIN_BLOCK_HEADER_VERSION,
 };
 use crate::util::secp::key::SecretKey;
 use crate::util::{from_hex, static_secp_instance, to_base64, Mutex};
@@ -806,6 +806,7 @@ where
 	) -> Result<Response<Body>, Error> {
 		let api = Foreign::new(wallet, mask, Some(check_middleware), test_mode);
 		api.set_tor_config(tor_config);
+		api.set_slate_transport(Some(SlateTransport::Http));
 		let res = Self::call_api(req, api).await?;
 		Ok(json_response_pretty(&res))
 	}
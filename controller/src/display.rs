@@ -626,6 +626,18 @@ pub fn info(
 			Fr->"Locked by previous transaction",
 			Fr->amount_to_hr_string(wallet_info.amount_locked, false)
 		]);
+		if wallet_info.amount_frozen > 0 {
+			table.add_row(row![
+				Fr->"Frozen",
+				Fr->amount_to_hr_string(wallet_info.amount_frozen, false)
+			]);
+		}
+		if wallet_info.amount_reserved > 0 {
+			table.add_row(row![
+				Fr->"Reserved by unlocked transaction",
+				Fr->amount_to_hr_string(wallet_info.amount_reserved, false)
+			]);
+		}
 		table.add_row(row![
 			Fw->"--------------------------------",
 			Fw->"-------------"
@@ -661,6 +673,18 @@ pub fn info(
 			Fr->"Locked by previous transaction",
 			Fr->amount_to_hr_string(wallet_info.amount_locked, false)
 		]);
+		if wallet_info.amount_frozen > 0 {
+			table.add_row(row![
+				Fr->"Frozen",
+				Fr->amount_to_hr_string(wallet_info.amount_frozen, false)
+			]);
+		}
+		if wallet_info.amount_reserved > 0 {
+			table.add_row(row![
+				Fr->"Reserved by unlocked transaction",
+				Fr->amount_to_hr_string(wallet_info.amount_reserved, false)
+			]);
+		}
 		table.add_row(row![
 			Fw->"--------------------------------",
 			Fw->"-------------"
@@ -687,7 +711,7 @@ pub fn info(
 		]);
 		table.add_row(row![
 			bFG->"Total",
-			FG->amount_to_hr_string(token_info.amount_awaiting_confirmation+token_info.amount_awaiting_finalization+token_info.amount_currently_spendable, false)
+			FG->amount_to_hr_string(token_info.amount_awaiting_confirmation+token_info.amount_awaiting_finalization+token_info.amount_currently_spendable+token_info.amount_frozen+token_info.amount_reserved, false)
 		]);
 		table.add_row(row![
 			bFG->"Reverted",
@@ -705,6 +729,14 @@ pub fn info(
 			Fr->"Locked by previous transaction",
 			Fr->amount_to_hr_string(token_info.amount_locked, false)
 		]);
+		table.add_row(row![
+			Fr->"Frozen",
+			Fr->amount_to_hr_string(token_info.amount_frozen, false)
+		]);
+		table.add_row(row![
+			Fr->"Reserved by unlocked transaction",
+			Fr->amount_to_hr_string(token_info.amount_reserved, false)
+		]);
 		table.add_row(row![
 			Fw->"--------------------------------",
 			Fw->"-------------"
@@ -0,0 +1,149 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test not enough funds errors explain what holds up the rest of the balance
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, InitTxArgs, OutputStatus, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn balance_breakdown_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let base = core::consensus::GRIN_BASE;
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	let args = |amount| InitTxArgs {
+		src_acct_name: None,
+		amount,
+		minimum_confirmations: 2,
+		max_outputs: 500,
+		num_change_outputs: 1,
+		..Default::default()
+	};
+
+	// one send locked, its change awaiting confirmation, and another holding a
+	// reservation on its inputs
+	let mut reserved = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let slate = api.init_send_tx(m, args(base))?;
+		api.tx_lock_outputs(m, &slate)?;
+		reserved = api.init_send_tx(m, args(base))?;
+		Ok(())
+	})?;
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, info) = api.retrieve_summary_info(m, true, 2)?;
+		match api.init_send_tx(m, args(1_000_000 * base)) {
+			Err(e) => {
+				let message = format!("{}", e.kind());
+				match e.kind() {
+					ErrorKind::NotEnoughFunds {
+						available,
+						breakdown,
+						..
+					} => {
+						assert_eq!(breakdown.immature_coinbase, info.amount_immature);
+						assert!(breakdown.immature_coinbase > 0);
+						assert_eq!(
+							breakdown.awaiting_confirmation,
+							info.amount_awaiting_finalization + info.amount_awaiting_confirmation
+						);
+						assert!(breakdown.awaiting_confirmation > 0);
+						// the reservation holds up inputs the summary counts as spendable
+						assert!(breakdown.locked > info.amount_locked);
+						assert_eq!(
+							breakdown.spendable + breakdown.locked,
+							info.amount_currently_spendable + info.amount_locked
+						);
+						assert_eq!(available, breakdown.spendable);
+					}
+					k => panic!("Unexpected error: {}", k),
+				}
+				assert!(message.contains("awaiting confirmation"));
+				assert!(message.contains("locked"));
+				assert!(message.contains("immature"));
+			}
+			Ok(_) => panic!("Send should fail with not enough funds"),
+		}
+		api.cancel_tx(m, None, Some(reserved.id))?;
+		Ok(())
+	})?;
+
+	// the helper sees the same split, the reservation being released
+	{
+		wallet_inst!(wallet1, w);
+		let parent_key_id = w.parent_key_id();
+		let height = w.last_confirmed_height()?;
+		let breakdown =
+			libwallet::spendable_balance_breakdown(&mut **w, height, 2, 2, &parent_key_id);
+		let locked: u64 = w
+			.iter()
+			.filter(|o| o.status == OutputStatus::Locked)
+			.map(|o| o.value)
+			.sum();
+		assert_eq!(breakdown.locked, locked);
+	}
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn balance_breakdown() {
+	let test_dir = "test_output/balance_breakdown";
+	setup(test_dir);
+	if let Err(e) = balance_breakdown_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
@@ -0,0 +1,183 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test paying several recipients in a single transaction
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, InitTxArgs, OutputStatus, Payout, SelectionStrategy, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn batch_payout_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask2 = (&mask2_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let base = core::consensus::GRIN_BASE;
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	let payouts: Vec<Payout> = [1, 2, 3]
+		.iter()
+		.enumerate()
+		.map(|(i, a)| Payout {
+			amount: a * base,
+			recipient: Some(format!("miner{}", i)),
+		})
+		.collect();
+
+	// wallet 1 pays out the batch to wallet 2, which holds the outputs
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		// payouts can't be combined with sending everything
+		let args = InitTxArgs {
+			src_acct_name: None,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			payouts: Some(payouts.clone()),
+			send_all: true,
+			..Default::default()
+		};
+		match api.init_send_tx(m, args) {
+			Err(e) => match e.kind() {
+				ErrorKind::InvalidPayouts(_) => {}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Payouts with send_all should fail"),
+		}
+
+		let args = InitTxArgs {
+			src_acct_name: None,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy: Some(SelectionStrategy::LargestFirst),
+			payouts: Some(payouts.clone()),
+			..Default::default()
+		};
+		slate = api.init_send_tx(m, args)?;
+		assert_eq!(slate.amount, 6 * base);
+		Ok(())
+	})?;
+
+	wallet::controller::foreign_single_use(wallet2.clone(), mask2_i.clone(), |api| {
+		// the payouts must add up to the slate amount
+		assert!(api.receive_payouts_tx(&slate, None, &payouts[..2]).is_err());
+		slate = api.receive_payouts_tx(&slate, None, &payouts)?;
+		Ok(())
+	})?;
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.tx_lock_outputs(m, &slate)?;
+		slate = api.finalize_tx(m, &slate)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 2, false);
+
+	// the sender's fee covers an output for each payout, and its log records them
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, txs) = api.retrieve_txs(m, true, None, Some(slate.id), None)?;
+		let tx = txs[0].clone();
+		assert!(tx.confirmed);
+		assert_eq!(
+			tx.fee,
+			Some(core::libtx::tx_fee(tx.num_inputs, 4, 1, 0, 0, 0, None))
+		);
+		assert_eq!(tx.amount_debited - tx.amount_credited, 6 * base + slate.fee);
+		assert_eq!(tx.payouts, Some(payouts.clone()));
+		Ok(())
+	})?;
+
+	// the recipient holds one output per payout
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let (_, txs) = api.retrieve_txs(m, true, None, Some(slate.id), None)?;
+		let tx = txs[0].clone();
+		assert!(tx.confirmed);
+		assert_eq!(tx.num_outputs, 3);
+		assert_eq!(tx.amount_credited, 6 * base);
+		assert_eq!(tx.payouts, Some(payouts.clone()));
+
+		let (_, outputs) = api.retrieve_outputs(m, false, true, Some(tx.id))?;
+		let mut values: Vec<u64> = outputs
+			.iter()
+			.filter(|o| o.output.status == OutputStatus::Unspent)
+			.map(|o| o.output.value)
+			.collect();
+		values.sort();
+		assert_eq!(values, vec![base, 2 * base, 3 * base]);
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn batch_payout() {
+	let test_dir = "test_output/batch_payout";
+	setup(test_dir);
+	if let Err(e) = batch_payout_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
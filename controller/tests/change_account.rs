@@ -0,0 +1,147 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test deriving change outputs under a separate account
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, InitTxArgs, OutputStatus, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn change_account_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	let args = InitTxArgs {
+		src_acct_name: None,
+		amount: core::consensus::GRIN_BASE,
+		minimum_confirmations: 2,
+		max_outputs: 500,
+		num_change_outputs: 1,
+		change_acct_name: Some("change".to_owned()),
+		..Default::default()
+	};
+
+	// change can't go to an account that doesn't exist
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		match api.init_send_tx(m, args.clone()) {
+			Err(e) => match e.kind() {
+				ErrorKind::UnknownAccountLabel(l) => assert_eq!(l, "change"),
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Send should fail with an unknown change account"),
+		}
+		Ok(())
+	})?;
+
+	let mut slate = Slate::blank(2, false);
+	let mut change_path = None;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		change_path = Some(api.create_account_path(m, "change")?);
+		slate = api.init_send_tx(m, args.clone())?;
+		Ok(())
+	})?;
+	{
+		// the change key is derived under the change account
+		wallet_inst!(wallet1, w);
+		let context = w.get_private_context(mask1, slate.id.as_bytes())?;
+		let outputs = context.get_outputs();
+		assert_eq!(outputs.len(), 1);
+		assert_eq!(Some(outputs[0].0.parent_path()), change_path);
+	}
+
+	slate = client1.send_tx_slate_direct("wallet2", &slate)?;
+	let mut change = 0;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.tx_lock_outputs(m, &slate)?;
+		slate = api.finalize_tx(m, &slate)?;
+		api.post_tx(m, &slate, false)?;
+		let (_, txs) = api.retrieve_txs(m, false, None, Some(slate.id), None)?;
+		change = txs[0].amount_credited;
+		assert!(change > 0);
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+
+	// and the change output is found in that account
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.set_active_account(m, "change")?;
+		let (_, outputs) = api.retrieve_outputs(m, false, true, None)?;
+		assert_eq!(outputs.len(), 1);
+		assert_eq!(outputs[0].output.value, change);
+		assert_eq!(outputs[0].output.status, OutputStatus::Unspent);
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn change_account() {
+	let test_dir = "test_output/change_account";
+	setup(test_dir);
+	if let Err(e) = change_account_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
@@ -0,0 +1,190 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test deriving change outputs on a dedicated branch of the account
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+use grin_wallet_util::grin_keychain as keychain;
+
+use self::keychain::{ExtKeychain, Keychain};
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, InitTxArgs, OutputStatus, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn change_branch_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let base = core::consensus::GRIN_BASE;
+	let account_path = ExtKeychain::derive_key_id(2, 0, 0, 0, 0);
+	let branch_path = ExtKeychain::derive_key_id(2, 0, 1, 0, 0);
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		// off unless asked for
+		let accounts = api.accounts(m)?;
+		assert!(!accounts[0].change_branch);
+		match api.set_account_change_branch(m, "missing", true) {
+			Err(e) => match e.kind() {
+				ErrorKind::UnknownAccountLabel(l) => assert_eq!(l, "missing"),
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Unknown account should be refused"),
+		}
+		api.set_account_change_branch(m, "default", true)?;
+		// new accounts follow the default account
+		api.create_account_path(m, "other")?;
+		let accounts = api.accounts(m)?;
+		assert_eq!(accounts.len(), 2);
+		assert!(accounts.iter().all(|a| a.change_branch));
+		Ok(())
+	})?;
+
+	let args = InitTxArgs {
+		src_acct_name: None,
+		amount: 2 * base,
+		minimum_confirmations: 2,
+		max_outputs: 500,
+		num_change_outputs: 1,
+		..Default::default()
+	};
+
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		slate = api.init_send_tx(m, args.clone())?;
+		slate = client1.send_tx_slate_direct("wallet2", &slate)?;
+		api.tx_lock_outputs(m, &slate)?;
+		slate = api.finalize_tx(m, &slate)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+
+	// the change key is on the branch, the output still belongs to the account
+	let mut change = None;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, outputs) = api.retrieve_outputs(m, false, true, None)?;
+		let outputs: Vec<_> = outputs
+			.into_iter()
+			.filter(|o| !o.output.is_coinbase)
+			.collect();
+		assert_eq!(outputs.len(), 1);
+		assert_eq!(outputs[0].output.status, OutputStatus::Unspent);
+		assert_eq!(outputs[0].output.key_id.parent_path(), branch_path);
+		assert_eq!(outputs[0].output.root_key_id, account_path);
+		change = Some(outputs[0].output.clone());
+		Ok(())
+	})?;
+	let change = change.unwrap();
+
+	// forget the change output and the setting, then scan them back
+	{
+		wallet_inst!(wallet1, w);
+		let mut batch = w.batch(mask1)?;
+		batch.delete(&change.key_id, &change.mmr_index)?;
+		batch.commit()?;
+	}
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.set_account_change_branch(m, "default", false)?;
+		api.scan(m, None, false)?;
+		let (_, outputs) = api.retrieve_outputs(m, false, true, None)?;
+		let restored: Vec<_> = outputs
+			.into_iter()
+			.filter(|o| o.output.key_id == change.key_id)
+			.collect();
+		assert_eq!(restored.len(), 1);
+		assert_eq!(restored[0].output.root_key_id, account_path);
+		assert_eq!(restored[0].output.value, change.value);
+
+		// the branch restores the account it belongs to, not a new one
+		let accounts = api.accounts(m)?;
+		assert_eq!(accounts.len(), 2);
+		assert_eq!(accounts[0].label, "default");
+		assert!(accounts[0].change_branch);
+		Ok(())
+	})?;
+
+	// and new change carries on after the keys found on the branch
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		slate = api.init_send_tx(m, args.clone())?;
+		Ok(())
+	})?;
+	{
+		wallet_inst!(wallet1, w);
+		let context = w.get_private_context(mask1, slate.id.as_bytes())?;
+		let outputs = context.get_outputs();
+		assert_eq!(outputs.len(), 1);
+		assert_eq!(outputs[0].0.parent_path(), branch_path);
+		assert!(outputs[0].0.to_path().last_path_index() > change.n_child);
+	}
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn change_branch() {
+	let test_dir = "test_output/change_branch";
+	setup(test_dir);
+	if let Err(e) = change_branch_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
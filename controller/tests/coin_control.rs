@@ -0,0 +1,178 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test sends spending explicitly chosen outputs
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, InitTxArgs, OutputStatus, SelectionStrategy};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn coin_control_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 8, false);
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		let (_, outputs) = api.retrieve_outputs(m, false, false, None)?;
+		let spendable: Vec<_> = outputs
+			.into_iter()
+			.filter(|o| {
+				o.output.status == OutputStatus::Unspent
+					&& o.output.lock_height <= info.last_confirmed_height
+			})
+			.map(|o| o.output)
+			.collect();
+		assert!(spendable.len() > 2);
+		let commit1 = spendable[1].commit.clone().unwrap();
+		let commit2 = spendable[2].commit.clone().unwrap();
+
+		// a required input covering the amount is the only input spent
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: core::consensus::GRIN_BASE,
+			minimum_confirmations: 1,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			required_inputs: Some(vec![commit1.clone()]),
+			..Default::default()
+		};
+		let slate = api.init_send_tx(m, args)?;
+		api.tx_lock_outputs(m, &slate)?;
+		let (_, outputs) = api.retrieve_outputs(m, false, false, None)?;
+		let locked: Vec<_> = outputs
+			.iter()
+			.filter(|o| o.output.status == OutputStatus::Locked)
+			.collect();
+		assert_eq!(locked.len(), 1);
+		assert_eq!(locked[0].output.commit, Some(commit1.clone()));
+
+		// the selector tops up required inputs that don't cover the amount
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: spendable[2].value + core::consensus::GRIN_BASE,
+			minimum_confirmations: 1,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			required_inputs: Some(vec![commit2]),
+			estimate_only: Some(true),
+			..Default::default()
+		};
+		let slate = api.init_send_tx(m, args)?;
+		assert_eq!(slate.amount, spendable[2].value * 2);
+
+		// locked and unknown outputs can't be required
+		for commit in &[commit1, "00".repeat(33)] {
+			let args = InitTxArgs {
+				src_acct_name: None,
+				amount: core::consensus::GRIN_BASE,
+				minimum_confirmations: 1,
+				max_outputs: 500,
+				num_change_outputs: 1,
+				required_inputs: Some(vec![commit.clone()]),
+				..Default::default()
+			};
+			match api.init_send_tx(m, args) {
+				Err(e) => match e.kind() {
+					ErrorKind::RequiredInputUnavailable(_) => {}
+					k => panic!("Unexpected error: {}", k),
+				},
+				Ok(_) => panic!("Send should fail with an unavailable input"),
+			}
+		}
+
+		// immature coinbase outputs are never selected, even when spending
+		// everything or asking for them explicitly
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		let (_, outputs) = api.retrieve_outputs(m, false, false, None)?;
+		let immature = outputs
+			.iter()
+			.find(|o| o.output.is_coinbase && o.output.lock_height > info.last_confirmed_height)
+			.unwrap();
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: core::consensus::GRIN_BASE,
+			minimum_confirmations: 1,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy: Some(SelectionStrategy::UseAll),
+			estimate_only: Some(true),
+			..Default::default()
+		};
+		let slate = api.init_send_tx(m, args)?;
+		assert_eq!(slate.amount, info.amount_currently_spendable);
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: core::consensus::GRIN_BASE,
+			minimum_confirmations: 1,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			required_inputs: Some(vec![immature.output.commit.clone().unwrap()]),
+			estimate_only: Some(true),
+			..Default::default()
+		};
+		assert!(api.init_send_tx(m, args).is_err());
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn coin_control() {
+	let test_dir = "test_output/coin_control";
+	setup(test_dir);
+	if let Err(e) = coin_control_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
@@ -0,0 +1,183 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test sends picking their inputs with an injected coin selector
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+use grin_wallet_util::grin_keychain::Identifier;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::api_impl::owner;
+use libwallet::{
+	CoinSelector, InitTxArgs, OutputData, SelectionParams, SingleRandomDraw, Slate,
+	StrategySelector, TokenOutputData,
+};
+use std::cell::RefCell;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+/// Selects as the default selector does, noting every target it's asked for
+#[derive(Default)]
+struct RecordingSelector {
+	targets: RefCell<Vec<u64>>,
+}
+
+impl CoinSelector for RecordingSelector {
+	fn select(
+		&self,
+		eligible: &[OutputData],
+		target: u64,
+		params: &SelectionParams,
+	) -> Option<Vec<OutputData>> {
+		self.targets.borrow_mut().push(target);
+		StrategySelector.select(eligible, target, params)
+	}
+
+	fn select_tokens(
+		&self,
+		eligible: &[TokenOutputData],
+		target: u64,
+		params: &SelectionParams,
+	) -> Option<Vec<TokenOutputData>> {
+		self.targets.borrow_mut().push(target);
+		StrategySelector.select_tokens(eligible, target, params)
+	}
+}
+
+fn coin_selector_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	let mut reward = 0;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, outputs) = api.retrieve_outputs(m, false, true, None)?;
+		reward = outputs.iter().map(|o| o.output.value).min().unwrap();
+		Ok(())
+	})?;
+
+	let args = |amount| InitTxArgs {
+		src_acct_name: None,
+		amount,
+		minimum_confirmations: 2,
+		max_outputs: 500,
+		num_change_outputs: 1,
+		..Default::default()
+	};
+
+	// the inputs of a send and the fee it pays, once it's cancelled again
+	let send_with = |selector: &dyn CoinSelector,
+	                 amount: u64|
+	 -> Result<(Vec<Identifier>, u64, u64), libwallet::Error> {
+		let slate: Slate;
+		let inputs;
+		{
+			wallet_inst!(wallet1, w);
+			slate =
+				owner::init_send_tx_with_selector(&mut **w, mask1, args(amount), selector, false)?;
+			let context = w.get_private_context(mask1, slate.id.as_bytes())?;
+			inputs = context.input_ids.clone();
+		}
+		wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+			api.cancel_tx(m, None, Some(slate.id))?;
+			Ok(())
+		})?;
+		let spent = inputs.iter().map(|i| i.2).sum();
+		Ok((inputs.into_iter().map(|i| i.0).collect(), spent, slate.fee))
+	};
+
+	// the default selector picks what a plain send does
+	let amount = reward * 3 / 2;
+	let (default_inputs, _, default_fee) = send_with(&StrategySelector, amount)?;
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		slate = api.init_send_tx(m, args(amount))?;
+		Ok(())
+	})?;
+	{
+		wallet_inst!(wallet1, w);
+		let context = w.get_private_context(mask1, slate.id.as_bytes())?;
+		let inputs: Vec<Identifier> = context.input_ids.iter().map(|i| i.0.clone()).collect();
+		assert_eq!(inputs, default_inputs);
+		assert_eq!(slate.fee, default_fee);
+	}
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.cancel_tx(m, None, Some(slate.id))?;
+		Ok(())
+	})?;
+
+	// a random draw covers the amount and fee, the same way for the same seed
+	let draw = SingleRandomDraw { seed: 7 };
+	let (inputs, spent, fee) = send_with(&draw, amount)?;
+	assert!(spent >= amount + fee);
+	assert_eq!(send_with(&draw, amount)?.0, inputs);
+
+	// selecting a single output leaves nothing for a fee above the minimum, so
+	// the selector is asked again for the larger target
+	let recording = RecordingSelector::default();
+	let min_fee = core::libtx::DEFAULT_BASE_FEE;
+	let (inputs, _, _) = send_with(&recording, reward - min_fee)?;
+	let targets = recording.targets.borrow().clone();
+	assert_eq!(targets.len(), 2);
+	assert_eq!(targets[0], reward);
+	assert!(targets[1] > targets[0]);
+	assert_eq!(inputs.len(), 2);
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn coin_selector() {
+	let test_dir = "test_output/coin_selector";
+	setup(test_dir);
+	if let Err(e) = coin_selector_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
@@ -0,0 +1,148 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test requiring more confirmations for coinbase outputs
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{InitTxArgs, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn coinbase_confirmations_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask2 = (&mask2_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	// the total each threshold leaves available, as estimated by sending it all
+	let send_all_total = |coinbase_min_confirmations: Option<u64>| {
+		let mut total = 0;
+		wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+			let args = InitTxArgs {
+				src_acct_name: None,
+				minimum_confirmations: 2,
+				coinbase_min_confirmations,
+				send_all: true,
+				estimate_only: Some(true),
+				..Default::default()
+			};
+			total = api.init_send_tx(m, args)?.amount;
+			Ok(())
+		})?;
+		Ok::<u64, libwallet::Error>(total)
+	};
+
+	let total = send_all_total(None)?;
+	assert!(total > 0);
+	// a stricter coinbase threshold leaves out the freshest rewards
+	assert!(send_all_total(Some(6))? < total);
+	// while a laxer one doesn't loosen the regular threshold
+	assert_eq!(send_all_total(Some(1))?, total);
+
+	// wallet 1 pays wallet 2, whose output isn't a coinbase
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: core::consensus::GRIN_BASE,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			..Default::default()
+		};
+		slate = api.init_send_tx(m, args)?;
+		slate = client1.send_tx_slate_direct("wallet2", &slate)?;
+		api.tx_lock_outputs(m, &slate)?;
+		slate = api.finalize_tx(m, &slate)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 2, false);
+
+	// the coinbase threshold doesn't apply to it
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			minimum_confirmations: 1,
+			coinbase_min_confirmations: Some(1000),
+			send_all: true,
+			estimate_only: Some(true),
+			..Default::default()
+		};
+		let slate = api.init_send_tx(m, args)?;
+		assert_eq!(slate.amount, core::consensus::GRIN_BASE);
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn coinbase_confirmations() {
+	let test_dir = "test_output/coinbase_confirmations";
+	setup(test_dir);
+	if let Err(e) = coinbase_confirmations_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
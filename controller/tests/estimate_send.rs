@@ -0,0 +1,188 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test previewing a send's selection without building it
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{
+	ChangePlan, ErrorKind, InitTxArgs, NodeClient, SelectionParams, SelectionStrategy, SendParams,
+	StrategySelector,
+};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn estimate_send_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let base = core::consensus::GRIN_BASE;
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	let (fee, total, num_inputs, num_change) = {
+		wallet_inst!(wallet1, w);
+		let height = w.w2n_client().get_chain_tip()?.0;
+		let parent_key_id = w.parent_key_id();
+		let params = SendParams {
+			minimum_confirmations: 2,
+			selection: SelectionParams {
+				max_outputs: 500,
+				strict_max_outputs: false,
+				selection_strategy: SelectionStrategy::SmallestFirst,
+			},
+			change_outputs: 2,
+			..Default::default()
+		};
+		let estimate =
+			libwallet::estimate_send(&mut **w, 2 * base, height, &params, &parent_key_id)?;
+
+		// a shortfall reports the same error a send would
+		match libwallet::estimate_send(&mut **w, 1_000 * base, height, &params, &parent_key_id) {
+			Err(e) => match e.kind() {
+				ErrorKind::NotEnoughFunds { needed, .. } => assert!(needed > 1_000 * base),
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Estimating more than the balance should fail"),
+		}
+
+		// every output is a block reward, any of them sends the same less its fee
+		assert_eq!(
+			libwallet::min_sendable_amount(&mut **w, height, 2, None, &parent_key_id),
+			core::consensus::REWARD - core::libtx::tx_fee(1, 1, 1, 0, 0, 0, None)
+		);
+		assert_eq!(
+			libwallet::min_sendable_amount(&mut **w, height, height + 1, None, &parent_key_id),
+			0
+		);
+		estimate
+	};
+	assert!(fee > 0);
+	assert!(total >= 2 * base + fee);
+	assert!(num_inputs > 0);
+	assert_eq!(num_change, 2);
+
+	// the same selection can be made from a snapshot of the wallet, which is
+	// only borrowed while the snapshot is taken
+	let eligible = {
+		wallet_inst!(wallet1, w);
+		let height = w.w2n_client().get_chain_tip()?.0;
+		let parent_key_id = w.parent_key_id();
+		libwallet::eligible_coins(
+			&mut **w,
+			height,
+			2,
+			2,
+			SelectionStrategy::SmallestFirst,
+			&[],
+			&parent_key_id,
+		)
+	};
+	let (coins, snapshot_total, _, snapshot_fee) = libwallet::select_coins_and_fee_from(
+		&eligible,
+		2 * base,
+		500,
+		false,
+		1,
+		2,
+		SelectionStrategy::SmallestFirst,
+		&StrategySelector,
+		None,
+		None,
+		None,
+		0,
+		&ChangePlan::Even,
+		&[],
+		false,
+		false,
+		0,
+		0,
+		1,
+	)?;
+	assert_eq!(coins.len(), num_inputs);
+	assert_eq!(snapshot_total, total);
+	assert_eq!(snapshot_fee, fee);
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		// previewing locked nothing
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		assert_eq!(info.amount_locked, 0);
+
+		// and matches what the send ends up selecting
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 2 * base,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 2,
+			selection_strategy: Some(SelectionStrategy::SmallestFirst),
+			..Default::default()
+		};
+		let slate = api.init_send_tx(m, args)?;
+		assert_eq!(slate.fee, fee);
+		api.tx_lock_outputs(m, &slate)?;
+		let (_, txs) = api.retrieve_txs(m, false, None, Some(slate.id), None)?;
+		assert_eq!(txs[0].num_inputs, num_inputs);
+		assert_eq!(txs[0].num_outputs, num_change);
+		assert_eq!(txs[0].amount_debited, total);
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn estimate_send() {
+	let test_dir = "test_output/estimate_send";
+	setup(test_dir);
+	if let Err(e) = estimate_send_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
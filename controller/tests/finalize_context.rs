@@ -0,0 +1,152 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test finalizing a transaction whose context no longer matches the wallet
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, InitTxArgs, OutputStatus, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn finalize_context_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask1 = (&mask1_i).as_ref();
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let _mask2 = (&mask2_i).as_ref();
+
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 5, false);
+
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: core::consensus::GRIN_BASE,
+			minimum_confirmations: 1,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			..Default::default()
+		};
+		slate = api.init_send_tx(m, args)?;
+		Ok(())
+	})?;
+	slate = client1.send_tx_slate_direct("wallet2", &slate)?;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.tx_lock_outputs(m, &slate)?;
+		Ok(())
+	})?;
+
+	// the change output disappears from under the locked transaction
+	let change = {
+		wallet_inst!(wallet1, w);
+		let context = w.get_private_context(mask1, slate.id.as_bytes())?;
+		let (id, mmr_index, _) = context.get_outputs()[0].clone();
+		let mut batch = w.batch(mask1)?;
+		let change = batch.get(&id, &mmr_index)?;
+		batch.delete(&id, &mmr_index)?;
+		batch.commit()?;
+		change
+	};
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		match api.finalize_tx(m, &slate) {
+			Err(e) => match e.kind() {
+				ErrorKind::ContextOutputMissing(s) => {
+					assert!(s.contains(&format!("{:?}", change.key_id)));
+				}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Finalizing should fail with a missing change output"),
+		}
+		Ok(())
+	})?;
+
+	// with the change back, an input spent in the meantime is refused too
+	let spent = {
+		wallet_inst!(wallet1, w);
+		let context = w.get_private_context(mask1, slate.id.as_bytes())?;
+		let (id, mmr_index, _) = context.get_inputs()[0].clone();
+		let mut batch = w.batch(mask1)?;
+		batch.save(change.clone())?;
+		let mut input = batch.get(&id, &mmr_index)?;
+		input.status = OutputStatus::Spent;
+		batch.save(input)?;
+		batch.commit()?;
+		id
+	};
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		match api.finalize_tx(m, &slate) {
+			Err(e) => match e.kind() {
+				ErrorKind::ContextOutputMismatch(s) => {
+					assert!(s.contains(&format!("{:?}", spent)));
+				}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Finalizing should fail with a spent input"),
+		}
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn finalize_context() {
+	let test_dir = "test_output/finalize_context";
+	setup(test_dir);
+	if let Err(e) = finalize_context_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
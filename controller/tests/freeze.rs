@@ -0,0 +1,126 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test frozen outputs are kept out of coin selection
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{InitTxArgs, OutputStatus, SelectionStrategy};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn freeze_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 8, false);
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		let (_, outputs) = api.retrieve_outputs(m, false, false, None)?;
+		let frozen = outputs
+			.iter()
+			.find(|o| {
+				o.output.status == OutputStatus::Unspent
+					&& o.output.lock_height <= info.last_confirmed_height
+			})
+			.unwrap()
+			.clone();
+		let commit = frozen.output.commit.clone().unwrap();
+		api.set_output_frozen(m, &commit, true)?;
+
+		// frozen amounts are reported separately from spendable amounts
+		let (_, frozen_info) = api.retrieve_summary_info(m, true, 1)?;
+		assert_eq!(frozen_info.amount_frozen, frozen.output.value);
+		assert_eq!(
+			frozen_info.amount_currently_spendable,
+			info.amount_currently_spendable - frozen.output.value
+		);
+		assert_eq!(frozen_info.total, info.total);
+
+		// even spending everything leaves the frozen output alone
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: core::consensus::GRIN_BASE,
+			minimum_confirmations: 1,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy: Some(SelectionStrategy::UseAll),
+			estimate_only: Some(true),
+			..Default::default()
+		};
+		let slate = api.init_send_tx(m, args)?;
+		assert_eq!(slate.amount, frozen_info.amount_currently_spendable);
+
+		// and the output is selectable again once unfrozen
+		api.set_output_frozen(m, &commit, false)?;
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		assert_eq!(info.amount_frozen, 0);
+		assert_eq!(
+			info.amount_currently_spendable,
+			frozen_info.amount_currently_spendable + frozen.output.value
+		);
+
+		// unknown commits are an error
+		assert!(api.set_output_frozen(m, "00", true).is_err());
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn freeze() {
+	let test_dir = "test_output/freeze";
+	setup(test_dir);
+	if let Err(e) = freeze_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
@@ -0,0 +1,150 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test confirming sent transactions by their kernel
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{InitTxArgs, IssueTokenArgs, OutputStatus, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn kernel_confirmation_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask1 = (&mask1_i).as_ref();
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let _mask2 = (&mask2_i).as_ref();
+
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 4, false);
+
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = IssueTokenArgs {
+			acct_name: None,
+			amount: 1000,
+			..Default::default()
+		};
+		slate = api.init_issue_token_tx(m, args)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+	let token_type = slate.token_type.clone();
+
+	// sending the whole token output leaves no token change to watch, only
+	// vcash change the token tx log doesn't know of
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 1000,
+			token_type: token_type.clone(),
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			..Default::default()
+		};
+		let slate_i = api.init_send_tx(m, args)?;
+		slate = client1.send_tx_slate_direct("wallet2", &slate_i)?;
+		api.tx_lock_outputs(m, &slate)?;
+		slate = api.finalize_tx(m, &slate)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+
+	let mut tx_id = 0;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, txs) = api.retrieve_token_txs(m, false, None, Some(slate.id), None)?;
+		assert!(!txs[0].confirmed);
+		assert_eq!(txs[0].token_amount_credited, 0);
+		assert!(txs[0].amount_credited > 0);
+		tx_id = txs[0].id;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 2, false);
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (refreshed, txs) = api.retrieve_token_txs(m, true, None, Some(slate.id), None)?;
+		assert!(refreshed);
+		assert!(txs[0].confirmed);
+		assert!(txs[0].confirmation_ts.is_some());
+
+		// the inputs of both assets are spent and the vcash change confirmed
+		let (_, outputs) = api.retrieve_outputs(m, true, false, Some(tx_id))?;
+		let change: Vec<_> = outputs
+			.iter()
+			.filter(|o| o.output.status != OutputStatus::Spent)
+			.collect();
+		assert_eq!(change.len(), 1);
+		assert_eq!(change[0].output.status, OutputStatus::Unspent);
+		assert!(change[0].output.height > 0);
+		assert!(outputs.len() > 1);
+		let (_, token_outputs) = api.retrieve_token_outputs(m, true, false, Some(tx_id))?;
+		assert!(!token_outputs.is_empty());
+		for o in token_outputs {
+			assert_eq!(o.output.status, OutputStatus::Spent);
+		}
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn kernel_confirmation() {
+	let test_dir = "test_output/kernel_confirmation";
+	setup(test_dir);
+	if let Err(e) = kernel_confirmation_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
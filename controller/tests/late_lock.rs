@@ -0,0 +1,156 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test late locking, where inputs aren't selected until finalization
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, InitTxArgs, SelectionStrategy, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn late_lock_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask2 = (&mask2_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let base = core::consensus::GRIN_BASE;
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		// the amount of a late locked send must be known up front
+		let args = InitTxArgs {
+			src_acct_name: None,
+			minimum_confirmations: 2,
+			send_all: true,
+			late_lock: true,
+			..Default::default()
+		};
+		match api.init_send_tx(m, args) {
+			Err(e) => match e.kind() {
+				ErrorKind::LateLock(_) => {}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Late locking with send_all should fail"),
+		}
+
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 2 * base,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			late_lock: true,
+			..Default::default()
+		};
+		slate = api.init_send_tx(m, args)?;
+		assert!(slate.fee > 0);
+		Ok(())
+	})?;
+
+	slate = client1.send_tx_slate_direct("wallet2", &slate)?;
+
+	// nothing is locked, nor logged, until the slate is finalized
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.tx_lock_outputs(m, &slate)?;
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		assert_eq!(info.amount_locked, 0);
+		let (_, txs) = api.retrieve_txs(m, true, None, Some(slate.id), None)?;
+		assert!(txs.is_empty());
+
+		slate = api.finalize_tx(m, &slate)?;
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		assert!(info.amount_locked > 0);
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 2, false);
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, txs) = api.retrieve_txs(m, true, None, Some(slate.id), None)?;
+		let tx = txs[0].clone();
+		assert!(tx.confirmed);
+		assert_eq!(tx.fee, Some(slate.fee));
+		assert_eq!(tx.amount_debited - tx.amount_credited, 2 * base + slate.fee);
+		assert_eq!(tx.selection_strategy, Some(SelectionStrategy::UseAll));
+		Ok(())
+	})?;
+
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		assert_eq!(info.amount_currently_spendable, 2 * base);
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn late_lock() {
+	let test_dir = "test_output/late_lock";
+	setup(test_dir);
+	if let Err(e) = late_lock_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
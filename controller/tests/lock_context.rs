@@ -0,0 +1,128 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test locking a transaction whose context references a missing output
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, InitTxArgs, OutputStatus, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn lock_context_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 5, false);
+
+	let mut num_txs = 0;
+	let mut num_outputs = 0;
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		num_txs = api.retrieve_txs(m, true, None, None, None)?.1.len();
+		num_outputs = api.retrieve_outputs(m, true, false, None)?.1.len();
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: core::consensus::GRIN_BASE,
+			minimum_confirmations: 1,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			..Default::default()
+		};
+		slate = api.init_send_tx(m, args)?;
+		Ok(())
+	})?;
+
+	// remove one of the inputs from under the transaction, as a concurrent
+	// check_repair or cancel might
+	let missing = {
+		wallet_inst!(wallet1, w);
+		let context = w.get_private_context(mask1, slate.id.as_bytes())?;
+		let (id, mmr_index, _) = context.get_inputs()[0].clone();
+		let mut batch = w.batch(mask1)?;
+		batch.delete(&id, &mmr_index)?;
+		batch.commit()?;
+		id
+	};
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		match api.tx_lock_outputs(m, &slate) {
+			Err(e) => match e.kind() {
+				ErrorKind::ContextOutputMissing(s) => {
+					assert!(s.contains(&format!("{:?}", missing)));
+				}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Locking should fail with a missing input"),
+		}
+
+		// nothing from the failed lock was persisted
+		let (_, txs) = api.retrieve_txs(m, false, None, None, None)?;
+		assert_eq!(txs.len(), num_txs);
+		let (_, outputs) = api.retrieve_outputs(m, true, false, None)?;
+		assert_eq!(outputs.len(), num_outputs - 1);
+		assert!(outputs
+			.iter()
+			.all(|o| o.output.status != OutputStatus::Locked
+				&& o.output.status != OutputStatus::Unconfirmed));
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn lock_context() {
+	let test_dir = "test_output/lock_context";
+	setup(test_dir);
+	if let Err(e) = lock_context_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
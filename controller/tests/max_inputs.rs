@@ -0,0 +1,165 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test a hard cap on the number of inputs isn't extended to cover the amount
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, InitTxArgs, OutputStatus, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn max_inputs_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask2 = (&mask2_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let base = core::consensus::GRIN_BASE;
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	// wallet 1 pays wallet 2 six small amounts
+	let mut slate = Slate::blank(2, false);
+	for _ in 0..6 {
+		wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+			let args = InitTxArgs {
+				src_acct_name: None,
+				amount: base,
+				minimum_confirmations: 2,
+				max_outputs: 500,
+				num_change_outputs: 1,
+				..Default::default()
+			};
+			slate = api.init_send_tx(m, args)?;
+			slate = client1.send_tx_slate_direct("wallet2", &slate)?;
+			api.tx_lock_outputs(m, &slate)?;
+			slate = api.finalize_tx(m, &slate)?;
+			api.post_tx(m, &slate, false)?;
+			Ok(())
+		})?;
+		let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 2, false);
+	}
+
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		assert_eq!(info.amount_currently_spendable, 6 * base);
+
+		// the soft limit of two outputs is extended to cover the amount, past the
+		// hard cap of three
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 4 * base,
+			minimum_confirmations: 1,
+			max_outputs: 2,
+			max_inputs_hard: Some(3),
+			num_change_outputs: 1,
+			..Default::default()
+		};
+		match api.init_send_tx(m, args) {
+			Err(e) => match e.kind() {
+				ErrorKind::TooManyInputs { selected, max } => {
+					assert!(selected > 3);
+					assert_eq!(max, 3);
+				}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Send over the hard cap on inputs should fail"),
+		}
+
+		// nothing was built, so no outputs are locked and no tx is logged
+		let (_, outputs) = api.retrieve_outputs(m, false, false, None)?;
+		assert_eq!(outputs.len(), 6);
+		assert!(outputs
+			.iter()
+			.all(|o| o.output.status == OutputStatus::Unspent));
+		let (_, txs) = api.retrieve_txs(m, false, None, None, None)?;
+		assert!(txs.iter().all(|t| t.num_inputs == 0));
+
+		// a cap the selection fits within doesn't get in the way
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 4 * base,
+			minimum_confirmations: 1,
+			max_outputs: 2,
+			max_inputs_hard: Some(6),
+			num_change_outputs: 1,
+			..Default::default()
+		};
+		slate = api.init_send_tx(m, args)?;
+		let (_, txs) = api.retrieve_txs(m, false, None, Some(slate.id), None)?;
+		assert!(txs[0].num_inputs > 3);
+		assert!(txs[0].num_inputs <= 6);
+		api.cancel_tx(m, None, Some(slate.id))?;
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn max_inputs() {
+	let test_dir = "test_output/max_inputs";
+	setup(test_dir);
+	if let Err(e) = max_inputs_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
@@ -0,0 +1,220 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test a send drawing its inputs from several accounts
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, InitTxArgs, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn multi_account_send_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask1 = (&mask1_i).as_ref();
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask2 = (&mask2_i).as_ref();
+
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	// mine into a savings account, then into the default one
+	let mut savings_path = None;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		savings_path = Some(api.create_account_path(m, "savings")?);
+		api.set_active_account(m, "savings")?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 5, false);
+	let mut default_path = None;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.set_active_account(m, "default")?;
+		default_path = Some(api.accounts(m)?[0].path.clone());
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 8, false);
+
+	let spendable = |acct: &str| -> Result<(u64, u64), libwallet::Error> {
+		let mut res = (0, 0);
+		wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+			api.set_active_account(m, acct)?;
+			let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+			res = (info.amount_currently_spendable, info.amount_locked);
+			api.set_active_account(m, "default")?;
+			Ok(())
+		})?;
+		Ok(res)
+	};
+	let (default_before, _) = spendable("default")?;
+	let (savings_before, _) = spendable("savings")?;
+	assert!(savings_before > 0);
+	let amount = default_before + savings_before / 2;
+
+	let args = InitTxArgs {
+		src_acct_name: None,
+		amount,
+		minimum_confirmations: 1,
+		max_outputs: 500,
+		num_change_outputs: 1,
+		selection_strategy_is_use_all: true,
+		..Default::default()
+	};
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		// the default account alone can't cover the amount
+		match api.init_send_tx(m, args.clone()) {
+			Err(e) => match e.kind() {
+				ErrorKind::NotEnoughFunds { .. } => {}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Send should fail without the savings account"),
+		}
+		// and further accounts must exist
+		let unknown = InitTxArgs {
+			extra_src_acct_names: Some(vec!["checking".to_owned()]),
+			..args.clone()
+		};
+		match api.init_send_tx(m, unknown) {
+			Err(e) => match e.kind() {
+				ErrorKind::UnknownAccountLabel(l) => assert_eq!(l, "checking"),
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Send should fail with an unknown account"),
+		}
+		// nor can a late locked send draw on them
+		let late_lock = InitTxArgs {
+			extra_src_acct_names: Some(vec!["savings".to_owned()]),
+			late_lock: true,
+			..args.clone()
+		};
+		match api.init_send_tx(m, late_lock) {
+			Err(e) => match e.kind() {
+				ErrorKind::LateLock(_) => {}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Late locked send should fail with further accounts"),
+		}
+		Ok(())
+	})?;
+
+	// drawing on the savings account as well covers it
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			extra_src_acct_names: Some(vec!["savings".to_owned()]),
+			..args.clone()
+		};
+		slate = api.init_send_tx(m, args)?;
+		Ok(())
+	})?;
+	{
+		// inputs come from both accounts, change goes to the sending one
+		wallet_inst!(wallet1, w);
+		let context = w.get_private_context(mask1, slate.id.as_bytes())?;
+		let input_parents: Vec<_> = context
+			.get_inputs()
+			.iter()
+			.map(|(id, _, _)| Some(id.parent_path()))
+			.collect();
+		assert!(input_parents.contains(&default_path));
+		assert!(input_parents.contains(&savings_path));
+		let outputs = context.get_outputs();
+		assert_eq!(outputs.len(), 1);
+		assert_eq!(Some(outputs[0].0.parent_path()), default_path);
+	}
+
+	slate = client1.send_tx_slate_direct("wallet2", &slate)?;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.tx_lock_outputs(m, &slate)?;
+		// the transaction is logged once, under the sending account
+		let (_, txs) = api.retrieve_txs(m, false, None, Some(slate.id), None)?;
+		assert_eq!(txs.len(), 1);
+		assert_eq!(Some(txs[0].parent_key_id.clone()), default_path);
+		assert!(txs[0].amount_debited > default_before);
+		api.set_active_account(m, "savings")?;
+		let (_, txs) = api.retrieve_txs(m, false, None, Some(slate.id), None)?;
+		assert!(txs.is_empty());
+		api.set_active_account(m, "default")?;
+		Ok(())
+	})?;
+
+	// both accounts have their inputs locked
+	let (default_spendable, default_locked) = spendable("default")?;
+	let (savings_spendable, savings_locked) = spendable("savings")?;
+	assert_eq!(default_spendable, 0);
+	assert_eq!(default_locked, default_before);
+	assert!(savings_locked > 0);
+	assert_eq!(savings_spendable + savings_locked, savings_before);
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		slate = api.finalize_tx(m, &slate)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		assert_eq!(info.amount_currently_spendable, amount);
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn multi_account_send() {
+	let test_dir = "test_output/multi_account_send";
+	setup(test_dir);
+	if let Err(e) = multi_account_send_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
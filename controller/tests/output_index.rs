@@ -0,0 +1,315 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test the backend's output indexes agree with scanning every output, and
+//! time selection against them on a large wallet
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+use grin_wallet_util::grin_keychain as keychain;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use keychain::{ExtKeychain, Identifier, Keychain};
+use libwallet::{
+	InitTxArgs, IssueTokenArgs, NodeClient, OutputData, OutputStatus, SelectionParams,
+	SelectionStrategy, SendParams, WalletBackend,
+};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+const STATUSES: [OutputStatus; 5] = [
+	OutputStatus::Unconfirmed,
+	OutputStatus::Unspent,
+	OutputStatus::Locked,
+	OutputStatus::Spent,
+	OutputStatus::Reverted,
+];
+
+/// Checks the indexed queries return exactly the outputs a scan finds, for
+/// every status and token type
+fn assert_indexed<'a, T: ?Sized, C, K>(w: &T, parent_key_id: &Identifier)
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	for status in STATUSES.iter() {
+		let mut indexed: Vec<_> = w
+			.outputs_for_parent(parent_key_id, &[status.clone()])
+			.map(|o| (o.key_id, o.mmr_index))
+			.collect();
+		let mut scanned: Vec<_> = w
+			.iter()
+			.filter(|o| o.root_key_id == *parent_key_id && o.status == *status)
+			.map(|o| (o.key_id, o.mmr_index))
+			.collect();
+		indexed.sort();
+		scanned.sort();
+		assert_eq!(indexed, scanned);
+
+		let mut token_types: Vec<String> = w.token_iter().map(|o| o.token_type).collect();
+		token_types.sort();
+		token_types.dedup();
+		for token_type in token_types.iter() {
+			let mut indexed: Vec<_> = w
+				.token_outputs_for(parent_key_id, token_type, &[status.clone()])
+				.map(|o| (o.key_id, o.mmr_index))
+				.collect();
+			let mut scanned: Vec<_> = w
+				.token_iter()
+				.filter(|o| {
+					o.root_key_id == *parent_key_id
+						&& o.token_type == *token_type
+						&& o.status == *status
+				})
+				.map(|o| (o.key_id, o.mmr_index))
+				.collect();
+			indexed.sort();
+			scanned.sort();
+			assert_eq!(indexed, scanned);
+		}
+	}
+}
+
+fn output_index_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let base = core::consensus::GRIN_BASE;
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+	{
+		wallet_inst!(wallet1, w);
+		let parent_key_id = w.parent_key_id();
+		assert_indexed(&**w, &parent_key_id);
+	}
+
+	// token outputs are indexed by their type
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = IssueTokenArgs {
+			acct_name: None,
+			amount: 1000,
+			..Default::default()
+		};
+		let slate = api.init_issue_token_tx(m, args)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+	{
+		wallet_inst!(wallet1, w);
+		let parent_key_id = w.parent_key_id();
+		assert_indexed(&**w, &parent_key_id);
+	}
+
+	// locking moves the inputs to the locked index, and cancelling moves them
+	// back while the change is dropped from it
+	let mut slate_id = None;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 2 * base,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			..Default::default()
+		};
+		let slate = api.init_send_tx(m, args)?;
+		api.tx_lock_outputs(m, &slate)?;
+		slate_id = Some(slate.id);
+		Ok(())
+	})?;
+	{
+		wallet_inst!(wallet1, w);
+		let parent_key_id = w.parent_key_id();
+		assert_indexed(&**w, &parent_key_id);
+		let locked = w
+			.outputs_for_parent(&parent_key_id, &[OutputStatus::Locked])
+			.count();
+		assert!(locked > 0);
+	}
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.cancel_tx(m, None, slate_id)?;
+		Ok(())
+	})?;
+	{
+		wallet_inst!(wallet1, w);
+		let parent_key_id = w.parent_key_id();
+		assert_indexed(&**w, &parent_key_id);
+		let locked = w
+			.outputs_for_parent(&parent_key_id, &[OutputStatus::Locked])
+			.count();
+		assert_eq!(locked, 0);
+	}
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+/// Compares scanning every output of a 100k-output wallet, as selection used to,
+/// with the indexed query, and times a whole selection on top of the index
+fn output_index_bench_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let num_outputs = 100_000;
+	let runs = 10;
+	wallet_inst!(wallet1, w);
+	let parent_key_id = w.parent_key_id();
+
+	// a long-lived wallet, with one output in a hundred left unspent
+	for chunk in 0..num_outputs / 10_000 {
+		let mut batch = w.batch(mask1)?;
+		for i in chunk * 10_000..(chunk + 1) * 10_000 {
+			let status = match i % 100 {
+				0 => OutputStatus::Unspent,
+				_ => OutputStatus::Spent,
+			};
+			batch.save(OutputData {
+				root_key_id: parent_key_id.clone(),
+				key_id: ExtKeychain::derive_key_id(3, 0, 0, i, 0),
+				n_child: i,
+				commit: None,
+				mmr_index: None,
+				value: 1_000_000,
+				status,
+				height: 1,
+				lock_height: 0,
+				is_coinbase: false,
+				is_frozen: false,
+				tx_log_entry: None,
+			})?;
+		}
+		batch.commit()?;
+	}
+
+	let start = Instant::now();
+	for _ in 0..runs {
+		let count = w
+			.iter()
+			.filter(|o| o.root_key_id == parent_key_id && o.status == OutputStatus::Unspent)
+			.count();
+		assert_eq!(count, num_outputs as usize / 100);
+	}
+	let scan = start.elapsed() / runs;
+
+	let start = Instant::now();
+	for _ in 0..runs {
+		let count = w
+			.outputs_for_parent(&parent_key_id, &[OutputStatus::Unspent])
+			.count();
+		assert_eq!(count, num_outputs as usize / 100);
+	}
+	let indexed = start.elapsed() / runs;
+
+	let params = SendParams {
+		minimum_confirmations: 1,
+		selection: SelectionParams {
+			max_outputs: 500,
+			strict_max_outputs: false,
+			selection_strategy: SelectionStrategy::SmallestFirst,
+		},
+		..Default::default()
+	};
+	let start = Instant::now();
+	for _ in 0..runs {
+		libwallet::estimate_send(&mut **w, 50_000_000, 10, &params, &parent_key_id)?;
+	}
+	let selection = start.elapsed() / runs;
+
+	println!(
+		"{} outputs: full scan {:?}, indexed query {:?}, selection {:?}",
+		num_outputs, scan, indexed, selection
+	);
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn output_index() {
+	let test_dir = "test_output/output_index";
+	setup(test_dir);
+	if let Err(e) = output_index_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
+
+/// Run with `cargo test --release -p grin_wallet_controller --test output_index
+/// -- --ignored --nocapture`
+#[ignore]
+#[test]
+fn output_index_bench() {
+	let test_dir = "test_output/output_index_bench";
+	setup(test_dir);
+	if let Err(e) = output_index_bench_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
@@ -20,7 +20,7 @@ extern crate grin_wallet_util;
 
 use grin_wallet_libwallet as libwallet;
 use impls::test_framework::{self, LocalWalletClient};
-use libwallet::{InitTxArgs, Slate};
+use libwallet::{InitTxArgs, IssueInvoiceTxArgs, IssueTokenArgs, Slate};
 use std::sync::atomic::Ordering;
 use std::thread;
 use std::time::Duration;
@@ -108,6 +108,13 @@ fn payment_proofs_test_impl(test_dir: &'static str) -> Result<(), libwallet::Err
 			slate_i.payment_proof.as_ref().unwrap().sender_address
 		);
 
+		// the proof address is reserved before anything is locked
+		{
+			wallet_inst!(wallet1, w);
+			let context = w.get_private_context(m, slate_i.id.as_bytes())?;
+			assert_eq!(context.payment_proof_derivation_index, Some(0));
+		}
+
 		// Check we are creating a tx with kernel features 0
 		// We will check this produces a Plain kernel later.
 		assert_eq!(0, slate.kernel_features);
@@ -116,7 +123,7 @@ fn payment_proofs_test_impl(test_dir: &'static str) -> Result<(), libwallet::Err
 		sender_api.tx_lock_outputs(m, &slate)?;
 
 		// Ensure what's stored in TX log for payment proof is correct
-		let (_, txs) = sender_api.retrieve_txs(m, true, None, Some(slate.id))?;
+		let (_, txs) = sender_api.retrieve_txs(m, true, None, Some(slate.id), None)?;
 		assert!(txs[0].payment_proof.is_some());
 		let pp = txs[0].clone().payment_proof.unwrap();
 		assert_eq!(
@@ -155,6 +162,125 @@ fn payment_proofs_test_impl(test_dir: &'static str) -> Result<(), libwallet::Err
 		Ok(())
 	})?;
 
+	// An invoice swaps the roles, the payer signs as the sender while paying
+	// and the invoicer completes the proof when finalizing
+	let mut payer_address = None;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		payer_address = Some(api.get_slatepack_address(m, 0)?);
+		Ok(())
+	})?;
+
+	let mut slate = Slate::blank(2, true);
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let args = IssueInvoiceTxArgs {
+			amount,
+			payment_proof_sender_address: payer_address.clone(),
+			..Default::default()
+		};
+		slate = api.issue_invoice_tx(m, args)?;
+		assert_eq!(
+			slate.payment_proof.as_ref().unwrap().sender_address,
+			payer_address.as_ref().unwrap().pub_key,
+		);
+		Ok(())
+	})?;
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: slate.amount,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy_is_use_all: true,
+			..Default::default()
+		};
+		slate = api.process_invoice_tx(m, &slate, args)?;
+		assert!(slate
+			.payment_proof
+			.as_ref()
+			.unwrap()
+			.sender_signature
+			.is_some());
+		api.tx_lock_outputs(m, &slate)?;
+		Ok(())
+	})?;
+
+	wallet::controller::foreign_single_use(wallet2.clone(), mask2_i.clone(), |api| {
+		slate = api.finalize_tx(&slate, false)?;
+		Ok(())
+	})?;
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 2, false);
+
+	let mut pp = None;
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let proof = api.retrieve_payment_proof(m, true, None, Some(slate.id))?;
+		assert_eq!(proof.amount, amount);
+		let res = api.verify_payment_proof(m, &proof)?;
+		assert_eq!(res, (false, true));
+		pp = Some(proof);
+		Ok(())
+	})?;
+
+	// the payer can verify the proof handed over by the invoicer
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let res = api.verify_payment_proof(m, pp.as_ref().unwrap())?;
+		assert_eq!(res, (true, false));
+		Ok(())
+	})?;
+
+	// A token proof commits to the token type and the token kernel
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = IssueTokenArgs {
+			acct_name: None,
+			amount: 1000,
+			..Default::default()
+		};
+		slate = api.init_issue_token_tx(m, args)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+	let token_type = slate.token_type.clone();
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |sender_api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 600,
+			token_type: token_type.clone(),
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			payment_proof_recipient_address: address.clone(),
+			..Default::default()
+		};
+		let slate_i = sender_api.init_send_tx(m, args)?;
+		slate = client1.send_tx_slate_direct("wallet2", &slate_i)?;
+		sender_api.tx_lock_outputs(m, &slate)?;
+		slate = sender_api.finalize_tx(m, &slate)?;
+		sender_api.post_tx(m, &slate, true)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 2, false);
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |sender_api, m| {
+		let mut pp = sender_api.retrieve_payment_proof(m, true, None, Some(slate.id))?;
+		assert_eq!(pp.token_type, token_type);
+		assert_eq!(pp.amount, 600);
+		let res = sender_api.verify_payment_proof(m, &pp)?;
+		assert_eq!(res, (true, false));
+
+		// the same proof doesn't pass for a base coin send of that amount
+		pp.token_type = None;
+		assert!(sender_api.verify_payment_proof(m, &pp).is_err());
+		Ok(())
+	})?;
+
 	// let logging finish
 	stopper.store(false, Ordering::Relaxed);
 	thread::sleep(Duration::from_millis(200));
@@ -0,0 +1,148 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test the receiver can record a known excess with its transaction
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+use grin_wallet_util::grin_util::secp::pedersen;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{InitTxArgs, NodeClient};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn recipient_excess_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask1 = (&mask1_i).as_ref();
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask2 = (&mask2_i).as_ref();
+
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 5, false);
+
+	let mut slates = vec![];
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		for _ in 0..2 {
+			let args = InitTxArgs {
+				src_acct_name: None,
+				amount: core::consensus::GRIN_BASE,
+				minimum_confirmations: 1,
+				max_outputs: 500,
+				num_change_outputs: 1,
+				..Default::default()
+			};
+			slates.push(api.init_send_tx(m, args)?);
+		}
+		Ok(())
+	})?;
+
+	let excess = pedersen::Commitment::from_vec(vec![9u8; 33]);
+	let mut slate_override = slates[0].clone();
+	let mut slate_plain = slates[1].clone();
+	{
+		wallet_inst!(wallet2, w);
+		let height = w.w2n_client().get_chain_tip()?.0;
+		let parent_key_id = w.parent_key_id();
+
+		// the given excess is recorded, even though the slate's can't be when invoicing
+		let (_, _, tx, token_tx) = libwallet::build_recipient_output(
+			&mut **w,
+			mask2,
+			&mut slate_override,
+			height,
+			parent_key_id.clone(),
+			true,
+			Some(excess.clone()),
+			None,
+			true,
+		)?;
+		assert!(token_tx.is_none());
+		assert_eq!(tx.unwrap().kernel_excess, Some(excess.clone()));
+
+		// without one, the excess is taken from the slate
+		let (_, _, tx, _) = libwallet::build_recipient_output(
+			&mut **w,
+			mask2,
+			&mut slate_plain,
+			height,
+			parent_key_id,
+			false,
+			None,
+			None,
+			true,
+		)?;
+		let tx = tx.unwrap();
+		assert!(tx.kernel_excess.is_some());
+		assert_ne!(tx.kernel_excess, Some(excess.clone()));
+	}
+
+	// the override is what's stored with the tx log entry
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let (_, txs) = api.retrieve_txs(m, false, None, Some(slate_override.id), None)?;
+		assert_eq!(txs.len(), 1);
+		assert_eq!(txs[0].kernel_excess, Some(excess.clone()));
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn recipient_excess() {
+	let test_dir = "test_output/recipient_excess";
+	setup(test_dir);
+	if let Err(e) = recipient_excess_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
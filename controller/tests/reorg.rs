@@ -0,0 +1,187 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test rolling back what blocks orphaned by a reorg confirmed
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{InitTxArgs, TxLogEntryType};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn reorg_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask1 = (&mask1_i).as_ref();
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask2 = (&mask2_i).as_ref();
+	// mines the fork
+	create_wallet_and_add!(
+		client3,
+		wallet3,
+		mask3_i,
+		test_dir,
+		"wallet3",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask3 = (&mask3_i).as_ref();
+
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let reward = core::consensus::REWARD;
+	let amount = core::consensus::GRIN_BASE;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 5, false);
+
+	let mut tx = None;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount,
+			minimum_confirmations: 1,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			..Default::default()
+		};
+		let slate_i = api.init_send_tx(m, args)?;
+		let slate = client1.send_tx_slate_direct("wallet2", &slate_i)?;
+		api.tx_lock_outputs(m, &slate)?;
+		let slate = api.finalize_tx(m, &slate)?;
+		tx = Some(slate.tx_or_err()?.clone());
+		Ok(())
+	})?;
+	let tx = tx.unwrap();
+	let fee = tx.fee();
+
+	// the send is confirmed at height 6, the chain goes on to 7
+	test_framework::award_block_to_wallet(&chain, vec![&tx], wallet1.clone(), mask1)?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 1, false);
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (refreshed, info) = api.retrieve_summary_info(m, true, 1)?;
+		assert!(refreshed);
+		assert_eq!(info.last_confirmed_height, 7);
+		assert_eq!(info.total, 7 * reward - amount);
+		Ok(())
+	})?;
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let (refreshed, info) = api.retrieve_summary_info(m, true, 1)?;
+		assert!(refreshed);
+		assert_eq!(info.amount_currently_spendable, amount);
+		Ok(())
+	})?;
+
+	// a longer fork from height 5 orphans blocks 6 and 7, the send makes it
+	// into the fork's second block
+	let mut prev = chain.get_header_by_height(5).unwrap();
+	for txs in vec![vec![], vec![&tx], vec![]] {
+		let mut block =
+			test_framework::create_block_for_wallet(&chain, prev, txs, wallet3.clone(), mask3)?;
+		test_framework::process_block(&chain, &mut block);
+		prev = block.header;
+	}
+	assert_eq!(chain.head_header().unwrap(), prev);
+
+	// the coinbases of the orphaned blocks are gone, the send is confirmed
+	// again at its new height
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (refreshed, info) = api.retrieve_summary_info(m, true, 1)?;
+		assert!(refreshed);
+		assert_eq!(info.last_confirmed_height, 8);
+		assert_eq!(info.total, 5 * reward - amount - fee);
+		assert_eq!(info.amount_locked, 0);
+
+		let (_, txs) = api.retrieve_txs(m, false, None, None, None)?;
+		let sent = txs
+			.iter()
+			.find(|t| t.tx_type == TxLogEntryType::TxSent)
+			.unwrap();
+		assert!(sent.confirmed);
+		let orphaned = txs
+			.iter()
+			.filter(|t| t.tx_type == TxLogEntryType::ConfirmedCoinbase && !t.confirmed)
+			.count();
+		assert_eq!(orphaned, 2);
+
+		let (_, outputs) = api.retrieve_outputs(m, false, false, Some(sent.id))?;
+		let change = outputs
+			.iter()
+			.find(|o| o.output.status == libwallet::OutputStatus::Unspent)
+			.unwrap();
+		assert_eq!(change.output.height, 7);
+		Ok(())
+	})?;
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let (refreshed, info) = api.retrieve_summary_info(m, true, 1)?;
+		assert!(refreshed);
+		assert_eq!(info.total, amount);
+		assert_eq!(info.amount_currently_spendable, amount);
+		assert_eq!(info.amount_reverted, 0);
+		let (_, txs) = api.retrieve_txs(m, false, None, None, None)?;
+		assert_eq!(txs.len(), 1);
+		assert!(txs[0].confirmed);
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn reorg() {
+	let test_dir = "test_output/reorg";
+	setup(test_dir);
+	if let Err(e) = reorg_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
@@ -0,0 +1,238 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test the inputs of sends not locked yet are kept out of other sends
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, InitTxArgs, NodeClient, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn reserved_inputs_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	let args = InitTxArgs {
+		src_acct_name: None,
+		amount: core::consensus::GRIN_BASE,
+		minimum_confirmations: 2,
+		max_outputs: 500,
+		num_change_outputs: 1,
+		..Default::default()
+	};
+
+	// two sends started before either is locked
+	let mut slate1 = Slate::blank(2, false);
+	let mut slate2 = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		slate1 = api.init_send_tx(m, args.clone())?;
+		slate2 = api.init_send_tx(m, args.clone())?;
+		Ok(())
+	})?;
+	let inputs1 = {
+		wallet_inst!(wallet1, w);
+		let context1 = w.get_private_context(mask1, slate1.id.as_bytes())?;
+		let context2 = w.get_private_context(mask1, slate2.id.as_bytes())?;
+		let inputs1 = context1.get_inputs();
+		let inputs2 = context2.get_inputs();
+		assert!(!inputs1.is_empty());
+		assert!(!inputs2.is_empty());
+		assert!(inputs1.iter().all(|i| !inputs2.contains(i)));
+		assert!(w.get_reservation(&slate1.id)?.is_some());
+		assert!(w.get_reservation(&slate2.id)?.is_some());
+		inputs1
+	};
+
+	// nor can another send require an input that is reserved
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, outputs) = api.retrieve_outputs(m, false, false, None)?;
+		let reserved = outputs
+			.iter()
+			.find(|o| o.output.key_id == inputs1[0].0)
+			.unwrap();
+		let args = InitTxArgs {
+			required_inputs: Some(vec![reserved.output.commit.clone().unwrap()]),
+			..args.clone()
+		};
+		match api.init_send_tx(m, args) {
+			Err(e) => match e.kind() {
+				ErrorKind::RequiredInputUnavailable(_) => {}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Send should fail with a reserved input"),
+		}
+		Ok(())
+	})?;
+
+	// cancelling the first releases its inputs for the next send, and locking
+	// the second turns its reservation into a lock
+	let mut slate3 = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.cancel_tx(m, None, Some(slate1.id))?;
+		api.tx_lock_outputs(m, &slate2)?;
+		slate3 = api.init_send_tx(m, args.clone())?;
+		Ok(())
+	})?;
+	{
+		wallet_inst!(wallet1, w);
+		assert!(w.get_reservation(&slate1.id)?.is_none());
+		assert!(w.get_reservation(&slate2.id)?.is_none());
+		let context3 = w.get_private_context(mask1, slate3.id.as_bytes())?;
+		assert_eq!(context3.get_inputs(), inputs1);
+	}
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, txs) = api.retrieve_txs(m, false, None, Some(slate2.id), None)?;
+		assert_eq!(txs.len(), 1);
+		assert_eq!(txs[0].num_inputs, 1);
+		Ok(())
+	})?;
+
+	// a reservation lapses along with the TTL of its send
+	let mut slate4 = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.cancel_tx(m, None, Some(slate3.id))?;
+		let args = InitTxArgs {
+			ttl_blocks: Some(2),
+			..args.clone()
+		};
+		slate4 = api.init_send_tx(m, args)?;
+		Ok(())
+	})?;
+	{
+		wallet_inst!(wallet1, w);
+		let height = w.w2n_client().get_chain_tip()?.0;
+		assert_eq!(
+			libwallet::release_expired_reservations(&mut **w, mask1, height)?,
+			0
+		);
+		assert!(w.get_reservation(&slate4.id)?.is_some());
+	}
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (refreshed, _) = api.retrieve_summary_info(m, true, 1)?;
+		assert!(refreshed);
+		Ok(())
+	})?;
+	{
+		wallet_inst!(wallet1, w);
+		assert!(w.get_reservation(&slate4.id)?.is_none());
+	}
+
+	// a soft reserve keeps the inputs unlocked and out of other sends until it
+	// times out
+	let mut slate5 = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		slate5 = api.init_send_tx(m, args.clone())?;
+		api.tx_reserve_outputs(m, &slate5, 2)?;
+		Ok(())
+	})?;
+	let inputs5 = {
+		wallet_inst!(wallet1, w);
+		let height = w.w2n_client().get_chain_tip()?.0;
+		let reservation = w.get_reservation(&slate5.id)?.unwrap();
+		assert_eq!(reservation.expiry_height, height + 2);
+		w.get_private_context(mask1, slate5.id.as_bytes())?
+			.get_inputs()
+	};
+	let mut slate6 = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, txs) = api.retrieve_txs(m, false, None, Some(slate5.id), None)?;
+		assert!(txs.is_empty());
+		let (_, outputs) = api.retrieve_outputs(m, false, false, None)?;
+		for (id, _, _) in inputs5.iter() {
+			let o = outputs.iter().find(|o| &o.output.key_id == id).unwrap();
+			assert_eq!(o.output.status, libwallet::OutputStatus::Unspent);
+		}
+		// the summary counts them as reserved rather than spendable
+		let (_, info) = api.retrieve_summary_info(m, false, 1)?;
+		let reserved: u64 = inputs5.iter().map(|(_, _, v)| v).sum();
+		assert_eq!(info.amount_reserved, reserved);
+		slate6 = api.init_send_tx(m, args.clone())?;
+		Ok(())
+	})?;
+	{
+		wallet_inst!(wallet1, w);
+		let context6 = w.get_private_context(mask1, slate6.id.as_bytes())?;
+		assert!(context6.get_inputs().iter().all(|i| !inputs5.contains(i)));
+	}
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.cancel_tx(m, None, Some(slate6.id))?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+	{
+		wallet_inst!(wallet1, w);
+		let height = w.w2n_client().get_chain_tip()?.0;
+		assert_eq!(
+			libwallet::release_expired_reservations(&mut **w, mask1, height)?,
+			1
+		);
+		assert!(w.get_reservation(&slate5.id)?.is_none());
+	}
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, info) = api.retrieve_summary_info(m, false, 1)?;
+		assert_eq!(info.amount_reserved, 0);
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn reserved_inputs() {
+	let test_dir = "test_output/reserved_inputs";
+	setup(test_dir);
+	if let Err(e) = reserved_inputs_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
@@ -0,0 +1,265 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test cancelling a scan and resuming it, and the progress it reports
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+use grin_wallet_util::grin_util as util;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{api_impl, ErrorKind, InitTxArgs, IssueTokenArgs, ScanProgress, StatusMessage};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use util::ZeroingString;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+/// The last progress reported on the channel
+fn last_progress(rx: &Receiver<StatusMessage>) -> Option<ScanProgress> {
+	rx.try_iter()
+		.filter_map(|m| match m {
+			StatusMessage::ScanProgress(p) => Some(p),
+			_ => None,
+		})
+		.last()
+}
+
+fn scan_resume_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let seed_phrase = "affair pistol cancel crush garment candy ancient flag work \
+	                   market crush dry stand focus mutual weapon offer ceiling rival turn team spring \
+	                   where swift";
+	let seed_phrase = Some(ZeroingString::from(seed_phrase));
+
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		seed_phrase,
+		&mut wallet_proxy,
+		false
+	);
+	let mask1 = (&mask1_i).as_ref();
+	// restored from wallet 1's seed
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		seed_phrase,
+		&mut wallet_proxy,
+		false
+	);
+	let mask2 = (&mask2_i).as_ref();
+
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 5, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = IssueTokenArgs {
+			acct_name: None,
+			amount: 1000,
+			..Default::default()
+		};
+		let slate = api.init_issue_token_tx(m, args)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+
+	let mut num_outputs = 0;
+	let mut total = 0;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, outputs) = api.retrieve_outputs(m, false, true, None)?;
+		num_outputs = outputs.len();
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		total = info.total;
+		Ok(())
+	})?;
+
+	// a scan cancelled before its first batch stores that nothing was checked
+	let (tx, rx) = channel();
+	let cancel = AtomicBool::new(true);
+	match api_impl::owner::scan(
+		wallet2.clone(),
+		mask2,
+		None,
+		false,
+		Some(&cancel),
+		&Some(tx.clone()),
+	) {
+		Err(e) => match e.kind() {
+			ErrorKind::ScanCancelled(_) => {}
+			k => panic!("Unexpected error: {}", k),
+		},
+		Ok(_) => panic!("Cancelled scan completed"),
+	}
+	let progress = last_progress(&rx).unwrap();
+	assert!(progress.cancelled);
+	assert_eq!(progress.outputs_found, 0);
+	{
+		wallet_inst!(wallet2, w);
+		let checkpoint = w.scan_checkpoint()?.unwrap();
+		assert_eq!(checkpoint.start_height, 0);
+		assert!(checkpoint.last_pmmr_index.is_some());
+		assert!(checkpoint.last_token_pmmr_index.is_none());
+	}
+
+	// the next scan picks it up, finding every output and clearing the
+	// checkpoint
+	cancel.store(false, Ordering::Relaxed);
+	api_impl::owner::scan(
+		wallet2.clone(),
+		mask2,
+		None,
+		false,
+		Some(&cancel),
+		&Some(tx),
+	)?;
+	let progress = last_progress(&rx).unwrap();
+	assert!(!progress.cancelled);
+	assert!(progress.scanning_tokens);
+	assert_eq!(progress.outputs_found, num_outputs);
+	assert_eq!(progress.token_outputs_found, 1);
+	{
+		wallet_inst!(wallet2, w);
+		assert!(w.scan_checkpoint()?.is_none());
+	}
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		assert_eq!(info.total, total);
+		let (_, token_infos) = api.retrieve_token_summary_info(m, true, 1)?;
+		assert_eq!(token_infos.len(), 1);
+		assert_eq!(token_infos[0].amount_currently_spendable, 1000);
+		Ok(())
+	})?;
+
+	// locked outputs found before a scan is cancelled are unlocked all the same,
+	// the resumed scan only looking past them. With a chain this short the
+	// only cancel point past any outputs is before the token outputs
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: core::consensus::GRIN_BASE,
+			minimum_confirmations: 1,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			..Default::default()
+		};
+		let slate = api.init_send_tx(m, args)?;
+		api.tx_lock_outputs(m, &slate)?;
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		assert!(info.amount_locked > 0);
+		Ok(())
+	})?;
+	let token_cancel = Arc::new(AtomicBool::new(false));
+	let (tx, rx) = channel();
+	let listener_cancel = token_cancel.clone();
+	let listener = thread::spawn(move || {
+		// cancel once the outputs are checked, before the token outputs are
+		for m in rx.iter() {
+			if let StatusMessage::ScanProgress(p) = m {
+				if !p.scanning_tokens {
+					listener_cancel.store(true, Ordering::Relaxed);
+				}
+			}
+		}
+	});
+	assert!(api_impl::owner::scan(
+		wallet2.clone(),
+		mask2,
+		None,
+		true,
+		Some(&token_cancel),
+		&Some(tx),
+	)
+	.is_err());
+	listener.join().unwrap();
+	{
+		wallet_inst!(wallet2, w);
+		let checkpoint = w.scan_checkpoint()?.unwrap();
+		assert!(checkpoint.last_token_pmmr_index.is_some());
+	}
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		assert_eq!(info.amount_locked, 0);
+		assert_eq!(info.total, total);
+		Ok(())
+	})?;
+	token_cancel.store(false, Ordering::Relaxed);
+	api_impl::owner::scan(
+		wallet2.clone(),
+		mask2,
+		None,
+		true,
+		Some(&token_cancel),
+		&None,
+	)?;
+	{
+		wallet_inst!(wallet2, w);
+		assert!(w.scan_checkpoint()?.is_none());
+	}
+
+	// giving a start height rescans from there, dropping any checkpoint
+	cancel.store(true, Ordering::Relaxed);
+	assert!(
+		api_impl::owner::scan(wallet2.clone(), mask2, None, false, Some(&cancel), &None).is_err()
+	);
+	{
+		wallet_inst!(wallet2, w);
+		assert!(w.scan_checkpoint()?.is_some());
+	}
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		api.scan(m, Some(1), false)?;
+		Ok(())
+	})?;
+	{
+		wallet_inst!(wallet2, w);
+		assert!(w.scan_checkpoint()?.is_none());
+	}
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn scan_resume() {
+	let test_dir = "test_output/scan_resume";
+	setup(test_dir);
+	if let Err(e) = scan_resume_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
@@ -0,0 +1,394 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test coin selection strategies pick the expected inputs and fees
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, InitTxArgs, SelectionStrategy, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn selection_strategy_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask2 = (&mask2_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let base = core::consensus::GRIN_BASE;
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	// give wallet 2 a spread of output values to choose from
+	for amount in &[1, 2, 3, 10] {
+		wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+			let args = InitTxArgs {
+				src_acct_name: None,
+				amount: amount * base,
+				minimum_confirmations: 2,
+				max_outputs: 500,
+				num_change_outputs: 1,
+				selection_strategy: Some(SelectionStrategy::SmallestFirst),
+				..Default::default()
+			};
+			let mut slate = api.init_send_tx(m, args)?;
+			slate = client1.send_tx_slate_direct("wallet2", &slate)?;
+			api.tx_lock_outputs(m, &slate)?;
+			slate = api.finalize_tx(m, &slate)?;
+			api.post_tx(m, &slate, false)?;
+			Ok(())
+		})?;
+		let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 2, false);
+	}
+
+	// estimates should reflect the number of inputs each strategy selects
+	let strategies = vec![
+		(SelectionStrategy::SmallestFirst, 3, 6 * base),
+		(SelectionStrategy::LargestFirst, 1, 10 * base),
+		(SelectionStrategy::BiggestFewest, 1, 10 * base),
+		(SelectionStrategy::UseAll, 4, 16 * base),
+	];
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		for (strategy, num_inputs, total) in strategies {
+			let args = InitTxArgs {
+				src_acct_name: None,
+				amount: 5 * base,
+				minimum_confirmations: 1,
+				max_outputs: 500,
+				num_change_outputs: 1,
+				selection_strategy: Some(strategy),
+				estimate_only: Some(true),
+				..Default::default()
+			};
+			let slate = api.init_send_tx(m, args)?;
+			assert_eq!(slate.amount, total);
+			assert_eq!(
+				slate.fee,
+				core::libtx::tx_fee(num_inputs, 2, 1, 0, 0, 0, None)
+			);
+		}
+
+		// a raised base fee should be reflected in the estimate
+		let fee_base = core::libtx::DEFAULT_BASE_FEE * 2;
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 5 * base,
+			minimum_confirmations: 1,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy: Some(SelectionStrategy::LargestFirst),
+			fee_base: Some(fee_base),
+			estimate_only: Some(true),
+			..Default::default()
+		};
+		let slate = api.init_send_tx(m, args)?;
+		assert_eq!(
+			slate.fee,
+			core::libtx::tx_fee(1, 2, 1, 0, 0, 0, Some(fee_base))
+		);
+
+		// fees over the maximum are refused, fees at it are fine
+		let fee = core::libtx::tx_fee(1, 2, 1, 0, 0, 0, None);
+		for &(max_fee, ok) in &[(fee - 1, false), (fee, true)] {
+			let args = InitTxArgs {
+				src_acct_name: None,
+				amount: 5 * base,
+				minimum_confirmations: 1,
+				max_outputs: 500,
+				num_change_outputs: 1,
+				selection_strategy: Some(SelectionStrategy::LargestFirst),
+				max_fee: Some(max_fee),
+				estimate_only: Some(true),
+				..Default::default()
+			};
+			match api.init_send_tx(m, args) {
+				Ok(slate) => {
+					assert!(ok);
+					assert_eq!(slate.fee, fee);
+				}
+				Err(e) => match e.kind() {
+					ErrorKind::FeeExceedsMaximum { fee: f, max } => {
+						assert!(!ok);
+						assert_eq!(f, fee);
+						assert_eq!(max, max_fee);
+					}
+					k => panic!("Unexpected error: {}", k),
+				},
+			}
+		}
+
+		// change below the dust threshold should be added to the fee
+		let amount = 10 * base - core::libtx::tx_fee(1, 2, 1, 0, 0, 0, None) - 5;
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount,
+			minimum_confirmations: 1,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy: Some(SelectionStrategy::LargestFirst),
+			dust_threshold: Some(100),
+			estimate_only: Some(true),
+			..Default::default()
+		};
+		let slate = api.init_send_tx(m, args)?;
+		assert_eq!(slate.amount, 10 * base);
+		assert_eq!(slate.fee, 10 * base - amount);
+
+		// fee estimates should match the selection a send would make
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 5 * base,
+			minimum_confirmations: 1,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy: Some(SelectionStrategy::LargestFirst),
+			..Default::default()
+		};
+		let estimate = api.estimate_fee(args)?;
+		let fee = core::libtx::tx_fee(1, 2, 1, 0, 0, 0, None);
+		assert!(estimate.sufficient_funds);
+		assert_eq!(estimate.num_inputs, 1);
+		assert_eq!(estimate.total, 10 * base);
+		assert_eq!(estimate.fee, fee);
+		assert_eq!(estimate.change, 5 * base - fee);
+
+		// and report the most we could send rather than failing
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 100 * base,
+			minimum_confirmations: 1,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy: Some(SelectionStrategy::LargestFirst),
+			..Default::default()
+		};
+		let estimate = api.estimate_fee(args)?;
+		assert!(!estimate.sufficient_funds);
+		assert_eq!(estimate.num_inputs, 4);
+		assert_eq!(estimate.total, 16 * base);
+
+		// when no window of max_outputs covers the amount, selection falls back
+		// to the largest outputs, which should surface as not enough funds
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 100 * base,
+			minimum_confirmations: 1,
+			max_outputs: 2,
+			num_change_outputs: 1,
+			selection_strategy: Some(SelectionStrategy::LargestFirst),
+			..Default::default()
+		};
+		match api.init_send_tx(m, args) {
+			Err(e) => match e.kind() {
+				ErrorKind::NotEnoughFunds {
+					available,
+					needed,
+					num_outputs,
+					max_output,
+					..
+				} => {
+					assert_eq!(available, 13 * base);
+					assert!(needed > 100 * base);
+					assert_eq!(num_outputs, 4);
+					assert_eq!(max_output, 10 * base);
+				}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Send should fail with not enough funds"),
+		}
+		Ok(())
+	})?;
+
+	// a real send with largest first should settle on a single input
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 5 * base,
+			minimum_confirmations: 1,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy: Some(SelectionStrategy::LargestFirst),
+			..Default::default()
+		};
+		slate = api.init_send_tx(m, args)?;
+		slate = client2.send_tx_slate_direct("wallet1", &slate)?;
+		api.tx_lock_outputs(m, &slate)?;
+		slate = api.finalize_tx(m, &slate)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let (refreshed, txs) = api.retrieve_txs(m, true, None, Some(slate.id), None)?;
+		assert!(refreshed);
+		let tx = txs[0].clone();
+		assert!(tx.confirmed);
+		assert_eq!(tx.num_inputs, 1);
+		assert_eq!(tx.fee, Some(core::libtx::tx_fee(1, 2, 1, 0, 0, 0, None)));
+		Ok(())
+	})?;
+
+	// with the fee included in the amount, the sender is debited exactly the
+	// amount and the recipient gets the rest
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 2 * base,
+			minimum_confirmations: 1,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy: Some(SelectionStrategy::LargestFirst),
+			amount_includes_fee: true,
+			..Default::default()
+		};
+		slate = api.init_send_tx(m, args)?;
+		assert_eq!(slate.amount + slate.fee, 2 * base);
+		slate = client2.send_tx_slate_direct("wallet1", &slate)?;
+		api.tx_lock_outputs(m, &slate)?;
+		slate = api.finalize_tx(m, &slate)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let (_, txs) = api.retrieve_txs(m, true, None, Some(slate.id), None)?;
+		let tx = txs[0].clone();
+		assert!(tx.confirmed);
+		assert_eq!(tx.amount_debited - tx.amount_credited, 2 * base);
+		assert_eq!(tx.fee, Some(slate.fee));
+		Ok(())
+	})?;
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, txs) = api.retrieve_txs(m, true, None, Some(slate.id), None)?;
+		assert_eq!(txs[0].amount_credited, 2 * base - slate.fee);
+		Ok(())
+	})?;
+
+	// sending everything spends all outputs and leaves no change
+	let mut spendable = 0;
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		spendable = info.amount_currently_spendable;
+
+		// a sweep capped by max_outputs only spends the largest outputs
+		let (_, outputs) = api.retrieve_outputs(m, false, true, None)?;
+		let largest = outputs.iter().map(|o| o.output.value).max().unwrap();
+		let args = InitTxArgs {
+			src_acct_name: None,
+			minimum_confirmations: 1,
+			max_outputs: 1,
+			num_change_outputs: 1,
+			send_all: true,
+			estimate_only: Some(true),
+			..Default::default()
+		};
+		let est = api.init_send_tx(m, args)?;
+		assert_eq!(est.amount, largest);
+
+		let args = InitTxArgs {
+			src_acct_name: None,
+			minimum_confirmations: 1,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			send_all: true,
+			..Default::default()
+		};
+		slate = api.init_send_tx(m, args)?;
+		assert_eq!(slate.amount + slate.fee, spendable);
+		slate = client2.send_tx_slate_direct("wallet1", &slate)?;
+		api.tx_lock_outputs(m, &slate)?;
+		slate = api.finalize_tx(m, &slate)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 2, false);
+
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		// with no change output only the kernel shows the tx made it on chain
+		match api.cancel_tx(m, None, Some(slate.id)) {
+			Err(e) => match e.kind() {
+				ErrorKind::TransactionNotCancellable(_) => {}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Cancelling a mined tx should fail"),
+		}
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		assert_eq!(info.total, 0);
+		let (_, txs) = api.retrieve_txs(m, true, None, Some(slate.id), None)?;
+		assert!(txs[0].confirmed);
+		assert_eq!(txs[0].amount_credited, 0);
+		assert_eq!(txs[0].fee, Some(slate.fee));
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn selection_strategy() {
+	let test_dir = "test_output/selection_strategy";
+	setup(test_dir);
+	if let Err(e) = selection_strategy_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
@@ -0,0 +1,180 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test the inputs, change and fees the send builders record balance
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{InitTxArgs, IssueTokenArgs, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn send_builders_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let base = core::consensus::GRIN_BASE;
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	// base send: the inputs pay the amount, the fee and the change
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 3 * base,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 2,
+			..Default::default()
+		};
+		slate = api.init_send_tx(m, args)?;
+		Ok(())
+	})?;
+	{
+		wallet_inst!(wallet1, w);
+		let context = w.get_private_context(mask1, slate.id.as_bytes())?;
+		let spent: u64 = context.input_ids.iter().map(|i| i.2).sum();
+		let change: u64 = context.output_ids.iter().map(|o| o.2).sum();
+		assert_eq!(context.output_ids.len(), 2);
+		assert_eq!(context.amount, 3 * base);
+		assert_eq!(context.fee, slate.fee);
+		assert_eq!(spent, context.amount + context.fee + change);
+		assert!(!context.changeless);
+	}
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.cancel_tx(m, None, Some(slate.id))?;
+		Ok(())
+	})?;
+
+	// token issuance: the inputs pay the fee and the change
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = IssueTokenArgs {
+			acct_name: None,
+			amount: 1000,
+			..Default::default()
+		};
+		slate = api.init_issue_token_tx(m, args)?;
+		let (_, txs) = api.retrieve_txs(m, false, None, Some(slate.id), None)?;
+		assert_eq!(txs[0].amount_debited, slate.fee + txs[0].amount_credited);
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+	let token_type = slate.token_type.clone();
+
+	// token send: the token inputs pay the amount, the token fee and the token
+	// change, the base inputs pay the fee and the change
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 600,
+			token_type: token_type.clone(),
+			token_fee: 50,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			..Default::default()
+		};
+		slate = api.init_send_tx(m, args)?;
+		Ok(())
+	})?;
+	{
+		wallet_inst!(wallet1, w);
+		let context = w.get_private_context(mask1, slate.id.as_bytes())?;
+		let token_spent: u64 = context.token_input_ids.iter().map(|i| i.2).sum();
+		let token_change: u64 = context.token_output_ids.iter().map(|o| o.2).sum();
+		assert_eq!(context.amount, 600);
+		assert_eq!(context.token_fee, 50);
+		assert_eq!(token_spent, 1000);
+		assert_eq!(token_change, 350);
+
+		let spent: u64 = context.input_ids.iter().map(|i| i.2).sum();
+		let change: u64 = context.output_ids.iter().map(|o| o.2).sum();
+		assert_eq!(context.fee, slate.fee);
+		assert_eq!(spent, context.fee + change);
+	}
+
+	// sending the whole token balance leaves no token change
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.cancel_tx(m, None, Some(slate.id))?;
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 0,
+			token_type: token_type.clone(),
+			token_fee: 50,
+			send_all: true,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			..Default::default()
+		};
+		slate = api.init_send_tx(m, args)?;
+		assert_eq!(slate.amount, 950);
+		Ok(())
+	})?;
+	{
+		wallet_inst!(wallet1, w);
+		let context = w.get_private_context(mask1, slate.id.as_bytes())?;
+		assert!(context.token_output_ids.is_empty());
+		assert_eq!(context.amount, 950);
+	}
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn send_builders() {
+	let test_dir = "test_output/send_builders";
+	setup(test_dir);
+	if let Err(e) = send_builders_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
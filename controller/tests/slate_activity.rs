@@ -0,0 +1,173 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test the combined record of what a slate moved of vcash and tokens
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{InitTxArgs, IssueTokenArgs, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn slate_activity_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask1 = (&mask1_i).as_ref();
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask2 = (&mask2_i).as_ref();
+
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 4, false);
+
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = IssueTokenArgs {
+			acct_name: None,
+			amount: 1000,
+			..Default::default()
+		};
+		slate = api.init_issue_token_tx(m, args)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+	let token_type = slate.token_type.clone();
+
+	// send 600 of the token paying a token fee of 50, the vcash fee comes
+	// from the vcash outputs
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 600,
+			token_type: token_type.clone(),
+			token_fee: 50,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			..Default::default()
+		};
+		let slate_i = api.init_send_tx(m, args)?;
+		slate = client1.send_tx_slate_direct("wallet2", &slate_i)?;
+		api.tx_lock_outputs(m, &slate)?;
+		slate = api.finalize_tx(m, &slate)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		let (_, token_infos) = api.retrieve_token_summary_info(m, false, 1)?;
+		let token_total = token_infos[0].amount_currently_spendable
+			+ token_infos[0].amount_awaiting_confirmation
+			+ token_infos[0].amount_immature;
+		assert_eq!(token_total, 350);
+
+		// the vcash spent by the token transactions is missing from the tx log
+		let (_, txs) = api.retrieve_txs(m, false, None, None, None)?;
+		let base_only: i64 = txs
+			.iter()
+			.map(|t| t.amount_credited as i64 - t.amount_debited as i64)
+			.sum();
+		assert!(base_only > info.total as i64);
+
+		// the slate records of both logs add up for both assets
+		let (_, activity) = api.retrieve_slate_activity(m, false, None)?;
+		let vcash: i64 = activity
+			.iter()
+			.map(|a| a.amount_credited as i64 - a.amount_debited as i64)
+			.sum();
+		assert_eq!(vcash, info.total as i64);
+		let tokens: i64 = activity
+			.iter()
+			.map(|a| a.token_amount_credited as i64 - a.token_amount_debited as i64)
+			.sum();
+		assert_eq!(tokens, token_total as i64);
+		assert!(activity.iter().all(|a| a.confirmed));
+
+		// the send, debit, change and fees of both assets together
+		let (_, activity) = api.retrieve_slate_activity(m, false, Some(slate.id))?;
+		assert_eq!(activity.len(), 1);
+		let send = &activity[0];
+		assert_eq!(send.tx_slate_id, Some(slate.id));
+		assert!(send.tx_log_ids.is_empty());
+		assert_eq!(send.token_tx_log_ids.len(), 1);
+		assert_eq!(send.token_type, token_type);
+		assert_eq!(send.fee, slate.fee);
+		assert_eq!(send.amount_debited - send.amount_credited, slate.fee);
+		assert_eq!(send.token_amount_debited, 1000);
+		assert_eq!(send.token_amount_credited, 350);
+		assert_eq!(send.token_fee, 50);
+		Ok(())
+	})?;
+
+	// the recipient's record of the same slate
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let (_, activity) = api.retrieve_slate_activity(m, true, Some(slate.id))?;
+		assert_eq!(activity.len(), 1);
+		assert_eq!(activity[0].token_amount_credited, 600);
+		assert_eq!(activity[0].amount_debited, 0);
+		assert_eq!(activity[0].fee, 0);
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn slate_activity() {
+	let test_dir = "test_output/slate_activity";
+	setup(test_dir);
+	if let Err(e) = slate_activity_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
@@ -0,0 +1,146 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test finalizing a slate that was tampered with by the recipient
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, InitTxArgs, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn assert_changed(res: Result<Slate, libwallet::Error>, field: &str) {
+	match res {
+		Err(e) => match e.kind() {
+			ErrorKind::SlateContextMismatch(s) => assert!(s.contains(field)),
+			k => panic!("Unexpected error: {}", k),
+		},
+		Ok(_) => panic!("Finalizing should fail with a changed {}", field),
+	}
+}
+
+fn slate_context_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask1 = (&mask1_i).as_ref();
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask2 = (&mask2_i).as_ref();
+
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 5, false);
+
+	let mut address = None;
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		address = Some(api.get_slatepack_address(m, 0)?);
+		Ok(())
+	})?;
+
+	let amount = core::consensus::GRIN_BASE;
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount,
+			minimum_confirmations: 1,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			payment_proof_recipient_address: address.clone(),
+			..Default::default()
+		};
+		slate = api.init_send_tx(m, args)?;
+		Ok(())
+	})?;
+	slate = client1.send_tx_slate_direct("wallet2", &slate)?;
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.tx_lock_outputs(m, &slate)?;
+
+		let mut tampered = slate.clone();
+		tampered.token_type = Some("00".repeat(32));
+		assert_changed(api.finalize_tx(m, &tampered), "token type");
+
+		let mut tampered = slate.clone();
+		tampered.amount = amount + 1;
+		assert_changed(api.finalize_tx(m, &tampered), "amount");
+
+		let mut tampered = slate.clone();
+		tampered.payment_proof = None;
+		assert_changed(api.finalize_tx(m, &tampered), "payment proof");
+
+		let mut tampered = slate.clone();
+		if let Some(ref mut p) = tampered.payment_proof {
+			p.receiver_address = p.sender_address;
+		}
+		assert_changed(
+			api.finalize_tx(m, &tampered),
+			"payment proof receiver address",
+		);
+
+		// the untouched slate still finalizes
+		let finalized = api.finalize_tx(m, &slate)?;
+		assert!(finalized.payment_proof.is_some());
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn slate_context() {
+	let test_dir = "test_output/slate_context";
+	setup(test_dir);
+	if let Err(e) = slate_context_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
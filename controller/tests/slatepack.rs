@@ -27,8 +27,8 @@ use std::thread;
 use std::time::Duration;
 
 use grin_wallet_libwallet::{
-	InitTxArgs, IssueInvoiceTxArgs, Slate, Slatepack, SlatepackAddress, Slatepacker,
-	SlatepackerArgs,
+	InitTxArgs, IssueInvoiceTxArgs, IssueTokenArgs, Slate, Slatepack, SlatepackAddress,
+	Slatepacker, SlatepackerArgs,
 };
 
 use ed25519_dalek::PublicKey as edDalekPublicKey;
@@ -498,8 +498,69 @@ fn slatepack_api_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
 		println!("{}", slatepack);
 		let slatepack_raw = api.decode_slatepack_message(m, slatepack.clone(), vec![0])?;
 		println!("{}", slatepack_raw);
-		let decoded_slate = api.slate_from_slatepack_message(m, slatepack, vec![0])?;
+		let decoded_slate = api.slate_from_slatepack_message(m, slatepack.clone(), vec![0])?;
 		println!("{}", decoded_slate);
+		assert_eq!(decoded_slate.id, slate.id);
+		assert_eq!(decoded_slate.amount, slate.amount);
+
+		// and an unencrypted one
+		let plain = api.create_slatepack_message(m, &slate, None, vec![])?;
+		let decoded_slate = api.slate_from_slatepack_message(m, plain.clone(), vec![])?;
+		assert_eq!(decoded_slate.id, slate.id);
+		assert_eq!(decoded_slate.amount, slate.amount);
+
+		// malformed armor is rejected rather than read
+		let corrupt = |armor: &str, i: usize| {
+			let mut bytes = armor.as_bytes().to_vec();
+			bytes[i] = if bytes[i] == b'2' { b'3' } else { b'2' };
+			String::from_utf8(bytes).unwrap()
+		};
+		let payload_start = "BEGINSLATEPACK. ".len();
+		for bad in vec![
+			corrupt(&plain, payload_start),
+			corrupt(&slatepack, payload_start + 20),
+			plain.replace("ENDSLATEPACK", "ENDSLATEPAK"),
+			plain.replace(". ENDSLATEPACK.", ""),
+			"BEGINSLATEPACK. 0OIl. ENDSLATEPACK.".to_owned(),
+			"BEGINSLATEPACK. 2. ENDSLATEPACK.".to_owned(),
+		] {
+			assert!(api.slate_from_slatepack_message(m, bad, vec![0]).is_err());
+		}
+		Ok(())
+	})?;
+
+	// token slates keep their token type through a slatepack
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+	let mut token_type = None;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = IssueTokenArgs {
+			acct_name: Some("mining".to_owned()),
+			amount: 1000,
+			..Default::default()
+		};
+		let slate = api.init_issue_token_tx(m, args)?;
+		api.post_tx(m, &slate, false)?;
+		token_type = slate.token_type.clone();
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: Some("mining".to_owned()),
+			amount: 600,
+			token_type: token_type.clone(),
+			minimum_confirmations: 2,
+			..Default::default()
+		};
+		let slate = api.init_send_tx(m, args)?;
+		let enc_addr = api.get_slatepack_address(m, 0)?;
+		for recipients in vec![vec![], vec![enc_addr]] {
+			let slatepack = api.create_slatepack_message(m, &slate, Some(0), recipients)?;
+			let decoded_slate = api.slate_from_slatepack_message(m, slatepack, vec![0])?;
+			assert_eq!(decoded_slate.id, slate.id);
+			assert_eq!(decoded_slate.amount, 600);
+			assert_eq!(decoded_slate.token_type, token_type);
+		}
 		Ok(())
 	})?;
 
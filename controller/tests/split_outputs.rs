@@ -0,0 +1,215 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test splitting large outputs into several smaller ones
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, IssueTokenArgs, OutputStatus, Slate, SplitOutputsArgs};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn split_outputs_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let base = core::consensus::GRIN_BASE;
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	// splitting into a single output is pointless
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = SplitOutputsArgs {
+			minimum_confirmations: 2,
+			num_outputs: 1,
+			..Default::default()
+		};
+		match api.init_split_outputs_tx(m, args) {
+			Err(e) => match e.kind() {
+				ErrorKind::SplitOutputs(_) => {}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Splitting into one output should fail"),
+		}
+		Ok(())
+	})?;
+
+	// the largest output is split whole into outputs of the same value
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = SplitOutputsArgs {
+			minimum_confirmations: 2,
+			num_outputs: 4,
+			..Default::default()
+		};
+		slate = api.init_split_outputs_tx(m, args)?;
+		let (_, txs) = api.retrieve_txs(m, false, None, Some(slate.id), None)?;
+		let tx = txs[0].clone();
+		assert_eq!(tx.num_inputs, 1);
+		assert_eq!(tx.num_outputs, 4);
+		assert_eq!(tx.amount_debited, tx.amount_credited + slate.fee);
+		assert_eq!(slate.amount, tx.amount_credited);
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 2, false);
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, txs) = api.retrieve_txs(m, true, None, Some(slate.id), None)?;
+		assert!(txs[0].confirmed);
+		let (_, outputs) = api.retrieve_outputs(m, false, true, Some(txs[0].id))?;
+		let split: Vec<u64> = outputs
+			.iter()
+			.filter(|o| o.output.status == OutputStatus::Unspent)
+			.map(|o| o.output.value)
+			.collect();
+		assert_eq!(split.len(), 4);
+		let min = *split.iter().min().unwrap();
+		let max = *split.iter().max().unwrap();
+		assert!(max - min <= 1);
+		assert_eq!(split.iter().sum::<u64>(), slate.amount);
+		Ok(())
+	})?;
+
+	// or into the given denominations, with the remainder left over
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = SplitOutputsArgs {
+			minimum_confirmations: 2,
+			num_outputs: 1,
+			denominations: Some(vec![2 * base, base]),
+			..Default::default()
+		};
+		slate = api.init_split_outputs_tx(m, args)?;
+		let (_, txs) = api.retrieve_txs(m, false, None, Some(slate.id), None)?;
+		let (_, outputs) = api.retrieve_outputs(m, false, false, Some(txs[0].id))?;
+		let values: Vec<u64> = outputs
+			.iter()
+			.filter(|o| o.output.status == OutputStatus::Unconfirmed)
+			.map(|o| o.output.value)
+			.collect();
+		assert_eq!(values.len(), 3);
+		assert!(values.contains(&base));
+		assert!(values.contains(&(2 * base)));
+		assert_eq!(values.iter().sum::<u64>(), slate.amount);
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 2, false);
+
+	// token outputs are split the same way, the fee coming from base outputs
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = IssueTokenArgs {
+			acct_name: None,
+			amount: 1000,
+			..Default::default()
+		};
+		slate = api.init_issue_token_tx(m, args)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+	let token_type = slate.token_type.clone();
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = SplitOutputsArgs {
+			token_type: token_type.clone(),
+			minimum_confirmations: 2,
+			num_outputs: 4,
+			denominations: Some(vec![100]),
+			..Default::default()
+		};
+		match api.init_split_outputs_tx(m, args) {
+			Err(e) => match e.kind() {
+				ErrorKind::SplitOutputs(_) => {}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Token denominations should be refused"),
+		}
+
+		let args = SplitOutputsArgs {
+			token_type: token_type.clone(),
+			minimum_confirmations: 2,
+			num_outputs: 4,
+			..Default::default()
+		};
+		slate = api.init_split_outputs_tx(m, args)?;
+		let (_, txs) = api.retrieve_token_txs(m, false, None, Some(slate.id), None)?;
+		let tx = txs[0].clone();
+		assert_eq!(tx.num_token_inputs, 1);
+		assert_eq!(tx.num_token_outputs, 4);
+		assert_eq!(tx.token_amount_debited, 1000);
+		assert_eq!(tx.token_amount_credited, 1000);
+		assert_eq!(tx.amount_debited, tx.amount_credited + slate.fee);
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, txs) = api.retrieve_token_txs(m, true, None, Some(slate.id), None)?;
+		assert!(txs[0].confirmed);
+		let (_, outputs) = api.retrieve_token_outputs(m, false, true, None)?;
+		let mut values: Vec<u64> = outputs.iter().map(|o| o.output.value).collect();
+		values.sort();
+		assert_eq!(values, vec![250, 250, 250, 250]);
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn split_outputs() {
+	let test_dir = "test_output/split_outputs";
+	setup(test_dir);
+	if let Err(e) = split_outputs_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
@@ -0,0 +1,143 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test token sends splitting token and base change over several outputs
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{InitTxArgs, IssueTokenArgs, OutputStatus, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn token_change_outputs_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	// wallet 1 issues a token
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = IssueTokenArgs {
+			acct_name: None,
+			amount: 1000,
+			..Default::default()
+		};
+		slate = api.init_issue_token_tx(m, args)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+	let token_type = slate.token_type.clone();
+
+	// (amount, token change outputs, base change outputs, expected token change)
+	let cases: Vec<(u64, u32, Option<u32>, Vec<u64>)> = vec![
+		(1000, 1, None, vec![]),
+		(600, 1, None, vec![400]),
+		(600, 3, Some(2), vec![133, 133, 134]),
+	];
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		for (amount, num_change_outputs, num_fee_change_outputs, expected) in cases {
+			let args = InitTxArgs {
+				src_acct_name: None,
+				amount,
+				token_type: token_type.clone(),
+				minimum_confirmations: 2,
+				max_outputs: 500,
+				num_change_outputs,
+				num_fee_change_outputs,
+				..Default::default()
+			};
+			slate = api.init_send_tx(m, args)?;
+			api.tx_lock_outputs(m, &slate)?;
+
+			let (_, txs) = api.retrieve_token_txs(m, false, None, Some(slate.id), None)?;
+			let tx = txs[0].clone();
+			assert_eq!(tx.num_token_outputs, expected.len());
+			assert_eq!(tx.num_outputs, num_fee_change_outputs.unwrap_or(1) as usize);
+
+			// the recipient's token output comes on top of our token change
+			let fee = core::libtx::tx_fee(
+				tx.num_inputs,
+				tx.num_outputs,
+				1,
+				tx.num_token_inputs,
+				tx.num_token_outputs + 1,
+				1,
+				None,
+			);
+			assert_eq!(slate.fee, fee);
+
+			let (_, outputs) = api.retrieve_token_outputs(m, false, false, Some(tx.id))?;
+			let mut change: Vec<u64> = outputs
+				.iter()
+				.filter(|o| o.output.status == OutputStatus::Unconfirmed)
+				.map(|o| o.output.value)
+				.collect();
+			change.sort();
+			assert_eq!(change, expected);
+
+			api.cancel_tx(m, None, Some(slate.id))?;
+		}
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn token_change_outputs() {
+	let test_dir = "test_output/token_change_outputs";
+	setup(test_dir);
+	if let Err(e) = token_change_outputs_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
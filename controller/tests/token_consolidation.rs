@@ -0,0 +1,200 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test selecting fragmented token outputs to consolidate
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, InitTxArgs, IssueTokenArgs, NodeClient, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn token_consolidation_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask2 = (&mask2_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	// wallet 1 issues a token
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = IssueTokenArgs {
+			acct_name: None,
+			amount: 1000,
+			..Default::default()
+		};
+		slate = api.init_issue_token_tx(m, args)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+	let token_type = slate.token_type.clone().unwrap();
+
+	// and fragments it over three outputs in wallet 2
+	for amount in [300, 100, 200].iter() {
+		wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+			let args = InitTxArgs {
+				src_acct_name: None,
+				amount: *amount,
+				token_type: Some(token_type.clone()),
+				minimum_confirmations: 2,
+				max_outputs: 500,
+				num_change_outputs: 1,
+				..Default::default()
+			};
+			slate = api.init_send_tx(m, args)?;
+			api.tx_lock_outputs(m, &slate)?;
+			Ok(())
+		})?;
+		slate = client1.send_tx_slate_direct("wallet2", &slate)?;
+		wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+			slate = api.finalize_tx(m, &slate)?;
+			api.post_tx(m, &slate, false)?;
+			Ok(())
+		})?;
+		let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+	}
+
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let (_, outputs) = api.retrieve_token_outputs(m, false, true, None)?;
+		assert_eq!(outputs.len(), 3);
+		Ok(())
+	})?;
+
+	{
+		wallet_inst!(wallet2, w);
+		let height = w.w2n_client().get_chain_tip()?.0;
+		let parent_key_id = w.parent_key_id();
+
+		// the whole token balance goes into the consolidated output
+		let (coins, total) = libwallet::select_token_coins_for_consolidation(
+			&mut **w,
+			&token_type,
+			height,
+			2,
+			500,
+			&parent_key_id,
+		)?;
+		assert_eq!(coins.len(), 3);
+		assert_eq!(total, 600);
+		assert_eq!(
+			libwallet::min_sendable_token_amount(&mut **w, &token_type, height, 2, &parent_key_id),
+			100
+		);
+
+		// the smallest outputs are consolidated first
+		let (coins, total) = libwallet::select_token_coins_for_consolidation(
+			&mut **w,
+			&token_type,
+			height,
+			2,
+			2,
+			&parent_key_id,
+		)?;
+		let values: Vec<u64> = coins.iter().map(|c| c.value).collect();
+		assert_eq!(values, vec![100, 200]);
+		assert_eq!(total, 300);
+
+		// nothing is eligible with more confirmations than the chain has
+		match libwallet::select_token_coins_for_consolidation(
+			&mut **w,
+			&token_type,
+			height,
+			height + 1,
+			500,
+			&parent_key_id,
+		) {
+			Err(e) => match e.kind() {
+				ErrorKind::NotEnoughTokenFunds {
+					token_type: t,
+					available,
+					..
+				} => {
+					assert_eq!(t, token_type);
+					assert_eq!(available, 0);
+				}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Consolidating without eligible outputs should fail"),
+		}
+		assert_eq!(
+			libwallet::min_sendable_token_amount(
+				&mut **w,
+				&token_type,
+				height,
+				height + 1,
+				&parent_key_id
+			),
+			0
+		);
+	}
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn token_consolidation() {
+	let test_dir = "test_output/token_consolidation";
+	setup(test_dir);
+	if let Err(e) = token_consolidation_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
@@ -0,0 +1,134 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test the token summary info, broken down by token type
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{InitTxArgs, IssueTokenArgs, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn token_info_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, token_infos) = api.retrieve_token_summary_info(m, true, 1)?;
+		assert!(token_infos.is_empty());
+		Ok(())
+	})?;
+
+	// wallet 1 issues a token
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = IssueTokenArgs {
+			acct_name: None,
+			amount: 1000,
+			..Default::default()
+		};
+		slate = api.init_issue_token_tx(m, args)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+	let token_type = slate.token_type.clone();
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, token_infos) = api.retrieve_token_summary_info(m, true, 1)?;
+		assert_eq!(token_infos.len(), 1);
+		let info = &token_infos[0];
+		assert_eq!(Some(info.token_type.clone()), token_type);
+		assert_eq!(info.amount_currently_spendable, 1000);
+		assert_eq!(info.amount_awaiting_confirmation, 0);
+		assert_eq!(info.num_outputs, 1);
+
+		// not yet spendable when asking for more confirmations
+		let (_, token_infos) = api.retrieve_token_summary_info(m, false, 10)?;
+		assert_eq!(token_infos[0].amount_currently_spendable, 0);
+		assert_eq!(token_infos[0].amount_awaiting_confirmation, 1000);
+
+		// locking a send moves the input to locked and the change to awaiting
+		// finalization
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 600,
+			token_type: token_type.clone(),
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			..Default::default()
+		};
+		slate = api.init_send_tx(m, args)?;
+		api.tx_lock_outputs(m, &slate)?;
+		let (_, token_infos) = api.retrieve_token_summary_info(m, false, 1)?;
+		let info = &token_infos[0];
+		assert_eq!(info.amount_currently_spendable, 0);
+		assert_eq!(info.amount_locked, 1000);
+		assert_eq!(info.amount_awaiting_finalization, 400);
+		assert_eq!(info.num_outputs, 2);
+
+		// the summary info carries the same breakdown
+		let (_, wallet_info) = api.retrieve_summary_info(m, false, 1)?;
+		assert_eq!(wallet_info.token_infos, token_infos);
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn token_info() {
+	let test_dir = "test_output/token_info";
+	setup(test_dir);
+	if let Err(e) = token_info_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
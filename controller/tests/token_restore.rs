@@ -0,0 +1,186 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test restoring token outputs and their log from seed
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_util as util;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{InitTxArgs, IssueTokenArgs, Slate, TokenTxLogEntryType};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use util::ZeroingString;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn token_restore_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let seed_phrase = "affair pistol cancel crush garment candy ancient flag work \
+	                   market crush dry stand focus mutual weapon offer ceiling rival turn team spring \
+	                   where swift";
+	let seed_phrase = Some(ZeroingString::from(seed_phrase));
+
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		seed_phrase,
+		&mut wallet_proxy,
+		false
+	);
+	let mask1 = (&mask1_i).as_ref();
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let _mask2 = (&mask2_i).as_ref();
+	// restored from wallet 1's seed
+	create_wallet_and_add!(
+		client3,
+		wallet3,
+		mask3_i,
+		test_dir,
+		"wallet3",
+		seed_phrase,
+		&mut wallet_proxy,
+		false
+	);
+	let mask3 = (&mask3_i).as_ref();
+
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	let issue_token = |amount: u64| -> Result<Slate, libwallet::Error> {
+		let mut slate = Slate::blank(2, false);
+		wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+			let args = IssueTokenArgs {
+				acct_name: None,
+				amount,
+				..Default::default()
+			};
+			slate = api.init_issue_token_tx(m, args)?;
+			api.post_tx(m, &slate, false)?;
+			Ok(())
+		})?;
+		let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+		Ok(slate)
+	};
+
+	// the first token is partly sent on, the second kept whole
+	let sent_type = issue_token(1000)?.token_type.unwrap();
+	let kept_type = issue_token(500)?.token_type.unwrap();
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 300,
+			token_type: Some(sent_type.clone()),
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			..Default::default()
+		};
+		let slate_i = api.init_send_tx(m, args)?;
+		let slate = client1.send_tx_slate_direct("wallet2", &slate_i)?;
+		api.tx_lock_outputs(m, &slate)?;
+		let slate = api.finalize_tx(m, &slate)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+
+	let mut balances = vec![];
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (refreshed, token_infos) = api.retrieve_token_summary_info(m, true, 1)?;
+		assert!(refreshed);
+		for t in token_infos {
+			balances.push((t.token_type, t.amount_currently_spendable));
+		}
+		Ok(())
+	})?;
+	balances.sort();
+
+	// a wallet with nothing but the seed gets back the token outputs, with
+	// an issue entry for the untouched issue and a received one for the
+	// change of the send
+	wallet::controller::owner_single_use(Some(wallet3.clone()), mask3, None, |api, m| {
+		api.scan(m, None, false)?;
+		let (_, token_infos) = api.retrieve_token_summary_info(m, true, 1)?;
+		let mut restored: Vec<_> = token_infos
+			.into_iter()
+			.map(|t| (t.token_type, t.amount_currently_spendable))
+			.collect();
+		restored.sort();
+		assert_eq!(restored, balances);
+
+		let (_, txs) = api.retrieve_token_txs(m, true, None, None, None)?;
+		assert_eq!(txs.len(), 2);
+		for t in txs.iter() {
+			assert!(t.confirmed);
+			assert_eq!(t.num_token_outputs, 1);
+		}
+		let received = txs.iter().find(|t| t.token_type == sent_type).unwrap();
+		assert_eq!(received.tx_type, TokenTxLogEntryType::TokenTxReceived);
+		assert_eq!(received.token_amount_credited, 700);
+		let issued = txs.iter().find(|t| t.token_type == kept_type).unwrap();
+		assert_eq!(issued.tx_type, TokenTxLogEntryType::TokenIssue);
+		assert_eq!(issued.token_amount_credited, 500);
+
+		let (_, token_outputs) = api.retrieve_token_outputs(m, false, true, None)?;
+		assert_eq!(token_outputs.len(), 2);
+		for o in token_outputs {
+			assert_eq!(o.output.status, libwallet::OutputStatus::Unspent);
+			assert!(o.output.height > 0);
+		}
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn token_restore() {
+	let test_dir = "test_output/token_restore";
+	setup(test_dir);
+	if let Err(e) = token_restore_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
@@ -0,0 +1,250 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test sweeping a whole token balance in one send
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, InitTxArgs, IssueTokenArgs, OutputStatus, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn token_send_all_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask2 = (&mask2_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	// wallet 1 issues a token
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = IssueTokenArgs {
+			acct_name: None,
+			amount: 1000,
+			..Default::default()
+		};
+		slate = api.init_issue_token_tx(m, args)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+	let token_type = slate.token_type.clone();
+
+	// sending more than the balance names the token short of funds, in raw
+	// token units
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 1500,
+			token_type: token_type.clone(),
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			..Default::default()
+		};
+		match api.init_send_tx(m, args) {
+			Err(e) => match e.kind() {
+				ErrorKind::NotEnoughTokenFunds {
+					token_type: t,
+					available,
+					needed,
+					breakdown,
+					breakdown_disp,
+				} => {
+					assert_eq!(Some(t), token_type);
+					assert_eq!(available, 1000);
+					assert_eq!(needed, 1500);
+					// nothing else is held up
+					assert_eq!(breakdown.spendable, 1000);
+					assert_eq!(breakdown_disp, "");
+				}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Sending more tokens than held should fail"),
+		}
+		Ok(())
+	})?;
+
+	// sweep the whole token balance to wallet 2
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			token_type: token_type.clone(),
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			send_all: true,
+			..Default::default()
+		};
+		slate = api.init_send_tx(m, args)?;
+		assert_eq!(slate.amount, 1000);
+		api.tx_lock_outputs(m, &slate)?;
+
+		let (_, txs) = api.retrieve_token_txs(m, false, None, Some(slate.id), None)?;
+		assert_eq!(txs[0].token_amount_debited, 1000);
+		assert_eq!(txs[0].token_amount_credited, 0);
+
+		// no token change is built
+		let (_, outputs) = api.retrieve_token_outputs(m, false, false, Some(txs[0].id))?;
+		assert!(outputs
+			.iter()
+			.all(|o| o.output.status != OutputStatus::Unconfirmed));
+		Ok(())
+	})?;
+
+	slate = client1.send_tx_slate_direct("wallet2", &slate)?;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		slate = api.finalize_tx(m, &slate)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, outputs) = api.retrieve_token_outputs(m, false, true, None)?;
+		assert!(outputs.is_empty());
+		Ok(())
+	})?;
+
+	// wallet 2 holds the tokens but nothing to pay the fee with
+	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+		let (_, outputs) = api.retrieve_token_outputs(m, false, true, None)?;
+		let total: u64 = outputs.iter().map(|o| o.output.value).sum();
+		assert_eq!(total, 1000);
+
+		let args = InitTxArgs {
+			src_acct_name: None,
+			token_type: token_type.clone(),
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			send_all: true,
+			..Default::default()
+		};
+		match api.init_send_tx(m, args) {
+			Err(e) => match e.kind() {
+				ErrorKind::NotEnoughFeeFunds {
+					available, needed, ..
+				} => {
+					assert_eq!(available, 0);
+					assert!(needed > 0);
+				}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Sending tokens without base funds for the fee should fail"),
+		}
+		Ok(())
+	})?;
+
+	// once it can pay fees, wallet 2 sends the tokens back over two outputs
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet2.clone(), mask2, 10, false);
+	for amount in [300, 200].iter() {
+		wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+			let args = InitTxArgs {
+				src_acct_name: None,
+				amount: *amount,
+				token_type: token_type.clone(),
+				minimum_confirmations: 2,
+				max_outputs: 500,
+				num_change_outputs: 1,
+				..Default::default()
+			};
+			slate = api.init_send_tx(m, args)?;
+			api.tx_lock_outputs(m, &slate)?;
+			Ok(())
+		})?;
+		slate = client2.send_tx_slate_direct("wallet1", &slate)?;
+		wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |api, m| {
+			slate = api.finalize_tx(m, &slate)?;
+			api.post_tx(m, &slate, false)?;
+			Ok(())
+		})?;
+		let _ = test_framework::award_blocks_to_wallet(&chain, wallet2.clone(), mask2, 3, false);
+	}
+
+	// a sweep capped at one output only spends the largest
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			token_type: token_type.clone(),
+			minimum_confirmations: 2,
+			max_outputs: 1,
+			num_change_outputs: 1,
+			send_all: true,
+			..Default::default()
+		};
+		slate = api.init_send_tx(m, args)?;
+		assert_eq!(slate.amount, 300);
+		api.cancel_tx(m, None, Some(slate.id))?;
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn token_send_all() {
+	let test_dir = "test_output/token_send_all";
+	setup(test_dir);
+	if let Err(e) = token_send_all_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
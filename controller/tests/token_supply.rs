@@ -0,0 +1,129 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test the supply tokens are issued with is checked
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, IssueTokenArgs, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn token_supply_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	let assert_refused = |res: Result<Slate, libwallet::Error>| match res {
+		Err(e) => match e.kind() {
+			ErrorKind::GenericError(_) => {}
+			k => panic!("Unexpected error: {}", k),
+		},
+		Ok(_) => panic!("Malformed token issue was built"),
+	};
+
+	// issues of nothing or over the maximum supply are refused before
+	// anything is locked
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		assert_refused(api.init_issue_token_tx(
+			m,
+			IssueTokenArgs {
+				acct_name: None,
+				amount: 0,
+				..Default::default()
+			},
+		));
+		assert_refused(api.init_issue_token_tx(
+			m,
+			IssueTokenArgs {
+				acct_name: None,
+				amount: 1001,
+				max_supply: Some(1000),
+			},
+		));
+		let (_, txs) = api.retrieve_token_txs(m, true, None, None, None)?;
+		assert!(txs.is_empty());
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		assert_eq!(info.amount_locked, 0);
+		Ok(())
+	})?;
+
+	// the whole of the maximum supply may be issued
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = IssueTokenArgs {
+			acct_name: None,
+			amount: 1000,
+			max_supply: Some(1000),
+		};
+		slate = api.init_issue_token_tx(m, args)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, token_infos) = api.retrieve_token_summary_info(m, true, 1)?;
+		assert_eq!(token_infos.len(), 1);
+		assert_eq!(Some(token_infos[0].token_type.clone()), slate.token_type);
+		assert_eq!(token_infos[0].amount_currently_spendable, 1000);
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn token_supply() {
+	let test_dir = "test_output/token_supply";
+	setup(test_dir);
+	if let Err(e) = token_supply_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
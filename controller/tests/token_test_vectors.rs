@@ -0,0 +1,115 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test token issues are reproducible in test mode, for test vectors
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_util as util;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::api_impl::owner;
+use libwallet::{IssueTokenArgs, Slate};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use util::ZeroingString;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+/// Issues a token in test mode from a fresh wallet on a fresh chain
+fn issue_token_vector(test_dir: &'static str) -> Result<Slate, libwallet::Error> {
+	let seed_phrase = "affair pistol cancel crush garment candy ancient flag work \
+	                   market crush dry stand focus mutual weapon offer ceiling rival turn team spring \
+	                   where swift";
+	let seed_phrase = Some(ZeroingString::from(seed_phrase));
+
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		seed_phrase,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 5, false);
+
+	let slate = {
+		wallet_inst!(wallet1, w);
+		let args = IssueTokenArgs {
+			acct_name: None,
+			amount: 1000,
+			..Default::default()
+		};
+		owner::init_issue_token_tx(&mut **w, mask1, args, true)?
+	};
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(slate)
+}
+
+fn token_test_vectors_test_impl(
+	test_dir_a: &'static str,
+	test_dir_b: &'static str,
+) -> Result<(), libwallet::Error> {
+	let slate_a = issue_token_vector(test_dir_a)?;
+	let mut slate_b = issue_token_vector(test_dir_b)?;
+	assert!(slate_a.token_type.is_some());
+
+	// slate ids come from a counter shared by the whole process, everything
+	// else only depends on the wallet's state
+	assert_ne!(slate_a.id, slate_b.id);
+	slate_b.id = slate_a.id;
+	assert_eq!(
+		serde_json::to_string(&slate_a).unwrap(),
+		serde_json::to_string(&slate_b).unwrap()
+	);
+	Ok(())
+}
+
+#[test]
+fn token_test_vectors() {
+	let test_dir_a = "test_output/token_test_vectors_a";
+	let test_dir_b = "test_output/token_test_vectors_b";
+	setup(test_dir_a);
+	setup(test_dir_b);
+	if let Err(e) = token_test_vectors_test_impl(test_dir_a, test_dir_b) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir_a);
+	clean_output_dir(test_dir_b);
+}
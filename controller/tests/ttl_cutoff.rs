@@ -20,7 +20,7 @@ extern crate grin_wallet_util;
 
 use grin_wallet_libwallet as libwallet;
 use impls::test_framework::{self, LocalWalletClient};
-use libwallet::{InitTxArgs, Slate, TxLogEntryType};
+use libwallet::{ErrorKind, InitTxArgs, Slate, TxLogEntryType};
 use std::sync::atomic::Ordering;
 use std::thread;
 use std::time::Duration;
@@ -95,7 +95,7 @@ fn ttl_cutoff_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error>
 		slate = client1.send_tx_slate_direct("wallet2", &slate_i)?;
 		sender_api.tx_lock_outputs(m, &slate)?;
 
-		let (_, txs) = sender_api.retrieve_txs(m, true, None, Some(slate.id))?;
+		let (_, txs) = sender_api.retrieve_txs(m, true, None, Some(slate.id), None)?;
 		let tx = txs[0].clone();
 
 		assert_eq!(tx.ttl_cutoff_height, Some(12));
@@ -106,23 +106,29 @@ fn ttl_cutoff_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error>
 	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 2, false);
 
 	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |sender_api, m| {
-		let (_, txs) = sender_api.retrieve_txs(m, true, None, Some(slate.id))?;
+		let (_, txs) = sender_api.retrieve_txs(m, true, None, Some(slate.id), None)?;
 		let tx = txs[0].clone();
 
 		assert_eq!(tx.ttl_cutoff_height, Some(12));
-		assert!(tx.tx_type == TxLogEntryType::TxSentCancelled);
+		assert!(tx.tx_type == TxLogEntryType::TxSentTtlCancelled);
+
+		// refreshing again leaves the entry alone and the inputs spendable
+		let (_, txs) = sender_api.retrieve_txs(m, true, None, Some(slate.id), None)?;
+		assert!(txs[0].tx_type == TxLogEntryType::TxSentTtlCancelled);
+		let (_, info) = sender_api.retrieve_summary_info(m, true, 1)?;
+		assert_eq!(info.amount_locked, 0);
 		Ok(())
 	})?;
 
 	// Should also be gone in wallet 2, and output gone
 	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |sender_api, m| {
-		let (_, txs) = sender_api.retrieve_txs(m, true, None, Some(slate.id))?;
+		let (_, txs) = sender_api.retrieve_txs(m, true, None, Some(slate.id), None)?;
 		let tx = txs[0].clone();
 		let outputs = sender_api.retrieve_outputs(m, false, true, None)?.1;
 		assert_eq!(outputs.len(), 0);
 
 		assert_eq!(tx.ttl_cutoff_height, Some(12));
-		assert!(tx.tx_type == TxLogEntryType::TxReceivedCancelled);
+		assert!(tx.tx_type == TxLogEntryType::TxReceivedTtlCancelled);
 		Ok(())
 	})?;
 
@@ -144,7 +150,7 @@ fn ttl_cutoff_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error>
 		sender_api.tx_lock_outputs(m, &slate_i)?;
 		slate = slate_i;
 
-		let (_, txs) = sender_api.retrieve_txs(m, true, None, Some(slate.id))?;
+		let (_, txs) = sender_api.retrieve_txs(m, true, None, Some(slate.id), None)?;
 		let tx = txs[0].clone();
 
 		assert_eq!(tx.ttl_cutoff_height, Some(14));
@@ -156,7 +162,7 @@ fn ttl_cutoff_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error>
 
 	// Wallet 2 will need to have updated past the TTL
 	wallet::controller::owner_single_use(Some(wallet2.clone()), mask2, None, |sender_api, m| {
-		let (_, _) = sender_api.retrieve_txs(m, true, None, Some(slate.id))?;
+		let (_, _) = sender_api.retrieve_txs(m, true, None, Some(slate.id), None)?;
 		Ok(())
 	})?;
 
@@ -168,6 +174,33 @@ fn ttl_cutoff_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error>
 		Ok(())
 	})?;
 
+	// A TTL that's already passed is refused before anything is selected or locked
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |sender_api, m| {
+		let (_, before) = sender_api.retrieve_txs(m, true, None, None, None)?;
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: amount,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			selection_strategy_is_use_all: true,
+			ttl_blocks: Some(0),
+			..Default::default()
+		};
+		match sender_api.init_send_tx(m, args) {
+			Ok(_) => panic!("Send with an expired TTL should fail"),
+			Err(e) => match e.kind() {
+				ErrorKind::GenericError(_) => {}
+				k => panic!("Unexpected error: {}", k),
+			},
+		}
+		let (_, after) = sender_api.retrieve_txs(m, false, None, None, None)?;
+		assert_eq!(after.len(), before.len());
+		let (_, info) = sender_api.retrieve_summary_info(m, false, 1)?;
+		assert_eq!(info.amount_locked, 0);
+		Ok(())
+	})?;
+
 	// let logging finish
 	stopper.store(false, Ordering::Relaxed);
 	thread::sleep(Duration::from_millis(200));
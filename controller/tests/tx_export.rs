@@ -0,0 +1,167 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test exporting the merged tx history of base and token transactions
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use chrono::prelude::*;
+use grin_wallet_libwallet as libwallet;
+use grin_wallet_util::grin_core::core::amount_to_hr_string;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{IssueTokenArgs, Slate, TxExportArgs, TxExportColumn, TxExportFormat};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn tx_export_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask1 = (&mask1_i).as_ref();
+
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 4, false);
+
+	// issuing a token spends vcash for the fee, so its entry moves both
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = IssueTokenArgs {
+			acct_name: None,
+			amount: 1000,
+			..Default::default()
+		};
+		slate = api.init_issue_token_tx(m, args)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 2, false);
+	let token_type = slate.token_type.clone().unwrap();
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.set_tx_note(
+			m,
+			None,
+			Some(slate.id),
+			Some("first issue, 1000".to_owned()),
+			vec![],
+		)?;
+
+		let (_, txs) = api.retrieve_txs(m, true, None, None, None)?;
+		let (_, token_txs) = api.retrieve_token_txs(m, false, None, None, None)?;
+		assert_eq!(token_txs.len(), 1);
+		let token_tx = &token_txs[0];
+		assert!(token_tx.amount_debited > 0);
+
+		// every column, a row per asset moved
+		let mut csv = vec![];
+		let (_, rows) = api.export_txs(m, false, &TxExportArgs::default(), &mut csv)?;
+		assert_eq!(rows, txs.len() + 2);
+		let csv = String::from_utf8(csv).unwrap();
+		let lines: Vec<&str> = csv.lines().collect();
+		let header: Vec<&str> = TxExportColumn::all().iter().map(|c| c.name()).collect();
+		assert_eq!(lines[0], header.join(","));
+		assert_eq!(lines.len(), rows + 1);
+
+		// the vcash movement of the token issue comes first, then the token
+		let token_id = format!(",{},{},", token_tx.id, slate.id);
+		let issue: Vec<&&str> = lines.iter().filter(|l| l.contains(&token_id)).collect();
+		assert_eq!(issue.len(), 2);
+		assert!(issue[0].contains(&format!("{},,", slate.id)));
+		assert!(issue[1].contains(&format!("{},{},", slate.id, token_type)));
+		assert!(issue[1].ends_with("\"first issue, 1000\","));
+
+		// selected columns as JSON
+		let args = TxExportArgs {
+			format: TxExportFormat::Json,
+			columns: vec![
+				TxExportColumn::Id,
+				TxExportColumn::TokenType,
+				TxExportColumn::AmountDebited,
+				TxExportColumn::AmountDebitedHr,
+				TxExportColumn::ConfirmationHeight,
+			],
+			..Default::default()
+		};
+		let mut json = vec![];
+		api.export_txs(m, false, &args, &mut json)?;
+		let json: Vec<serde_json::Map<String, serde_json::Value>> =
+			serde_json::from_slice(&json).unwrap();
+		assert_eq!(json.len(), rows);
+		for row in json.iter() {
+			assert_eq!(row.len(), 5);
+		}
+		let issue: Vec<_> = json.iter().filter(|r| r["id"] == token_tx.id).collect();
+		assert_eq!(issue[0]["token_type"], serde_json::Value::Null);
+		assert_eq!(issue[0]["amount_debited"], token_tx.amount_debited);
+		assert_eq!(
+			issue[0]["amount_debited_hr"],
+			amount_to_hr_string(token_tx.amount_debited, false)
+		);
+		assert_eq!(issue[1]["token_type"], token_type.to_string());
+		assert_eq!(issue[1]["amount_debited"], 0);
+		assert!(issue[1]["confirmation_height"].is_u64());
+
+		// nothing was created before the first transaction, or in the future
+		let first = txs.iter().map(|t| t.creation_ts).min().unwrap();
+		for (from, to) in vec![(None, Some(first)), (Some(Utc::now()), None)] {
+			let args = TxExportArgs {
+				from,
+				to,
+				..Default::default()
+			};
+			let mut csv = vec![];
+			let (_, rows) = api.export_txs(m, false, &args, &mut csv)?;
+			assert_eq!(rows, 0);
+			assert_eq!(String::from_utf8(csv).unwrap().lines().count(), 1);
+		}
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn tx_export() {
+	let test_dir = "test_output/tx_export";
+	setup(test_dir);
+	if let Err(e) = tx_export_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
@@ -0,0 +1,210 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test notes and labels kept with transactions after the fact
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{ErrorKind, InitTxArgs, Slate, TxNoteFilter, MAX_TX_NOTE_LEN};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn tx_note_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let mask1 = (&mask1_i).as_ref();
+	create_wallet_and_add!(
+		client2,
+		wallet2,
+		mask2_i,
+		test_dir,
+		"wallet2",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+	let _mask2 = (&mask2_i).as_ref();
+
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 5, false);
+
+	let mut slate = Slate::blank(2, false);
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: core::consensus::GRIN_BASE,
+			minimum_confirmations: 1,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			..Default::default()
+		};
+		let slate_i = api.init_send_tx(m, args)?;
+		slate = client1.send_tx_slate_direct("wallet2", &slate_i)?;
+		api.tx_lock_outputs(m, &slate)?;
+		slate = api.finalize_tx(m, &slate)?;
+		api.post_tx(m, &slate, false)?;
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 2, false);
+
+	let assert_invalid = |res: Result<(), libwallet::Error>| match res {
+		Err(e) => match e.kind() {
+			ErrorKind::InvalidTxNote(_) => {}
+			k => panic!("Unexpected error: {}", k),
+		},
+		Ok(_) => panic!("Invalid note was kept"),
+	};
+
+	let mut coinbase_id = 0;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, txs) = api.retrieve_txs(m, true, None, None, None)?;
+		coinbase_id = txs[0].id;
+
+		// by tx id and by slate id
+		api.set_tx_note(
+			m,
+			Some(coinbase_id),
+			None,
+			Some("payroll June".to_owned()),
+			vec!["payroll".to_owned()],
+		)?;
+		api.set_tx_note(
+			m,
+			None,
+			Some(slate.id),
+			Some("invoice #1234".to_owned()),
+			vec!["invoice".to_owned(), "client".to_owned()],
+		)?;
+
+		// notes and labels over the limits or holding control characters
+		assert_invalid(api.set_tx_note(
+			m,
+			Some(coinbase_id),
+			None,
+			Some("x".repeat(MAX_TX_NOTE_LEN + 1)),
+			vec![],
+		));
+		assert_invalid(api.set_tx_note(
+			m,
+			Some(coinbase_id),
+			None,
+			Some("bad\u{7}note".to_owned()),
+			vec![],
+		));
+		assert_invalid(api.set_tx_note(m, Some(coinbase_id), None, None, vec!["".to_owned()]));
+
+		match api.set_tx_note(m, Some(9999), None, None, vec![]) {
+			Err(e) => match e.kind() {
+				ErrorKind::TransactionDoesntExist(_) => {}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Note kept with a missing transaction"),
+		}
+		assert!(api.set_tx_note(m, None, None, None, vec![]).is_err());
+		Ok(())
+	})?;
+
+	// the notes survive a check and repair of the wallet
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.scan(m, None, true)?;
+		let (_, txs) = api.retrieve_txs(m, false, Some(coinbase_id), None, None)?;
+		assert_eq!(txs[0].note, Some("payroll June".to_owned()));
+		assert_eq!(txs[0].labels, vec!["payroll".to_owned()]);
+		Ok(())
+	})?;
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		// substring match on the note
+		let filter = TxNoteFilter {
+			note: Some("June".to_owned()),
+			..Default::default()
+		};
+		let (_, txs) = api.retrieve_txs(m, false, None, None, Some(filter))?;
+		assert_eq!(txs.len(), 1);
+		assert_eq!(txs[0].id, coinbase_id);
+
+		// exact match on the label
+		let filter = TxNoteFilter {
+			label: Some("inv".to_owned()),
+			..Default::default()
+		};
+		let (_, txs) = api.retrieve_txs(m, false, None, None, Some(filter))?;
+		assert!(txs.is_empty());
+		let filter = TxNoteFilter {
+			note: Some("#1234".to_owned()),
+			label: Some("client".to_owned()),
+		};
+		let (_, txs) = api.retrieve_txs(m, false, None, None, Some(filter))?;
+		assert_eq!(txs.len(), 1);
+		assert_eq!(txs[0].tx_slate_id, Some(slate.id));
+		Ok(())
+	})?;
+
+	// and can be removed again
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		api.set_tx_note(m, Some(coinbase_id), None, None, vec![])?;
+		let (_, txs) = api.retrieve_txs(m, false, Some(coinbase_id), None, None)?;
+		assert_eq!(txs[0].note, None);
+		assert!(txs[0].labels.is_empty());
+		let filter = TxNoteFilter {
+			label: Some("payroll".to_owned()),
+			..Default::default()
+		};
+		let (_, txs) = api.retrieve_txs(m, false, None, None, Some(filter))?;
+		assert!(txs.is_empty());
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn tx_note() {
+	let test_dir = "test_output/tx_note";
+	setup(test_dir);
+	if let Err(e) = tx_note_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
@@ -0,0 +1,185 @@
+// Copyright 2019 The Grin Developers
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test transaction previews match the transactions actually built
+#[macro_use]
+extern crate log;
+extern crate grin_wallet_controller as wallet;
+extern crate grin_wallet_impls as impls;
+
+use grin_wallet_util::grin_core as core;
+
+use grin_wallet_libwallet as libwallet;
+use impls::test_framework::{self, LocalWalletClient};
+use libwallet::{InitTxArgs, IssueTokenArgs, OutputStatus};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use common::{clean_output_dir, create_wallet_proxy, setup};
+
+fn tx_preview_test_impl(test_dir: &'static str) -> Result<(), libwallet::Error> {
+	let mut wallet_proxy = create_wallet_proxy(test_dir);
+	let chain = wallet_proxy.chain.clone();
+	let stopper = wallet_proxy.running.clone();
+
+	create_wallet_and_add!(
+		client1,
+		wallet1,
+		mask1_i,
+		test_dir,
+		"wallet1",
+		None,
+		&mut wallet_proxy,
+		false
+	);
+
+	let mask1 = (&mask1_i).as_ref();
+
+	// Set the wallet proxy listener running
+	thread::spawn(move || {
+		if let Err(e) = wallet_proxy.run() {
+			error!("Wallet Proxy error: {}", e);
+		}
+	});
+
+	let base = core::consensus::GRIN_BASE;
+
+	// Mine into wallet 1
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 10, false);
+
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		let (_, outputs) = api.retrieve_outputs(m, true, false, None)?;
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 2 * base,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 2,
+			..Default::default()
+		};
+		let preview = api.preview_tx(args.clone())?;
+		let spent: u64 = preview.inputs.iter().map(|i| i.value).sum();
+		let change: u64 = preview.change.iter().sum();
+		assert!(preview.inputs.iter().all(|i| i.commit.is_some()));
+		assert_eq!(preview.amount, 2 * base);
+		assert_eq!(preview.change.len(), 2);
+		assert_eq!(spent, 2 * base + preview.fee + change);
+		assert_eq!(
+			preview.fee,
+			core::libtx::tx_fee(preview.inputs.len(), 3, 1, 0, 0, 0, None)
+		);
+		assert_eq!(preview.balance_after, info.total - 2 * base - preview.fee);
+		assert_eq!(preview.token_balance_after, None);
+
+		// nothing was built or stored, so previewing again gives the same result
+		assert_eq!(api.preview_tx(args.clone())?, preview);
+		let (_, after) = api.retrieve_outputs(m, true, false, None)?;
+		assert_eq!(after.len(), outputs.len());
+		assert!(after
+			.iter()
+			.all(|o| o.output.status != OutputStatus::Locked));
+
+		// and the send spends exactly the previewed outputs
+		let slate = api.init_send_tx(m, args)?;
+		api.tx_lock_outputs(m, &slate)?;
+		assert_eq!(slate.fee, preview.fee);
+		let (_, txs) = api.retrieve_txs(m, false, None, Some(slate.id), None)?;
+		assert_eq!(txs[0].num_inputs, preview.inputs.len());
+		assert_eq!(txs[0].amount_debited, spent);
+		assert_eq!(txs[0].amount_credited, change);
+		api.cancel_tx(m, None, Some(slate.id))?;
+		Ok(())
+	})?;
+
+	// token issuance
+	let mut token_type = None;
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let args = IssueTokenArgs {
+			acct_name: None,
+			amount: 1000,
+			..Default::default()
+		};
+		// an issue init_issue_token_tx refuses is refused by its preview too
+		let over_max = IssueTokenArgs {
+			max_supply: Some(999),
+			..args.clone()
+		};
+		assert!(api.preview_issue_token_tx(over_max).is_err());
+		let no_supply = IssueTokenArgs {
+			amount: 0,
+			..args.clone()
+		};
+		assert!(api.preview_issue_token_tx(no_supply).is_err());
+
+		let preview = api.preview_issue_token_tx(args.clone())?;
+		assert_eq!(preview.token_balance_after, Some(1000));
+		assert!(preview.token_inputs.is_empty());
+
+		let slate = api.init_issue_token_tx(m, args)?;
+		assert_eq!(slate.fee, preview.fee);
+		api.post_tx(m, &slate, false)?;
+		token_type = slate.token_type.clone();
+		Ok(())
+	})?;
+	let _ = test_framework::award_blocks_to_wallet(&chain, wallet1.clone(), mask1, 3, false);
+
+	// token send, paying a token fee
+	wallet::controller::owner_single_use(Some(wallet1.clone()), mask1, None, |api, m| {
+		let (_, info) = api.retrieve_summary_info(m, true, 1)?;
+		let args = InitTxArgs {
+			src_acct_name: None,
+			amount: 600,
+			token_type: token_type.clone(),
+			token_fee: 50,
+			minimum_confirmations: 2,
+			max_outputs: 500,
+			num_change_outputs: 1,
+			..Default::default()
+		};
+		let preview = api.preview_tx(args.clone())?;
+		assert_eq!(preview.amount, 600);
+		assert_eq!(preview.token_fee, 50);
+		assert_eq!(preview.token_inputs.len(), 1);
+		assert_eq!(preview.token_inputs[0].value, 1000);
+		assert_eq!(preview.token_change, vec![350]);
+		assert_eq!(preview.token_balance_after, Some(350));
+		assert_eq!(preview.balance_after, info.total - preview.fee);
+
+		let slate = api.init_send_tx(m, args)?;
+		api.tx_lock_outputs(m, &slate)?;
+		assert_eq!(slate.fee, preview.fee);
+		let (_, txs) = api.retrieve_token_txs(m, false, None, Some(slate.id), None)?;
+		assert_eq!(txs[0].num_inputs, preview.inputs.len());
+		assert_eq!(txs[0].token_amount_credited, 350);
+		Ok(())
+	})?;
+
+	// let logging finish
+	stopper.store(false, Ordering::Relaxed);
+	thread::sleep(Duration::from_millis(200));
+	Ok(())
+}
+
+#[test]
+fn tx_preview() {
+	let test_dir = "test_output/tx_preview";
+	setup(test_dir);
+	if let Err(e) = tx_preview_test_impl(test_dir) {
+		panic!("Libwallet Error: {} - {}", e, e.backtrace().unwrap());
+	}
+	clean_output_dir(test_dir);
+}
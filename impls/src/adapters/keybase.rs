@@ -401,6 +401,7 @@ impl SlateReceiver for KeybaseAllChannels {
 								Some(mask.as_ref().unwrap()),
 								&slate,
 								None,
+								None,
 								false,
 							)
 						};
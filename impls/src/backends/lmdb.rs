@@ -31,8 +31,9 @@ use crate::store::{self, option_to_not_found, to_key, to_key_u64};
 use crate::core::core::Transaction;
 use crate::core::ser;
 use crate::libwallet::{
-	AcctPathMapping, Context, Error, ErrorKind, NodeClient, OutputData, ScannedBlockInfo,
-	TxLogEntry, WalletBackend, WalletInitStatus, WalletOutputBatch,
+	AcctPathMapping, ConfirmedBlock, Context, Error, ErrorKind, NodeClient, OutputData,
+	OutputReservation, OutputStatus, ScanCheckpoint, ScannedBlockInfo, TxLogEntry, WalletBackend,
+	WalletInitStatus, WalletOutputBatch,
 };
 use crate::libwallet::{TokenOutputData, TokenTxLogEntry};
 use crate::util::secp::constants::SECRET_KEY_SIZE;
@@ -56,8 +57,17 @@ const LAST_SCANNED_BLOCK: u8 = b'l';
 const LAST_SCANNED_KEY: &str = "LAST_SCANNED_KEY";
 const WALLET_INIT_STATUS: u8 = b'w';
 const WALLET_INIT_STATUS_KEY: &str = "WALLET_INIT_STATUS";
+const SCAN_CHECKPOINT: u8 = b'k';
+const SCAN_CHECKPOINT_KEY: &str = "SCAN_CHECKPOINT";
 const TOKEN_OUTPUT_PREFIX: u8 = b'T';
 const TOKEN_TX_LOG_ENTRY_PREFIX: u8 = b'L';
+const RESERVATION_PREFIX: u8 = b'r';
+const CONFIRMED_BLOCK_PREFIX: u8 = b'b';
+const OUTPUT_INDEX_PREFIX: u8 = b's';
+const TOKEN_OUTPUT_INDEX_PREFIX: u8 = b'S';
+const OUTPUT_INDEX_VERSION: u8 = b'v';
+const OUTPUT_INDEX_VERSION_KEY: &str = "OUTPUT_INDEX_VERSION";
+const CURRENT_OUTPUT_INDEX_VERSION: u64 = 1;
 
 /// test to see if database files exist in the current directory. If so,
 /// use a DB backend for all operations
@@ -66,6 +76,158 @@ pub fn wallet_db_exists(data_file_dir: &str) -> bool {
 	db_path.exists()
 }
 
+/// Helper to set the frozen flag of the output or token output with the given commit
+fn set_output_frozen<K>(
+	batch: &mut dyn WalletOutputBatch<K>,
+	commit: &str,
+	frozen: bool,
+) -> Result<(), Error>
+where
+	K: Keychain,
+{
+	let commit = Some(commit.to_owned());
+	if let Some(mut out) = batch.iter().find(|o| o.commit == commit) {
+		out.is_frozen = frozen;
+		return batch.save(out);
+	}
+	if let Some(mut out) = batch.token_iter().find(|o| o.commit == commit) {
+		out.is_frozen = frozen;
+		return batch.save_token(out);
+	}
+	Err(ErrorKind::GenericError(format!("No output found with commit {}", commit.unwrap())).into())
+}
+
+/// Entry of the output indexes, pointing at an output by the key it's stored
+/// under
+#[derive(Serialize, Deserialize)]
+struct OutputIndexEntry {
+	key_id: Identifier,
+	mmr_index: Option<u64>,
+}
+
+impl ser::Writeable for OutputIndexEntry {
+	fn write<W: ser::Writer>(&self, writer: &mut W) -> Result<(), ser::Error> {
+		writer.write_bytes(&serde_json::to_vec(self).map_err(|_| ser::Error::CorruptedData)?)
+	}
+}
+
+impl ser::Readable for OutputIndexEntry {
+	fn read<R: ser::Reader>(reader: &mut R) -> Result<OutputIndexEntry, ser::Error> {
+		let data = reader.read_bytes_len_prefix()?;
+		serde_json::from_slice(&data[..]).map_err(|_| ser::Error::CorruptedData)
+	}
+}
+
+/// Key bytes identifying an account, and the token type if any, in the output
+/// indexes. The token type is length prefixed so no type's keys can be
+/// mistaken for another's.
+fn index_owner_bytes(parent_key_id: &Identifier, token_type: Option<&str>) -> Vec<u8> {
+	let mut bytes = parent_key_id.to_bytes().to_vec();
+	if let Some(t) = token_type {
+		bytes.push(t.len() as u8);
+		bytes.extend_from_slice(t.as_bytes());
+	}
+	bytes
+}
+
+fn status_index_byte(status: &OutputStatus) -> u8 {
+	match status {
+		OutputStatus::Unconfirmed => 0,
+		OutputStatus::Unspent => 1,
+		OutputStatus::Locked => 2,
+		OutputStatus::Spent => 3,
+		OutputStatus::Reverted => 4,
+	}
+}
+
+/// Prefix shared by the index keys of the outputs of an account with the
+/// given status
+fn output_index_prefix(
+	prefix: u8,
+	parent_key_id: &Identifier,
+	token_type: Option<&str>,
+	status: &OutputStatus,
+) -> Vec<u8> {
+	let mut bytes = index_owner_bytes(parent_key_id, token_type);
+	bytes.push(status_index_byte(status));
+	to_key(prefix, &mut bytes)
+}
+
+/// Index key of an output, ordered by account, token type and status so each
+/// combination can be read with a prefix iteration
+fn output_index_key(
+	prefix: u8,
+	parent_key_id: &Identifier,
+	token_type: Option<&str>,
+	status: &OutputStatus,
+	key_id: &Identifier,
+	mmr_index: &Option<u64>,
+) -> Vec<u8> {
+	let mut bytes = index_owner_bytes(parent_key_id, token_type);
+	bytes.push(status_index_byte(status));
+	bytes.extend_from_slice(&key_id.to_bytes());
+	match mmr_index {
+		Some(i) => to_key_u64(prefix, &mut bytes, *i),
+		None => to_key(prefix, &mut bytes),
+	}
+}
+
+fn base_output_index_key(out: &OutputData) -> Vec<u8> {
+	output_index_key(
+		OUTPUT_INDEX_PREFIX,
+		&out.root_key_id,
+		None,
+		&out.status,
+		&out.key_id,
+		&out.mmr_index,
+	)
+}
+
+fn token_output_index_key(out: &TokenOutputData) -> Vec<u8> {
+	output_index_key(
+		TOKEN_OUTPUT_INDEX_PREFIX,
+		&out.root_key_id,
+		Some(&out.token_type),
+		&out.status,
+		&out.key_id,
+		&out.mmr_index,
+	)
+}
+
+/// Indexes the outputs of a wallet created before the output indexes were, so
+/// they can be found by account and status
+fn build_output_indexes(store: &store::Store) -> Result<(), Error> {
+	let version_key = to_key(
+		OUTPUT_INDEX_VERSION,
+		&mut OUTPUT_INDEX_VERSION_KEY.as_bytes().to_vec(),
+	);
+	if store.get_ser::<u64>(&version_key)? == Some(CURRENT_OUTPUT_INDEX_VERSION) {
+		return Ok(());
+	}
+
+	let outputs: Vec<OutputData> = store.iter(&[OUTPUT_PREFIX])?.map(|o| o.1).collect();
+	let token_outputs: Vec<TokenOutputData> =
+		store.iter(&[TOKEN_OUTPUT_PREFIX])?.map(|o| o.1).collect();
+	let batch = store.batch()?;
+	for out in outputs {
+		let entry = OutputIndexEntry {
+			key_id: out.key_id.clone(),
+			mmr_index: out.mmr_index,
+		};
+		batch.put_ser(&base_output_index_key(&out), &entry)?;
+	}
+	for out in token_outputs {
+		let entry = OutputIndexEntry {
+			key_id: out.key_id.clone(),
+			mmr_index: out.mmr_index,
+		};
+		batch.put_ser(&token_output_index_key(&out), &entry)?;
+	}
+	batch.put_ser(&version_key, &CURRENT_OUTPUT_INDEX_VERSION)?;
+	batch.commit()?;
+	Ok(())
+}
+
 /// Helper to derive XOR keys for storing private transaction keys in the DB
 /// (blind_xor_key, nonce_xor_key)
 fn private_ctx_xor_keys<K>(
@@ -135,21 +297,26 @@ where
 
 		// Make sure default wallet derivation path always exists
 		// as well as path (so it can be retrieved by batches to know where to store
-		// completed transactions, for reference
+		// completed transactions, for reference. Whether its change is derived on
+		// a change branch is kept from the stored mapping.
+		let label = "default".to_owned();
+		let acct_key = to_key(ACCOUNT_PATH_MAPPING_PREFIX, &mut label.as_bytes().to_vec());
+		let change_branch = store
+			.get_ser::<AcctPathMapping>(&acct_key)?
+			.map(|m| m.change_branch)
+			.unwrap_or(false);
 		let default_account = AcctPathMapping {
-			label: "default".to_owned(),
+			label,
 			path: LMDBBackend::<C, K>::default_path(),
+			change_branch,
 		};
-		let acct_key = to_key(
-			ACCOUNT_PATH_MAPPING_PREFIX,
-			&mut default_account.label.as_bytes().to_vec(),
-		);
 
 		{
 			let batch = store.batch()?;
 			batch.put_ser(&acct_key, &default_account)?;
 			batch.commit()?;
 		}
+		build_output_indexes(&store)?;
 
 		let res = LMDBBackend {
 			db: store,
@@ -276,6 +443,21 @@ where
 		/*}*/
 	}
 
+	fn calc_commits_for_cache(
+		&mut self,
+		keychain_mask: Option<&SecretKey>,
+		outputs: &[(u64, Identifier)],
+	) -> Result<Vec<Option<String>>, Error> {
+		// unmask the keychain once for the lot, rather than per output
+		let keychain = self.keychain(keychain_mask)?;
+		let mut commits = Vec::with_capacity(outputs.len());
+		for (amount, id) in outputs {
+			let commit = keychain.commit(*amount, id, SwitchCommitmentType::Regular)?;
+			commits.push(Some(commit.0.to_vec().to_hex()));
+		}
+		Ok(commits)
+	}
+
 	/// Set parent path by account name
 	fn set_parent_key_id_by_name(&mut self, label: &str) -> Result<(), Error> {
 		let label = label.to_owned();
@@ -327,6 +509,56 @@ where
 		Box::new(self.db.iter(&[TOKEN_OUTPUT_PREFIX]).unwrap().map(|o| o.1))
 	}
 
+	fn outputs_for_parent<'a>(
+		&'a self,
+		parent_key_id: &Identifier,
+		status: &[OutputStatus],
+	) -> Box<dyn Iterator<Item = OutputData> + 'a> {
+		let entries: Vec<OutputIndexEntry> = status
+			.iter()
+			.flat_map(|s| {
+				let prefix = output_index_prefix(OUTPUT_INDEX_PREFIX, parent_key_id, None, s);
+				self.db
+					.iter::<OutputIndexEntry>(&prefix)
+					.unwrap()
+					.map(|o| o.1)
+			})
+			.collect();
+		Box::new(
+			entries
+				.into_iter()
+				.filter_map(move |e| self.get(&e.key_id, &e.mmr_index).ok()),
+		)
+	}
+
+	fn token_outputs_for<'a>(
+		&'a self,
+		parent_key_id: &Identifier,
+		token_type: &str,
+		status: &[OutputStatus],
+	) -> Box<dyn Iterator<Item = TokenOutputData> + 'a> {
+		let entries: Vec<OutputIndexEntry> = status
+			.iter()
+			.flat_map(|s| {
+				let prefix = output_index_prefix(
+					TOKEN_OUTPUT_INDEX_PREFIX,
+					parent_key_id,
+					Some(token_type),
+					s,
+				);
+				self.db
+					.iter::<OutputIndexEntry>(&prefix)
+					.unwrap()
+					.map(|o| o.1)
+			})
+			.collect();
+		Box::new(
+			entries
+				.into_iter()
+				.filter_map(move |e| self.get_token(&e.key_id, &e.mmr_index).ok()),
+		)
+	}
+
 	fn get_tx_log_entry(&self, u: &Uuid) -> Result<Option<TxLogEntry>, Error> {
 		let key = to_key(TX_LOG_ENTRY_PREFIX, &mut u.as_bytes().to_vec());
 		self.db.get_ser(&key).map_err(|e| e.into())
@@ -371,6 +603,37 @@ where
 		Ok(ctx)
 	}
 
+	fn reservation_iter<'a>(&'a self) -> Box<dyn Iterator<Item = OutputReservation> + 'a> {
+		Box::new(self.db.iter(&[RESERVATION_PREFIX]).unwrap().map(|o| o.1))
+	}
+
+	fn get_reservation(&self, slate_id: &Uuid) -> Result<Option<OutputReservation>, Error> {
+		let key = to_key(RESERVATION_PREFIX, &mut slate_id.as_bytes().to_vec());
+		self.db.get_ser(&key).map_err(|e| e.into())
+	}
+
+	fn confirmed_block_iter<'a>(&'a self) -> Box<dyn Iterator<Item = ConfirmedBlock> + 'a> {
+		Box::new(
+			self.db
+				.iter(&[CONFIRMED_BLOCK_PREFIX])
+				.unwrap()
+				.map(|o| o.1),
+		)
+	}
+
+	fn get_confirmed_block(
+		&self,
+		parent_key_id: &Identifier,
+		height: u64,
+	) -> Result<Option<ConfirmedBlock>, Error> {
+		let key = to_key_u64(
+			CONFIRMED_BLOCK_PREFIX,
+			&mut parent_key_id.to_bytes().to_vec(),
+			height,
+		);
+		self.db.get_ser(&key).map_err(|e| e.into())
+	}
+
 	fn acct_path_iter<'a>(&'a self) -> Box<dyn Iterator<Item = AcctPathMapping> + 'a> {
 		Box::new(
 			self.db
@@ -511,6 +774,15 @@ where
 		};
 		Ok(status)
 	}
+
+	fn scan_checkpoint<'a>(&mut self) -> Result<Option<ScanCheckpoint>, Error> {
+		let batch = self.db.batch()?;
+		let checkpoint_key = to_key(
+			SCAN_CHECKPOINT,
+			&mut SCAN_CHECKPOINT_KEY.as_bytes().to_vec(),
+		);
+		Ok(batch.get_ser(&checkpoint_key)?)
+	}
 }
 
 /// An atomic batch in which all changes can be committed all at once or
@@ -537,26 +809,62 @@ where
 	}
 
 	fn save(&mut self, out: OutputData) -> Result<(), Error> {
+		// Move the index entry along with the status of an output saved before
+		if let Ok(old) = self.get(&out.key_id, &out.mmr_index) {
+			let _ = self
+				.db
+				.borrow()
+				.as_ref()
+				.unwrap()
+				.delete(&base_output_index_key(&old));
+		}
+
 		// Save the output data to the db.
 		{
 			let key = match out.mmr_index {
 				Some(i) => to_key_u64(OUTPUT_PREFIX, &mut out.key_id.to_bytes().to_vec(), i),
 				None => to_key(OUTPUT_PREFIX, &mut out.key_id.to_bytes().to_vec()),
 			};
-			self.db.borrow().as_ref().unwrap().put_ser(&key, &out)?;
+			let entry = OutputIndexEntry {
+				key_id: out.key_id.clone(),
+				mmr_index: out.mmr_index,
+			};
+			let db = self.db.borrow();
+			db.as_ref().unwrap().put_ser(&key, &out)?;
+			db.as_ref()
+				.unwrap()
+				.put_ser(&base_output_index_key(&out), &entry)?;
 		}
 
 		Ok(())
 	}
 
 	fn save_token(&mut self, out: TokenOutputData) -> Result<(), Error> {
+		// Move the index entry along with the status of an output saved before
+		if let Ok(old) = self.get_token(&out.key_id, &out.mmr_index) {
+			let _ = self
+				.db
+				.borrow()
+				.as_ref()
+				.unwrap()
+				.delete(&token_output_index_key(&old));
+		}
+
 		// Save the output data to the db.
 		{
 			let key = match out.mmr_index {
 				Some(i) => to_key_u64(TOKEN_OUTPUT_PREFIX, &mut out.key_id.to_bytes().to_vec(), i),
 				None => to_key(TOKEN_OUTPUT_PREFIX, &mut out.key_id.to_bytes().to_vec()),
 			};
-			self.db.borrow().as_ref().unwrap().put_ser(&key, &out)?;
+			let entry = OutputIndexEntry {
+				key_id: out.key_id.clone(),
+				mmr_index: out.mmr_index,
+			};
+			let db = self.db.borrow();
+			db.as_ref().unwrap().put_ser(&key, &out)?;
+			db.as_ref()
+				.unwrap()
+				.put_ser(&token_output_index_key(&out), &entry)?;
 		}
 
 		Ok(())
@@ -613,7 +921,15 @@ where
 	}
 
 	fn delete(&mut self, id: &Identifier, mmr_index: &Option<u64>) -> Result<(), Error> {
-		// Delete the output data.
+		// Delete the output data, and its index entry.
+		if let Ok(old) = self.get(id, mmr_index) {
+			let _ = self
+				.db
+				.borrow()
+				.as_ref()
+				.unwrap()
+				.delete(&base_output_index_key(&old));
+		}
 		{
 			let key = match mmr_index {
 				Some(i) => to_key_u64(OUTPUT_PREFIX, &mut id.to_bytes().to_vec(), *i),
@@ -626,7 +942,15 @@ where
 	}
 
 	fn token_delete(&mut self, id: &Identifier, mmr_index: &Option<u64>) -> Result<(), Error> {
-		// Delete the output data.
+		// Delete the output data, and its index entry.
+		if let Ok(old) = self.get_token(id, mmr_index) {
+			let _ = self
+				.db
+				.borrow()
+				.as_ref()
+				.unwrap()
+				.delete(&token_output_index_key(&old));
+		}
 		{
 			let key = match mmr_index {
 				Some(i) => to_key_u64(TOKEN_OUTPUT_PREFIX, &mut id.to_bytes().to_vec(), *i),
@@ -719,6 +1043,28 @@ where
 		Ok(())
 	}
 
+	fn save_scan_checkpoint(&mut self, checkpoint: ScanCheckpoint) -> Result<(), Error> {
+		let checkpoint_key = to_key(
+			SCAN_CHECKPOINT,
+			&mut SCAN_CHECKPOINT_KEY.as_bytes().to_vec(),
+		);
+		self.db
+			.borrow()
+			.as_ref()
+			.unwrap()
+			.put_ser(&checkpoint_key, &checkpoint)?;
+		Ok(())
+	}
+
+	fn delete_scan_checkpoint(&mut self) -> Result<(), Error> {
+		let checkpoint_key = to_key(
+			SCAN_CHECKPOINT,
+			&mut SCAN_CHECKPOINT_KEY.as_bytes().to_vec(),
+		);
+		let _ = self.db.borrow().as_ref().unwrap().delete(&checkpoint_key);
+		Ok(())
+	}
+
 	fn save_child_index(&mut self, parent_id: &Identifier, child_n: u32) -> Result<(), Error> {
 		let deriv_key = to_key(DERIV_PREFIX, &mut parent_id.to_bytes().to_vec());
 		self.db
@@ -800,6 +1146,14 @@ where
 		self.save_token(out.clone())
 	}
 
+	fn freeze_output(&mut self, commit: &str) -> Result<(), Error> {
+		set_output_frozen(self, commit, true)
+	}
+
+	fn unfreeze_output(&mut self, commit: &str) -> Result<(), Error> {
+		set_output_frozen(self, commit, false)
+	}
+
 	fn save_private_context(&mut self, slate_id: &[u8], ctx: &Context) -> Result<(), Error> {
 		let ctx_key = to_key_u64(PRIVATE_TX_CONTEXT_PREFIX, &mut slate_id.to_vec(), 0);
 		let (blind_xor_key, nonce_xor_key) = private_ctx_xor_keys(self.keychain(), slate_id)?;
@@ -828,6 +1182,50 @@ where
 			.map_err(|e| e.into())
 	}
 
+	fn save_reservation(&mut self, reservation: &OutputReservation) -> Result<(), Error> {
+		let key = to_key(
+			RESERVATION_PREFIX,
+			&mut reservation.slate_id.as_bytes().to_vec(),
+		);
+		self.db
+			.borrow()
+			.as_ref()
+			.unwrap()
+			.put_ser(&key, reservation)?;
+		Ok(())
+	}
+
+	fn delete_reservation(&mut self, slate_id: &Uuid) -> Result<(), Error> {
+		let key = to_key(RESERVATION_PREFIX, &mut slate_id.as_bytes().to_vec());
+		// the reservation may well be gone already
+		let _ = self.db.borrow().as_ref().unwrap().delete(&key);
+		Ok(())
+	}
+
+	fn save_confirmed_block(&mut self, block: &ConfirmedBlock) -> Result<(), Error> {
+		let key = to_key_u64(
+			CONFIRMED_BLOCK_PREFIX,
+			&mut block.parent_key_id.to_bytes().to_vec(),
+			block.height,
+		);
+		self.db.borrow().as_ref().unwrap().put_ser(&key, block)?;
+		Ok(())
+	}
+
+	fn delete_confirmed_block(
+		&mut self,
+		parent_key_id: &Identifier,
+		height: u64,
+	) -> Result<(), Error> {
+		let key = to_key_u64(
+			CONFIRMED_BLOCK_PREFIX,
+			&mut parent_key_id.to_bytes().to_vec(),
+			height,
+		);
+		let _ = self.db.borrow().as_ref().unwrap().delete(&key);
+		Ok(())
+	}
+
 	fn commit(&self) -> Result<(), Error> {
 		let db = self.db.replace(None);
 		db.unwrap().commit()?;
@@ -146,6 +146,38 @@ impl NodeClient for HTTPNodeClient {
 		Ok((result.height, result.last_block_pushed))
 	}
 
+	/// Get the hash of the main chain header at a height
+	fn get_header_hash(&self, height: u64) -> Result<Option<String>, libwallet::Error> {
+		let method = "get_header";
+		let params = json!([height, null, null]);
+		// have to handle this manually since the error needs to be parsed
+		let url = format!("{}{}", self.node_url(), ENDPOINT);
+		let client = Client::new();
+		let req = build_request(method, &params);
+		let res = client.post::<Request, Response>(url.as_str(), self.node_api_secret(), &req);
+
+		match res {
+			Err(e) => {
+				let report = format!("Error calling {}: {}", method, e);
+				error!("{}", report);
+				Err(libwallet::ErrorKind::ClientCallback(report).into())
+			}
+			Ok(inner) => match inner.clone().into_result::<api::BlockHeaderPrintable>() {
+				Ok(h) => Ok(Some(h.hash)),
+				Err(e) => {
+					let contents = format!("{:?}", inner);
+					if contents.contains("NotFound") {
+						Ok(None)
+					} else {
+						let report = format!("Unable to parse response for {}: {}", method, e);
+						error!("{}", report);
+						Err(libwallet::ErrorKind::ClientCallback(report).into())
+					}
+				}
+			},
+		}
+	}
+
 	/// Get kernel implementation
 	fn get_kernel(
 		&mut self,
@@ -19,6 +19,7 @@
 use crate::api::{self, LocatedTokenTxKernel, LocatedTxKernel};
 use crate::chain::types::NoopAdapter;
 use crate::chain::Chain;
+use crate::core::core::hash::Hashed;
 use crate::core::core::verifier_cache::LruVerifierCache;
 use crate::core::core::{TokenKey, TokenTxKernel, Transaction, TxKernel};
 use crate::core::global::{set_local_chain_type, ChainTypes};
@@ -156,6 +157,7 @@ where
 			trace!("Wallet Client Proxy Received: {:?}", m);
 			let resp = match m.method.as_ref() {
 				"get_chain_tip" => self.get_chain_tip(m)?,
+				"get_header_hash" => self.get_header_hash(m)?,
 				"get_outputs_from_node" => self.get_outputs_from_node(m)?,
 				"get_token_outputs_from_node" => self.get_token_outputs_from_node(m)?,
 				"get_outputs_by_pmmr_index" => self.get_outputs_by_pmmr_index(m)?,
@@ -229,8 +231,14 @@ where
 			let w = w_lock.lc_provider()?.wallet_inst()?;
 			let mask = wallet.2.clone();
 			// receive tx
-			match foreign::receive_tx(&mut **w, (&mask).as_ref(), &Slate::from(slate), None, false)
-			{
+			match foreign::receive_tx(
+				&mut **w,
+				(&mask).as_ref(),
+				&Slate::from(slate),
+				None,
+				None,
+				false,
+			) {
 				Err(e) => {
 					return Ok(WalletProxyMessage {
 						sender_id: m.dest,
@@ -267,6 +275,26 @@ where
 		})
 	}
 
+	/// get the hash of the header at a height
+	fn get_header_hash(
+		&mut self,
+		m: WalletProxyMessage,
+	) -> Result<WalletProxyMessage, libwallet::Error> {
+		let height = m.body.parse::<u64>().unwrap();
+		let hash = self
+			.chain
+			.get_header_by_height(height)
+			.ok()
+			.map(|h| h.hash().to_hex());
+
+		Ok(WalletProxyMessage {
+			sender_id: "node".to_owned(),
+			dest: m.sender_id,
+			method: m.method,
+			body: serde_json::to_string(&hash).unwrap(),
+		})
+	}
+
 	/// get api outputs
 	fn get_outputs_from_node(
 		&mut self,
@@ -598,6 +626,29 @@ impl NodeClient for LocalWalletClient {
 		Ok((split[0].parse::<u64>().unwrap(), split[1].to_owned()))
 	}
 
+	/// Return the hash of the header at a height from a given node
+	fn get_header_hash(&self, height: u64) -> Result<Option<String>, libwallet::Error> {
+		let m = WalletProxyMessage {
+			sender_id: self.id.clone(),
+			dest: self.node_url().to_owned(),
+			method: "get_header_hash".to_owned(),
+			body: format!("{}", height),
+		};
+		{
+			let p = self.proxy_tx.lock();
+			p.send(m).context(libwallet::ErrorKind::ClientCallback(
+				"Get header hash send".to_owned(),
+			))?;
+		}
+		let r = self.rx.lock();
+		let m = r.recv().unwrap();
+		trace!("Received get_header_hash response: {:?}", m.clone());
+		let res: Option<String> = serde_json::from_str(&m.body).context(
+			libwallet::ErrorKind::ClientCallback("Parsing get_header_hash response".to_owned()),
+		)?;
+		Ok(res)
+	}
+
 	/// Retrieve outputs from node
 	fn get_outputs_from_node(
 		&self,
@@ -24,8 +24,8 @@ use crate::internal::{selection, tx, updater};
 use crate::slate_versions::SlateVersion;
 use crate::TokenTxLogEntryType;
 use crate::{
-	address, BlockFees, CbData, Error, ErrorKind, NodeClient, Slate, SlateState, TxLogEntryType,
-	VersionInfo, WalletBackend,
+	address, BlockFees, CbData, Error, ErrorKind, NodeClient, Payout, Slate, SlateState,
+	SlateTransport, TxLogEntryType, VersionInfo, WalletBackend,
 };
 
 const FOREIGN_API_VERSION: u16 = 2;
@@ -53,12 +53,41 @@ where
 	updater::build_coinbase(&mut *w, keychain_mask, block_fees, test_mode)
 }
 
-/// Receive a tx as recipient
+/// Receive a tx as recipient, recording how the slate was exchanged if the
+/// caller knows the `transport`
 pub fn receive_tx<'a, T: ?Sized, C, K>(
 	w: &mut T,
 	keychain_mask: Option<&SecretKey>,
 	slate: &Slate,
 	dest_acct_name: Option<&str>,
+	transport: Option<SlateTransport>,
+	use_test_rng: bool,
+) -> Result<Slate, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	receive_payouts_tx(
+		w,
+		keychain_mask,
+		slate,
+		dest_acct_name,
+		&[],
+		transport,
+		use_test_rng,
+	)
+}
+
+/// Receive a batch payout as recipient, with an output for each of the payouts
+/// instead of a single output for the whole amount
+pub fn receive_payouts_tx<'a, T: ?Sized, C, K>(
+	w: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	slate: &Slate,
+	dest_acct_name: Option<&str>,
+	payouts: &[Payout],
+	transport: Option<SlateTransport>,
 	use_test_rng: bool,
 ) -> Result<Slate, Error>
 where
@@ -107,6 +136,10 @@ where
 		}
 	}
 
+	// don't trust the fee or token type the sender set
+	ret_slate.check_min_fee()?;
+	ret_slate.check_token_type()?;
+
 	// if this is compact mode, we need to create the transaction now
 	if ret_slate.is_compact() {
 		ret_slate.tx = Some(Transaction::empty());
@@ -121,7 +154,9 @@ where
 		&mut ret_slate,
 		height,
 		&parent_key_id,
+		payouts,
 		false,
+		transport,
 		use_test_rng,
 	)?;
 
@@ -176,7 +211,7 @@ where
 			let mut temp_ctx = context.clone();
 			temp_ctx.sec_key = context.initial_sec_key.clone();
 			temp_ctx.sec_nonce = context.initial_sec_nonce.clone();
-			selection::repopulate_tx(&mut *w, keychain_mask, &mut sl, &temp_ctx, false)?;
+			selection::repopulate_tx(&mut *w, keychain_mask, &mut sl, &temp_ctx, false, true)?;
 		}
 		tx::complete_tx(&mut *w, keychain_mask, &mut sl, &context)?;
 		tx::update_stored_tx(&mut *w, keychain_mask, &context, &mut sl, true)?;
@@ -22,27 +22,36 @@ use crate::grin_util::secp::key::SecretKey;
 use crate::grin_util::Mutex;
 use crate::util::{OnionV3Address, OnionV3AddressError};
 
-use crate::api_impl::owner_updater::StatusMessage;
+use crate::api_impl::owner_updater::{ScanProgress, StatusMessage};
 use crate::grin_keychain::{Identifier, Keychain};
-use crate::internal::{keys, scan, selection, tx, updater};
+use crate::internal::selection::{CoinSelector, SendParams, StrategySelector};
+use crate::internal::{export, keys, scan, selection, tx, updater};
 use crate::slate::{PaymentInfo, Slate, SlateState};
-use crate::types::{AcctPathMapping, NodeClient, TxLogEntry, WalletBackend, WalletInfo};
+use crate::types::{
+	AcctPathMapping, FeeEstimate, NodeClient, SelectionStrategy, SlateTransport, TxLogEntry,
+	TxPreview, WalletBackend, WalletInfo, WalletTokenInfo,
+};
 use crate::{
 	address, wallet_lock, InitTxArgs, IssueInvoiceTxArgs, NodeHeightResult, OutputCommitMapping,
-	PaymentProof, ScannedBlockInfo, Slatepack, SlatepackAddress, Slatepacker, SlatepackerArgs,
-	TxLogEntryType, WalletInitStatus, WalletInst, WalletLCProvider,
+	PaymentProof, ScanCheckpoint, ScannedBlockInfo, Slatepack, SlatepackAddress, Slatepacker,
+	SlatepackerArgs, TxExportArgs, TxLogEntryType, TxNoteFilter, WalletInitStatus, WalletInst,
+	WalletLCProvider, MAX_TX_LABELS, MAX_TX_LABEL_LEN, MAX_TX_NOTE_LEN,
 };
 use crate::{Error, ErrorKind};
 use ed25519_dalek::PublicKey as DalekPublicKey;
 use ed25519_dalek::SecretKey as DalekSecretKey;
 
 use std::convert::TryFrom;
+use std::io::Write;
+use std::sync::atomic::AtomicBool;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
 
 use crate::internal::token_scan;
 use crate::types::{TokenTxLogEntry, TokenTxLogEntryType};
-use crate::{IssueTokenArgs, TokenOutputCommitMapping};
+use crate::{
+	ConsolidateArgs, IssueTokenArgs, SlateActivity, SplitOutputsArgs, TokenOutputCommitMapping,
+};
 
 /// List of accounts
 pub fn accounts<'a, T: ?Sized, C, K>(w: &mut T) -> Result<Vec<AcctPathMapping>, Error>
@@ -68,6 +77,21 @@ where
 	keys::new_acct_path(&mut *w, keychain_mask, label)
 }
 
+/// derive change of an account on its own branch, or alongside received outputs
+pub fn set_account_change_branch<'a, T: ?Sized, C, K>(
+	w: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	label: &str,
+	enabled: bool,
+) -> Result<(), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	keys::set_change_branch(&mut *w, keychain_mask, label, enabled)
+}
+
 /// set active account
 pub fn set_active_account<'a, T: ?Sized, C, K>(w: &mut T, label: &str) -> Result<(), Error>
 where
@@ -330,7 +354,7 @@ where
 	))
 }
 
-/// Retrieve txs
+/// Retrieve txs, only those whose note and labels pass `note_filter` if given
 pub fn retrieve_txs<'a, L, C, K>(
 	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
 	keychain_mask: Option<&SecretKey>,
@@ -338,6 +362,7 @@ pub fn retrieve_txs<'a, L, C, K>(
 	refresh_from_node: bool,
 	tx_id: Option<u32>,
 	tx_slate_id: Option<Uuid>,
+	note_filter: Option<&TxNoteFilter>,
 ) -> Result<(bool, Vec<TxLogEntry>), Error>
 where
 	L: WalletLCProvider<'a, C, K>,
@@ -357,12 +382,15 @@ where
 
 	wallet_lock!(wallet_inst, w);
 	let parent_key_id = w.parent_key_id();
-	let txs = updater::retrieve_txs(&mut **w, tx_id, tx_slate_id, Some(&parent_key_id), false)?;
+	let mut txs = updater::retrieve_txs(&mut **w, tx_id, tx_slate_id, Some(&parent_key_id), false)?;
+	if let Some(f) = note_filter {
+		txs.retain(|t| f.matches(&t.note, &t.labels));
+	}
 
 	Ok((validated, txs))
 }
 
-/// Retrieve token txs
+/// Retrieve token txs, only those whose note and labels pass `note_filter` if given
 pub fn retrieve_token_txs<'a, L, C, K>(
 	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
 	keychain_mask: Option<&SecretKey>,
@@ -370,6 +398,7 @@ pub fn retrieve_token_txs<'a, L, C, K>(
 	refresh_from_node: bool,
 	tx_id: Option<u32>,
 	tx_slate_id: Option<Uuid>,
+	note_filter: Option<&TxNoteFilter>,
 ) -> Result<(bool, Vec<TokenTxLogEntry>), Error>
 where
 	L: WalletLCProvider<'a, C, K>,
@@ -389,12 +418,78 @@ where
 
 	wallet_lock!(wallet_inst, w);
 	let parent_key_id = w.parent_key_id();
-	let txs =
+	let mut txs =
 		updater::retrieve_token_txs(&mut **w, tx_id, tx_slate_id, Some(&parent_key_id), false)?;
+	if let Some(f) = note_filter {
+		txs.retain(|t| f.matches(&t.note, &t.labels));
+	}
 
 	Ok((validated, txs))
 }
 
+/// Retrieve what each slate moved of both assets
+pub fn retrieve_slate_activity<'a, L, C, K>(
+	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+	keychain_mask: Option<&SecretKey>,
+	status_send_channel: &Option<Sender<StatusMessage>>,
+	refresh_from_node: bool,
+	tx_slate_id: Option<Uuid>,
+) -> Result<(bool, Vec<SlateActivity>), Error>
+where
+	L: WalletLCProvider<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let validated = if refresh_from_node {
+		update_wallet_state(
+			wallet_inst.clone(),
+			keychain_mask,
+			status_send_channel,
+			false,
+		)?
+	} else {
+		false
+	};
+
+	wallet_lock!(wallet_inst, w);
+	let parent_key_id = w.parent_key_id();
+	let activity = updater::retrieve_slate_activity(&mut **w, tx_slate_id, Some(&parent_key_id))?;
+
+	Ok((validated, activity))
+}
+
+/// Export the tx history
+pub fn export_txs<'a, L, C, K>(
+	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+	keychain_mask: Option<&SecretKey>,
+	status_send_channel: &Option<Sender<StatusMessage>>,
+	refresh_from_node: bool,
+	args: &TxExportArgs,
+	writer: &mut dyn Write,
+) -> Result<(bool, usize), Error>
+where
+	L: WalletLCProvider<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let validated = if refresh_from_node {
+		update_wallet_state(
+			wallet_inst.clone(),
+			keychain_mask,
+			status_send_channel,
+			false,
+		)?
+	} else {
+		false
+	};
+
+	wallet_lock!(wallet_inst, w);
+	let parent_key_id = w.parent_key_id();
+	let rows = export::export_txs(&mut **w, &parent_key_id, args, writer)?;
+
+	Ok((validated, rows))
+}
+
 /// Retrieve summary info
 pub fn retrieve_summary_info<'a, L, C, K>(
 	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
@@ -425,6 +520,42 @@ where
 	Ok((validated, wallet_info))
 }
 
+/// Retrieve token summary info
+pub fn retrieve_token_summary_info<'a, L, C, K>(
+	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+	keychain_mask: Option<&SecretKey>,
+	status_send_channel: &Option<Sender<StatusMessage>>,
+	refresh_from_node: bool,
+	minimum_confirmations: u64,
+) -> Result<(bool, Vec<WalletTokenInfo>), Error>
+where
+	L: WalletLCProvider<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let validated = if refresh_from_node {
+		update_wallet_state(
+			wallet_inst.clone(),
+			keychain_mask,
+			status_send_channel,
+			false,
+		)?
+	} else {
+		false
+	};
+
+	wallet_lock!(wallet_inst, w);
+	let parent_key_id = w.parent_key_id();
+	let current_height = w.last_confirmed_height()?;
+	let token_infos = updater::retrieve_token_info(
+		&mut **w,
+		&parent_key_id,
+		current_height,
+		minimum_confirmations,
+	);
+	Ok((validated, token_infos))
+}
+
 /// Initiate issue token tx
 pub fn init_issue_token_tx<'a, T: ?Sized, C, K>(
 	w: &mut T,
@@ -451,20 +582,145 @@ where
 	let mut slate = tx::new_tx_slate(&mut *w, args.amount, None, false, 2, use_test_rng, None)?;
 
 	let height = w.w2n_client().get_chain_tip()?.0;
-	let context = tx::fill_tx_to_slate(
+	let (context, _token_type) = tx::fill_tx_to_slate(
 		&mut *w,
 		keychain_mask,
 		&mut slate,
+		args.max_supply,
 		height,
 		1,
 		1,
 		1,
+		SelectionStrategy::SmallestFirst,
+		&parent_key_id,
+		use_test_rng,
+	)?;
+
+	selection::lock_tx_context(
+		&mut *w,
+		keychain_mask,
+		&slate,
+		height,
+		&context,
+		None,
+		None,
+		None,
+	)?;
+	Ok(slate)
+}
+
+/// Consolidate small outputs into one
+pub fn init_consolidate_tx<'a, T: ?Sized, C, K>(
+	w: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	args: ConsolidateArgs,
+	use_test_rng: bool,
+) -> Result<Slate, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let parent_key_id = match args.acct_name {
+		Some(d) => {
+			let pm = w.get_acct_path(d)?;
+			match pm {
+				Some(p) => p.path,
+				None => w.parent_key_id(),
+			}
+		}
+		None => w.parent_key_id(),
+	};
+
+	let mut slate = tx::new_tx_slate(&mut *w, 0, None, false, 2, use_test_rng, None)?;
+
+	let height = w.w2n_client().get_chain_tip()?.0;
+	let context = tx::consolidate_to_slate(
+		&mut *w,
+		keychain_mask,
+		&mut slate,
+		height,
+		args.minimum_confirmations,
+		args.max_outputs as usize,
+		args.min_outputs as usize,
+		args.below_value,
+		args.fee_base,
+		&parent_key_id,
+		use_test_rng,
+	)?;
+
+	selection::lock_tx_context(
+		&mut *w,
+		keychain_mask,
+		&slate,
+		height,
+		&context,
+		None,
+		None,
+		None,
+	)?;
+	Ok(slate)
+}
+
+/// Split large outputs into several smaller ones
+pub fn init_split_outputs_tx<'a, T: ?Sized, C, K>(
+	w: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	args: SplitOutputsArgs,
+	use_test_rng: bool,
+) -> Result<Slate, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let parent_key_id = match args.acct_name {
+		Some(d) => {
+			let pm = w.get_acct_path(d)?;
+			match pm {
+				Some(p) => p.path,
+				None => w.parent_key_id(),
+			}
+		}
+		None => w.parent_key_id(),
+	};
+
+	let mut slate = tx::new_tx_slate(
+		&mut *w,
+		0,
+		args.token_type.clone(),
 		false,
+		2,
+		use_test_rng,
+		None,
+	)?;
+
+	let height = w.w2n_client().get_chain_tip()?.0;
+	let context = tx::split_to_slate(
+		&mut *w,
+		keychain_mask,
+		&mut slate,
+		height,
+		args.minimum_confirmations,
+		args.max_outputs as usize,
+		args.num_outputs as usize,
+		args.amount,
+		args.denominations,
+		args.fee_base,
 		&parent_key_id,
 		use_test_rng,
 	)?;
 
-	selection::lock_tx_context(&mut *w, keychain_mask, &slate, height, &context, None)?;
+	selection::lock_tx_context(
+		&mut *w,
+		keychain_mask,
+		&slate,
+		height,
+		&context,
+		None,
+		None,
+		None,
+	)?;
 	Ok(slate)
 }
 
@@ -505,6 +761,7 @@ where
 		false,
 		tx_id,
 		tx_slate_id,
+		None,
 	)?;
 	let token_txs = retrieve_token_txs(
 		wallet_inst.clone(),
@@ -513,6 +770,7 @@ where
 		false,
 		tx_id,
 		tx_slate_id,
+		None,
 	)?;
 	if txs.1.len() != 1 && token_txs.1.len() != 1 {
 		return Err(ErrorKind::PaymentProofRetrieval("Transaction doesn't exist".into()).into());
@@ -631,6 +889,215 @@ where
 	}
 }
 
+/// Freeze or unfreeze an output, keeping it out of automatic coin selection
+pub fn set_output_frozen<'a, T: ?Sized, C, K>(
+	w: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	commit: &str,
+	frozen: bool,
+) -> Result<(), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let mut batch = w.batch(keychain_mask)?;
+	if frozen {
+		batch.freeze_output(commit)?;
+	} else {
+		batch.unfreeze_output(commit)?;
+	}
+	batch.commit()?;
+	Ok(())
+}
+
+/// Keep a note and labels with a transaction, replacing any it had. Every tx log
+/// entry of the transaction is updated, so a token transaction keeps the note on
+/// both its token entry and the entry for its fee.
+pub fn set_tx_note<'a, T: ?Sized, C, K>(
+	w: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	tx_id: Option<u32>,
+	tx_slate_id: Option<Uuid>,
+	note: Option<String>,
+	labels: Vec<String>,
+) -> Result<(), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	check_tx_note(&note, &labels)?;
+	let tx_id_string = match (tx_id, tx_slate_id) {
+		(Some(i), _) => i.to_string(),
+		(None, Some(s)) => s.to_string(),
+		(None, None) => {
+			return Err(ErrorKind::GenericError(
+				"Either a transaction id or a slate id must be provided".to_owned(),
+			)
+			.into())
+		}
+	};
+	let parent_key_id = w.parent_key_id();
+	let txs = updater::retrieve_txs(w, tx_id, tx_slate_id, Some(&parent_key_id), false)?;
+	let token_txs =
+		updater::retrieve_token_txs(w, tx_id, tx_slate_id, Some(&parent_key_id), false)?;
+	if txs.is_empty() && token_txs.is_empty() {
+		return Err(ErrorKind::TransactionDoesntExist(tx_id_string).into());
+	}
+
+	let mut batch = w.batch(keychain_mask)?;
+	for mut t in txs {
+		t.note = note.clone();
+		t.labels = labels.clone();
+		batch.save_tx_log_entry(t, &parent_key_id)?;
+	}
+	for mut t in token_txs {
+		t.note = note.clone();
+		t.labels = labels.clone();
+		batch.save_token_tx_log_entry(t, &parent_key_id)?;
+	}
+	batch.commit()?;
+	Ok(())
+}
+
+/// Check a note and labels fit within the limits, and hold nothing that would
+/// garble the tx log when displayed
+fn check_tx_note(note: &Option<String>, labels: &[String]) -> Result<(), Error> {
+	if let Some(n) = note {
+		if n.len() > MAX_TX_NOTE_LEN {
+			return Err(ErrorKind::InvalidTxNote(format!(
+				"note of {} bytes exceeds the limit of {}",
+				n.len(),
+				MAX_TX_NOTE_LEN
+			))
+			.into());
+		}
+		if n.chars().any(|c| c.is_control() && c != '\n') {
+			return Err(ErrorKind::InvalidTxNote(
+				"note can't contain control characters".to_owned(),
+			)
+			.into());
+		}
+	}
+	if labels.len() > MAX_TX_LABELS {
+		return Err(ErrorKind::InvalidTxNote(format!(
+			"{} labels exceed the limit of {}",
+			labels.len(),
+			MAX_TX_LABELS
+		))
+		.into());
+	}
+	for l in labels {
+		if l.is_empty() || l.len() > MAX_TX_LABEL_LEN {
+			return Err(ErrorKind::InvalidTxNote(format!(
+				"labels must be between 1 and {} bytes",
+				MAX_TX_LABEL_LEN
+			))
+			.into());
+		}
+		if l.chars().any(char::is_control) {
+			return Err(ErrorKind::InvalidTxNote(
+				"labels can't contain control characters".to_owned(),
+			)
+			.into());
+		}
+	}
+	Ok(())
+}
+
+/// Estimate the inputs, change and fee of a send, without building or locking anything
+pub fn estimate_fee<'a, T: ?Sized, C, K>(w: &mut T, args: InitTxArgs) -> Result<FeeEstimate, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let parent_key_id = match args.src_acct_name {
+		Some(ref d) => {
+			let pm = w.get_acct_path(d.to_owned())?;
+			match pm {
+				Some(p) => p.path,
+				None => w.parent_key_id(),
+			}
+		}
+		None => w.parent_key_id(),
+	};
+
+	let (amount, recipient_outputs) = args.payout_amount()?;
+	tx::estimate_fee(
+		&mut *w,
+		amount,
+		args.token_type.clone(),
+		args.token_fee,
+		&args.send_params(recipient_outputs)?,
+		&parent_key_id,
+	)
+}
+
+/// Preview the exact inputs, change and fee of a send, without building or locking anything
+pub fn preview_tx<'a, T: ?Sized, C, K>(w: &mut T, args: InitTxArgs) -> Result<TxPreview, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let parent_key_id = match args.src_acct_name.clone() {
+		Some(ref d) => {
+			let pm = w.get_acct_path(d.to_owned())?;
+			match pm {
+				Some(p) => p.path,
+				None => w.parent_key_id(),
+			}
+		}
+		None => w.parent_key_id(),
+	};
+
+	let (amount, recipient_outputs) = args.payout_amount()?;
+	tx::preview_tx(
+		&mut *w,
+		amount,
+		args.token_type.clone(),
+		args.token_fee,
+		&args.send_params(recipient_outputs)?,
+		&parent_key_id,
+	)
+}
+
+/// Preview the inputs, change and fee of a token issuance, without building or locking anything
+pub fn preview_issue_token_tx<'a, T: ?Sized, C, K>(
+	w: &mut T,
+	args: IssueTokenArgs,
+) -> Result<TxPreview, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let parent_key_id = match args.acct_name {
+		Some(d) => {
+			let pm = w.get_acct_path(d)?;
+			match pm {
+				Some(p) => p.path,
+				None => w.parent_key_id(),
+			}
+		}
+		None => w.parent_key_id(),
+	};
+
+	// the same selection init_issue_token_tx makes
+	tx::preview_issue_token_tx(
+		&mut *w,
+		args.amount,
+		args.max_supply,
+		1,
+		1,
+		1,
+		SelectionStrategy::SmallestFirst,
+		&parent_key_id,
+	)
+}
+
 /// Initiate tx as sender
 pub fn init_send_tx<'a, T: ?Sized, C, K>(
 	w: &mut T,
@@ -643,7 +1110,25 @@ where
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
 {
-	let parent_key_id = match args.src_acct_name {
+	init_send_tx_with_selector(w, keychain_mask, args, &StrategySelector, use_test_rng)
+}
+
+/// Initiate tx as sender, with the inputs picked by the given coin selector
+/// rather than by the selection strategy alone. Estimates and late locked
+/// sends still select by strategy.
+pub fn init_send_tx_with_selector<'a, T: ?Sized, C, K>(
+	w: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	args: InitTxArgs,
+	coin_selector: &dyn CoinSelector,
+	use_test_rng: bool,
+) -> Result<Slate, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let parent_key_id = match args.src_acct_name.clone() {
 		Some(d) => {
 			let pm = w.get_acct_path(d)?;
 			match pm {
@@ -654,10 +1139,19 @@ where
 		None => w.parent_key_id(),
 	};
 
+	let extra_parent_key_ids =
+		extra_src_acct_key_ids(&mut *w, &args, args.token_type.is_some(), &parent_key_id)?;
+	let change_parent_key_id = match change_acct_key_id(&mut *w, &args)? {
+		None if !extra_parent_key_ids.is_empty() => Some(parent_key_id.clone()),
+		c => c,
+	};
+
+	let (amount, recipient_outputs) = args.payout_amount()?;
+	let memo = args.checked_memo()?;
 	let mut slate = tx::new_tx_slate(
 		&mut *w,
-		args.amount,
-		args.token_type,
+		amount,
+		args.token_type.clone(),
 		false,
 		2,
 		use_test_rng,
@@ -670,57 +1164,72 @@ where
 
 	// if we just want to estimate, don't save a context, just send the results
 	// back
+	let params = args.send_params(recipient_outputs)?;
 	if let Some(true) = args.estimate_only {
-		let (total, fee) = tx::estimate_send_tx(
-			&mut *w,
-			keychain_mask,
-			args.amount,
-			args.minimum_confirmations,
-			args.max_outputs as usize,
-			args.num_change_outputs as usize,
-			args.selection_strategy_is_use_all,
-			&parent_key_id,
-		)?;
+		let (total, fee) =
+			tx::estimate_send_tx(&mut *w, keychain_mask, amount, &params, &parent_key_id)?;
 		slate.amount = total;
 		slate.fee = fee;
 		return Ok(slate);
 	}
 
-	let height = w.w2n_client().get_chain_tip()?.0;
-	let mut context = tx::add_inputs_to_slate(
-		&mut *w,
-		keychain_mask,
-		&mut slate,
-		height,
-		args.minimum_confirmations,
-		args.max_outputs as usize,
-		args.num_change_outputs as usize,
-		args.selection_strategy_is_use_all,
-		&parent_key_id,
-		true,
-		use_test_rng,
-	)?;
-
-	// Payment Proof, add addresses to slate and save address
+	// Payment Proof, add addresses to slate, building the transaction
+	// reserves the address on our context
 	// TODO: Note we only use single derivation path for now,
 	// probably want to allow sender to specify which one
-	let deriv_path = 0u32;
-
-	if let Some(a) = args.payment_proof_recipient_address {
+	if let Some(a) = args.payment_proof_recipient_address.clone() {
 		let k = w.keychain(keychain_mask)?;
 
-		let sec_addr_key = address::address_from_derivation_path(&k, &parent_key_id, deriv_path)?;
+		let sec_addr_key = address::address_from_derivation_path(
+			&k,
+			&parent_key_id,
+			selection::PAYMENT_PROOF_DERIVATION_INDEX,
+		)?;
 		let sender_address = OnionV3Address::from_private(&sec_addr_key.0)?;
 
 		slate.payment_proof = Some(PaymentInfo {
 			sender_address: sender_address.to_ed25519()?,
 			receiver_address: a.pub_key,
 			receiver_signature: None,
+			sender_signature: None,
 		});
-
-		context.payment_proof_derivation_index = Some(deriv_path);
 	}
 
+	let height = w.w2n_client().get_chain_tip()?.0;
+	let mut context = if args.late_lock {
+		// only estimate the fee for now, inputs are selected when finalizing
+		let late_lock_args = args.late_lock_args(recipient_outputs, change_parent_key_id)?;
+		let (_, fee) =
+			tx::estimate_send_tx(&mut *w, keychain_mask, amount, &params, &parent_key_id)?;
+		tx::create_late_lock_context(
+			&mut *w,
+			keychain_mask,
+			&mut slate,
+			fee,
+			late_lock_args,
+			&parent_key_id,
+			use_test_rng,
+		)?
+	} else {
+		tx::add_inputs_to_slate(
+			&mut *w,
+			keychain_mask,
+			&mut slate,
+			height,
+			&params,
+			coin_selector,
+			args.token_fee,
+			args.verify_balance,
+			&parent_key_id,
+			&extra_parent_key_ids,
+			change_parent_key_id.as_ref(),
+			true,
+			use_test_rng,
+		)?
+	};
+	context.payouts = args.payouts.clone().unwrap_or_default();
+	context.memo = memo;
+
 	// Save the aggsig context in our DB for when we
 	// recieve the transaction back
 	{
@@ -736,6 +1245,57 @@ where
 	Ok(slate)
 }
 
+/// The parent to derive change outputs under, if the args name a change account
+fn change_acct_key_id<'a, T: ?Sized, C, K>(
+	w: &mut T,
+	args: &InitTxArgs,
+) -> Result<Option<Identifier>, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	match args.change_acct_name.clone() {
+		Some(d) => match w.get_acct_path(d.clone())? {
+			Some(p) => Ok(Some(p.path)),
+			None => Err(ErrorKind::UnknownAccountLabel(d).into()),
+		},
+		None => Ok(None),
+	}
+}
+
+/// The further accounts to draw inputs from, if the args name any besides the
+/// sending account
+fn extra_src_acct_key_ids<'a, T: ?Sized, C, K>(
+	w: &mut T,
+	args: &InitTxArgs,
+	is_token: bool,
+	parent_key_id: &Identifier,
+) -> Result<Vec<Identifier>, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let mut ids: Vec<Identifier> = vec![];
+	for d in args.extra_src_acct_names.clone().unwrap_or_default() {
+		let id = match w.get_acct_path(d.clone())? {
+			Some(p) => p.path,
+			None => return Err(ErrorKind::UnknownAccountLabel(d).into()),
+		};
+		if id != *parent_key_id && !ids.contains(&id) {
+			ids.push(id);
+		}
+	}
+	if is_token && !ids.is_empty() {
+		return Err(ErrorKind::GenericError(
+			"token sends can only draw outputs from a single account".to_owned(),
+		)
+		.into());
+	}
+	Ok(ids)
+}
+
 /// Initiate a transaction as the recipient (invoicing)
 pub fn issue_invoice_tx<'a, T: ?Sized, C, K>(
 	w: &mut T,
@@ -769,16 +1329,38 @@ where
 		None,
 	)?;
 	let height = w.w2n_client().get_chain_tip()?.0;
-	let context = tx::add_output_to_slate(
+	let mut context = tx::add_output_to_slate(
 		&mut *w,
 		keychain_mask,
 		&mut slate,
 		height,
 		&parent_key_id,
+		&[],
 		true,
+		None,
 		use_test_rng,
 	)?;
 
+	// Payment Proof, with the roles swapped: the payer signs as the sender
+	// and we sign as the recipient when finalizing
+	let deriv_path = 0u32;
+
+	if let Some(a) = args.payment_proof_sender_address {
+		let k = w.keychain(keychain_mask)?;
+
+		let sec_addr_key = address::address_from_derivation_path(&k, &parent_key_id, deriv_path)?;
+		let receiver_address = OnionV3Address::from_private(&sec_addr_key.0)?;
+
+		let proof = PaymentInfo {
+			sender_address: a.pub_key,
+			receiver_address: receiver_address.to_ed25519()?,
+			receiver_signature: None,
+			sender_signature: None,
+		};
+		context.set_payment_proof(&proof, deriv_path);
+		slate.payment_proof = Some(proof);
+	}
+
 	if let Some(v) = args.target_slate_version {
 		slate.version_info.version = v;
 	};
@@ -814,7 +1396,7 @@ where
 {
 	let mut ret_slate = slate.clone();
 	check_ttl(w, &ret_slate)?;
-	let parent_key_id = match args.src_acct_name {
+	let parent_key_id = match args.src_acct_name.clone() {
 		Some(d) => {
 			let pm = w.get_acct_path(d)?;
 			match pm {
@@ -824,6 +1406,16 @@ where
 		}
 		None => w.parent_key_id(),
 	};
+	let extra_parent_key_ids = extra_src_acct_key_ids(
+		&mut *w,
+		&args,
+		ret_slate.token_type.is_some(),
+		&parent_key_id,
+	)?;
+	let change_parent_key_id = match change_acct_key_id(&mut *w, &args)? {
+		None if !extra_parent_key_ids.is_empty() => Some(parent_key_id.clone()),
+		c => c,
+	};
 	// Don't do this multiple times
 	if slate.token_type.clone().is_some() {
 		let tx = updater::retrieve_token_txs(
@@ -868,16 +1460,24 @@ where
 	// if self sending, make sure to store 'initiator' keys
 	let context_res = w.get_private_context(keychain_mask, slate.id.as_bytes());
 
+	// an invoice names the amount, so it's paid as it is to a single output
+	let params = SendParams {
+		send_all: false,
+		amount_includes_fee: false,
+		..args.send_params(1)?
+	};
 	let mut context = tx::add_inputs_to_slate(
 		&mut *w,
 		keychain_mask,
 		&mut ret_slate,
 		height,
-		args.minimum_confirmations,
-		args.max_outputs as usize,
-		args.num_change_outputs as usize,
-		args.selection_strategy_is_use_all,
+		&params,
+		&StrategySelector,
+		0,
+		args.verify_balance,
 		&parent_key_id,
+		&extra_parent_key_ids,
+		change_parent_key_id.as_ref(),
 		false,
 		use_test_rng,
 	)?;
@@ -906,7 +1506,39 @@ where
 	// TODO: Revisit post-HF3
 	if ret_slate.is_compact() {
 		tx::sub_inputs_from_offset(&mut *w, keychain_mask, &context, &mut ret_slate)?;
-		selection::repopulate_tx(&mut *w, keychain_mask, &mut ret_slate, &context, false)?;
+		selection::repopulate_tx(
+			&mut *w,
+			keychain_mask,
+			&mut ret_slate,
+			&context,
+			false,
+			false,
+		)?;
+	}
+
+	// Payment Proof, sign as the sender now that the excess is known. The
+	// invoicer signs as the recipient when finalizing.
+	let excess = ret_slate.calc_excess(keychain.secp())?;
+	if let Some(ref mut p) = ret_slate.payment_proof {
+		let deriv_path = 0u32;
+		let sender_key =
+			address::address_from_derivation_path(&keychain, &parent_key_id, deriv_path)?;
+		let sender_address = OnionV3Address::from_private(&sender_key.0)?;
+		if p.sender_address != sender_address.to_ed25519()? {
+			return Err(ErrorKind::PaymentProof(
+				"Sender address of invoice payment proof doesn't belong to this wallet".to_owned(),
+			)
+			.into());
+		}
+		let sig = tx::create_payment_proof_signature(
+			ret_slate.token_type.clone(),
+			ret_slate.amount,
+			&excess,
+			p.sender_address,
+			sender_key,
+		)?;
+		p.sender_signature = Some(sig);
+		context.set_payment_proof(p, deriv_path);
 	}
 
 	// Save the aggsig context in our DB for when we
@@ -932,11 +1564,13 @@ where
 	Ok(ret_slate)
 }
 
-/// Lock sender outputs
+/// Lock sender outputs, recording how the slate was exchanged if the caller
+/// knows the `transport`
 pub fn tx_lock_outputs<'a, T: ?Sized, C, K>(
 	w: &mut T,
 	keychain_mask: Option<&SecretKey>,
 	slate: &Slate,
+	transport: Option<SlateTransport>,
 ) -> Result<(), Error>
 where
 	T: WalletBackend<'a, C, K>,
@@ -944,12 +1578,16 @@ where
 	K: Keychain + 'a,
 {
 	let context = w.get_private_context(keychain_mask, slate.id.as_bytes())?;
+	// late locked transactions lock their inputs when they're finalized
+	if context.late_lock_args.is_some() {
+		return Ok(());
+	}
 	let mut sl = slate.clone();
 	let mut excess_override = None;
 	if sl.is_compact() && sl.tx == None {
 		// attempt to repopulate if we're the initiator
 		sl.tx = Some(Transaction::empty());
-		selection::repopulate_tx(&mut *w, keychain_mask, &mut sl, &context, true)?;
+		selection::repopulate_tx(&mut *w, keychain_mask, &mut sl, &context, true, false)?;
 	} else if sl.participant_data.len() == 1 {
 		// purely for invoice workflow, payer needs the excess back temporarily for storage
 		excess_override = context.calculated_excess;
@@ -962,6 +1600,39 @@ where
 		height,
 		&context,
 		excess_override,
+		transport,
+		None,
+	)
+}
+
+/// Reserve sender outputs for the given number of blocks instead of locking
+/// them, keeping them out of other sends while the slate is looked over
+pub fn tx_reserve_outputs<'a, T: ?Sized, C, K>(
+	w: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	slate: &Slate,
+	reserve_blocks: u64,
+) -> Result<(), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let context = w.get_private_context(keychain_mask, slate.id.as_bytes())?;
+	// late locked transactions select their inputs when they're finalized
+	if context.late_lock_args.is_some() {
+		return Ok(());
+	}
+	let height = w.w2n_client().get_chain_tip()?.0;
+	selection::lock_tx_context(
+		&mut *w,
+		keychain_mask,
+		slate,
+		height,
+		&context,
+		None,
+		None,
+		Some(reserve_blocks),
 	)
 }
 
@@ -978,18 +1649,33 @@ where
 {
 	let mut sl = slate.clone();
 	check_ttl(w, &sl)?;
-	let context = w.get_private_context(keychain_mask, sl.id.as_bytes())?;
+	let mut context = w.get_private_context(keychain_mask, sl.id.as_bytes())?;
 	let parent_key_id = w.parent_key_id();
 
+	// a late locked transaction only selects and locks its inputs now
+	if let Some(late_lock_args) = context.late_lock_args.take() {
+		tx::select_late_lock_inputs(
+			&mut *w,
+			keychain_mask,
+			&mut sl,
+			&mut context,
+			&late_lock_args,
+		)?;
+		{
+			let mut batch = w.batch(keychain_mask)?;
+			batch.save_private_context(sl.id.as_bytes(), &context)?;
+			batch.commit()?;
+		}
+		tx_lock_outputs(&mut *w, keychain_mask, &sl, None)?;
+	}
+
 	// since we're now actually inserting our inputs, pick an offset and adjust
 	// our contribution to the excess by offset amount
-	// TODO: Post HF3, this should allow for inputs to be picked at this stage
-	// as opposed to locking them prior to this stage, as the excess to this point
-	// will just be the change output
 
 	if sl.is_compact() {
 		tx::sub_inputs_from_offset(&mut *w, keychain_mask, &context, &mut sl)?;
-		selection::repopulate_tx(&mut *w, keychain_mask, &mut sl, &context, true)?;
+		// our outputs are stored by now, as the transaction is locked
+		selection::repopulate_tx(&mut *w, keychain_mask, &mut sl, &context, true, true)?;
 	}
 
 	tx::complete_tx(&mut *w, keychain_mask, &mut sl, &context)?;
@@ -1109,12 +1795,15 @@ where
 
 /// check repair
 /// Accepts a wallet inst instead of a raw wallet so it can
-/// lock as little as possible
+/// lock as little as possible. Without a start height, a scan cancelled
+/// earlier is resumed from where it stopped, while giving one always rescans
+/// from there. Raising `cancel` stops the scan, storing where it got to.
 pub fn scan<'a, L, C, K>(
 	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
 	keychain_mask: Option<&SecretKey>,
 	start_height: Option<u64>,
 	delete_unconfirmed: bool,
+	cancel: Option<&AtomicBool>,
 	status_send_channel: &Option<Sender<StatusMessage>>,
 ) -> Result<(), Error>
 where
@@ -1128,38 +1817,108 @@ where
 		w.w2n_client().get_chain_tip()?
 	};
 
-	let start_height = match start_height {
-		Some(h) => h,
-		None => 0,
+	let checkpoint = {
+		wallet_lock!(wallet_inst, w);
+		match start_height {
+			Some(_) => {
+				let mut batch = w.batch(keychain_mask)?;
+				batch.delete_scan_checkpoint()?;
+				batch.commit()?;
+				None
+			}
+			None => w.scan_checkpoint()?,
+		}
+	};
+
+	let start_height = match (&checkpoint, start_height) {
+		(Some(c), _) => c.start_height,
+		(None, Some(h)) => h,
+		(None, None) => 0,
+	};
+	let (resume_index, resume_token_index) = match checkpoint {
+		Some(c) => (c.last_pmmr_index, c.last_token_pmmr_index),
+		None => (None, None),
 	};
 
+	let mut progress = ScanProgress::default();
 	let mut info = scan::scan(
 		wallet_inst.clone(),
 		keychain_mask,
 		delete_unconfirmed,
 		start_height,
 		tip.0,
+		resume_index,
+		cancel,
+		&mut progress,
 		status_send_channel,
 	)?;
 	info.hash = tip.1;
+	if progress.cancelled {
+		return save_scan_checkpoint(
+			wallet_inst,
+			keychain_mask,
+			ScanCheckpoint {
+				start_height,
+				last_pmmr_index: Some(info.last_pmmr_index),
+				last_token_pmmr_index: resume_token_index,
+			},
+		);
+	}
 
-	token_scan::token_scan(
+	let token_info = token_scan::token_scan(
 		wallet_inst.clone(),
 		keychain_mask,
 		delete_unconfirmed,
 		start_height,
 		tip.0,
+		resume_token_index,
+		cancel,
+		&mut progress,
 		status_send_channel,
 	)?;
+	if progress.cancelled {
+		return save_scan_checkpoint(
+			wallet_inst,
+			keychain_mask,
+			ScanCheckpoint {
+				start_height,
+				last_pmmr_index: Some(info.last_pmmr_index),
+				last_token_pmmr_index: Some(token_info.last_pmmr_index),
+			},
+		);
+	}
 
 	wallet_lock!(wallet_inst, w);
 	let mut batch = w.batch(keychain_mask)?;
 	batch.save_last_scanned_block(info)?;
+	batch.delete_scan_checkpoint()?;
 	batch.commit()?;
 
 	Ok(())
 }
 
+/// Stores where a cancelled scan stopped, returning the scan as cancelled
+fn save_scan_checkpoint<'a, L, C, K>(
+	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
+	keychain_mask: Option<&SecretKey>,
+	checkpoint: ScanCheckpoint,
+) -> Result<(), Error>
+where
+	L: WalletLCProvider<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let msg = format!(
+		"stopped at output index {:?} and token output index {:?}",
+		checkpoint.last_pmmr_index, checkpoint.last_token_pmmr_index,
+	);
+	wallet_lock!(wallet_inst, w);
+	let mut batch = w.batch(keychain_mask)?;
+	batch.save_scan_checkpoint(checkpoint)?;
+	batch.commit()?;
+	Err(ErrorKind::ScanCancelled(msg).into())
+}
+
 /// node height
 pub fn node_height<'a, L, C, K>(
 	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
@@ -1326,12 +2085,16 @@ where
 		}
 	}
 
+	let mut progress = ScanProgress::default();
 	let mut info = scan::scan(
 		wallet_inst.clone(),
 		keychain_mask,
 		false,
 		start_index,
 		tip.0,
+		None,
+		None,
+		&mut progress,
 		status_send_channel,
 	)?;
 
@@ -1343,6 +2106,9 @@ where
 		false,
 		start_index,
 		tip.0,
+		None,
+		None,
+		&mut progress,
 		status_send_channel,
 	)?;
 
@@ -1355,24 +2121,11 @@ where
 		batch.commit()?;
 	}
 
-	// Step 5: Cancel any transactions with an expired TTL
-	for tx in txs {
-		if let Some(e) = tx.ttl_cutoff_height {
-			if tip.0 >= e {
-				wallet_lock!(wallet_inst, w);
-				let parent_key_id = w.parent_key_id();
-				tx::cancel_tx(&mut **w, keychain_mask, &parent_key_id, Some(tx.id), None)?;
-			}
-		}
-	}
-	for tx in token_txs {
-		if let Some(e) = tx.ttl_cutoff_height {
-			if tip.0 >= e {
-				wallet_lock!(wallet_inst, w);
-				let parent_key_id = w.parent_key_id();
-				tx::cancel_tx(&mut **w, keychain_mask, &parent_key_id, Some(tx.id), None)?;
-			}
-		}
+	// Step 5: Cancel any transactions with an expired TTL, and release any
+	// expired input reservations
+	{
+		wallet_lock!(wallet_inst, w);
+		tx::cancel_expired_txs(&mut **w, keychain_mask, &parent_key_id, tip.0)?;
 	}
 
 	Ok(result)
@@ -1544,7 +2297,10 @@ where
 		if tx.confirmed {
 			continue;
 		}
-		if tx.amount_debited != 0 && tx.amount_credited != 0 {
+		// other transactions with change are confirmed by their outputs, our
+		// sends are always looked up so their inputs and change settle along
+		if tx.amount_debited != 0 && tx.amount_credited != 0 && tx.tx_type != TxLogEntryType::TxSent
+		{
 			continue;
 		}
 		if let Some(e) = tx.kernel_excess {
@@ -1556,11 +2312,21 @@ where
 			if let Some(k) = kernel {
 				debug!("Kernel Retrieved: {:?}", k);
 				wallet_lock!(wallet_inst, w);
-				let mut batch = w.batch(keychain_mask)?;
-				tx.confirmed = true;
-				tx.update_confirmation_ts();
-				batch.save_tx_log_entry(tx.clone(), &parent_key_id)?;
-				batch.commit()?;
+				{
+					let mut batch = w.batch(keychain_mask)?;
+					tx.confirmed = true;
+					tx.update_confirmation_ts();
+					batch.save_tx_log_entry(tx.clone(), &parent_key_id)?;
+					batch.commit()?;
+				}
+				updater::confirm_tx_outputs(
+					&mut **w,
+					keychain_mask,
+					&parent_key_id,
+					tx.id,
+					k.1,
+					false,
+				)?;
 			}
 		} else {
 			warn!("Attempted to update via kernel excess for transaction {:?}, but kernel excess was not stored", tx.tx_slate_id);
@@ -1599,7 +2365,12 @@ where
 		if tx.confirmed {
 			continue;
 		}
-		if tx.amount_debited != 0 && tx.amount_credited != 0 {
+		// token sends are always looked up, their vcash change can't confirm
+		// them as it isn't in the tx log
+		if tx.amount_debited != 0
+			&& tx.amount_credited != 0
+			&& tx.tx_type != TokenTxLogEntryType::TokenTxSent
+		{
 			continue;
 		}
 		if let Some(e) = tx.token_kernel_excess {
@@ -1611,11 +2382,21 @@ where
 			if let Some(k) = kernel {
 				debug!("Kernel Retrieved: {:?}", k);
 				wallet_lock!(wallet_inst, w);
-				let mut batch = w.batch(keychain_mask)?;
-				tx.confirmed = true;
-				tx.update_confirmation_ts();
-				batch.save_token_tx_log_entry(tx.clone(), &parent_key_id)?;
-				batch.commit()?;
+				{
+					let mut batch = w.batch(keychain_mask)?;
+					tx.confirmed = true;
+					tx.update_confirmation_ts();
+					batch.save_token_tx_log_entry(tx.clone(), &parent_key_id)?;
+					batch.commit()?;
+				}
+				updater::confirm_tx_outputs(
+					&mut **w,
+					keychain_mask,
+					&parent_key_id,
+					tx.id,
+					k.1,
+					true,
+				)?;
 			}
 		} else {
 			warn!("Attempted to update via kernel excess for token transaction {:?}, but kernel excess was not stored", tx.tx_slate_id);
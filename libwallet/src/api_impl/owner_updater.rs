@@ -48,6 +48,29 @@ pub enum StatusMessage {
 	ScanningComplete(String),
 	/// Warning of issues that may have occured during an update
 	UpdateWarning(String),
+	/// Progress of a scan, sent as each batch of outputs is checked
+	ScanProgress(ScanProgress),
+}
+
+/// Where a scan has got to through the output or token output PMMR
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ScanProgress {
+	/// Whether token outputs are being scanned, rather than outputs
+	pub scanning_tokens: bool,
+	/// First PMMR index of the range being scanned
+	pub start_index: u64,
+	/// Highest PMMR index checked so far
+	pub current_index: u64,
+	/// Last PMMR index of the range being scanned
+	pub end_index: u64,
+	/// Percentage of the range checked so far
+	pub percentage_complete: u8,
+	/// Outputs found to belong to the wallet so far
+	pub outputs_found: usize,
+	/// Token outputs found to belong to the wallet so far
+	pub token_outputs_found: usize,
+	/// Whether the scan was cancelled, stopping at `current_index`
+	pub cancelled: bool,
 }
 
 /// Helper function that starts a simple log thread for updater messages
@@ -77,6 +100,7 @@ pub fn start_updater_log_thread(
 					}
 					StatusMessage::ScanningComplete(s) => warn!("{}", s),
 					StatusMessage::UpdateWarning(s) => warn!("{}", s),
+					StatusMessage::ScanProgress(p) => debug!("{:?}", p),
 				}
 			}
 		})?;
@@ -14,18 +14,25 @@
 
 //! Types specific to the wallet api, mostly argument serialization
 
+use chrono::prelude::*;
+
 use crate::grin_core::libtx::secp_ser;
 use crate::grin_keychain::Identifier;
+use crate::grin_util::from_hex;
 use crate::grin_util::secp::pedersen;
+use crate::internal::selection::{SelectionParams, SendParams};
 use crate::slate_versions::ser as dalek_ser;
 use crate::slate_versions::SlateVersion;
-use crate::types::{OutputData, TokenOutputData};
-use crate::SlatepackAddress;
+use crate::types::{
+	ChangePlan, LateLockArgs, OutputData, Payout, SelectionStrategy, TokenOutputData,
+};
+use crate::{Error, ErrorKind, SlatepackAddress};
 
 use ed25519_dalek::Signature as DalekSignature;
+use uuid::Uuid;
 
 /// ISSUE TOKEN TX API Args
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize, Default)]
 pub struct IssueTokenArgs {
 	/// The human readable account name from which to draw outputs
 	/// for the transaction, overriding whatever the active account is as set via the
@@ -33,6 +40,102 @@ pub struct IssueTokenArgs {
 	pub acct_name: Option<String>,
 	/// amount to issue
 	pub amount: u64,
+	/// Optionally the largest supply the token may be issued with, the issue
+	/// is refused if `amount` goes over it
+	#[serde(default)]
+	pub max_supply: Option<u64>,
+}
+
+/// Consolidate TX API Args
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ConsolidateArgs {
+	/// The human readable account name whose outputs to consolidate, overriding
+	/// whatever the active account is as set via the
+	/// [`set_active_account`](../grin_wallet_api/owner/struct.Owner.html#method.set_active_account) method.
+	pub acct_name: Option<String>,
+	/// The minimum number of confirmations an output should have in order to be
+	/// consolidated.
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub minimum_confirmations: u64,
+	/// The most outputs to spend into the consolidated output, smallest first.
+	pub max_outputs: u32,
+	/// The fewest outputs worth consolidating. If fewer outputs qualify, no
+	/// transaction is built.
+	pub min_outputs: u32,
+	/// Optionally only consolidate outputs below this value, in nanogrins.
+	#[serde(with = "secp_ser::opt_string_or_u64")]
+	#[serde(default)]
+	pub below_value: Option<u64>,
+	/// Optionally override the base fee used to calculate the transaction fee,
+	/// in nanogrins per weight unit. If `None` the default base fee is used.
+	#[serde(with = "secp_ser::opt_string_or_u64")]
+	#[serde(default)]
+	pub fee_base: Option<u64>,
+}
+
+impl Default for ConsolidateArgs {
+	fn default() -> ConsolidateArgs {
+		ConsolidateArgs {
+			acct_name: None,
+			minimum_confirmations: 10,
+			max_outputs: 500,
+			min_outputs: 2,
+			below_value: None,
+			fee_base: None,
+		}
+	}
+}
+
+/// Split Outputs TX API Args
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SplitOutputsArgs {
+	/// The human readable account name whose outputs to split, overriding
+	/// whatever the active account is as set via the
+	/// [`set_active_account`](../grin_wallet_api/owner/struct.Owner.html#method.set_active_account) method.
+	pub acct_name: Option<String>,
+	/// Optionally split outputs of this token type rather than base outputs.
+	/// The fee is paid from base outputs as for any token transaction.
+	#[serde(default)]
+	pub token_type: Option<String>,
+	/// The minimum number of confirmations an output should have in order to be
+	/// split.
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub minimum_confirmations: u64,
+	/// The most outputs to spend, largest first.
+	pub max_outputs: u32,
+	/// The number of outputs to split into, of roughly equal value.
+	pub num_outputs: u32,
+	/// Optionally the least amount to split, in nanogrins (or token units).
+	/// As many of the largest outputs as needed to cover it, and the fee, are
+	/// split whole. If `None` the largest output is split on its own.
+	#[serde(with = "secp_ser::opt_string_or_u64")]
+	#[serde(default)]
+	pub amount: Option<u64>,
+	/// Optionally split into one output of each of these denominations that
+	/// fits, largest first, with any remainder in a final output, rather than
+	/// into `num_outputs` equal ones. Not supported for token outputs.
+	#[serde(default)]
+	pub denominations: Option<Vec<u64>>,
+	/// Optionally override the base fee used to calculate the transaction fee,
+	/// in nanogrins per weight unit. If `None` the default base fee is used.
+	#[serde(with = "secp_ser::opt_string_or_u64")]
+	#[serde(default)]
+	pub fee_base: Option<u64>,
+}
+
+impl Default for SplitOutputsArgs {
+	fn default() -> SplitOutputsArgs {
+		SplitOutputsArgs {
+			acct_name: None,
+			token_type: None,
+			minimum_confirmations: 10,
+			max_outputs: 500,
+			num_outputs: 2,
+			amount: None,
+			denominations: None,
+			fee_base: None,
+		}
+	}
 }
 
 /// Send TX API Args
@@ -60,6 +163,18 @@ pub struct SendTXArgs {
 	pub target_slate_version: Option<u16>,
 }
 
+/// Largest memo, in bytes, a transaction can be sent with
+pub const MAX_MEMO_LEN: usize = 256;
+
+/// Largest note, in bytes, that can be kept with a transaction
+pub const MAX_TX_NOTE_LEN: usize = 1024;
+
+/// Most labels a transaction can be tagged with
+pub const MAX_TX_LABELS: usize = 16;
+
+/// Largest label, in bytes, a transaction can be tagged with
+pub const MAX_TX_LABEL_LEN: usize = 64;
+
 /// V2 Init / Send TX API Args
 #[derive(Clone, Serialize, Deserialize)]
 pub struct InitTxArgs {
@@ -67,6 +182,13 @@ pub struct InitTxArgs {
 	/// for the transaction, overriding whatever the active account is as set via the
 	/// [`set_active_account`](../grin_wallet_api/owner/struct.Owner.html#method.set_active_account) method.
 	pub src_acct_name: Option<String>,
+	/// Optionally further human readable account names to draw outputs from along with
+	/// `src_acct_name`, funding one send from several accounts. The transaction is still
+	/// logged under `src_acct_name`, and change is derived under it unless `change_acct_name`
+	/// is set. Estimates and late locked sends only draw from `src_acct_name`, and token
+	/// sends don't support further accounts.
+	#[serde(default)]
+	pub extra_src_acct_names: Option<Vec<String>>,
 	#[serde(with = "secp_ser::string_or_u64")]
 	/// The amount to send, in nanogrins. (`1 G = 1_000_000_000nG`)
 	pub amount: u64,
@@ -76,15 +198,29 @@ pub struct InitTxArgs {
 	/// The minimum number of confirmations an output
 	/// should have in order to be included in the transaction.
 	pub minimum_confirmations: u64,
+	/// If set, the minimum number of confirmations a coinbase output should
+	/// have, whenever it's stricter than `minimum_confirmations`.
+	#[serde(with = "secp_ser::opt_string_or_u64")]
+	#[serde(default)]
+	pub coinbase_min_confirmations: Option<u64>,
 	/// By default, the wallet selects as many inputs as possible in a
 	/// transaction, to reduce the Output set and the fees. The wallet will attempt to spend
 	/// include up to `max_outputs` in a transaction, however if this is not enough to cover
 	/// the whole amount, the wallet will include more outputs. This parameter should be considered
 	/// a soft limit.
 	pub max_outputs: u32,
+	/// If `true`, `max_outputs` is a hard limit instead, and the transaction
+	/// fails with insufficient funds if it can't be built within it.
+	#[serde(default)]
+	pub strict_max_outputs: bool,
 	/// The target number of change outputs to create in the transaction.
 	/// The actual number created will be `num_change_outputs` + whatever remainder is needed.
+	/// For token sends this is the number of token change outputs.
 	pub num_change_outputs: u32,
+	/// For token sends, the number of change outputs to create from the base inputs
+	/// paying the fee. If `None` a single change output is created. Ignored for plain sends.
+	#[serde(default)]
+	pub num_fee_change_outputs: Option<u32>,
 	/// If `true`, attempt to use up as many outputs as
 	/// possible to create the transaction, up the 'soft limit' of `max_outputs`. This helps
 	/// to reduce the size of the UTXO set and the amount of data stored in the wallet, and
@@ -93,6 +229,82 @@ pub struct InitTxArgs {
 	/// as many outputs as are needed to meet the amount, (and no more) starting with the smallest
 	/// value outputs.
 	pub selection_strategy_is_use_all: bool,
+	/// Optionally choose the coin selection strategy explicitly. If set, this takes
+	/// precedence over `selection_strategy_is_use_all`.
+	#[serde(default)]
+	pub selection_strategy: Option<SelectionStrategy>,
+	/// Optionally override the base fee used to calculate the transaction fee,
+	/// in nanogrins per weight unit. If `None` the default base fee is used.
+	#[serde(with = "secp_ser::opt_string_or_u64")]
+	#[serde(default)]
+	pub fee_base: Option<u64>,
+	/// Optionally refuse to build the transaction if its fee would exceed this amount,
+	/// in nanogrins. If `None` any fee is accepted.
+	#[serde(with = "secp_ser::opt_string_or_u64")]
+	#[serde(default)]
+	pub max_fee: Option<u64>,
+	/// Optionally refuse to build the transaction if it would spend more than this
+	/// many inputs. Unlike `max_outputs`, this is never extended to cover the amount
+	/// and fails the transaction with `TooManyInputs` instead. If `None` there is no cap.
+	#[serde(default)]
+	pub max_inputs_hard: Option<usize>,
+	/// Optionally set a dust threshold, in nanogrins. Change below this amount is added
+	/// to the fee instead of creating a change output, and change is never split into
+	/// outputs smaller than this. If `None` no threshold is applied.
+	#[serde(with = "secp_ser::opt_string_or_u64")]
+	#[serde(default)]
+	pub dust_threshold: Option<u64>,
+	/// Optionally choose how change is split into change outputs. If `None`, change is
+	/// split evenly over `num_change_outputs`.
+	#[serde(default)]
+	pub change_plan: Option<ChangePlan>,
+	/// Optionally the human readable account name to derive change outputs under, keeping
+	/// change apart from the account the funds are drawn from. If `None`, change goes to the
+	/// active account.
+	#[serde(default)]
+	pub change_acct_name: Option<String>,
+	/// Optionally list outputs, by hex commitment, that must be spent by this transaction
+	/// (coin control). Further outputs are only selected if these don't cover the amount.
+	#[serde(default)]
+	pub required_inputs: Option<Vec<String>>,
+	/// If true, send the whole spendable balance of the account (or of the token, for
+	/// token sends). `amount` is ignored and the fee is deducted from what is sent, so no
+	/// change output is created.
+	#[serde(default)]
+	pub send_all: bool,
+	/// If true, `amount` is what the sender is debited, fee included, and the recipient
+	/// receives `amount` less the fee. Only applies to plain sends, not token sends.
+	#[serde(default)]
+	pub amount_includes_fee: bool,
+	/// Optionally pay several recipients in one transaction (batch payout). The slate
+	/// amount is the sum of the payouts, `amount` is ignored, and the fee allows for one
+	/// output per payout. Can't be combined with token sends, `send_all` or
+	/// `amount_includes_fee`.
+	#[serde(default)]
+	pub payouts: Option<Vec<Payout>>,
+	/// Fee paid in the token itself by a token send, taken from the token inputs on top
+	/// of `amount` (or out of the amount with `send_all`). The base fee is still paid as
+	/// usual. Ignored for plain sends.
+	#[serde(with = "secp_ser::string_or_u64")]
+	#[serde(default)]
+	pub token_fee: u64,
+	/// If true, don't select or lock any inputs yet. Only the fee is estimated, and inputs
+	/// and change are selected with the parameters given here when the slate comes back to
+	/// be finalized. Not supported for token sends, `send_all`, `amount_includes_fee` or
+	/// `required_inputs`.
+	#[serde(default)]
+	pub late_lock: bool,
+	/// If true, check that the inputs selected cover exactly the amount, the change and the
+	/// fee before the transaction is returned, failing with `UnbalancedTransaction` if not.
+	/// Off by default, as selection already builds a balanced transaction.
+	#[serde(default)]
+	pub verify_balance: bool,
+	/// Optional memo of up to `MAX_MEMO_LEN` bytes, such as an invoice id, kept with the
+	/// transaction in the sender's tx log, serialized as hex. Transactions have no room
+	/// for arbitrary data, so the memo is only stored locally and the recipient never
+	/// sees it. Not supported for token sends.
+	#[serde(with = "dalek_ser::option_bytes_hex", default)]
+	pub memo: Option<Vec<u8>>,
 	/// Optionally set the output target slate version (acceptable
 	/// down to the minimum slate version compatible with the current. If `None` the slate
 	/// is generated with the latest version.
@@ -132,12 +344,31 @@ impl Default for InitTxArgs {
 	fn default() -> InitTxArgs {
 		InitTxArgs {
 			src_acct_name: None,
+			extra_src_acct_names: None,
 			amount: 0,
 			token_type: None,
 			minimum_confirmations: 10,
+			coinbase_min_confirmations: None,
 			max_outputs: 500,
+			strict_max_outputs: false,
 			num_change_outputs: 1,
+			num_fee_change_outputs: None,
 			selection_strategy_is_use_all: true,
+			selection_strategy: None,
+			fee_base: None,
+			max_fee: None,
+			max_inputs_hard: None,
+			dust_threshold: None,
+			change_plan: None,
+			change_acct_name: None,
+			required_inputs: None,
+			send_all: false,
+			amount_includes_fee: false,
+			payouts: None,
+			token_fee: 0,
+			late_lock: false,
+			verify_balance: false,
+			memo: None,
 			target_slate_version: None,
 			ttl_blocks: None,
 			estimate_only: Some(false),
@@ -147,6 +378,140 @@ impl Default for InitTxArgs {
 	}
 }
 
+impl InitTxArgs {
+	/// The number of base change outputs of a token send
+	pub fn fee_change_outputs(&self) -> usize {
+		self.num_fee_change_outputs.unwrap_or(1) as usize
+	}
+
+	/// The selection strategy to use, falling back on `selection_strategy_is_use_all`
+	/// if no strategy was set explicitly
+	pub fn strategy(&self) -> SelectionStrategy {
+		match self.selection_strategy {
+			Some(s) => s,
+			None => self.selection_strategy_is_use_all.into(),
+		}
+	}
+
+	/// The commitments of the outputs this transaction must spend, if any
+	pub fn required_commits(&self) -> Result<Vec<pedersen::Commitment>, Error> {
+		let mut commits = vec![];
+		for c in self.required_inputs.iter().flatten() {
+			let bytes = from_hex(c).map_err(|_| {
+				ErrorKind::RequiredInputUnavailable(format!("invalid commitment {}", c))
+			})?;
+			commits.push(pedersen::Commitment::from_vec(bytes));
+		}
+		Ok(commits)
+	}
+
+	/// The parameters a send with these arguments selects its inputs and builds
+	/// its change under, paying the amount to `recipient_outputs` outputs
+	pub fn send_params(&self, recipient_outputs: usize) -> Result<SendParams, Error> {
+		Ok(SendParams {
+			minimum_confirmations: self.minimum_confirmations,
+			coinbase_min_confirmations: self.coinbase_min_confirmations,
+			selection: SelectionParams {
+				max_outputs: self.max_outputs as usize,
+				strict_max_outputs: self.strict_max_outputs,
+				selection_strategy: self.strategy(),
+			},
+			recipient_outputs,
+			change_outputs: self.num_change_outputs as usize,
+			fee_change_outputs: self.fee_change_outputs(),
+			fee_base: self.fee_base,
+			max_fee: self.max_fee,
+			max_inputs_hard: self.max_inputs_hard,
+			dust_threshold: self.dust_threshold.unwrap_or(0),
+			change_plan: self.change_plan.clone().unwrap_or_default(),
+			required_inputs: self.required_commits()?,
+			send_all: self.send_all,
+			amount_includes_fee: self.amount_includes_fee,
+			..Default::default()
+		})
+	}
+
+	/// The amount to send and the number of outputs it is paid to: the sum of the
+	/// payouts for a batch payout, or `amount` to a single output otherwise
+	pub fn payout_amount(&self) -> Result<(u64, usize), Error> {
+		let payouts = match self.payouts {
+			Some(ref p) => p,
+			None => return Ok((self.amount, 1)),
+		};
+		if self.token_type.is_some() || self.send_all || self.amount_includes_fee {
+			return Err(ErrorKind::InvalidPayouts(
+				"can't be combined with token sends, send_all or amount_includes_fee".to_owned(),
+			)
+			.into());
+		}
+		if payouts.is_empty() || payouts.iter().any(|p| p.amount == 0) {
+			return Err(
+				ErrorKind::InvalidPayouts("payout amounts must be positive".to_owned()).into(),
+			);
+		}
+		let total = payouts
+			.iter()
+			.try_fold(0u64, |acc, p| acc.checked_add(p.amount))
+			.ok_or_else(|| ErrorKind::InvalidPayouts("total amount overflows".to_owned()))?;
+		Ok((total, payouts.len()))
+	}
+
+	/// The memo to keep with the transaction, once checked against `MAX_MEMO_LEN`
+	pub fn checked_memo(&self) -> Result<Option<Vec<u8>>, Error> {
+		match self.memo {
+			Some(ref m) if m.len() > MAX_MEMO_LEN => Err(ErrorKind::MemoTooLarge {
+				size: m.len(),
+				max: MAX_MEMO_LEN,
+			}
+			.into()),
+			Some(_) if self.token_type.is_some() => Err(ErrorKind::GenericError(
+				"memos aren't supported for token sends".to_owned(),
+			)
+			.into()),
+			_ => Ok(self.memo.clone()),
+		}
+	}
+
+	/// The selection parameters to keep in the context of a late locked transaction
+	pub fn late_lock_args(
+		&self,
+		recipient_outputs: usize,
+		change_parent_key_id: Option<Identifier>,
+	) -> Result<LateLockArgs, Error> {
+		if self.token_type.is_some() {
+			return Err(
+				ErrorKind::LateLock("not supported for token transactions".to_owned()).into(),
+			);
+		}
+		if self.send_all || self.amount_includes_fee || self.required_inputs.is_some() {
+			return Err(ErrorKind::LateLock(
+				"can't be combined with send_all, amount_includes_fee or required_inputs"
+					.to_owned(),
+			)
+			.into());
+		}
+		// the inputs are only selected from the sending account at finalize
+		if self.extra_src_acct_names.is_some() {
+			return Err(
+				ErrorKind::LateLock("can't draw inputs from further accounts".to_owned()).into(),
+			);
+		}
+		Ok(LateLockArgs {
+			minimum_confirmations: self.minimum_confirmations,
+			coinbase_min_confirmations: self.coinbase_min_confirmations,
+			max_outputs: self.max_outputs as usize,
+			strict_max_outputs: self.strict_max_outputs,
+			max_inputs_hard: self.max_inputs_hard,
+			recipient_outputs,
+			change_outputs: self.num_change_outputs as usize,
+			selection_strategy: self.strategy(),
+			dust_threshold: self.dust_threshold.unwrap_or(0),
+			change_plan: self.change_plan.clone().unwrap_or_default(),
+			change_parent_key_id,
+		})
+	}
+}
+
 /// V2 Issue Invoice Tx Args
 #[derive(Clone, Serialize, Deserialize)]
 pub struct IssueInvoiceTxArgs {
@@ -163,6 +528,10 @@ pub struct IssueInvoiceTxArgs {
 	/// down to the minimum slate version compatible with the current. If `None` the slate
 	/// is generated with the latest version.
 	pub target_slate_version: Option<u16>,
+	/// If set, require a payment proof from the particular payer, who signs it
+	/// along with paying the invoice
+	#[serde(default)]
+	pub payment_proof_sender_address: Option<SlatepackAddress>,
 }
 
 impl Default for IssueInvoiceTxArgs {
@@ -172,10 +541,199 @@ impl Default for IssueInvoiceTxArgs {
 			amount: 0,
 			token_type: None,
 			target_slate_version: None,
+			payment_proof_sender_address: None,
 		}
 	}
 }
 
+/// Filter on the notes and labels kept with transactions, for narrowing down
+/// the transactions retrieved from the tx log
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TxNoteFilter {
+	/// Only keep transactions whose note contains this text
+	#[serde(default)]
+	pub note: Option<String>,
+	/// Only keep transactions tagged with exactly this label
+	#[serde(default)]
+	pub label: Option<String>,
+}
+
+impl TxNoteFilter {
+	/// Whether a transaction with the given note and labels passes the filter
+	pub fn matches(&self, note: &Option<String>, labels: &[String]) -> bool {
+		let f_note = match (&self.note, note) {
+			(Some(f), Some(n)) => n.contains(f.as_str()),
+			(Some(_), None) => false,
+			(None, _) => true,
+		};
+		let f_label = match self.label {
+			Some(ref l) => labels.contains(l),
+			None => true,
+		};
+		f_note && f_label
+	}
+}
+
+/// Format the transaction history is exported in
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TxExportFormat {
+	/// Comma separated values, with a header row naming the columns
+	Csv,
+	/// A JSON array holding an object per row, keyed by column name
+	Json,
+}
+
+/// Columns of an exported transaction history. Each row is the movement of a
+/// single asset, so a token transaction that also spends vcash takes two rows.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TxExportColumn {
+	/// Time the transaction was created
+	Date,
+	/// Tx log entry type
+	Type,
+	/// Local id of the tx log entry
+	Id,
+	/// Slate the transaction was built from, if any
+	SlateId,
+	/// Token moved by the row, empty when it's vcash
+	TokenType,
+	/// Amount credited, in base units
+	AmountCredited,
+	/// Amount credited, human readable
+	AmountCreditedHr,
+	/// Amount debited, in base units
+	AmountDebited,
+	/// Amount debited, human readable
+	AmountDebitedHr,
+	/// Fee paid in the row's asset, in base units
+	Fee,
+	/// Fee paid in the row's asset, human readable
+	FeeHr,
+	/// Excess of the kernel moving the row's asset
+	KernelExcess,
+	/// Whether the transaction is confirmed
+	Confirmed,
+	/// Height the transaction was confirmed at, when known
+	ConfirmationHeight,
+	/// Note kept with the transaction
+	Note,
+	/// Labels the transaction is tagged with
+	Labels,
+}
+
+impl TxExportColumn {
+	/// Every column, in the order they're exported when none are selected
+	pub fn all() -> Vec<TxExportColumn> {
+		vec![
+			TxExportColumn::Date,
+			TxExportColumn::Type,
+			TxExportColumn::Id,
+			TxExportColumn::SlateId,
+			TxExportColumn::TokenType,
+			TxExportColumn::AmountCredited,
+			TxExportColumn::AmountCreditedHr,
+			TxExportColumn::AmountDebited,
+			TxExportColumn::AmountDebitedHr,
+			TxExportColumn::Fee,
+			TxExportColumn::FeeHr,
+			TxExportColumn::KernelExcess,
+			TxExportColumn::Confirmed,
+			TxExportColumn::ConfirmationHeight,
+			TxExportColumn::Note,
+			TxExportColumn::Labels,
+		]
+	}
+
+	/// Name of the column in the CSV header row, and key of its JSON field
+	pub fn name(&self) -> &'static str {
+		match *self {
+			TxExportColumn::Date => "date",
+			TxExportColumn::Type => "type",
+			TxExportColumn::Id => "id",
+			TxExportColumn::SlateId => "slate_id",
+			TxExportColumn::TokenType => "token_type",
+			TxExportColumn::AmountCredited => "amount_credited",
+			TxExportColumn::AmountCreditedHr => "amount_credited_hr",
+			TxExportColumn::AmountDebited => "amount_debited",
+			TxExportColumn::AmountDebitedHr => "amount_debited_hr",
+			TxExportColumn::Fee => "fee",
+			TxExportColumn::FeeHr => "fee_hr",
+			TxExportColumn::KernelExcess => "kernel_excess",
+			TxExportColumn::Confirmed => "confirmed",
+			TxExportColumn::ConfirmationHeight => "confirmation_height",
+			TxExportColumn::Note => "note",
+			TxExportColumn::Labels => "labels",
+		}
+	}
+}
+
+/// Transaction history export args
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TxExportArgs {
+	/// Format to write the history in
+	pub format: TxExportFormat,
+	/// Columns to export, in order. Every column is exported if empty
+	#[serde(default)]
+	pub columns: Vec<TxExportColumn>,
+	/// Only export transactions created at or after this time
+	#[serde(default)]
+	pub from: Option<DateTime<Utc>>,
+	/// Only export transactions created before this time
+	#[serde(default)]
+	pub to: Option<DateTime<Utc>>,
+}
+
+impl Default for TxExportArgs {
+	fn default() -> TxExportArgs {
+		TxExportArgs {
+			format: TxExportFormat::Csv,
+			columns: vec![],
+			from: None,
+			to: None,
+		}
+	}
+}
+
+/// What the transactions of one slate moved of both assets, put together from
+/// the tx log and the token tx log. A token transaction only has a token tx
+/// log entry, which also holds the vcash it spent on the fee and the change of
+/// that. Entries not built from a slate, such as coinbase rewards, each make a
+/// record of their own. Fees are part of the amounts debited, so the credits
+/// less the debits of all records add up to the balance of each asset.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SlateActivity {
+	/// Slate the transactions were built from, if any
+	pub tx_slate_id: Option<Uuid>,
+	/// Ids of the tx log entries of the slate
+	pub tx_log_ids: Vec<u32>,
+	/// Ids of the token tx log entries of the slate
+	pub token_tx_log_ids: Vec<u32>,
+	/// Token the slate moved, if any
+	pub token_type: Option<String>,
+	/// Vcash credited, the change when sending
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub amount_credited: u64,
+	/// Vcash debited, the fee included
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub amount_debited: u64,
+	/// Token credited, the change when sending
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub token_amount_credited: u64,
+	/// Token debited, the token fee included
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub token_amount_debited: u64,
+	/// Vcash fee paid
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub fee: u64,
+	/// Token fee paid
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub token_fee: u64,
+	/// Time the first entry of the slate was created
+	pub creation_ts: DateTime<Utc>,
+	/// Whether every entry of the slate is confirmed
+	pub confirmed: bool,
+}
+
 /// Fees in block to use for coinbase amount calculation
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BlockFees {
@@ -19,6 +19,7 @@ use crate::grin_core::libtx;
 use crate::grin_keychain;
 use crate::grin_store;
 use crate::grin_util::secp;
+use crate::types::BalanceBreakdown;
 use crate::util;
 use failure::{Backtrace, Context, Fail};
 use std::env;
@@ -36,8 +37,8 @@ pub struct Error {
 pub enum ErrorKind {
 	/// Not enough funds
 	#[fail(
-		display = "Not enough funds. Required: {}, Available: {}",
-		needed_disp, available_disp
+		display = "Not enough funds. Required: {}, Available: {}, Spendable outputs: {}{}",
+		needed_disp, available_disp, num_outputs, breakdown_disp
 	)]
 	NotEnoughFunds {
 		/// available funds
@@ -48,12 +49,105 @@ pub enum ErrorKind {
 		needed: u64,
 		/// Display friendly
 		needed_disp: String,
+		/// Number of outputs eligible to spend
+		num_outputs: usize,
+		/// Value of the largest output eligible to spend
+		max_output: u64,
+		/// How the rest of the balance is held up, when known
+		breakdown: BalanceBreakdown,
+		/// Display friendly note of the balance held up, if any
+		breakdown_disp: String,
+	},
+
+	/// Not enough of a token, in raw token units as tokens don't share the
+	/// coin's decimals
+	#[fail(
+		display = "Not enough funds of token {}. Required: {}, Available: {}{}",
+		token_type, needed, available, breakdown_disp
+	)]
+	NotEnoughTokenFunds {
+		/// Token type short of funds
+		token_type: String,
+		/// available token funds
+		available: u64,
+		/// Needed token funds
+		needed: u64,
+		/// How the rest of the token balance is held up, when known
+		breakdown: BalanceBreakdown,
+		/// Display friendly note of the token balance held up, if any
+		breakdown_disp: String,
 	},
 
 	/// Fee error
 	#[fail(display = "Fee Error: {}", _0)]
 	Fee(String),
 
+	/// Fee over the maximum the sender allowed
+	#[fail(display = "Fee of {} exceeds the maximum of {}", fee, max)]
+	FeeExceedsMaximum {
+		/// The calculated fee
+		fee: u64,
+		/// The maximum fee allowed
+		max: u64,
+	},
+
+	/// Transaction memo is over the size limit
+	#[fail(display = "Memo of {} bytes exceeds the limit of {}", size, max)]
+	MemoTooLarge {
+		/// The size of the memo
+		size: usize,
+		/// The maximum size allowed
+		max: usize,
+	},
+
+	/// More inputs selected than the sender's hard limit allows
+	#[fail(display = "{} inputs exceeds the limit of {}", selected, max)]
+	TooManyInputs {
+		/// The number of inputs selected
+		selected: usize,
+		/// The maximum number of inputs allowed
+		max: usize,
+	},
+
+	/// Fee to be deducted from the amount is as large as the amount itself
+	#[fail(display = "Fee of {} exceeds the amount of {}", fee, amount)]
+	FeeExceedsAmount {
+		/// The calculated fee
+		fee: u64,
+		/// The amount the fee was to be deducted from
+		amount: u64,
+	},
+
+	/// Token slate that can't be a valid token transaction
+	#[fail(display = "Invalid token slate: {}", _0)]
+	InvalidTokenSlate(String),
+
+	/// Fee set by the sender is below the minimum the transaction needs
+	#[fail(display = "Fee of {} is below the minimum of {}", fee, min)]
+	FeeBelowMinimum {
+		/// The fee set by the sender
+		fee: u64,
+		/// The minimum fee for the transaction
+		min: u64,
+	},
+
+	/// Not enough base funds to pay the fee of a token transaction, which
+	/// unlike the token amount is always paid from base outputs
+	#[fail(
+		display = "Not enough funds to pay the fee of a token transaction. Required: {}, Available: {}",
+		needed_disp, available_disp
+	)]
+	NotEnoughFeeFunds {
+		/// available base funds
+		available: u64,
+		/// Display friendly
+		available_disp: String,
+		/// Fee needed
+		needed: u64,
+		/// Display friendly
+		needed_disp: String,
+	},
+
 	/// LibTX Error
 	#[fail(display = "LibTx Error")]
 	LibTX(libtx::ErrorKind),
@@ -150,6 +244,10 @@ pub enum ErrorKind {
 	#[fail(display = "Transaction {} doesn't exist", _0)]
 	TransactionDoesntExist(String),
 
+	/// Transaction note or labels can't be kept with a transaction
+	#[fail(display = "Invalid transaction note: {}", _0)]
+	InvalidTxNote(String),
+
 	/// Transaction already rolled back
 	#[fail(display = "Transaction {} cannot be cancelled", _0)]
 	TransactionNotCancellable(String),
@@ -326,6 +424,49 @@ pub enum ErrorKind {
 	#[fail(display = "Stored Tx error: {}", _0)]
 	StoredTx(String),
 
+	/// An input or output stored in a transaction context isn't in the wallet
+	#[fail(display = "Transaction context output not found in wallet: {}", _0)]
+	ContextOutputMissing(String),
+
+	/// An input or output stored in a transaction context no longer matches
+	/// the wallet
+	#[fail(display = "Transaction context doesn't match the wallet: {}", _0)]
+	ContextOutputMismatch(String),
+
+	/// A slate returned to us was changed from what its transaction context
+	/// was built for
+	#[fail(display = "Slate doesn't match its transaction context: {}", _0)]
+	SlateContextMismatch(String),
+
+	/// The inputs of a transaction we're sending don't cover exactly its
+	/// outputs and fee
+	#[fail(display = "Transaction doesn't balance: {}", _0)]
+	UnbalancedTransaction(String),
+
+	/// An input required by coin control can't be spent
+	#[fail(display = "Required input unavailable: {}", _0)]
+	RequiredInputUnavailable(String),
+
+	/// The payouts of a batch payout are invalid
+	#[fail(display = "Invalid payouts: {}", _0)]
+	InvalidPayouts(String),
+
+	/// A late locked transaction can't be created or its inputs can't be selected
+	#[fail(display = "Late locking error: {}", _0)]
+	LateLock(String),
+
+	/// Outputs can't be consolidated
+	#[fail(display = "Consolidation error: {}", _0)]
+	Consolidation(String),
+
+	/// Outputs can't be split
+	#[fail(display = "Split outputs error: {}", _0)]
+	SplitOutputs(String),
+
+	/// A scan was cancelled, to be resumed by the next one
+	#[fail(display = "Scan cancelled: {}", _0)]
+	ScanCancelled(String),
+
 	/// Other
 	#[fail(display = "Generic error: {}", _0)]
 	GenericError(String),
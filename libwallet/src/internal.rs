@@ -21,6 +21,7 @@
 #![deny(unused_mut)]
 #![warn(missing_docs)]
 
+pub mod export;
 pub mod keys;
 pub mod scan;
 pub mod selection;
@@ -0,0 +1,282 @@
+// Copyright 2019 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export of the transaction history, merging the tx log and the token tx log
+//! into a single timeline
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use chrono::prelude::*;
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::grin_core::core::amount_to_hr_string;
+use crate::grin_keychain::{Identifier, Keychain};
+use crate::grin_util::secp::pedersen;
+use crate::grin_util::ToHex;
+use crate::internal::{keys, updater};
+use crate::types::{NodeClient, OutputStatus, WalletBackend};
+use crate::{Error, ErrorKind, TxExportArgs, TxExportColumn, TxExportFormat};
+
+/// The movement of a single asset by a transaction
+struct TxExportRow {
+	creation_ts: DateTime<Utc>,
+	tx_type: String,
+	id: u32,
+	tx_slate_id: Option<Uuid>,
+	token_type: Option<String>,
+	amount_credited: u64,
+	amount_debited: u64,
+	fee: Option<u64>,
+	kernel_excess: Option<pedersen::Commitment>,
+	confirmed: bool,
+	confirmation_height: Option<u64>,
+	note: Option<String>,
+	labels: Vec<String>,
+}
+
+impl TxExportRow {
+	fn value(&self, column: TxExportColumn) -> Value {
+		let opt = |v: Option<Value>| v.unwrap_or(Value::Null);
+		match column {
+			TxExportColumn::Date => self.creation_ts.to_rfc3339().into(),
+			TxExportColumn::Type => self.tx_type.clone().into(),
+			TxExportColumn::Id => self.id.into(),
+			TxExportColumn::SlateId => opt(self.tx_slate_id.map(|s| s.to_string().into())),
+			TxExportColumn::TokenType => opt(self.token_type.clone().map(Value::from)),
+			TxExportColumn::AmountCredited => self.amount_credited.into(),
+			TxExportColumn::AmountCreditedHr => {
+				amount_to_hr_string(self.amount_credited, false).into()
+			}
+			TxExportColumn::AmountDebited => self.amount_debited.into(),
+			TxExportColumn::AmountDebitedHr => {
+				amount_to_hr_string(self.amount_debited, false).into()
+			}
+			TxExportColumn::Fee => opt(self.fee.map(Value::from)),
+			TxExportColumn::FeeHr => opt(self.fee.map(|f| amount_to_hr_string(f, false).into())),
+			TxExportColumn::KernelExcess => opt(self.kernel_excess.map(|e| {
+				let excess: &[u8] = e.0.as_ref();
+				excess.to_hex().into()
+			})),
+			TxExportColumn::Confirmed => self.confirmed.into(),
+			TxExportColumn::ConfirmationHeight => opt(self.confirmation_height.map(Value::from)),
+			TxExportColumn::Note => opt(self.note.clone().map(Value::from)),
+			TxExportColumn::Labels => self.labels.clone().into(),
+		}
+	}
+}
+
+/// Writes the transaction history of the account at `parent_key_id` to `writer`,
+/// returning the number of rows written. Base and token transactions are merged
+/// into one timeline, ordered by creation time. Token transactions spending vcash
+/// as well, to pay their fee, take a row for each asset, the vcash one first.
+pub fn export_txs<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	parent_key_id: &Identifier,
+	args: &TxExportArgs,
+	writer: &mut dyn Write,
+) -> Result<usize, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let heights = confirmation_heights(wallet, parent_key_id);
+	let in_range = |ts: &DateTime<Utc>| {
+		args.from.map_or(true, |from| *ts >= from) && args.to.map_or(true, |to| *ts < to)
+	};
+
+	let mut rows = vec![];
+	for tx in updater::retrieve_txs(wallet, None, None, Some(parent_key_id), false)? {
+		if !in_range(&tx.creation_ts) {
+			continue;
+		}
+		rows.push(TxExportRow {
+			creation_ts: tx.creation_ts,
+			tx_type: format!("{:?}", tx.tx_type),
+			id: tx.id,
+			tx_slate_id: tx.tx_slate_id,
+			token_type: None,
+			amount_credited: tx.amount_credited,
+			amount_debited: tx.amount_debited,
+			fee: tx.fee,
+			kernel_excess: tx.kernel_excess,
+			confirmed: tx.confirmed,
+			confirmation_height: heights.get(&tx.id).cloned(),
+			note: tx.note,
+			labels: tx.labels,
+		});
+	}
+	for tx in updater::retrieve_token_txs(wallet, None, None, Some(parent_key_id), false)? {
+		if !in_range(&tx.creation_ts) {
+			continue;
+		}
+		let confirmation_height = heights.get(&tx.id).cloned();
+		if tx.amount_credited > 0 || tx.amount_debited > 0 {
+			rows.push(TxExportRow {
+				creation_ts: tx.creation_ts,
+				tx_type: format!("{:?}", tx.tx_type),
+				id: tx.id,
+				tx_slate_id: tx.tx_slate_id,
+				token_type: None,
+				amount_credited: tx.amount_credited,
+				amount_debited: tx.amount_debited,
+				fee: tx.fee,
+				kernel_excess: tx.kernel_excess,
+				confirmed: tx.confirmed,
+				confirmation_height,
+				note: tx.note.clone(),
+				labels: tx.labels.clone(),
+			});
+		}
+		rows.push(TxExportRow {
+			creation_ts: tx.creation_ts,
+			tx_type: format!("{:?}", tx.tx_type),
+			id: tx.id,
+			tx_slate_id: tx.tx_slate_id,
+			token_type: Some(tx.token_type),
+			amount_credited: tx.token_amount_credited,
+			amount_debited: tx.token_amount_debited,
+			fee: tx.token_fee,
+			kernel_excess: tx.token_kernel_excess.or(tx.kernel_excess),
+			confirmed: tx.confirmed,
+			confirmation_height,
+			note: tx.note,
+			labels: tx.labels,
+		});
+	}
+	// stable, so both rows of a token transaction stay together
+	rows.sort_by_key(|r| (r.creation_ts, r.id));
+
+	let columns = match args.columns.is_empty() {
+		true => TxExportColumn::all(),
+		false => args.columns.clone(),
+	};
+	match args.format {
+		TxExportFormat::Csv => write_csv(writer, &columns, &rows)?,
+		TxExportFormat::Json => write_json(writer, &columns, &rows)?,
+	}
+	Ok(rows.len())
+}
+
+/// Height each transaction of the account was confirmed at, taken from the
+/// outputs it created that the wallet still holds. Spent outputs point to the
+/// transaction spending them, so they can't tell when theirs was confirmed.
+fn confirmation_heights<'a, T: ?Sized, C, K>(
+	wallet: &T,
+	parent_key_id: &Identifier,
+) -> HashMap<u32, u64>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let mut heights: HashMap<u32, u64> = HashMap::new();
+	let mut add = |key_id: &Identifier, tx_log_entry: Option<u32>, height: u64| {
+		if keys::account_path_for(&key_id.parent_path()) != *parent_key_id {
+			return;
+		}
+		if let Some(id) = tx_log_entry {
+			let h = heights.entry(id).or_insert(height);
+			*h = (*h).max(height);
+		}
+	};
+	for o in wallet.iter().filter(|o| o.status == OutputStatus::Unspent) {
+		add(&o.key_id, o.tx_log_entry, o.height);
+	}
+	for o in wallet
+		.token_iter()
+		.filter(|o| o.status == OutputStatus::Unspent)
+	{
+		add(&o.key_id, o.tx_log_entry, o.height);
+	}
+	heights
+}
+
+/// Quotes a CSV cell if it holds a separator, quote or line break
+fn csv_cell(value: &Value) -> String {
+	let cell = match value {
+		Value::Null => "".to_owned(),
+		Value::String(s) => s.clone(),
+		Value::Array(a) => a
+			.iter()
+			.map(|v| v.as_str().unwrap_or_default().to_owned())
+			.collect::<Vec<_>>()
+			.join("; "),
+		v => v.to_string(),
+	};
+	if cell.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+		format!("\"{}\"", cell.replace('"', "\"\""))
+	} else {
+		cell
+	}
+}
+
+fn write_csv(
+	writer: &mut dyn Write,
+	columns: &[TxExportColumn],
+	rows: &[TxExportRow],
+) -> Result<(), Error> {
+	let header: Vec<&str> = columns.iter().map(|c| c.name()).collect();
+	writeln!(writer, "{}", header.join(","))?;
+	for row in rows {
+		let cells: Vec<String> = columns.iter().map(|c| csv_cell(&row.value(*c))).collect();
+		writeln!(writer, "{}", cells.join(","))?;
+	}
+	Ok(())
+}
+
+fn write_json(
+	writer: &mut dyn Write,
+	columns: &[TxExportColumn],
+	rows: &[TxExportRow],
+) -> Result<(), Error> {
+	let objects: Vec<Map<String, Value>> = rows
+		.iter()
+		.map(|row| {
+			columns
+				.iter()
+				.map(|c| (c.name().to_owned(), row.value(*c)))
+				.collect()
+		})
+		.collect();
+	serde_json::to_writer_pretty(writer, &objects)
+		.map_err(|e| ErrorKind::Format(format!("Unable to write tx export: {}", e)))?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn csv_cells_are_quoted() {
+		assert_eq!(csv_cell(&Value::Null), "");
+		assert_eq!(csv_cell(&Value::from(12u64)), "12");
+		assert_eq!(csv_cell(&Value::from("payroll")), "payroll");
+		assert_eq!(csv_cell(&Value::from("a, b")), "\"a, b\"");
+		assert_eq!(
+			csv_cell(&Value::from("invoice \"1234\"\nJune")),
+			"\"invoice \"\"1234\"\"\nJune\""
+		);
+		assert_eq!(
+			csv_cell(&Value::from(vec![
+				"invoice".to_owned(),
+				"client".to_owned()
+			])),
+			"invoice; client"
+		);
+	}
+}
@@ -32,6 +32,99 @@ where
 	Ok(child)
 }
 
+/// Get next available key under the given parent, rather than the wallet's
+/// active one
+pub fn next_available_key_for<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	parent_key_id: &Identifier,
+) -> Result<Identifier, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let active_key_id = wallet.parent_key_id();
+	wallet.set_parent_key_id(parent_key_id.clone());
+	let child = wallet.next_child(keychain_mask);
+	wallet.set_parent_key_id(active_key_id);
+	child
+}
+
+/// Parent path of the dedicated change branch of an account at `m/k/0`, which
+/// is `m/k/1` (the internal chain of the BIP32 spec). Accounts that aren't laid
+/// out that way have no change branch.
+pub fn change_branch_path(parent_key_id: &Identifier) -> Option<Identifier> {
+	let mut p = parent_key_id.to_path();
+	if p.depth != 2 || <u32>::from(p.path[1]) != 0 {
+		return None;
+	}
+	p.path[1] = ChildNumber::from(1);
+	Some(p.to_identifier())
+}
+
+/// The account path a parent path found on chain belongs to. Change branches
+/// at `m/k/1` belong to the account at `m/k/0`, any other parent (including
+/// the flat layout, where change shares the account path) is its own account.
+pub fn account_path_for(parent_key_id: &Identifier) -> Identifier {
+	let mut p = parent_key_id.to_path();
+	if p.depth != 2 || <u32>::from(p.path[1]) != 1 {
+		return parent_key_id.clone();
+	}
+	p.path[1] = ChildNumber::from(0);
+	p.to_identifier()
+}
+
+/// Parent under which change outputs of the given account are derived: its
+/// change branch if enabled for the account, the account path itself otherwise
+pub fn change_parent_for<'a, T: ?Sized, C, K>(wallet: &T, parent_key_id: &Identifier) -> Identifier
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let enabled = wallet
+		.acct_path_iter()
+		.any(|m| &m.path == parent_key_id && m.change_branch);
+	match change_branch_path(parent_key_id) {
+		Some(branch) if enabled => branch,
+		_ => parent_key_id.clone(),
+	}
+}
+
+/// Turns deriving change outputs on a dedicated branch on or off for the
+/// account with the given label. Outputs already derived keep their keys, as
+/// scanning covers both the account path and its change branch.
+pub fn set_change_branch<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	label: &str,
+	enabled: bool,
+) -> Result<(), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let mut mapping = match wallet.acct_path_iter().find(|m| m.label == label) {
+		Some(m) => m,
+		None => return Err(ErrorKind::UnknownAccountLabel(label.to_owned()).into()),
+	};
+	if enabled && change_branch_path(&mapping.path).is_none() {
+		return Err(ErrorKind::GenericError(format!(
+			"Account {} at path {} has no change branch",
+			label, mapping.path
+		))
+		.into());
+	}
+	mapping.change_branch = enabled;
+
+	let mut batch = wallet.batch(keychain_mask)?;
+	batch.save_acct_path(mapping)?;
+	batch.commit()?;
+	Ok(())
+}
+
 /// Retrieve an existing key from a wallet
 pub fn retrieve_existing_key<'a, T: ?Sized, C, K>(
 	wallet: &T,
@@ -93,9 +186,16 @@ where
 		}
 	};
 
+	// new accounts derive change the same way as the default one, which is
+	// set when the wallet is created
+	let change_branch = wallet
+		.acct_path_iter()
+		.any(|m| m.label == "default" && m.change_branch);
+
 	let save_path = AcctPathMapping {
 		label: label,
 		path: return_id.clone(),
+		change_branch,
 	};
 
 	let mut batch = wallet.batch(keychain_mask)?;
@@ -120,6 +220,7 @@ where
 	let save_path = AcctPathMapping {
 		label: label,
 		path: path.clone(),
+		change_branch: false,
 	};
 
 	let mut batch = wallet.batch(keychain_mask)?;
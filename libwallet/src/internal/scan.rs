@@ -13,7 +13,7 @@
 // limitations under the License.
 //! Functions to restore a wallet's outputs from just the master seed
 
-use crate::api_impl::owner_updater::StatusMessage;
+use crate::api_impl::owner_updater::{ScanProgress, StatusMessage};
 use crate::grin_core::consensus::{valid_header_version, WEEK_HEIGHT_ORIGIN};
 use crate::grin_core::core::HeaderVersion;
 use crate::grin_core::global;
@@ -27,6 +27,7 @@ use crate::types::*;
 use crate::{wallet_lock, Error, OutputCommitMapping};
 use std::cmp;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
 
@@ -144,11 +145,31 @@ where
 	Ok(wallet_outputs)
 }
 
+/// Whether the given cancellation flag has been raised
+pub fn scan_cancelled(cancel: Option<&AtomicBool>) -> bool {
+	cancel.map(|c| c.load(Ordering::Relaxed)).unwrap_or(false)
+}
+
+/// Sends the progress of a scan on the status channel, if any
+pub fn send_scan_progress(
+	progress: &ScanProgress,
+	status_send_channel: &Option<Sender<StatusMessage>>,
+) {
+	if let Some(ref s) = status_send_channel {
+		let _ = s.send(StatusMessage::ScanProgress(progress.clone()));
+	}
+}
+
+/// Collects our outputs in the given PMMR index range, along with the highest
+/// index checked. Raising the cancellation flag stops it between batches,
+/// marking `progress` as cancelled.
 fn collect_chain_outputs<'a, C, K>(
 	keychain: &K,
 	client: C,
 	start_index: u64,
 	end_index: Option<u64>,
+	cancel: Option<&AtomicBool>,
+	progress: &mut ScanProgress,
 	status_send_channel: &Option<Sender<StatusMessage>>,
 ) -> Result<(Vec<OutputResult>, u64), Error>
 where
@@ -160,13 +181,32 @@ where
 	let mut start_index = start_index;
 	let mut result_vec: Vec<OutputResult> = vec![];
 	let last_retrieved_return_index;
+	progress.scanning_tokens = false;
+	progress.start_index = start_index;
+	if let Some(end) = end_index {
+		progress.end_index = end;
+		// a resumed scan may have nothing left to check
+		if start_index > end {
+			progress.current_index = end;
+			return Ok((result_vec, end));
+		}
+	}
 	loop {
+		if scan_cancelled(cancel) {
+			// everything before this batch has been checked
+			last_retrieved_return_index = start_index.saturating_sub(1);
+			progress.current_index = last_retrieved_return_index;
+			progress.cancelled = true;
+			send_scan_progress(progress, status_send_channel);
+			break;
+		}
+
 		let (highest_index, last_retrieved_index, outputs) =
 			client.get_outputs_by_pmmr_index(start_index, end_index, batch_size)?;
 
 		let range = highest_index as f64 - start_index_stat as f64;
-		let progress = last_retrieved_index as f64 - start_index_stat as f64;
-		let perc_complete = cmp::min(((progress / range) * 100.0) as u8, 99);
+		let checked = last_retrieved_index as f64 - start_index_stat as f64;
+		let perc_complete = cmp::min(((checked / range) * 100.0) as u8, 99);
 
 		let msg = format!(
 			"Checking {} outputs, up to index {}. (Highest index: {})",
@@ -178,12 +218,18 @@ where
 			let _ = s.send(StatusMessage::Scanning(msg, perc_complete));
 		}
 
-		result_vec.append(&mut identify_utxo_outputs(
+		let mut found = identify_utxo_outputs(
 			keychain,
 			outputs.clone(),
 			status_send_channel,
 			perc_complete as u8,
-		)?);
+		)?;
+		progress.outputs_found += found.len();
+		progress.current_index = last_retrieved_index;
+		progress.end_index = highest_index;
+		progress.percentage_complete = perc_complete;
+		send_scan_progress(progress, status_send_channel);
+		result_vec.append(&mut found);
 
 		if highest_index <= last_retrieved_index {
 			last_retrieved_return_index = last_retrieved_index;
@@ -212,10 +258,14 @@ where
 	let commit = w.calc_commit_for_cache(keychain_mask, output.value, &output.key_id)?;
 	let mut batch = w.batch(keychain_mask)?;
 
-	let parent_key_id = output.key_id.parent_path();
-	if !found_parents.contains_key(&parent_key_id) {
-		found_parents.insert(parent_key_id.clone(), 0);
-		if let Some(ref mut s) = tx_stats {
+	// change derived on an account's change branch is restored to the account
+	let key_parent_id = output.key_id.parent_path();
+	let parent_key_id = keys::account_path_for(&key_parent_id);
+	if !found_parents.contains_key(&key_parent_id) {
+		found_parents.insert(key_parent_id.clone(), 0);
+	}
+	if let Some(ref mut s) = tx_stats {
+		if !s.contains_key(&parent_key_id) {
 			s.insert(
 				parent_key_id.clone(),
 				RestoredTxStats {
@@ -266,12 +316,13 @@ where
 		height: output.height,
 		lock_height: output.lock_height,
 		is_coinbase: output.is_coinbase,
+		is_frozen: false,
 		tx_log_entry: Some(log_id),
 	});
 
-	let max_child_index = *found_parents.get(&parent_key_id).unwrap();
+	let max_child_index = *found_parents.get(&key_parent_id).unwrap();
 	if output.n_child >= max_child_index {
-		found_parents.insert(parent_key_id, output.n_child);
+		found_parents.insert(key_parent_id, output.n_child);
 	}
 
 	batch.commit()?;
@@ -289,7 +340,7 @@ where
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
 {
-	let parent_key_id = output.key_id.parent_path();
+	let parent_key_id = keys::account_path_for(&output.key_id.parent_path());
 	wallet_lock!(wallet_inst, w);
 	let updated_tx_entry = if output.tx_log_entry.is_some() {
 		let entries = updater::retrieve_txs(
@@ -321,15 +372,77 @@ where
 	Ok(())
 }
 
+/// Restores labels, account paths and child derivation indices for the parent
+/// paths outputs were found under. Outputs found on an account's change branch
+/// restore the account itself, and turn its change branch on so that new change
+/// keys carry on from the ones found.
+pub fn restore_account_paths<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	found_parents: &HashMap<Identifier, u32>,
+	status_send_channel: &Option<Sender<StatusMessage>>,
+) -> Result<(), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let label_base = "account";
+	let mut accounts: Vec<Identifier> = wallet.acct_path_iter().map(|m| m.path).collect();
+	let mut acct_index = accounts.len();
+	let mut branch_accounts: Vec<Identifier> = vec![];
+	for (path, max_child_index) in found_parents.iter() {
+		let acct_path = keys::account_path_for(path);
+		if acct_path != *path && !branch_accounts.contains(&acct_path) {
+			branch_accounts.push(acct_path.clone());
+		}
+		// Only restore paths that don't exist
+		if !accounts.contains(&acct_path) {
+			let label = format!("{}_{}", label_base, acct_index);
+			let msg = format!("Setting account {} at path {}", label, acct_path);
+			if let Some(ref s) = status_send_channel {
+				let _ = s.send(StatusMessage::Scanning(msg, 99));
+			}
+			keys::set_acct_path(wallet, keychain_mask, &label, &acct_path)?;
+			accounts.push(acct_path);
+			acct_index += 1;
+		}
+		let current_child_index = wallet.current_child_index(&path)?;
+		if *max_child_index >= current_child_index {
+			let mut batch = wallet.batch(keychain_mask)?;
+			debug!("Next child for path {} is {}", path, max_child_index + 1);
+			batch.save_child_index(path, max_child_index + 1)?;
+			batch.commit()?;
+		}
+	}
+
+	let labels: Vec<String> = wallet
+		.acct_path_iter()
+		.filter(|m| branch_accounts.contains(&m.path) && !m.change_branch)
+		.map(|m| m.label)
+		.collect();
+	for label in labels {
+		debug!("Account {} has change on its change branch", label);
+		keys::set_change_branch(wallet, keychain_mask, &label, true)?;
+	}
+	Ok(())
+}
+
 /// Check / repair wallet contents by scanning against chain
 /// assume wallet contents have been freshly updated with contents
-/// of latest block
+/// of latest block. A scan resumed after `resume_index` only checks the
+/// outputs past it. If cancelled, the outputs found so far are restored and
+/// unlocked, `progress` is marked cancelled and the returned info holds the
+/// highest index checked.
 pub fn scan<'a, L, C, K>(
 	wallet_inst: Arc<Mutex<Box<dyn WalletInst<'a, L, C, K>>>>,
 	keychain_mask: Option<&SecretKey>,
 	delete_unconfirmed: bool,
 	start_height: u64,
 	end_height: u64,
+	resume_index: Option<u64>,
+	cancel: Option<&AtomicBool>,
+	progress: &mut ScanProgress,
 	status_send_channel: &Option<Sender<StatusMessage>>,
 ) -> Result<ScannedBlockInfo, Error>
 where
@@ -348,12 +461,18 @@ where
 
 	// Retrieve the actual PMMR index range we're looking for
 	let pmmr_range = client.height_range_to_pmmr_indices(start_height, Some(end_height))?;
+	let start_index = match resume_index {
+		Some(i) => cmp::max(pmmr_range.0, i + 1),
+		None => pmmr_range.0,
+	};
 
 	let (chain_outs, last_index) = collect_chain_outputs(
 		&keychain,
 		client,
-		pmmr_range.0,
+		start_index,
 		Some(pmmr_range.1),
+		cancel,
+		progress,
 		status_send_channel,
 	)?;
 	let msg = format!(
@@ -433,7 +552,8 @@ where
 	}
 
 	if delete_unconfirmed {
-		// Unlock locked outputs
+		// Unlock the locked outputs found, even if the scan was cancelled
+		// after finding them, as a resumed one won't look at them again
 		for m in locked_outs.into_iter() {
 			let mut o = m.0;
 			let msg = format!(
@@ -452,58 +572,43 @@ where
 			batch.commit()?;
 		}
 
-		let unconfirmed_outs: Vec<&OutputCommitMapping> = wallet_outputs
-			.iter()
-			.filter(|o| o.output.status == OutputStatus::Unconfirmed)
-			.collect();
-		// Delete unconfirmed outputs
-		for m in unconfirmed_outs.into_iter() {
-			let o = m.output.clone();
-			let msg = format!(
-				"Unconfirmed output for {} with ID {} ({:?}) not in UTXO set. \
-				 Deleting and cancelling associated transaction log entries.",
-				o.value, o.key_id, m.commit,
-			);
-			if let Some(ref s) = status_send_channel {
-				let _ = s.send(StatusMessage::Scanning(msg, 99));
+		// outputs past where a cancelled scan stopped weren't checked, the
+		// resumed scan deletes the unconfirmed ones once it completes
+		if !progress.cancelled {
+			let unconfirmed_outs: Vec<&OutputCommitMapping> = wallet_outputs
+				.iter()
+				.filter(|o| o.output.status == OutputStatus::Unconfirmed)
+				.collect();
+			// Delete unconfirmed outputs
+			for m in unconfirmed_outs.into_iter() {
+				let o = m.output.clone();
+				let msg = format!(
+					"Unconfirmed output for {} with ID {} ({:?}) not in UTXO set. \
+					 Deleting and cancelling associated transaction log entries.",
+					o.value, o.key_id, m.commit,
+				);
+				if let Some(ref s) = status_send_channel {
+					let _ = s.send(StatusMessage::Scanning(msg, 99));
+				}
+				cancel_tx_log_entry(wallet_inst.clone(), keychain_mask, &o)?;
+				wallet_lock!(wallet_inst, w);
+				let mut batch = w.batch(keychain_mask)?;
+				batch.delete(&o.key_id, &o.mmr_index)?;
+				batch.commit()?;
 			}
-			cancel_tx_log_entry(wallet_inst.clone(), keychain_mask, &o)?;
-			wallet_lock!(wallet_inst, w);
-			let mut batch = w.batch(keychain_mask)?;
-			batch.delete(&o.key_id, &o.mmr_index)?;
-			batch.commit()?;
 		}
 	}
 
 	// restore labels, account paths and child derivation indices
 	wallet_lock!(wallet_inst, w);
-	let label_base = "account";
-	let accounts: Vec<Identifier> = w.acct_path_iter().map(|m| m.path).collect();
-	let mut acct_index = accounts.len();
-	for (path, max_child_index) in found_parents.iter() {
-		// Only restore paths that don't exist
-		if !accounts.contains(path) {
-			let label = format!("{}_{}", label_base, acct_index);
-			let msg = format!("Setting account {} at path {}", label, path);
-			if let Some(ref s) = status_send_channel {
-				let _ = s.send(StatusMessage::Scanning(msg, 99));
-			}
-			keys::set_acct_path(&mut **w, keychain_mask, &label, path)?;
-			acct_index += 1;
-		}
-		let current_child_index = w.current_child_index(&path)?;
-		if *max_child_index >= current_child_index {
-			let mut batch = w.batch(keychain_mask)?;
-			debug!("Next child for account {} is {}", path, max_child_index + 1);
-			batch.save_child_index(path, max_child_index + 1)?;
-			batch.commit()?;
-		}
-	}
+	restore_account_paths(&mut **w, keychain_mask, &found_parents, status_send_channel)?;
 
 	if let Some(ref s) = status_send_channel {
-		let _ = s.send(StatusMessage::ScanningComplete(
-			"Scanning Complete".to_owned(),
-		));
+		if !progress.cancelled {
+			let _ = s.send(StatusMessage::ScanningComplete(
+				"Scanning Complete".to_owned(),
+			));
+		}
 	}
 
 	Ok(ScannedBlockInfo {
@@ -15,6 +15,7 @@
 //! Selection of inputs for building transactions
 
 use crate::address;
+use crate::blake2::blake2b::blake2b;
 use crate::error::{Error, ErrorKind};
 use crate::grin_core::core::amount_to_hr_string;
 use crate::grin_core::core::transaction::TokenKey;
@@ -26,11 +27,194 @@ use crate::grin_core::libtx::{
 use crate::grin_keychain::{Identifier, Keychain};
 use crate::grin_util::secp::key::{SecretKey, ZERO_KEY};
 use crate::grin_util::secp::pedersen;
+use crate::grin_util::ToHex;
 use crate::internal::keys;
 use crate::slate::Slate;
+use crate::slatepack::SlatepackAddress;
 use crate::types::*;
 use crate::util::OnionV3Address;
-use std::collections::HashMap;
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use uuid::Uuid;
+
+/// Derivation path of the address our payment proofs are signed with
+pub const PAYMENT_PROOF_DERIVATION_INDEX: u32 = 0;
+
+/// How many blocks the inputs of a transaction without a TTL stay reserved
+/// when it isn't locked
+pub const RESERVATION_TTL_BLOCKS: u64 = 1440;
+
+/// A change output built for a transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeOutput {
+	/// Value of the output
+	pub amount: u64,
+	/// Key the output is derived from
+	pub key_id: Identifier,
+	/// MMR index, if known
+	pub mmr_index: Option<u64>,
+}
+
+/// What a send selects its inputs and builds its change under, as taken by
+/// `estimate_send`, `select_send_tx` and `build_send_tx`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SendParams {
+	/// Confirmations an output needs before it's spent
+	pub minimum_confirmations: u64,
+	/// Confirmations a coinbase output needs, `minimum_confirmations` if none
+	pub coinbase_min_confirmations: Option<u64>,
+	/// Most outputs to spend and the strategy they're picked by
+	pub selection: SelectionParams,
+	/// Outputs the amount is paid to
+	pub recipient_outputs: usize,
+	/// Change outputs to split the change into
+	pub change_outputs: usize,
+	/// For token sends, change outputs to split the change of the base inputs
+	/// paying the fee into, `change_outputs` being the token change outputs
+	pub fee_change_outputs: usize,
+	/// Kernels of the transaction
+	pub num_kernels: usize,
+	/// Base fee the fee is worked out from, the default one if none
+	pub fee_base: Option<u64>,
+	/// Highest fee the send may pay
+	pub max_fee: Option<u64>,
+	/// Most inputs to spend, never gone over
+	pub max_inputs_hard: Option<usize>,
+	/// Change below which it goes to the fee rather than a change output
+	pub dust_threshold: u64,
+	/// How the change is split into change outputs
+	pub change_plan: ChangePlan,
+	/// Commitments of the outputs the send must spend
+	pub required_inputs: Vec<pedersen::Commitment>,
+	/// Whether every eligible output is spent, the fee coming out of the amount
+	pub send_all: bool,
+	/// Whether the fee comes out of the amount rather than on top of it
+	pub amount_includes_fee: bool,
+	/// Whether a single output covering the send is spent rather than the
+	/// several the strategy selects, see `select_coins_and_fee_from`
+	pub prefer_fewer_inputs: bool,
+	/// Fee agreed before the inputs were selected, which the change of the
+	/// built transaction is worked out around
+	pub fixed_fee: Option<u64>,
+}
+
+impl Default for SendParams {
+	fn default() -> SendParams {
+		SendParams {
+			minimum_confirmations: 10,
+			coinbase_min_confirmations: None,
+			selection: SelectionParams {
+				max_outputs: 500,
+				strict_max_outputs: false,
+				selection_strategy: SelectionStrategy::UseAll,
+			},
+			recipient_outputs: 1,
+			change_outputs: 1,
+			fee_change_outputs: 1,
+			num_kernels: 1,
+			fee_base: None,
+			max_fee: None,
+			max_inputs_hard: None,
+			dust_threshold: 0,
+			change_plan: ChangePlan::Even,
+			required_inputs: vec![],
+			send_all: false,
+			amount_includes_fee: false,
+			prefer_fewer_inputs: false,
+			fixed_fee: None,
+		}
+	}
+}
+
+impl SendParams {
+	/// The parameters the base inputs of a token send are selected under. They
+	/// only pay the fee, so nothing is sent out of them, and their change goes
+	/// to `fee_change_outputs` outputs.
+	pub fn fee_params(&self) -> SendParams {
+		SendParams {
+			change_outputs: self.fee_change_outputs,
+			send_all: false,
+			amount_includes_fee: false,
+			fixed_fee: None,
+			..self.clone()
+		}
+	}
+}
+
+/// The inputs, change and fee `select_send_tx` settled on
+pub struct SelectionResult<K, B>
+where
+	K: Keychain,
+	B: ProofBuild,
+{
+	/// Elements adding the inputs and change to the transaction
+	pub elems: Vec<Box<build::Append<K, B>>>,
+	/// Outputs spent
+	pub inputs: Vec<OutputData>,
+	/// Change outputs built
+	pub change: Vec<ChangeOutput>,
+	/// Amount paid, less the fee when it's taken out of the amount
+	pub amount: u64,
+	/// Transaction fee
+	pub fee: u64,
+	/// Total value of the inputs
+	pub total: u64,
+}
+
+impl<K, B> SelectionResult<K, B>
+where
+	K: Keychain,
+	B: ProofBuild,
+{
+	/// Whether no change outputs were built, which may leak the sender's key
+	pub fn is_changeless(&self) -> bool {
+		self.change.is_empty()
+	}
+}
+
+/// The token inputs, change and token fee `select_send_token_tx` settled on
+pub struct TokenSelectionResult<K, B>
+where
+	K: Keychain,
+	B: ProofBuild,
+{
+	/// Elements adding the token inputs and change to the transaction
+	pub elems: Vec<Box<build::Append<K, B>>>,
+	/// Token outputs spent
+	pub inputs: Vec<TokenOutputData>,
+	/// Token change outputs built
+	pub change: Vec<ChangeOutput>,
+	/// Token amount paid, less the token fee when sending everything
+	pub amount: u64,
+	/// Token fee paid from the token inputs
+	pub fee: u64,
+	/// Total value of the token inputs
+	pub total: u64,
+}
+
+/// Refuse to build on a slate whose TTL cutoff height is already behind us,
+/// as the transaction could never be mined
+fn check_ttl_height(slate: &Slate, current_height: u64) -> Result<(), Error> {
+	if slate.ttl_cutoff_height != 0 && slate.ttl_cutoff_height <= current_height {
+		return Err(ErrorKind::GenericError(format!(
+			"Transaction TTL cutoff height {} has already passed (current height {})",
+			slate.ttl_cutoff_height, current_height
+		))
+		.into());
+	}
+	Ok(())
+}
+
+/// Whether the blinding factors of our inputs go into our excess sum when
+/// building a transaction on the slate. Compact slates, from version 4 on,
+/// don't carry our inputs to the other party: they're kept in the context,
+/// made up for in the offset and put back into the transaction by
+/// `repopulate_tx` once we finalize. Earlier slates carry the whole
+/// transaction, so our inputs are part of the excess from the start.
+pub fn should_include_inputs_in_sum(slate: &Slate) -> bool {
+	!slate.is_compact()
+}
 
 /// Initialize a transaction on the sender side, returns a corresponding
 /// libwallet transaction slate with the appropriate inputs selected,
@@ -43,11 +227,12 @@ pub fn build_send_tx<'a, T: ?Sized, C, K>(
 	keychain_mask: Option<&SecretKey>,
 	slate: &mut Slate,
 	current_height: u64,
-	minimum_confirmations: u64,
-	max_outputs: usize,
-	change_outputs: usize,
-	selection_strategy_is_use_all: bool,
+	params: &SendParams,
+	coin_selector: &dyn CoinSelector,
+	verify_balance: bool,
 	parent_key_id: Identifier,
+	extra_parent_key_ids: &[Identifier],
+	change_parent_key_id: Option<&Identifier>,
 	is_invoice: bool,
 	use_test_nonce: bool,
 ) -> Result<Context, Error>
@@ -56,30 +241,43 @@ where
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
 {
-	//TODO: Revise HF3. If we're sending V4 slates, only include
-	// change outputs in excess sum
-	let include_inputs_in_sum = !slate.is_compact();
+	check_ttl_height(slate, current_height)?;
 
-	let (elems, inputs, change_amounts_derivations, fee) = select_send_tx(
+	// Only sends of everything work their amount out here, a plain send of
+	// nothing makes no sense. Token issues, which send nothing, build their
+	// output with `token_issue_output` instead.
+	if slate.amount == 0 && !params.send_all {
+		return Err(ErrorKind::GenericError("amount must be greater than zero".to_owned()).into());
+	}
+
+	let include_inputs_in_sum = should_include_inputs_in_sum(slate);
+
+	let selected = select_send_tx(
 		wallet,
 		keychain_mask,
 		slate.amount,
 		current_height,
-		minimum_confirmations,
-		max_outputs,
-		change_outputs,
-		selection_strategy_is_use_all,
+		params,
+		coin_selector,
 		&parent_key_id,
+		extra_parent_key_ids,
+		change_parent_key_id,
 		0,
 		0,
 		include_inputs_in_sum,
 	)?;
 
+	// when sending everything, or paying the fee out of the amount, the
+	// recipient gets whatever the fee leaves
+	slate.amount = selected.amount;
+	let fee = selected.fee;
+	let changeless = selected.is_changeless();
+
 	// Update the fee on the slate so we account for this when building the tx.
 	slate.fee = fee;
 
 	let (blinding, _) =
-		slate.add_transaction_elements(keychain, &ProofBuilder::new(keychain), elems)?;
+		slate.add_transaction_elements(keychain, &ProofBuilder::new(keychain), selected.elems)?;
 
 	// Create our own private context
 	let mut context = Context::new(
@@ -93,21 +291,30 @@ where
 
 	context.fee = fee;
 	context.amount = slate.amount;
+	context.changeless = changeless;
+	context.selection_strategy = Some(params.selection.selection_strategy);
+	context.change_parent_key_id = change_parent_key_id.cloned();
+	// reserve the proof address now, so locking can't find it missing
+	if let Some(ref p) = slate.payment_proof {
+		context.set_payment_proof(p, PAYMENT_PROOF_DERIVATION_INDEX);
+	}
 
 	// Store our private identifiers for each input
-	for input in inputs {
+	for input in selected.inputs {
 		context.add_input(&input.key_id, &input.mmr_index, input.value);
+		if let Some(ref commit) = input.commit {
+			context.add_input_commit(&input.key_id, commit);
+		}
 	}
 
-	let mut commits: HashMap<Identifier, Option<String>> = HashMap::new();
-
 	// Store change output(s) and cached commits
-	for (change_amount, id, mmr_index) in &change_amounts_derivations {
-		context.add_output(&id, &mmr_index, *change_amount);
-		commits.insert(
-			id.clone(),
-			wallet.calc_commit_for_cache(keychain_mask, *change_amount, &id)?,
-		);
+	for change in &selected.change {
+		context.add_output(&change.key_id, &change.mmr_index, change.amount);
+	}
+	cache_change_commits(wallet, keychain_mask, &mut context, &selected.change)?;
+
+	if verify_balance {
+		verify_sender_balance(&context)?;
 	}
 
 	Ok(context)
@@ -119,11 +326,12 @@ pub fn build_send_token_tx<'a, T: ?Sized, C, K>(
 	keychain_mask: Option<&SecretKey>,
 	slate: &mut Slate,
 	current_height: u64,
-	minimum_confirmations: u64,
-	max_outputs: usize,
-	change_outputs: usize,
-	selection_strategy_is_use_all: bool,
+	params: &SendParams,
+	coin_selector: &dyn CoinSelector,
+	token_fee: u64,
+	verify_balance: bool,
 	parent_key_id: Identifier,
+	change_parent_key_id: Option<&Identifier>,
 	is_invoice: bool,
 	use_test_nonce: bool,
 ) -> Result<Context, Error>
@@ -137,47 +345,61 @@ where
 			"token type should not be none".to_owned(),
 		))?;
 	}
+	check_ttl_height(slate, current_height)?;
 
-	//TODO: Revise HF3. If we're sending V4 slates, only include
-	// change outputs in excess sum
-	let include_inputs_in_sum = !slate.is_compact();
+	// Only our base inputs follow `should_include_inputs_in_sum`. The token
+	// kernel has no offset to make up for the token inputs, so they're always
+	// part of the token excess, and `repopulate_tx` reinstates them along with
+	// the token change at finalize
+	let include_inputs_in_sum = should_include_inputs_in_sum(slate);
 
-	let (mut token_elems, token_inputs, token_change_amounts_derivations) = select_send_token_tx(
+	let mut token_selected = select_send_token_tx(
 		wallet,
 		keychain_mask,
 		slate.amount,
+		token_fee,
 		slate.token_type.clone().unwrap().as_str(),
 		current_height,
-		minimum_confirmations,
-		max_outputs,
-		change_outputs,
-		selection_strategy_is_use_all,
+		params.minimum_confirmations,
+		params.selection.max_outputs,
+		params.change_outputs,
+		params.selection.selection_strategy,
+		coin_selector,
+		params.dust_threshold,
+		params.send_all,
 		&parent_key_id,
+		change_parent_key_id,
 		true,
 	)?;
 
-	let token_output_len = token_change_amounts_derivations.len() + 1;
-	let token_inout_len = token_elems.len() - token_change_amounts_derivations.len();
+	// when sending everything, the whole token balance less the token fee goes
+	// to the recipient
+	slate.amount = token_selected.amount;
 
-	let (mut elems, inputs, change_amounts_derivations, fee) = select_send_tx(
+	let token_output_len = token_selected.change.len() + 1;
+	let token_inout_len = token_selected.elems.len() - token_selected.change.len();
+
+	let mut selected = select_send_tx(
 		wallet,
 		keychain_mask,
 		0,
 		current_height,
-		minimum_confirmations,
-		max_outputs,
-		1,
-		selection_strategy_is_use_all,
+		&params.fee_params(),
+		coin_selector,
 		&parent_key_id,
+		&[],
+		change_parent_key_id,
 		token_inout_len,
 		token_output_len,
 		include_inputs_in_sum,
-	)?;
+	)
+	.map_err(fee_funds_error)?;
 
 	let mut all_elems = vec![];
-	all_elems.append(&mut token_elems);
-	all_elems.append(&mut elems);
+	all_elems.append(&mut token_selected.elems);
+	all_elems.append(&mut selected.elems);
 
+	let fee = selected.fee;
 	slate.fee = fee;
 	let (blinding, token_blinding) =
 		slate.add_transaction_elements(keychain, &ProofBuilder::new(keychain), all_elems)?;
@@ -193,33 +415,177 @@ where
 	);
 
 	context.fee = fee;
+	context.token_fee = token_selected.fee;
 	context.amount = slate.amount;
+	context.selection_strategy = Some(params.selection.selection_strategy);
+	context.change_parent_key_id = change_parent_key_id.cloned();
+	context.token_type = slate.token_type.clone();
+	if let Some(ref p) = slate.payment_proof {
+		context.set_payment_proof(p, PAYMENT_PROOF_DERIVATION_INDEX);
+	}
 
 	// Store our private identifiers for each input
-	for input in inputs {
+	for input in selected.inputs {
 		context.add_input(&input.key_id, &input.mmr_index, input.value);
+		if let Some(ref commit) = input.commit {
+			context.add_input_commit(&input.key_id, commit);
+		}
 	}
 
 	// Store change output(s) and cached commits
-	for (change_amount, id, mmr_index) in &change_amounts_derivations {
-		context.add_output(&id, &mmr_index, *change_amount);
+	for change in &selected.change {
+		context.add_output(&change.key_id, &change.mmr_index, change.amount);
 	}
 
 	// Store our private identifiers for each token input
-	for input in token_inputs {
+	for input in token_selected.inputs {
 		context.add_token_input(&input.key_id, &input.mmr_index, input.value);
 	}
 
 	// Store change token output(s) and cached commits
-	for (change_amount, id, mmr_index) in &token_change_amounts_derivations {
-		context.add_token_output(&id, &mmr_index, *change_amount);
+	for change in &token_selected.change {
+		context.add_token_output(&change.key_id, &change.mmr_index, change.amount);
+	}
+	let all_change: Vec<_> = selected
+		.change
+		.iter()
+		.chain(token_selected.change.iter())
+		.cloned()
+		.collect();
+	cache_change_commits(wallet, keychain_mask, &mut context, &all_change)?;
+
+	if verify_balance {
+		verify_sender_balance(&context)?;
 	}
 
 	Ok(context)
 }
 
+/// Checks the values of a sender's context add up: our inputs must hold
+/// exactly the amount sent, our change and the fee. On token sends the vcash
+/// inputs only pay the fee and the change, and the token inputs the amount,
+/// the token change and the token fee.
+pub fn verify_sender_balance(context: &Context) -> Result<(), Error> {
+	let sum = |values: Vec<(Identifier, Option<u64>, u64)>| -> i128 {
+		values.iter().map(|(_, _, v)| *v as i128).sum()
+	};
+	let check = |asset: &str, inputs: i128, outputs: i128, fee: u64, amount: u64| {
+		let excess = inputs - outputs - fee as i128 - amount as i128;
+		match excess {
+			0 => Ok(()),
+			_ => Err(ErrorKind::UnbalancedTransaction(format!(
+				"{} inputs {} less change {}, fee {} and amount {} leave {}",
+				asset, inputs, outputs, fee, amount, excess
+			))),
+		}
+	};
+	match context.token_type {
+		Some(ref token_type) => {
+			check(
+				"vcash",
+				sum(context.get_inputs()),
+				sum(context.get_outputs()),
+				context.fee,
+				0,
+			)?;
+			check(
+				token_type.as_str(),
+				sum(context.get_token_inputs()),
+				sum(context.get_token_outputs()),
+				context.token_fee,
+				context.amount,
+			)?;
+		}
+		None => check(
+			"vcash",
+			sum(context.get_inputs()),
+			sum(context.get_outputs()),
+			context.fee,
+			context.amount,
+		)?,
+	}
+	Ok(())
+}
+
+/// Computes the commitments of our change outputs in one go, keeping them in
+/// the context so locking can reuse them
+fn cache_change_commits<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	context: &mut Context,
+	change: &[ChangeOutput],
+) -> Result<(), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let outputs: Vec<_> = change
+		.iter()
+		.map(|c| (c.amount, c.key_id.clone()))
+		.collect();
+	let commits = wallet.calc_commits_for_cache(keychain_mask, &outputs)?;
+	for ((_, id), commit) in outputs.iter().zip(commits) {
+		if let Some(commit) = commit {
+			context.add_output_commit(id, &commit);
+		}
+	}
+	Ok(())
+}
+
+/// Looks up the cached commitments of the given context outputs, computing any
+/// that weren't cached when the context was built
+fn output_commits_for<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	context: &Context,
+	outputs: &[(Identifier, Option<u64>, u64)],
+) -> Result<HashMap<Identifier, (Option<String>, u64)>, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let missing: Vec<_> = outputs
+		.iter()
+		.filter(|(id, _, _)| context.get_output_commit(id).is_none())
+		.map(|(id, _, amount)| (*amount, id.clone()))
+		.collect();
+	let mut computed: HashMap<Identifier, Option<String>> = missing
+		.iter()
+		.map(|(_, id)| id.clone())
+		.zip(wallet.calc_commits_for_cache(keychain_mask, &missing)?)
+		.collect();
+	Ok(outputs
+		.iter()
+		.map(|(id, _, amount)| {
+			let commit = match context.get_output_commit(id) {
+				Some(c) => Some(c),
+				None => computed.remove(id).unwrap_or(None),
+			};
+			(id.clone(), (commit, *amount))
+		})
+		.collect())
+}
+
+/// Slatepack address of the other party of a transaction, as known from the
+/// payment proof of its slate. The counterparty of the sender is the receiver
+/// of the proof, and the other way around.
+fn counterparty_address(slate: &Slate, is_sender: bool) -> Option<String> {
+	slate.payment_proof.as_ref().map(|p| {
+		let address = match is_sender {
+			true => &p.receiver_address,
+			false => &p.sender_address,
+		};
+		SlatepackAddress::new(address).to_string()
+	})
+}
+
 /// Locks all corresponding outputs in the context, creates
-/// change outputs and tx log entry
+/// change outputs and tx log entry. The tx log entry records how the slate was
+/// exchanged, if the caller knows the `transport`. A `soft_reserve` of some
+/// blocks only reserves the inputs until then instead, leaving them unlocked
+/// and the transaction unlogged until it's locked for good.
 pub fn lock_tx_context<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
 	keychain_mask: Option<&SecretKey>,
@@ -227,44 +593,37 @@ pub fn lock_tx_context<'a, T: ?Sized, C, K>(
 	current_height: u64,
 	context: &Context,
 	excess_override: Option<pedersen::Commitment>,
+	transport: Option<SlateTransport>,
+	soft_reserve: Option<u64>,
 ) -> Result<(), Error>
 where
 	T: WalletBackend<'a, C, K>,
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
 {
-	let mut output_commits: HashMap<Identifier, (Option<String>, u64)> = HashMap::new();
-	// Store cached commits before locking wallet
-	let mut total_change = 0;
-	for (id, _, change_amount) in &context.get_outputs() {
-		output_commits.insert(
-			id.clone(),
-			(
-				wallet.calc_commit_for_cache(keychain_mask, *change_amount, &id)?,
-				*change_amount,
-			),
-		);
-		total_change += change_amount;
+	if let Some(blocks) = soft_reserve {
+		let reservation = OutputReservation::new(slate.id, context, current_height + blocks);
+		let mut batch = wallet.batch(keychain_mask)?;
+		batch.save_reservation(&reservation)?;
+		batch.commit()?;
+		return Ok(());
 	}
 
+	// Store cached commits before locking wallet
+	let outputs = context.get_outputs();
+	let output_commits = output_commits_for(wallet, keychain_mask, context, &outputs)?;
+	let total_change: u64 = outputs.iter().map(|(_, _, amount)| amount).sum();
+
 	debug!("Vcash Change amount is: {}", total_change);
 
 	let keychain = wallet.keychain(keychain_mask)?;
 
-	if slate.token_type.is_some() {
-		let mut token_output_commits: HashMap<Identifier, (Option<String>, u64)> = HashMap::new();
+	if let Some(token_type) = slate.token_type.clone() {
 		// Store cached commits before locking wallet
-		let mut token_total_change = 0;
-		for (id, _, change_amount) in &context.get_token_outputs() {
-			token_output_commits.insert(
-				id.clone(),
-				(
-					wallet.calc_commit_for_cache(keychain_mask, *change_amount, &id)?,
-					*change_amount,
-				),
-			);
-			token_total_change += change_amount;
-		}
+		let token_outputs = context.get_token_outputs();
+		let token_output_commits =
+			output_commits_for(wallet, keychain_mask, context, &token_outputs)?;
+		let token_total_change: u64 = token_outputs.iter().map(|(_, _, amount)| amount).sum();
 
 		debug!("Token Change amount is: {}", token_total_change);
 
@@ -273,6 +632,10 @@ where
 		let slate_id = slate.id;
 		let height = current_height;
 		let parent_key_id = context.parent_key_id.clone();
+		let change_parent_key_id = context
+			.change_parent_key_id
+			.clone()
+			.unwrap_or_else(|| parent_key_id.clone());
 		let mut batch = wallet.batch(keychain_mask)?;
 		let log_id = batch.next_tx_log_id(&parent_key_id)?;
 		let token_tx_type = if lock_token_inputs.len() == 0 {
@@ -282,10 +645,17 @@ where
 		};
 		let mut t = TokenTxLogEntry::new(parent_key_id.clone(), token_tx_type.clone(), log_id);
 		t.tx_slate_id = Some(slate_id);
-		t.token_type = slate.token_type.clone().unwrap();
+		t.selection_strategy = context.selection_strategy;
+		t.counterparty_address = counterparty_address(slate, true);
+		t.transport = transport;
+		t.slate_version = Some(slate.version_info.version);
+		t.token_type = token_type.clone();
 		let filename = format!("{}.vcashtx", slate_id);
 		t.stored_tx = Some(filename);
 		t.fee = Some(context.fee);
+		if context.token_fee > 0 {
+			t.token_fee = Some(context.token_fee);
+		}
 		t.ttl_cutoff_height = match slate.ttl_cutoff_height {
 			0 => None,
 			n => Some(n),
@@ -302,7 +672,9 @@ where
 		let mut amount_debited = 0;
 		t.num_inputs = lock_inputs.len();
 		for id in lock_inputs {
-			let mut coin = batch.get(&id.0, &id.1).unwrap();
+			let mut coin = batch
+				.get(&id.0, &id.1)
+				.map_err(|e| ErrorKind::ContextOutputMissing(format!("input {:?}: {}", id.0, e)))?;
 			coin.tx_log_entry = Some(log_id);
 			amount_debited += coin.value;
 			batch.lock_output(&mut coin)?;
@@ -312,12 +684,16 @@ where
 		let mut token_amount_debited = 0;
 		t.num_token_inputs = lock_token_inputs.len();
 		for id in lock_token_inputs {
-			let mut coin = batch.get_token(&id.0, &id.1).unwrap();
+			let mut coin = batch.get_token(&id.0, &id.1).map_err(|e| {
+				ErrorKind::ContextOutputMissing(format!("token input {:?}: {}", id.0, e))
+			})?;
 			coin.tx_log_entry = Some(log_id);
 			token_amount_debited += coin.value;
 			batch.lock_token_output(&mut coin)?;
 		}
 		t.token_amount_debited = token_amount_debited;
+		// the inputs are locked from now on
+		batch.delete_reservation(&slate_id)?;
 
 		// store extra payment proof info, if required
 		if let Some(ref p) = slate.payment_proof {
@@ -341,7 +717,7 @@ where
 				receiver_signature: p.receiver_signature,
 				sender_address: sender_address.to_ed25519()?,
 				sender_address_path,
-				sender_signature: None,
+				sender_signature: p.sender_signature,
 			});
 		};
 
@@ -351,7 +727,7 @@ where
 			let (commit, change_amount) = output_commits.get(&id).unwrap().clone();
 			t.amount_credited += change_amount;
 			batch.save(OutputData {
-				root_key_id: parent_key_id.clone(),
+				root_key_id: change_parent_key_id.clone(),
 				key_id: id.clone(),
 				n_child: id.to_path().last_path_index(),
 				commit: commit,
@@ -361,6 +737,7 @@ where
 				height: height,
 				lock_height: 0,
 				is_coinbase: false,
+				is_frozen: false,
 				tx_log_entry: Some(log_id),
 			})?;
 		}
@@ -371,17 +748,18 @@ where
 			let (commit, change_amount) = token_output_commits.get(&id).unwrap().clone();
 			t.token_amount_credited += change_amount;
 			batch.save_token(TokenOutputData {
-				root_key_id: parent_key_id.clone(),
+				root_key_id: change_parent_key_id.clone(),
 				key_id: id.clone(),
 				n_child: id.to_path().last_path_index(),
 				commit: commit,
-				token_type: slate.token_type.clone().unwrap(),
+				token_type: token_type.clone(),
 				mmr_index: None,
 				value: change_amount,
 				status: OutputStatus::Unconfirmed,
 				height: height,
 				lock_height: 0,
 				is_token_issue: (token_tx_type == TokenTxLogEntryType::TokenIssue),
+				is_frozen: false,
 				tx_log_entry: Some(log_id),
 			})?;
 		}
@@ -392,13 +770,25 @@ where
 		let slate_id = slate.id;
 		let height = current_height;
 		let parent_key_id = context.parent_key_id.clone();
+		let change_parent_key_id = context
+			.change_parent_key_id
+			.clone()
+			.unwrap_or_else(|| parent_key_id.clone());
 		let mut batch = wallet.batch(keychain_mask)?;
 		let log_id = batch.next_tx_log_id(&parent_key_id)?;
 		let mut t = TxLogEntry::new(parent_key_id.clone(), TxLogEntryType::TxSent, log_id);
 		t.tx_slate_id = Some(slate_id);
+		t.selection_strategy = context.selection_strategy;
+		t.counterparty_address = counterparty_address(slate, true);
+		t.transport = transport;
+		t.slate_version = Some(slate.version_info.version);
 		let filename = format!("{}.vcashtx", slate_id);
 		t.stored_tx = Some(filename);
 		t.fee = Some(context.fee);
+		if !context.payouts.is_empty() {
+			t.payouts = Some(context.payouts.clone());
+		}
+		t.memo = context.memo.clone();
 		t.ttl_cutoff_height = match slate.ttl_cutoff_height {
 			0 => None,
 			n => Some(n),
@@ -415,13 +805,17 @@ where
 		let mut amount_debited = 0;
 		t.num_inputs = lock_inputs.len();
 		for id in lock_inputs {
-			let mut coin = batch.get(&id.0, &id.1).unwrap();
+			let mut coin = batch
+				.get(&id.0, &id.1)
+				.map_err(|e| ErrorKind::ContextOutputMissing(format!("input {:?}: {}", id.0, e)))?;
 			coin.tx_log_entry = Some(log_id);
 			amount_debited += coin.value;
 			batch.lock_output(&mut coin)?;
 		}
 
 		t.amount_debited = amount_debited;
+		// the inputs are locked from now on
+		batch.delete_reservation(&slate_id)?;
 
 		// store extra payment proof info, if required
 		if let Some(ref p) = slate.payment_proof {
@@ -445,7 +839,7 @@ where
 				receiver_signature: p.receiver_signature,
 				sender_address: sender_address.to_ed25519()?,
 				sender_address_path,
-				sender_signature: None,
+				sender_signature: p.sender_signature,
 			});
 		};
 
@@ -455,7 +849,7 @@ where
 			let (commit, change_amount) = output_commits.get(&id).unwrap().clone();
 			t.amount_credited += change_amount;
 			batch.save(OutputData {
-				root_key_id: parent_key_id.clone(),
+				root_key_id: change_parent_key_id.clone(),
 				key_id: id.clone(),
 				n_child: id.to_path().last_path_index(),
 				commit: commit,
@@ -465,6 +859,7 @@ where
 				height: height,
 				lock_height: 0,
 				is_coinbase: false,
+				is_frozen: false,
 				tx_log_entry: Some(log_id),
 			})?;
 		}
@@ -478,7 +873,9 @@ where
 
 /// Creates a new output in the wallet for the recipient,
 /// returning the key of the fresh output
-/// Also creates a new transaction containing the output
+/// Also creates a new transaction containing the output. The excess recorded
+/// with the transaction, for later kernel lookup, is the `excess_override` if
+/// one is given, as the slate's can't be relied on when invoicing.
 pub fn build_recipient_output<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
 	keychain_mask: Option<&SecretKey>,
@@ -486,6 +883,8 @@ pub fn build_recipient_output<'a, T: ?Sized, C, K>(
 	current_height: u64,
 	parent_key_id: Identifier,
 	is_invoice: bool,
+	excess_override: Option<pedersen::Commitment>,
+	transport: Option<SlateTransport>,
 	use_test_rng: bool,
 ) -> Result<
 	(
@@ -501,23 +900,25 @@ where
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
 {
+	check_ttl_height(slate, current_height)?;
+	let token_key = slate.token_key()?;
+
 	// Create a potential output for this transaction
-	let key_id = keys::next_available_key(wallet, keychain_mask).unwrap();
+	let key_id = keys::next_available_key(wallet, keychain_mask)?;
 	let keychain = wallet.keychain(keychain_mask)?;
 	let key_id_inner = key_id.clone();
 	let amount = slate.amount;
 	let height = current_height;
 
 	let slate_id = slate.id;
-	let elem = if slate.token_type.clone().is_some() {
-		vec![build::token_output(
+	let elem = match token_key.clone() {
+		Some(token_key) => vec![build::token_output(
 			amount,
-			TokenKey::from_hex(slate.token_type.clone().unwrap().as_str())?,
+			token_key,
 			false,
 			key_id.clone(),
-		)]
-	} else {
-		vec![build::output(amount, key_id.clone())]
+		)],
+		None => vec![build::output(amount, key_id.clone())],
 	};
 	let (blinding, token_blinding) =
 		slate.add_transaction_elements(&keychain, &ProofBuilder::new(&keychain), elem)?;
@@ -536,23 +937,27 @@ where
 		is_invoice,
 	);
 
-	if slate.token_type.clone().is_some() {
+	if token_key.is_some() {
 		context.add_token_output(&key_id, &None, amount);
 	} else {
 		context.add_output(&key_id, &None, amount);
 	};
 	context.amount = amount;
 	context.fee = slate.fee;
+	context.token_type = slate.token_type.clone();
 	let commit = wallet.calc_commit_for_cache(keychain_mask, amount, &key_id_inner)?;
 	let mut batch = wallet.batch(keychain_mask)?;
 	let log_id = batch.next_tx_log_id(&parent_key_id)?;
-	if slate.token_type.clone().is_some() {
+	if token_key.is_some() {
 		let mut t = TokenTxLogEntry::new(
 			parent_key_id.clone(),
 			TokenTxLogEntryType::TokenTxReceived,
 			log_id,
 		);
 		t.tx_slate_id = Some(slate_id);
+		t.counterparty_address = counterparty_address(slate, false);
+		t.transport = transport;
+		t.slate_version = Some(slate.version_info.version);
 		t.token_type = slate.token_type.clone().unwrap();
 		t.token_amount_credited = amount;
 		t.num_token_outputs = 1;
@@ -560,8 +965,14 @@ where
 			0 => None,
 			n => Some(n),
 		};
-		// when invoicing, this will be invalid
-		if let Ok(e) = slate.calc_excess(keychain.secp()) {
+		// when invoicing, the excess isn't known until we finalize, which stores
+		// it along with the token kernel excess
+		if !is_invoice {
+			if let Ok(e) = slate.calc_excess(keychain.secp()) {
+				t.kernel_excess = Some(e)
+			}
+		}
+		if let Some(e) = excess_override {
 			t.kernel_excess = Some(e)
 		}
 		t.kernel_lookup_min_height = Some(current_height);
@@ -577,6 +988,7 @@ where
 			height: height,
 			lock_height: 0,
 			is_token_issue: false,
+			is_frozen: false,
 			tx_log_entry: Some(log_id),
 		})?;
 		batch.save_token_tx_log_entry(t.clone(), &parent_key_id)?;
@@ -586,6 +998,9 @@ where
 	} else {
 		let mut t = TxLogEntry::new(parent_key_id.clone(), TxLogEntryType::TxReceived, log_id);
 		t.tx_slate_id = Some(slate_id);
+		t.counterparty_address = counterparty_address(slate, false);
+		t.transport = transport;
+		t.slate_version = Some(slate.version_info.version);
 		t.amount_credited = amount;
 		t.num_outputs = 1;
 		t.ttl_cutoff_height = match slate.ttl_cutoff_height {
@@ -596,6 +1011,9 @@ where
 		if let Ok(e) = slate.calc_excess(keychain.secp()) {
 			t.kernel_excess = Some(e)
 		}
+		if let Some(e) = excess_override {
+			t.kernel_excess = Some(e)
+		}
 		t.kernel_lookup_min_height = Some(current_height);
 		batch.save(OutputData {
 			root_key_id: parent_key_id.clone(),
@@ -608,6 +1026,7 @@ where
 			height: height,
 			lock_height: 0,
 			is_coinbase: false,
+			is_frozen: false,
 			tx_log_entry: Some(log_id),
 		})?;
 		batch.save_tx_log_entry(t.clone(), &parent_key_id)?;
@@ -617,6 +1036,121 @@ where
 	}
 }
 
+/// Creates a new output in the wallet for each recipient, given as its amount
+/// and the parent key (account) it belongs to. The amounts must add up to the
+/// slate amount. Also creates a single new transaction, under `parent_key_id`,
+/// crediting the outputs.
+pub fn build_recipient_outputs<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	slate: &mut Slate,
+	current_height: u64,
+	parent_key_id: Identifier,
+	recipients: Vec<(u64, Identifier)>,
+	transport: Option<SlateTransport>,
+	use_test_rng: bool,
+) -> Result<(Context, TxLogEntry), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	if slate.token_type.is_some() {
+		return Err(
+			ErrorKind::InvalidPayouts("token transactions can't be batched".to_owned()).into(),
+		);
+	}
+	check_ttl_height(slate, current_height)?;
+	if recipients.is_empty() || recipients.iter().any(|(amount, _)| *amount == 0) {
+		return Err(ErrorKind::InvalidPayouts("payout amounts must be positive".to_owned()).into());
+	}
+	let total = recipients
+		.iter()
+		.try_fold(0u64, |acc, (amount, _)| acc.checked_add(*amount));
+	if total != Some(slate.amount) {
+		return Err(ErrorKind::InvalidPayouts(format!(
+			"payouts don't add up to the transaction amount of {}",
+			amount_to_hr_string(slate.amount, false)
+		))
+		.into());
+	}
+
+	// Create an output for each recipient, under its own parent
+	let keychain = wallet.keychain(keychain_mask)?;
+	let mut elems = vec![];
+	let mut key_ids = vec![];
+	for (amount, recipient_key_id) in &recipients {
+		let key_id = keys::next_available_key_for(wallet, keychain_mask, recipient_key_id)?;
+		elems.push(build::output(*amount, key_id.clone()));
+		key_ids.push(key_id);
+	}
+	// all outputs are added at once, so the returned blinding sum covers every
+	// one of them rather than just the last
+	let (blinding, token_blinding) =
+		slate.add_transaction_elements(&keychain, &ProofBuilder::new(&keychain), elems)?;
+
+	// Add blinding sum to our context
+	let mut context = Context::new(
+		keychain.secp(),
+		blinding.secret_key(keychain.secp())?,
+		token_blinding.secret_key(keychain.secp())?,
+		&parent_key_id,
+		use_test_rng,
+		false,
+	);
+	let outputs: Vec<_> = key_ids
+		.iter()
+		.zip(&recipients)
+		.map(|(key_id, (amount, _))| (*amount, key_id.clone()))
+		.collect();
+	for (amount, key_id) in &outputs {
+		context.add_output(key_id, &None, *amount);
+	}
+	let commits = wallet.calc_commits_for_cache(keychain_mask, &outputs)?;
+	context.amount = slate.amount;
+	context.fee = slate.fee;
+
+	let mut batch = wallet.batch(keychain_mask)?;
+	let log_id = batch.next_tx_log_id(&parent_key_id)?;
+	let mut t = TxLogEntry::new(parent_key_id.clone(), TxLogEntryType::TxReceived, log_id);
+	t.tx_slate_id = Some(slate.id);
+	t.counterparty_address = counterparty_address(slate, false);
+	t.transport = transport;
+	t.slate_version = Some(slate.version_info.version);
+	t.amount_credited = slate.amount;
+	t.num_outputs = recipients.len();
+	t.ttl_cutoff_height = match slate.ttl_cutoff_height {
+		0 => None,
+		n => Some(n),
+	};
+	if let Ok(e) = slate.calc_excess(keychain.secp()) {
+		t.kernel_excess = Some(e)
+	}
+	t.kernel_lookup_min_height = Some(current_height);
+	for ((key_id, (amount, recipient_key_id)), commit) in
+		key_ids.iter().zip(&recipients).zip(commits)
+	{
+		batch.save(OutputData {
+			root_key_id: recipient_key_id.clone(),
+			key_id: key_id.clone(),
+			mmr_index: None,
+			n_child: key_id.to_path().last_path_index(),
+			commit,
+			value: *amount,
+			status: OutputStatus::Unconfirmed,
+			height: current_height,
+			lock_height: 0,
+			is_coinbase: false,
+			is_frozen: false,
+			tx_log_entry: Some(log_id),
+		})?;
+	}
+	batch.save_tx_log_entry(t.clone(), &parent_key_id)?;
+	batch.commit()?;
+
+	Ok((context, t))
+}
+
 /// Builds a transaction to send to someone from the HD seed associated with the
 /// wallet and the amount to send. Handles reading through the wallet data file,
 /// selecting outputs to spend and building the change.
@@ -625,76 +1159,147 @@ pub fn select_send_tx<'a, T: ?Sized, C, K, B>(
 	keychain_mask: Option<&SecretKey>,
 	amount: u64,
 	current_height: u64,
-	minimum_confirmations: u64,
-	max_outputs: usize,
-	change_outputs: usize,
-	selection_strategy_is_use_all: bool,
+	params: &SendParams,
+	coin_selector: &dyn CoinSelector,
 	parent_key_id: &Identifier,
+	extra_parent_key_ids: &[Identifier],
+	change_parent_key_id: Option<&Identifier>,
 	token_inputs: usize,
 	token_outputs: usize,
 	include_inputs_in_sum: bool,
-) -> Result<
-	(
-		Vec<Box<build::Append<K, B>>>,
-		Vec<OutputData>,
-		Vec<(u64, Identifier, Option<u64>)>, // change amounts and derivations
-		u64,                                 // fee
-	),
-	Error,
->
+) -> Result<SelectionResult<K, B>, Error>
 where
 	T: WalletBackend<'a, C, K>,
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
 	B: ProofBuild,
 {
-	let (coins, _total, amount, fee) = select_coins_and_fee(
-		wallet,
-		amount,
-		current_height,
-		minimum_confirmations,
-		max_outputs,
-		change_outputs,
-		selection_strategy_is_use_all,
-		&parent_key_id,
-		token_inputs,
-		token_outputs,
-	)?;
+	let minimum_confirmations = params.minimum_confirmations;
+	// coinbase outputs need the stricter of the two thresholds
+	let min_coinbase_confirmations = params
+		.coinbase_min_confirmations
+		.unwrap_or(minimum_confirmations);
+	// inputs may come from further accounts than the one sending
+	let mut parent_key_ids = vec![parent_key_id.clone()];
+	parent_key_ids.extend(
+		extra_parent_key_ids
+			.iter()
+			.filter(|id| *id != parent_key_id)
+			.cloned(),
+	);
+
+	let (coins, total, amount, fee) = if params.send_all {
+		select_all_coins_and_fee(
+			wallet,
+			current_height,
+			minimum_confirmations,
+			min_coinbase_confirmations,
+			params.selection.max_outputs,
+			params.fee_base,
+			params.max_fee,
+			&parent_key_ids,
+			token_inputs,
+			token_outputs,
+			params.num_kernels,
+		)?
+	} else {
+		let required_inputs = select_coins_explicit(
+			wallet,
+			&params.required_inputs,
+			current_height,
+			minimum_confirmations,
+			min_coinbase_confirmations,
+			&parent_key_ids,
+		)?;
+		select_coins_and_fee(
+			wallet,
+			amount,
+			current_height,
+			params,
+			coin_selector,
+			&required_inputs,
+			&parent_key_ids,
+			token_inputs,
+			token_outputs,
+		)?
+	};
+
+	// the fee of a late locked transaction was agreed with the recipient before
+	// its inputs were selected, so the change is built around that fee as long
+	// as it still covers the inputs selected now
+	let fee = match params.fixed_fee {
+		Some(f) if f < fee => {
+			return Err(ErrorKind::LateLock(format!(
+				"agreed fee of {} is below the fee of {} the selected inputs require",
+				f, fee
+			))
+			.into());
+		}
+		Some(f) => f,
+		None => fee,
+	};
 
 	// build transaction skeleton with inputs and change
-	let (parts, change_amounts_derivations) = inputs_and_change(
+	let (elems, change) = inputs_and_change(
 		&coins,
 		wallet,
 		keychain_mask,
 		amount,
 		fee,
-		change_outputs,
+		params.change_outputs,
+		params.dust_threshold,
+		&params.change_plan,
+		change_parent_key_id,
 		include_inputs_in_sum,
 	)?;
 
-	Ok((parts, coins, change_amounts_derivations, fee))
+	Ok(SelectionResult {
+		elems,
+		inputs: coins,
+		change,
+		amount,
+		fee,
+		total,
+	})
 }
 
-/// Builds a transaction to send to someone from the HD seed associated with the
-/// wallet and the amount to send. Handles reading through the wallet data file,
-/// selecting outputs to spend and building the change.
-pub fn select_send_token_tx<'a, T: ?Sized, C, K, B>(
+/// Reports base funds that can't cover the fee of a token transaction as such,
+/// the token balance having been covered already
+pub fn fee_funds_error(e: Error) -> Error {
+	match e.kind() {
+		ErrorKind::NotEnoughFunds {
+			available,
+			available_disp,
+			needed,
+			needed_disp,
+			..
+		} => ErrorKind::NotEnoughFeeFunds {
+			available,
+			available_disp,
+			needed,
+			needed_disp,
+		}
+		.into(),
+		_ => e,
+	}
+}
+
+/// Previews the selection `select_send_tx` would make for a send, without
+/// building the transaction. No change keys are derived and nothing is
+/// written to the wallet, so it's safe to call as often as needed. A balance
+/// that doesn't cover the send fails with the same `NotEnoughFunds` error.
+pub fn estimate_send<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
-	keychain_mask: Option<&SecretKey>,
 	amount: u64,
-	token_type: &str,
 	current_height: u64,
-	minimum_confirmations: u64,
-	max_outputs: usize,
-	change_outputs: usize,
-	selection_strategy_is_use_all: bool,
+	params: &SendParams,
 	parent_key_id: &Identifier,
-	include_inputs_in_sum: bool,
 ) -> Result<
 	(
-		Vec<Box<build::Append<K, B>>>,
-		Vec<TokenOutputData>,
-		Vec<(u64, Identifier, Option<u64>)>, // change amounts and derivations
+		u64,   // fee
+		u64,   // total
+		usize, // number of inputs
+		usize, // number of change outputs
 	),
 	Error,
 >
@@ -702,52 +1307,31 @@ where
 	T: WalletBackend<'a, C, K>,
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
-	B: ProofBuild,
 {
-	let (coins, _total, amount) = select_token_coins_and_fee(
-		wallet,
-		amount,
-		token_type,
-		current_height,
-		minimum_confirmations,
-		max_outputs,
-		selection_strategy_is_use_all,
-		&parent_key_id,
-	)?;
+	let (coins, _, fee, change) =
+		preview_send(wallet, amount, current_height, params, parent_key_id, 0, 0)?;
+	let total = coins.iter().map(|c| c.value).sum();
+	Ok((fee, total, coins.len(), change.len()))
+}
 
-	// build transaction skeleton with inputs and change
-	let token_type = TokenKey::from_hex(&token_type)?;
-	let (parts, change_amounts_derivations) = token_inputs_and_change(
-		&coins,
-		wallet,
-		keychain_mask,
-		amount,
-		token_type,
-		change_outputs,
-		include_inputs_in_sum,
-	)?;
-
-	Ok((parts, coins, change_amounts_derivations))
-}
-
-/// Select outputs and calculating fee.
-pub fn select_coins_and_fee<'a, T: ?Sized, C, K>(
+/// As `estimate_send`, but returns the selected outputs and the amounts of the
+/// change outputs `inputs_and_change` would build. The base leg of a token
+/// transaction passes its token input and output counts, so the fee covers
+/// them.
+pub fn preview_send<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
 	amount: u64,
 	current_height: u64,
-	minimum_confirmations: u64,
-	max_outputs: usize,
-	change_outputs: usize,
-	selection_strategy_is_use_all: bool,
+	params: &SendParams,
 	parent_key_id: &Identifier,
 	token_inputs: usize,
 	token_outputs: usize,
 ) -> Result<
 	(
 		Vec<OutputData>,
-		u64, // total
-		u64, // amount
-		u64, // fee
+		u64,      // amount
+		u64,      // fee
+		Vec<u64>, // change amounts
 	),
 	Error,
 >
@@ -756,193 +1340,222 @@ where
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
 {
-	let min_fee = DEFAULT_BASE_FEE;
-	let amount_with_fee = amount + min_fee;
+	let minimum_confirmations = params.minimum_confirmations;
+	let min_coinbase_confirmations = params
+		.coinbase_min_confirmations
+		.unwrap_or(minimum_confirmations);
 
-	// select some spendable coins from the wallet
-	let (max_outputs, mut coins) = select_coins(
+	let (coins, total, amount, fee) = if params.send_all {
+		select_all_coins_and_fee(
+			wallet,
+			current_height,
+			minimum_confirmations,
+			min_coinbase_confirmations,
+			params.selection.max_outputs,
+			params.fee_base,
+			params.max_fee,
+			std::slice::from_ref(parent_key_id),
+			token_inputs,
+			token_outputs,
+			params.num_kernels,
+		)?
+	} else {
+		let required_inputs = select_coins_explicit(
+			wallet,
+			&params.required_inputs,
+			current_height,
+			minimum_confirmations,
+			min_coinbase_confirmations,
+			std::slice::from_ref(parent_key_id),
+		)?;
+		select_coins_and_fee(
+			wallet,
+			amount,
+			current_height,
+			params,
+			&StrategySelector,
+			&required_inputs,
+			std::slice::from_ref(parent_key_id),
+			token_inputs,
+			token_outputs,
+		)?
+	};
+
+	// same change as inputs_and_change would build, without the keys
+	let change = change_from(total, amount, fee, coins.iter().map(|c| c.value))?;
+	let change_amounts = if change == 0 {
+		vec![]
+	} else {
+		plan_change(
+			change,
+			params.change_outputs,
+			params.dust_threshold,
+			&params.change_plan,
+		)
+	};
+
+	Ok((coins, amount, fee, change_amounts))
+}
+
+/// The smallest amount worth sending from the wallet: what the smallest
+/// eligible output covering the fee leaves the recipient when spent alone,
+/// without change. Zero if no single output covers the fee.
+pub fn min_sendable_amount<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	current_height: u64,
+	minimum_confirmations: u64,
+	coinbase_min_confirmations: Option<u64>,
+	parent_key_id: &Identifier,
+) -> u64
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let min_coinbase_confirmations = coinbase_min_confirmations.unwrap_or(minimum_confirmations);
+	let eligible = eligible_coins(
 		wallet,
-		amount_with_fee,
 		current_height,
 		minimum_confirmations,
-		max_outputs,
-		selection_strategy_is_use_all,
+		min_coinbase_confirmations,
+		SelectionStrategy::SmallestFirst,
+		&[],
 		parent_key_id,
 	);
+	min_sendable_from(
+		eligible.iter().map(|o| o.value),
+		tx_fee(1, 1, 1, 0, 0, 0, None),
+	)
+}
 
-	// sender is responsible for setting the fee on the partial tx
-	// recipient should double check the fee calculation and not blindly trust the
-	// sender
-
-	// TODO - Is it safe to spend without a change output? (1 input -> 1 output)
-	// TODO - Does this not potentially reveal the senders private key?
-	//
-
-	// First attempt to spend without change
-	let output_len = if amount == 0 { 0 } else { 1 };
-
-	let token_kernel_len = if token_outputs == 0 { 0 } else { 1 };
-	let mut fee = tx_fee(
-		coins.len(),
-		output_len,
-		1,
-		token_inputs,
-		token_outputs,
-		token_kernel_len,
-		None,
+/// The smallest token amount worth sending from the wallet, its smallest
+/// eligible token output spent whole. The fee is paid from base outputs, so
+/// unlike `min_sendable_amount` nothing is taken off. Zero if the wallet holds
+/// none of the token.
+pub fn min_sendable_token_amount<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	token_type: &str,
+	current_height: u64,
+	minimum_confirmations: u64,
+	parent_key_id: &Identifier,
+) -> u64
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let eligible = eligible_token_coins(
+		wallet,
+		token_type,
+		current_height,
+		minimum_confirmations,
+		SelectionStrategy::SmallestFirst,
+		parent_key_id,
 	);
-	let mut total: u64 = coins.iter().map(|c| c.value).sum();
-	let mut amount_with_fee = amount + fee;
-
-	if total == 0 {
-		return Err(ErrorKind::NotEnoughFunds {
-			available: 0,
-			available_disp: amount_to_hr_string(0, false),
-			needed: amount_with_fee as u64,
-			needed_disp: amount_to_hr_string(amount_with_fee as u64, false),
-		}
-		.into());
-	}
-
-	// The amount with fee is more than the total values of our max outputs
-	if total < amount_with_fee && coins.len() == max_outputs {
-		return Err(ErrorKind::NotEnoughFunds {
-			available: total,
-			available_disp: amount_to_hr_string(total, false),
-			needed: amount_with_fee as u64,
-			needed_disp: amount_to_hr_string(amount_with_fee as u64, false),
-		}
-		.into());
-	}
-
-	let num_outputs = change_outputs + output_len;
-
-	// We need to add a change address or amount with fee is more than total
-	if total != amount_with_fee {
-		fee = tx_fee(
-			coins.len(),
-			num_outputs,
-			1,
-			token_inputs,
-			token_outputs,
-			token_kernel_len,
-			None,
-		);
-		amount_with_fee = amount + fee;
-
-		// Here check if we have enough outputs for the amount including fee otherwise
-		// look for other outputs and check again
-		while total < amount_with_fee {
-			// End the loop if we have selected all the outputs and still not enough funds
-			if coins.len() == max_outputs {
-				return Err(ErrorKind::NotEnoughFunds {
-					available: total as u64,
-					available_disp: amount_to_hr_string(total, false),
-					needed: amount_with_fee as u64,
-					needed_disp: amount_to_hr_string(amount_with_fee as u64, false),
-				}
-				.into());
-			}
+	min_sendable_from(eligible.iter().map(|o| o.value), 0)
+}
 
-			// select some spendable coins from the wallet
-			coins = select_coins(
-				wallet,
-				amount_with_fee,
-				current_height,
-				minimum_confirmations,
-				max_outputs,
-				selection_strategy_is_use_all,
-				parent_key_id,
-			)
-			.1;
-			fee = tx_fee(
-				coins.len(),
-				num_outputs,
-				1,
-				token_inputs,
-				token_outputs,
-				token_kernel_len,
-				None,
-			);
-			total = coins.iter().map(|c| c.value).sum();
-			amount_with_fee = amount + fee;
-		}
-	}
-	Ok((coins, total, amount, fee))
+/// The smallest of the output values above the fee, less the fee
+fn min_sendable_from<I>(values: I, fee: u64) -> u64
+where
+	I: Iterator<Item = u64>,
+{
+	values
+		.filter(|v| *v > fee)
+		.min()
+		.map(|v| v - fee)
+		.unwrap_or(0)
 }
 
-/// Select outputs and calculating fee.
-pub fn select_token_coins_and_fee<'a, T: ?Sized, C, K>(
+/// Builds a transaction to send to someone from the HD seed associated with the
+/// wallet and the amount to send. Handles reading through the wallet data file,
+/// selecting outputs to spend and building the change. A nonzero `token_fee` is
+/// paid from the token inputs on top of the amount, or out of the amount when
+/// sending everything.
+pub fn select_send_token_tx<'a, T: ?Sized, C, K, B>(
 	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
 	amount: u64,
+	token_fee: u64,
 	token_type: &str,
 	current_height: u64,
 	minimum_confirmations: u64,
 	max_outputs: usize,
-	selection_strategy_is_use_all: bool,
+	change_outputs: usize,
+	selection_strategy: SelectionStrategy,
+	coin_selector: &dyn CoinSelector,
+	dust_threshold: u64,
+	send_all: bool,
 	parent_key_id: &Identifier,
-) -> Result<
-	(
-		Vec<TokenOutputData>,
-		u64, // total
-		u64, // amount
-	),
-	Error,
->
+	change_parent_key_id: Option<&Identifier>,
+	include_inputs_in_sum: bool,
+) -> Result<TokenSelectionResult<K, B>, Error>
 where
 	T: WalletBackend<'a, C, K>,
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
+	B: ProofBuild,
 {
-	// select some spendable coins from the wallet
-	let (max_outputs, coins) = select_token_coins(
+	let (coins, total, amount, token_fee) = select_token_coins_for_send(
 		wallet,
 		amount,
+		token_fee,
 		token_type,
 		current_height,
 		minimum_confirmations,
 		max_outputs,
-		selection_strategy_is_use_all,
+		selection_strategy,
+		coin_selector,
+		send_all,
 		parent_key_id,
-	);
-
-	let total: u64 = coins.iter().map(|c| c.value).sum();
-
-	if total == 0 {
-		return Err(ErrorKind::NotEnoughFunds {
-			available: 0,
-			available_disp: amount_to_hr_string(0, false),
-			needed: amount as u64,
-			needed_disp: amount_to_hr_string(amount as u64, false),
-		})?;
-	}
+	)?;
 
-	// The amount with fee is more than the total values of our max outputs
-	if total < amount && coins.len() == max_outputs {
-		return Err(ErrorKind::NotEnoughFunds {
-			available: total,
-			available_disp: amount_to_hr_string(total, false),
-			needed: amount as u64,
-			needed_disp: amount_to_hr_string(amount as u64, false),
-		})?;
-	}
+	// build transaction skeleton with inputs and change
+	let token_type = TokenKey::from_hex(&token_type)?;
+	let (elems, change) = token_inputs_and_change(
+		&coins,
+		wallet,
+		keychain_mask,
+		amount,
+		token_fee,
+		token_type,
+		change_outputs,
+		dust_threshold,
+		change_parent_key_id,
+		include_inputs_in_sum,
+	)?;
 
-	Ok((coins, total, amount))
+	Ok(TokenSelectionResult {
+		elems,
+		inputs: coins,
+		change,
+		amount,
+		fee: token_fee,
+		total,
+	})
 }
 
-/// Selects inputs and change for a transaction
-pub fn inputs_and_change<'a, T: ?Sized, C, K, B>(
-	coins: &[OutputData],
+/// Previews the token inputs and change `select_send_token_tx` would use for a
+/// token send, without deriving change keys or writing to the wallet
+pub fn preview_send_token<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
-	keychain_mask: Option<&SecretKey>,
 	amount: u64,
-	fee: u64,
-	num_change_outputs: usize,
-	include_inputs_in_sum: bool,
+	token_fee: u64,
+	token_type: &str,
+	current_height: u64,
+	minimum_confirmations: u64,
+	max_outputs: usize,
+	change_outputs: usize,
+	selection_strategy: SelectionStrategy,
+	dust_threshold: u64,
+	send_all: bool,
+	parent_key_id: &Identifier,
 ) -> Result<
 	(
-		Vec<Box<build::Append<K, B>>>,
-		Vec<(u64, Identifier, Option<u64>)>,
+		Vec<TokenOutputData>,
+		u64,      // amount
+		u64,      // token fee
+		Vec<u64>, // change amounts
 	),
 	Error,
 >
@@ -950,73 +1563,54 @@ where
 	T: WalletBackend<'a, C, K>,
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
-	B: ProofBuild,
 {
-	let mut parts = vec![];
-
-	// calculate the total across all inputs, and how much is left
-	let total: u64 = coins.iter().map(|c| c.value).sum();
-
-	// if we are spending 10,000 coins to send 1,000 then our change will be 9,000
-	// if the fee is 80 then the recipient will receive 1000 and our change will be
-	// 8,920
-	let change = total - amount - fee;
-
-	// build inputs using the appropriate derived key_ids
-	if include_inputs_in_sum {
-		for coin in coins {
-			if coin.is_coinbase {
-				parts.push(build::coinbase_input(coin.value, coin.key_id.clone()));
-			} else {
-				parts.push(build::input(coin.value, coin.key_id.clone()));
-			}
-		}
-	}
-
-	let mut change_amounts_derivations = vec![];
+	let (coins, total, amount, token_fee) = select_token_coins_for_send(
+		wallet,
+		amount,
+		token_fee,
+		token_type,
+		current_height,
+		minimum_confirmations,
+		max_outputs,
+		selection_strategy,
+		&StrategySelector,
+		send_all,
+		parent_key_id,
+	)?;
 
-	if change == 0 {
-		debug!("No change (sending exactly amount + fee), no change outputs to build");
+	// same change as token_inputs_and_change would build, without the keys
+	let change = change_from(total, amount, token_fee, coins.iter().map(|c| c.value))
+		.map_err(|e| token_funds_error(e, token_type))?;
+	let change_amounts = if change == 0 {
+		vec![]
 	} else {
-		debug!(
-			"Building change outputs: total change: {} ({} outputs)",
-			change, num_change_outputs
-		);
-
-		let part_change = change / num_change_outputs as u64;
-		let remainder_change = change % part_change;
-
-		for x in 0..num_change_outputs {
-			// n-1 equal change_outputs and a final one accounting for any remainder
-			let change_amount = if x == (num_change_outputs - 1) {
-				part_change + remainder_change
-			} else {
-				part_change
-			};
-
-			let change_key = wallet.next_child(keychain_mask).unwrap();
-
-			change_amounts_derivations.push((change_amount, change_key.clone(), None));
-			parts.push(build::output(change_amount, change_key));
-		}
-	}
+		plan_token_change(change, change_outputs, dust_threshold)
+	};
 
-	Ok((parts, change_amounts_derivations))
+	Ok((coins, amount, token_fee, change_amounts))
 }
 
-/// Selects token inputs and change for a transaction
-pub fn token_inputs_and_change<'a, T: ?Sized, C, K, B>(
-	coins: &Vec<TokenOutputData>,
+/// Selects the token outputs paying a token send and its token fee. When
+/// sending everything, the amount is what the whole balance leaves once the
+/// token fee is paid.
+fn select_token_coins_for_send<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
-	keychain_mask: Option<&SecretKey>,
 	amount: u64,
-	token_type: TokenKey,
-	num_change_outputs: usize,
-	include_inputs_in_sum: bool,
+	token_fee: u64,
+	token_type: &str,
+	current_height: u64,
+	minimum_confirmations: u64,
+	max_outputs: usize,
+	selection_strategy: SelectionStrategy,
+	coin_selector: &dyn CoinSelector,
+	send_all: bool,
+	parent_key_id: &Identifier,
 ) -> Result<
 	(
-		Vec<Box<build::Append<K, B>>>,
-		Vec<(u64, Identifier, Option<u64>)>,
+		Vec<TokenOutputData>,
+		u64, // total
+		u64, // amount
+		u64, // token fee
 	),
 	Error,
 >
@@ -1024,251 +1618,171 @@ where
 	T: WalletBackend<'a, C, K>,
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
-	B: ProofBuild,
 {
-	let mut parts = vec![];
-
-	// calculate the total across all inputs, and how much is left
-	let total: u64 = coins.iter().map(|c| c.value).sum();
-
-	// if we are spending 10,000 coins to send 1,000 then our change will be 9,000
-	// if the fee is 80 then the recipient will receive 1000 and our change will be
-	// 8,920
-	let change = total - amount;
-
-	// build inputs using the appropriate derived key_ids
-	if include_inputs_in_sum {
-		for coin in coins {
-			parts.push(build::build_token_input(
-				coin.value,
-				token_type,
-				coin.is_token_issue,
-				coin.key_id.clone(),
-			));
-		}
-	}
-
-	let mut change_amounts_derivations = vec![];
-
-	if change == 0 {
-		debug!("No Token change (sending exactly amount + fee), no change outputs to build");
-	} else {
-		debug!(
-			"Building Token change outputs: total change: {} ({} outputs)",
-			change, num_change_outputs
+	if !send_all {
+		return select_token_coins_and_fee(
+			wallet,
+			amount,
+			token_fee,
+			token_type,
+			current_height,
+			minimum_confirmations,
+			max_outputs,
+			selection_strategy,
+			coin_selector,
+			parent_key_id,
 		);
-
-		let part_change = change / num_change_outputs as u64;
-		let remainder_change = change % part_change;
-
-		for x in 0..num_change_outputs {
-			// n-1 equal change_outputs and a final one accounting for any remainder
-			let change_amount = if x == (num_change_outputs - 1) {
-				part_change + remainder_change
-			} else {
-				part_change
-			};
-
-			let change_key = wallet.next_child(keychain_mask).unwrap();
-
-			change_amounts_derivations.push((change_amount, change_key.clone(), None));
-			parts.push(build::token_output(
-				change_amount,
-				token_type,
-				false,
-				change_key,
-			));
+	}
+	let (coins, total, _) = select_all_token_coins(
+		wallet,
+		token_type,
+		current_height,
+		minimum_confirmations,
+		max_outputs,
+		parent_key_id,
+	)?;
+	if token_fee >= total {
+		return Err(ErrorKind::FeeExceedsAmount {
+			fee: token_fee,
+			amount: total,
 		}
+		.into());
 	}
-
-	Ok((parts, change_amounts_derivations))
+	Ok((coins, total, total - token_fee, token_fee))
 }
 
-/// Select spendable coins from a wallet.
-/// Default strategy is to spend the maximum number of outputs (up to
-/// max_outputs). Alternative strategy is to spend smallest outputs first
-/// but only as many as necessary. When we introduce additional strategies
-/// we should pass something other than a bool in.
-/// TODO: Possibly move this into another trait to be owned by a wallet?
-
-pub fn select_coins<'a, T: ?Sized, C, K>(
+/// Selects every eligible output of the given accounts to send the whole
+/// balance, without change. The amount sent is the total of the outputs less
+/// the fee. A wallet holding more than `max_outputs` eligible outputs only
+/// sweeps the largest of them, so the transaction stays within the same bound
+/// as any other send.
+pub fn select_all_coins_and_fee<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
-	amount: u64,
 	current_height: u64,
 	minimum_confirmations: u64,
+	min_coinbase_confirmations: u64,
 	max_outputs: usize,
-	select_all: bool,
-	parent_key_id: &Identifier,
-) -> (usize, Vec<OutputData>)
-//    max_outputs_available, Outputs
+	fee_base: Option<u64>,
+	max_fee: Option<u64>,
+	parent_key_ids: &[Identifier],
+	token_inputs: usize,
+	token_outputs: usize,
+	num_kernels: usize,
+) -> Result<
+	(
+		Vec<OutputData>,
+		u64, // total
+		u64, // amount
+		u64, // fee
+	),
+	Error,
+>
 where
 	T: WalletBackend<'a, C, K>,
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
 {
-	// first find all eligible outputs based on number of confirmations
-	let mut eligible = wallet
-		.iter()
-		.filter(|out| {
-			out.root_key_id == *parent_key_id
-				&& out.eligible_to_spend(current_height, minimum_confirmations)
-		})
-		.collect::<Vec<OutputData>>();
-
-	let max_available = eligible.len();
-
-	// sort eligible outputs by increasing value
-	eligible.sort_by_key(|out| out.value);
+	let mut coins = eligible_coins_for_parents(
+		wallet,
+		current_height,
+		minimum_confirmations,
+		min_coinbase_confirmations,
+		SelectionStrategy::LargestFirst,
+		&[],
+		parent_key_ids,
+	);
+	coins.truncate(max_outputs);
+	let total: u64 = coins.iter().map(|c| c.value).sum();
 
-	// use a sliding window to identify potential sets of possible outputs to spend
-	// Case of amount > total amount of max_outputs(500):
-	// The limit exists because by default, we always select as many inputs as
-	// possible in a transaction, to reduce both the Output set and the fees.
-	// But that only makes sense up to a point, hence the limit to avoid being too
-	// greedy. But if max_outputs(500) is actually not enough to cover the whole
-	// amount, the wallet should allow going over it to satisfy what the user
-	// wants to send. So the wallet considers max_outputs more of a soft limit.
-	if eligible.len() > max_outputs {
-		for window in eligible.windows(max_outputs) {
-			let windowed_eligibles = window.to_vec();
-			if let Some(outputs) = select_from(amount, select_all, windowed_eligibles) {
-				return (max_available, outputs);
-			}
-		}
-		// Not exist in any window of which total amount >= amount.
-		// Then take coins from the smallest one up to the total amount of selected
-		// coins = the amount.
-		if let Some(outputs) = select_from(amount, false, eligible.clone()) {
-			debug!(
-				"Extending maximum number of outputs. {} outputs selected.",
-				outputs.len()
-			);
-			return (max_available, outputs);
-		}
-	} else if let Some(outputs) = select_from(amount, select_all, eligible.clone()) {
-		return (max_available, outputs);
+	// the recipient's output is the only one
+	let token_kernel_len = if token_outputs == 0 { 0 } else { 1 };
+	let fee = tx_fee(
+		coins.len(),
+		1,
+		num_kernels,
+		token_inputs,
+		token_outputs,
+		token_kernel_len,
+		fee_base,
+	);
+	if total <= fee {
+		return Err(not_enough_funds(
+			total,
+			fee + 1,
+			coins.iter().map(|c| c.value),
+		));
 	}
-
-	// we failed to find a suitable set of outputs to spend,
-	// so return the largest amount we can so we can provide guidance on what is
-	// possible
-	eligible.reverse();
-	(
-		max_available,
-		eligible.iter().take(max_outputs).cloned().collect(),
-	)
-}
-
-fn select_from(amount: u64, select_all: bool, outputs: Vec<OutputData>) -> Option<Vec<OutputData>> {
-	let total = outputs.iter().fold(0, |acc, x| acc + x.value);
-	if total >= amount {
-		if select_all {
-			Some(outputs.to_vec())
-		} else {
-			let mut selected_amount = 0;
-			Some(
-				outputs
-					.iter()
-					.take_while(|out| {
-						let res = selected_amount < amount;
-						selected_amount += out.value;
-						res
-					})
-					.cloned()
-					.collect(),
-			)
+	if let Some(max) = max_fee {
+		if fee > max {
+			return Err(ErrorKind::FeeExceedsMaximum { fee, max }.into());
 		}
-	} else {
-		None
 	}
+	Ok((coins, total, total - fee, fee))
 }
 
-pub fn build_issue_token_tx<'a, T: ?Sized, C, K>(
+/// Selects every eligible token output of the given type to send the whole
+/// token balance, without change. As with `select_all_coins_and_fee`, only the
+/// largest `max_outputs` outputs are swept.
+pub fn select_all_token_coins<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
-	keychain: &K,
-	keychain_mask: Option<&SecretKey>,
-	slate: &mut Slate,
+	token_type: &str,
 	current_height: u64,
 	minimum_confirmations: u64,
 	max_outputs: usize,
-	change_outputs: usize,
-	selection_strategy_is_use_all: bool,
-	parent_key_id: Identifier,
-	use_test_nonce: bool,
-) -> Result<Context, Error>
+	parent_key_id: &Identifier,
+) -> Result<
+	(
+		Vec<TokenOutputData>,
+		u64, // total
+		u64, // amount
+	),
+	Error,
+>
 where
 	T: WalletBackend<'a, C, K>,
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
 {
-	let (mut elems, inputs, change_amounts_derivations, fee) = select_send_tx(
+	let mut coins = eligible_token_coins(
 		wallet,
-		keychain_mask,
-		0,
+		token_type,
 		current_height,
 		minimum_confirmations,
-		max_outputs,
-		change_outputs,
-		selection_strategy_is_use_all,
-		&parent_key_id,
-		0,
-		1,
-		true,
-	)?;
-
-	let token_type = TokenKey::new_token_key();
-	let (mut token_elems, (amount, key_id, mmr_index)) =
-		token_issue_output(wallet, keychain_mask, slate.amount, token_type.clone())?;
-
-	let mut all_elems = vec![];
-	all_elems.append(&mut token_elems);
-	all_elems.append(&mut elems);
-
-	slate.fee = fee;
-	let (blinding, token_blinding) =
-		slate.add_transaction_elements(keychain, &ProofBuilder::new(keychain), all_elems)?;
-
-	slate.token_type = Some(token_type.to_hex());
-	slate.construct_issue_token_kernel(keychain, amount, &key_id)?;
-
-	// Create our own private context
-	let mut context = Context::new(
-		keychain.secp(),
-		blinding.secret_key(&keychain.secp()).unwrap(),
-		token_blinding.secret_key(&keychain.secp()).unwrap(),
-		&parent_key_id,
-		use_test_nonce,
-		false,
+		SelectionStrategy::LargestFirst,
+		parent_key_id,
 	);
-
-	context.fee = fee;
-
-	// Store our private identifiers for each input
-	for input in inputs {
-		context.add_input(&input.key_id, &input.mmr_index, input.value);
-	}
-
-	// Store change output(s) and cached commits
-	for (change_amount, id, mmr_index) in &change_amounts_derivations {
-		context.add_output(&id, &mmr_index, *change_amount);
+	coins.truncate(max_outputs);
+	let total: u64 = coins.iter().map(|c| c.value).sum();
+	if total == 0 {
+		return Err(not_enough_token_funds(token_type, 0, 1));
 	}
-
-	// Store change token output(s) and cached commits
-	context.add_token_output(&key_id, &mmr_index, amount);
-
-	Ok(context)
+	Ok((coins, total, total))
 }
 
-/// Selects token inputs and change for a transaction
-pub fn token_issue_output<'a, T: ?Sized, C, K, B>(
+/// Select outputs and calculating fee. The fee accounts for
+/// `params.recipient_outputs` outputs paying the amount, one unless this is a
+/// batch payout. If `params.amount_includes_fee` is set, the amount is the
+/// total to debit, and the returned amount is what is left for the recipient
+/// once the fee is deducted from it. Outputs are drawn from any of the accounts
+/// in `parent_key_ids`, always spending the `required_inputs` looked up from
+/// `params.required_inputs`. The fee pays for `params.num_kernels` kernels, one
+/// unless the transaction is to be aggregated with others.
+pub fn select_coins_and_fee<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
-	keychain_mask: Option<&SecretKey>,
 	amount: u64,
-	token_type: TokenKey,
+	current_height: u64,
+	params: &SendParams,
+	coin_selector: &dyn CoinSelector,
+	required_inputs: &[OutputData],
+	parent_key_ids: &[Identifier],
+	token_inputs: usize,
+	token_outputs: usize,
 ) -> Result<
 	(
-		Vec<Box<build::Append<K, B>>>,
-		(u64, Identifier, Option<u64>),
+		Vec<OutputData>,
+		u64, // total
+		u64, // amount
+		u64, // fee
 	),
 	Error,
 >
@@ -1276,115 +1790,2724 @@ where
 	T: WalletBackend<'a, C, K>,
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
-	B: ProofBuild,
 {
-	let mut parts = vec![];
-
-	let token_key = wallet.next_child(keychain_mask).unwrap();
-	parts.push(build::token_output(
+	let minimum_confirmations = params.minimum_confirmations;
+	let min_coinbase_confirmations = params
+		.coinbase_min_confirmations
+		.unwrap_or(minimum_confirmations);
+	// scan the wallet once, the fee loop then only reselects from this list
+	let eligible = eligible_coins_for_parents(
+		wallet,
+		current_height,
+		minimum_confirmations,
+		min_coinbase_confirmations,
+		params.selection.selection_strategy,
+		required_inputs,
+		parent_key_ids,
+	);
+	select_coins_and_fee_from(
+		&eligible,
 		amount,
-		token_type,
-		true,
-		token_key.clone(),
-	));
-
-	Ok((parts, (amount, token_key.clone(), None)))
+		params.selection.max_outputs,
+		params.selection.strict_max_outputs,
+		params.recipient_outputs,
+		params.change_outputs,
+		params.selection.selection_strategy,
+		coin_selector,
+		params.fee_base,
+		params.max_fee,
+		params.max_inputs_hard,
+		params.dust_threshold,
+		&params.change_plan,
+		required_inputs,
+		params.amount_includes_fee,
+		params.prefer_fewer_inputs,
+		token_inputs,
+		token_outputs,
+		params.num_kernels,
+	)
+	.map_err(|e| {
+		// explain what keeps the rest of the balance from being spent
+		let breakdown = parent_key_ids
+			.iter()
+			.map(|id| {
+				spendable_balance_breakdown(
+					wallet,
+					current_height,
+					minimum_confirmations,
+					min_coinbase_confirmations,
+					id,
+				)
+			})
+			.fold(BalanceBreakdown::default(), |acc, b| BalanceBreakdown {
+				spendable: acc.spendable + b.spendable,
+				awaiting_confirmation: acc.awaiting_confirmation + b.awaiting_confirmation,
+				locked: acc.locked + b.locked,
+				immature_coinbase: acc.immature_coinbase + b.immature_coinbase,
+			});
+		with_breakdown(e, breakdown)
+	})
 }
 
-pub fn select_token_coins<'a, T: ?Sized, C, K>(
+/// Select outputs and calculate the fee as `select_coins_and_fee` does, and
+/// also break the fee down by the elements of the transaction paying it
+pub fn select_coins_and_fee_detailed<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
 	amount: u64,
-	token_type: &str,
 	current_height: u64,
-	minimum_confirmations: u64,
-	max_outputs: usize,
-	select_all: bool,
-	parent_key_id: &Identifier,
-) -> (usize, Vec<TokenOutputData>)
-//    max_outputs_available, Outputs
+	params: &SendParams,
+	coin_selector: &dyn CoinSelector,
+	required_inputs: &[OutputData],
+	parent_key_ids: &[Identifier],
+	token_inputs: usize,
+	token_outputs: usize,
+) -> Result<
+	(
+		Vec<OutputData>,
+		u64, // total
+		u64, // amount
+		u64, // fee
+		FeeBreakdown,
+	),
+	Error,
+>
 where
 	T: WalletBackend<'a, C, K>,
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
 {
-	// first find all eligible outputs based on number of confirmations
-	let mut eligible = wallet
-		.token_iter()
-		.filter(|out| {
-			out.root_key_id == *parent_key_id
-				&& out.token_type == token_type
-				&& out.eligible_to_spend(current_height, minimum_confirmations)
-		})
-		.collect::<Vec<TokenOutputData>>();
-
-	let max_available = eligible.len();
-
-	// sort eligible outputs by increasing value
-	eligible.sort_by_key(|out| out.value);
+	let (coins, total, amount, fee) = select_coins_and_fee(
+		wallet,
+		amount,
+		current_height,
+		params,
+		coin_selector,
+		required_inputs,
+		parent_key_ids,
+		token_inputs,
+		token_outputs,
+	)?;
+	let breakdown = fee_breakdown(
+		&coins,
+		total,
+		amount,
+		fee,
+		params.recipient_outputs,
+		params.change_outputs,
+		params.dust_threshold,
+		&params.change_plan,
+		params.fee_base,
+		token_inputs,
+		token_outputs,
+		params.num_kernels,
+	)?;
+	Ok((coins, total, amount, fee, breakdown))
+}
 
-	// use a sliding window to identify potential sets of possible outputs to spend
-	// Case of amount > total amount of max_outputs(500):
-	// The limit exists because by default, we always select as many inputs as
-	// possible in a transaction, to reduce both the Output set and the fees.
-	// But that only makes sense up to a point, hence the limit to avoid being too
-	// greedy. But if max_outputs(500) is actually not enough to cover the whole
-	// amount, the wallet should allow going over it to satisfy what the user
-	// wants to send. So the wallet considers max_outputs more of a soft limit.
-	if eligible.len() > max_outputs {
-		for window in eligible.windows(max_outputs) {
-			let windowed_eligibles = window.iter().cloned().collect::<Vec<_>>();
-			if let Some(outputs) = select_token_from(amount, select_all, windowed_eligibles) {
-				return (max_available, outputs);
-			}
-		}
-		// Not exist in any window of which total amount >= amount.
-		// Then take coins from the smallest one up to the total amount of selected
-		// coins = the amount.
-		if let Some(outputs) = select_token_from(amount, false, eligible.clone()) {
-			debug!(
+/// Break down the fee of a selection made by `select_coins_and_fee`, counting
+/// the elements of the transaction it builds: the outputs paying the amount,
+/// the change outputs the plan makes of what is left, and the kernels plus a
+/// token kernel when there are token outputs.
+fn fee_breakdown(
+	coins: &[OutputData],
+	total: u64,
+	amount: u64,
+	fee: u64,
+	recipient_outputs: usize,
+	change_outputs: usize,
+	dust_threshold: u64,
+	change_plan: &ChangePlan,
+	fee_base: Option<u64>,
+	token_inputs: usize,
+	token_outputs: usize,
+	num_kernels: usize,
+) -> Result<FeeBreakdown, Error> {
+	let output_len = if amount == 0 { 0 } else { recipient_outputs };
+	let change = change_from(total, amount, fee, coins.iter().map(|c| c.value))?;
+	let num_change = if change == 0 {
+		0
+	} else {
+		plan_change(change, change_outputs, dust_threshold, change_plan).len()
+	};
+	let token_kernel_len = if token_outputs == 0 { 0 } else { 1 };
+	Ok(FeeBreakdown::new(
+		coins.len(),
+		output_len + num_change,
+		num_kernels,
+		token_inputs,
+		token_outputs,
+		token_kernel_len,
+		fee_base,
+		fee,
+	))
+}
+
+/// Select outputs and calculate the fee as `select_coins_and_fee` does, from a
+/// snapshot of the eligible outputs as returned by `eligible_coins` rather than
+/// the wallet itself. The wallet only needs to be borrowed while taking the
+/// snapshot, and the fee is then settled in memory. The selection reflects the
+/// wallet as it was when the snapshot was taken, so outputs spent or locked
+/// since may still be selected, and the caller is left to check them.
+/// With `prefer_fewer_inputs`, a selection of several outputs is swapped for
+/// the smallest single eligible output covering the amount and the fee of
+/// spending it alone, when there is one and no inputs are required. The
+/// transaction gets smaller at the cost of a larger change output.
+pub fn select_coins_and_fee_from(
+	eligible: &[OutputData],
+	amount: u64,
+	max_outputs: usize,
+	strict_max_outputs: bool,
+	recipient_outputs: usize,
+	change_outputs: usize,
+	selection_strategy: SelectionStrategy,
+	coin_selector: &dyn CoinSelector,
+	fee_base: Option<u64>,
+	max_fee: Option<u64>,
+	max_inputs_hard: Option<usize>,
+	dust_threshold: u64,
+	change_plan: &ChangePlan,
+	required_inputs: &[OutputData],
+	amount_includes_fee: bool,
+	prefer_fewer_inputs: bool,
+	token_inputs: usize,
+	token_outputs: usize,
+	num_kernels: usize,
+) -> Result<
+	(
+		Vec<OutputData>,
+		u64, // total
+		u64, // amount
+		u64, // fee
+	),
+	Error,
+> {
+	let select = |eligible: &[OutputData]| {
+		if amount_includes_fee {
+			coins_and_fee_within(
+				eligible,
+				amount,
+				max_outputs,
+				strict_max_outputs,
+				recipient_outputs,
+				change_outputs,
+				selection_strategy,
+				coin_selector,
+				fee_base,
+				dust_threshold,
+				change_plan,
+				required_inputs,
+				token_inputs,
+				token_outputs,
+				num_kernels,
+			)
+		} else {
+			coins_and_fee_from(
+				eligible,
+				amount,
+				max_outputs,
+				strict_max_outputs,
+				recipient_outputs,
+				change_outputs,
+				selection_strategy,
+				coin_selector,
+				fee_base,
+				dust_threshold,
+				change_plan,
+				required_inputs,
+				token_inputs,
+				token_outputs,
+				num_kernels,
+			)
+		}
+	};
+	let mut selected = select(eligible)?;
+
+	// once a window of outputs covers the send, look for a single output that
+	// covers it alone
+	if prefer_fewer_inputs && selected.0.len() > 1 && required_inputs.is_empty() {
+		if let Some(single) = single_covering_output(eligible, amount, select) {
+			selected = single;
+		}
+	}
+	let (coins, total, amount, fee) = selected;
+
+	// the selection may have been extended past max_outputs to cover the amount,
+	// which a hard cap doesn't allow
+	if let Some(max) = max_inputs_hard {
+		if coins.len() > max {
+			return Err(ErrorKind::TooManyInputs {
+				selected: coins.len(),
+				max,
+			}
+			.into());
+		}
+	}
+
+	// only check the fee once it has settled, so we report the one we'd pay
+	if let Some(max) = max_fee {
+		if fee > max {
+			return Err(ErrorKind::FeeExceedsMaximum { fee, max }.into());
+		}
+	}
+	Ok((coins, total, amount, fee))
+}
+
+/// The selection spending the smallest single eligible output that covers the
+/// amount and the fee of spending it alone, if there is one, keeping the change
+/// down
+fn single_covering_output<S, F>(eligible: &[OutputData], amount: u64, select: F) -> Option<S>
+where
+	F: Fn(&[OutputData]) -> Result<S, Error>,
+{
+	let mut larger: Vec<&OutputData> = eligible.iter().filter(|c| c.value >= amount).collect();
+	larger.sort_by_key(|c| c.value);
+	larger
+		.into_iter()
+		.find_map(|c| select(std::slice::from_ref(c)).ok())
+}
+
+/// Select outputs covering a gross amount from the eligible outputs of a wallet,
+/// and deduct the fee from it. As the outputs only need to cover the gross
+/// amount, the selection doesn't depend on the fee and needs no iterating.
+/// Change is never given to the fee as dust, so the sender is debited exactly
+/// the gross amount.
+fn coins_and_fee_within(
+	eligible: &[OutputData],
+	amount: u64,
+	max_outputs: usize,
+	strict_max_outputs: bool,
+	recipient_outputs: usize,
+	change_outputs: usize,
+	selection_strategy: SelectionStrategy,
+	coin_selector: &dyn CoinSelector,
+	fee_base: Option<u64>,
+	dust_threshold: u64,
+	change_plan: &ChangePlan,
+	required_inputs: &[OutputData],
+	token_inputs: usize,
+	token_outputs: usize,
+	num_kernels: usize,
+) -> Result<
+	(
+		Vec<OutputData>,
+		u64, // total
+		u64, // amount
+		u64, // fee
+	),
+	Error,
+> {
+	let coins = select_coins_from(
+		coin_selector,
+		eligible,
+		amount,
+		max_outputs,
+		strict_max_outputs,
+		selection_strategy,
+		required_inputs,
+	)
+	.1;
+	let total: u64 = coins.iter().map(|c| c.value).sum();
+	if total < amount || total == 0 {
+		return Err(not_enough_funds(
+			total,
+			cmp::max(amount, 1),
+			eligible.iter().chain(required_inputs).map(|o| o.value),
+		));
+	}
+
+	// the change doesn't depend on the fee, so pay for exactly the change
+	// outputs the plan creates from it
+	let num_outputs = if total == amount {
+		recipient_outputs
+	} else {
+		plan_change(total - amount, change_outputs, dust_threshold, change_plan).len()
+			+ recipient_outputs
+	};
+	let token_kernel_len = if token_outputs == 0 { 0 } else { 1 };
+	let fee = tx_fee(
+		coins.len(),
+		num_outputs,
+		num_kernels,
+		token_inputs,
+		token_outputs,
+		token_kernel_len,
+		fee_base,
+	);
+	if fee >= amount {
+		return Err(ErrorKind::FeeExceedsAmount { fee, amount }.into());
+	}
+	Ok((coins, total, amount - fee, fee))
+}
+
+/// Select outputs and calculate the fee from the eligible outputs of a wallet,
+/// as returned by `eligible_coins`
+fn coins_and_fee_from(
+	eligible: &[OutputData],
+	amount: u64,
+	max_outputs: usize,
+	strict_max_outputs: bool,
+	recipient_outputs: usize,
+	change_outputs: usize,
+	selection_strategy: SelectionStrategy,
+	coin_selector: &dyn CoinSelector,
+	fee_base: Option<u64>,
+	dust_threshold: u64,
+	change_plan: &ChangePlan,
+	required_inputs: &[OutputData],
+	token_inputs: usize,
+	token_outputs: usize,
+	num_kernels: usize,
+) -> Result<
+	(
+		Vec<OutputData>,
+		u64, // total
+		u64, // amount
+		u64, // fee
+	),
+	Error,
+> {
+	let min_fee = fee_base.unwrap_or(DEFAULT_BASE_FEE);
+	let amount_with_fee = with_fee(amount, min_fee)?;
+
+	// select some spendable coins from the wallet
+	let (max_available, mut coins) = select_coins_from(
+		coin_selector,
+		eligible,
+		amount_with_fee,
+		max_outputs,
+		strict_max_outputs,
+		selection_strategy,
+		required_inputs,
+	);
+	// a soft limit doesn't bound the reselection once the fee has grown, while
+	// a hard one still does
+	let retry_max_outputs = match strict_max_outputs {
+		true => max_outputs,
+		false => max_available,
+	};
+
+	// sender is responsible for setting the fee on the partial tx
+	// recipient should double check the fee calculation and not blindly trust the
+	// sender
+
+	// TODO - Is it safe to spend without a change output? (1 input -> 1 output)
+	// TODO - Does this not potentially reveal the senders private key?
+	//
+
+	// First attempt to spend without change
+	let output_len = if amount == 0 { 0 } else { recipient_outputs };
+
+	let token_kernel_len = if token_outputs == 0 { 0 } else { 1 };
+	let mut fee = tx_fee(
+		coins.len(),
+		output_len,
+		num_kernels,
+		token_inputs,
+		token_outputs,
+		token_kernel_len,
+		fee_base,
+	);
+	let mut total: u64 = coins.iter().map(|c| c.value).sum();
+	let mut amount_with_fee = with_fee(amount, fee)?;
+
+	if total == 0 {
+		return Err(not_enough_funds(
+			0,
+			amount_with_fee,
+			eligible.iter().chain(required_inputs).map(|o| o.value),
+		));
+	}
+
+	// The amount with fee is more than the total values of our max outputs
+	if total < amount_with_fee && coins.len() == max_available {
+		return Err(not_enough_funds(
+			total,
+			amount_with_fee,
+			eligible.iter().chain(required_inputs).map(|o| o.value),
+		));
+	}
+
+	// Denominated change can create more outputs than requested, so budget the
+	// fee for the most the change plan could produce
+	let num_outputs = change_plan.max_change_outputs(change_outputs) + output_len;
+
+	// We need to add a change address or amount with fee is more than total
+	if total != amount_with_fee {
+		fee = tx_fee(
+			coins.len(),
+			num_outputs,
+			num_kernels,
+			token_inputs,
+			token_outputs,
+			token_kernel_len,
+			fee_base,
+		);
+		amount_with_fee = with_fee(amount, fee)?;
+
+		// Here check if we have enough outputs for the amount including fee otherwise
+		// look for other outputs and check again
+		while total < amount_with_fee {
+			// End the loop if we have selected all the outputs and still not enough funds
+			if coins.len() == max_available {
+				return Err(not_enough_funds(
+					total,
+					amount_with_fee,
+					eligible.iter().chain(required_inputs).map(|o| o.value),
+				));
+			}
+
+			// select some spendable coins from the wallet
+			coins = select_coins_from(
+				coin_selector,
+				eligible,
+				amount_with_fee,
+				retry_max_outputs,
+				strict_max_outputs,
+				selection_strategy,
+				required_inputs,
+			)
+			.1;
+			total = coins.iter().map(|c| c.value).sum();
+
+			// selection only falls back to an insufficient set when there is
+			// no set covering the amount, so trying again won't help
+			if total < amount_with_fee {
+				return Err(not_enough_funds(
+					total,
+					amount_with_fee,
+					eligible.iter().chain(required_inputs).map(|o| o.value),
+				));
+			}
+
+			fee = tx_fee(
+				coins.len(),
+				num_outputs,
+				num_kernels,
+				token_inputs,
+				token_outputs,
+				token_kernel_len,
+				fee_base,
+			);
+			amount_with_fee = with_fee(amount, fee)?;
+		}
+
+		// If the change we would create is dust, don't build a change output at
+		// all and give the remainder to the fee instead. Dropping the change
+		// outputs only lowers the weight of the transaction, so the fee still
+		// covers what is required.
+		let change = total - amount_with_fee;
+		if change > 0 && change < dust_threshold {
+			debug!(
+				"Change {} is below dust threshold {}, adding it to the fee",
+				change, dust_threshold
+			);
+			fee = total - amount;
+		} else if change > 0 {
+			// The fee was budgeted for the most change outputs the plan could
+			// create, now the change is known only pay for the ones it does.
+			// Lowering the fee adds to the change, so the budgeted fee is kept if
+			// that would call for another change output.
+			let num_change = plan_change(change, change_outputs, dust_threshold, change_plan).len();
+			let settled_fee = tx_fee(
+				coins.len(),
+				num_change + output_len,
+				num_kernels,
+				token_inputs,
+				token_outputs,
+				token_kernel_len,
+				fee_base,
+			);
+			let settled_change = total - amount - settled_fee;
+			if plan_change(settled_change, change_outputs, dust_threshold, change_plan).len()
+				== num_change
+			{
+				fee = settled_fee;
+			}
+		}
+	}
+	Ok((coins, total, amount, fee))
+}
+
+/// Select token outputs covering the amount and the token-denominated fee.
+/// The base fee of a token transaction is paid separately from base inputs, so
+/// `token_fee` is zero unless a fee is charged in the token itself.
+pub fn select_token_coins_and_fee<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	amount: u64,
+	token_fee: u64,
+	token_type: &str,
+	current_height: u64,
+	minimum_confirmations: u64,
+	max_outputs: usize,
+	selection_strategy: SelectionStrategy,
+	coin_selector: &dyn CoinSelector,
+	parent_key_id: &Identifier,
+) -> Result<
+	(
+		Vec<TokenOutputData>,
+		u64, // total
+		u64, // amount
+		u64, // token fee
+	),
+	Error,
+>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	// select some spendable coins from the wallet
+	let eligible = eligible_token_coins(
+		wallet,
+		token_type,
+		current_height,
+		minimum_confirmations,
+		selection_strategy,
+		parent_key_id,
+	);
+	let amount_with_fee = amount + token_fee;
+	let (_, coins) = select_token_coins_from(
+		coin_selector,
+		eligible,
+		amount_with_fee,
+		max_outputs,
+		selection_strategy,
+	);
+
+	let total: u64 = coins.iter().map(|c| c.value).sum();
+
+	// Either the amount is more than the total values of our max outputs, or
+	// select_token_coins fell back to the largest set it could find
+	if total == 0 || total < amount_with_fee {
+		let breakdown = spendable_token_balance_breakdown(
+			wallet,
+			token_type,
+			current_height,
+			minimum_confirmations,
+			parent_key_id,
+		);
+		return Err(ErrorKind::NotEnoughTokenFunds {
+			token_type: token_type.to_owned(),
+			available: total,
+			needed: amount_with_fee,
+			breakdown,
+			breakdown_disp: breakdown_disp(&breakdown, |v| v.to_string()),
+		}
+		.into());
+	}
+
+	Ok((coins, total, amount, token_fee))
+}
+
+/// Selects inputs and change for a transaction. Change keys are derived under
+/// `change_parent_key_id` if given, or the active account otherwise.
+pub fn inputs_and_change<'a, T: ?Sized, C, K, B>(
+	coins: &[OutputData],
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	amount: u64,
+	fee: u64,
+	num_change_outputs: usize,
+	dust_threshold: u64,
+	change_plan: &ChangePlan,
+	change_parent_key_id: Option<&Identifier>,
+	include_inputs_in_sum: bool,
+) -> Result<(Vec<Box<build::Append<K, B>>>, Vec<ChangeOutput>), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+	B: ProofBuild,
+{
+	build_inputs_and_change(
+		coins,
+		amount,
+		fee,
+		num_change_outputs,
+		dust_threshold,
+		change_plan,
+		include_inputs_in_sum,
+		|| next_change_key(wallet, keychain_mask, change_parent_key_id),
+	)
+}
+
+/// Selects inputs and change for a transaction as `inputs_and_change` does,
+/// with change keys derived up front by the caller rather than by the wallet,
+/// so a signer can authorize the change addresses beforehand. One key must be
+/// given for each of the `num_change_outputs`, and they're used in order. Keys
+/// are left over if the change turns out to be nothing or dust, while a change
+/// plan creating more outputs than there are keys is an error.
+pub fn inputs_and_change_with_keys<K, B>(
+	coins: &[OutputData],
+	amount: u64,
+	fee: u64,
+	num_change_outputs: usize,
+	dust_threshold: u64,
+	change_plan: &ChangePlan,
+	change_keys: Vec<Identifier>,
+	include_inputs_in_sum: bool,
+) -> Result<(Vec<Box<build::Append<K, B>>>, Vec<ChangeOutput>), Error>
+where
+	K: Keychain,
+	B: ProofBuild,
+{
+	if change_keys.len() != num_change_outputs {
+		return Err(ErrorKind::GenericError(format!(
+			"{} change keys given for {} change outputs",
+			change_keys.len(),
+			num_change_outputs
+		))
+		.into());
+	}
+	let num_keys = change_keys.len();
+	let mut keys = change_keys.into_iter();
+	build_inputs_and_change(
+		coins,
+		amount,
+		fee,
+		num_change_outputs,
+		dust_threshold,
+		change_plan,
+		include_inputs_in_sum,
+		|| {
+			keys.next().ok_or_else(|| {
+				ErrorKind::GenericError(format!(
+					"change plan creates more change outputs than the {} keys given",
+					num_keys
+				))
+				.into()
+			})
+		},
+	)
+}
+
+/// Builds the inputs and change outputs of a transaction, taking the key of
+/// each change output from `next_key` as it's built
+fn build_inputs_and_change<K, B, F>(
+	coins: &[OutputData],
+	amount: u64,
+	fee: u64,
+	num_change_outputs: usize,
+	dust_threshold: u64,
+	change_plan: &ChangePlan,
+	include_inputs_in_sum: bool,
+	mut next_key: F,
+) -> Result<(Vec<Box<build::Append<K, B>>>, Vec<ChangeOutput>), Error>
+where
+	K: Keychain,
+	B: ProofBuild,
+	F: FnMut() -> Result<Identifier, Error>,
+{
+	let mut parts = vec![];
+
+	// calculate the total across all inputs, and how much is left
+	let total: u64 = coins.iter().map(|c| c.value).sum();
+
+	// if we are spending 10,000 coins to send 1,000 then our change will be 9,000
+	// if the fee is 80 then the recipient will receive 1000 and our change will be
+	// 8,920
+	let change = change_from(total, amount, fee, coins.iter().map(|c| c.value))?;
+
+	// build inputs using the appropriate derived key_ids
+	if include_inputs_in_sum {
+		for coin in coins {
+			if coin.is_coinbase {
+				parts.push(build::coinbase_input(coin.value, coin.key_id.clone()));
+			} else {
+				parts.push(build::input(coin.value, coin.key_id.clone()));
+			}
+		}
+	}
+
+	let mut change_outputs = vec![];
+
+	if change == 0 {
+		debug!("No change (sending exactly amount + fee), no change outputs to build");
+	} else {
+		let change_amounts = plan_change(change, num_change_outputs, dust_threshold, change_plan);
+		debug!(
+			"Building change outputs: total change: {} ({} outputs)",
+			change,
+			change_amounts.len()
+		);
+
+		for change_amount in change_amounts {
+			let change_key = next_key()?;
+
+			change_outputs.push(ChangeOutput {
+				amount: change_amount,
+				key_id: change_key.clone(),
+				mmr_index: None,
+			});
+			parts.push(build::output(change_amount, change_key));
+		}
+	}
+
+	Ok((parts, change_outputs))
+}
+
+/// Selects token inputs and change for a transaction, deriving change keys as
+/// `inputs_and_change` does
+pub fn token_inputs_and_change<'a, T: ?Sized, C, K, B>(
+	coins: &Vec<TokenOutputData>,
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	amount: u64,
+	token_fee: u64,
+	token_type: TokenKey,
+	num_change_outputs: usize,
+	dust_threshold: u64,
+	change_parent_key_id: Option<&Identifier>,
+	include_inputs_in_sum: bool,
+) -> Result<(Vec<Box<build::Append<K, B>>>, Vec<ChangeOutput>), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+	B: ProofBuild,
+{
+	let mut parts = vec![];
+
+	// calculate the total across all inputs, and how much is left
+	let total: u64 = coins.iter().map(|c| c.value).sum();
+
+	// if we are spending 10,000 tokens to send 1,000 then our change will be 9,000
+	// if the token fee is 80 then the recipient will receive 1000 and our change
+	// will be 8,920
+	let change = change_from(total, amount, token_fee, coins.iter().map(|c| c.value))
+		.map_err(|e| token_funds_error(e, &token_type.to_hex()))?;
+
+	// build inputs using the appropriate derived key_ids
+	if include_inputs_in_sum {
+		for coin in coins {
+			parts.push(build::build_token_input(
+				coin.value,
+				token_type,
+				coin.is_token_issue,
+				coin.key_id.clone(),
+			));
+		}
+	}
+
+	let mut change_outputs = vec![];
+
+	if change == 0 {
+		debug!("No Token change (sending exactly amount + fee), no change outputs to build");
+	} else {
+		let change_amounts = plan_token_change(change, num_change_outputs, dust_threshold);
+		debug!(
+			"Building Token change outputs: total change: {} ({} outputs)",
+			change,
+			change_amounts.len()
+		);
+
+		for change_amount in change_amounts {
+			let change_key = next_change_key(wallet, keychain_mask, change_parent_key_id)?;
+
+			change_outputs.push(ChangeOutput {
+				amount: change_amount,
+				key_id: change_key.clone(),
+				mmr_index: None,
+			});
+			parts.push(build::token_output(
+				change_amount,
+				token_type,
+				false,
+				change_key,
+			));
+		}
+	}
+
+	Ok((parts, change_outputs))
+}
+
+/// Derives the key of the next change output for the account at
+/// `change_parent_key_id` if given and the active account otherwise, on the
+/// account's change branch if it has one enabled
+fn next_change_key<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	change_parent_key_id: Option<&Identifier>,
+) -> Result<Identifier, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let parent_key_id = match change_parent_key_id {
+		Some(p) => p.clone(),
+		None => wallet.parent_key_id(),
+	};
+	let change_parent = keys::change_parent_for(wallet, &parent_key_id);
+	keys::next_available_key_for(wallet, keychain_mask, &change_parent)
+}
+
+/// The change left once the amount and fee are paid from inputs totalling
+/// `total`. Inputs that don't cover both are reported as not enough funds, and
+/// an amount and fee too large to add up as an error, rather than letting the
+/// subtraction wrap around.
+fn change_from<I>(total: u64, amount: u64, fee: u64, input_values: I) -> Result<u64, Error>
+where
+	I: Iterator<Item = u64>,
+{
+	let needed = with_fee(amount, fee)?;
+	total
+		.checked_sub(needed)
+		.ok_or_else(|| not_enough_funds(total, needed, input_values))
+}
+
+/// The amount plus the fee, or an error if they're too large to add up
+fn with_fee(amount: u64, fee: u64) -> Result<u64, Error> {
+	amount.checked_add(fee).ok_or_else(|| {
+		ErrorKind::GenericError(format!("amount {} plus fee {} overflows", amount, fee)).into()
+	})
+}
+
+/// Amounts of the change outputs to build according to the change plan
+fn plan_change(
+	change: u64,
+	num_change_outputs: usize,
+	dust_threshold: u64,
+	change_plan: &ChangePlan,
+) -> Vec<u64> {
+	match change_plan {
+		ChangePlan::Even => split_change(
+			change,
+			change_outputs_above_dust(change, num_change_outputs, dust_threshold),
+			ChangeDistribution::LastAbsorbs,
+		),
+		ChangePlan::Balanced => split_change(
+			change,
+			change_outputs_above_dust(change, num_change_outputs, dust_threshold),
+			ChangeDistribution::Balanced,
+		),
+		ChangePlan::Denominated(denominations) => {
+			let mut denominations = denominations.clone();
+			denominations.sort_by(|a, b| b.cmp(a));
+
+			let mut remaining = change;
+			let mut amounts = vec![];
+			for denomination in denominations {
+				if denomination > 0 && denomination <= remaining {
+					amounts.push(denomination);
+					remaining -= denomination;
+				}
+			}
+			if remaining > 0 {
+				amounts.push(remaining);
+			}
+			amounts
+		}
+	}
+}
+
+/// Amounts of the token change outputs to build. Token change is always split
+/// evenly, with the remainder in the final output.
+fn plan_token_change(change: u64, num_change_outputs: usize, dust_threshold: u64) -> Vec<u64> {
+	split_change(
+		change,
+		change_outputs_above_dust(change, num_change_outputs, dust_threshold),
+		ChangeDistribution::LastAbsorbs,
+	)
+}
+
+/// Splits the change into equal change outputs, with the remainder either all
+/// in the final one or spread one nanogrin each over the first outputs.
+/// Never creates more outputs than there is change to put in them, so no zero
+/// value outputs (and their keys) are wasted.
+fn split_change(
+	change: u64,
+	num_change_outputs: usize,
+	distribution: ChangeDistribution,
+) -> Vec<u64> {
+	let num_change_outputs = cmp::max(1, cmp::min(num_change_outputs as u64, change) as usize);
+	let part_change = change / num_change_outputs as u64;
+	let remainder_change = change - part_change * num_change_outputs as u64;
+
+	let mut amounts = vec![part_change; num_change_outputs];
+	match distribution {
+		ChangeDistribution::LastAbsorbs => amounts[num_change_outputs - 1] += remainder_change,
+		ChangeDistribution::Balanced => {
+			for amount in amounts.iter_mut().take(remainder_change as usize) {
+				*amount += 1;
+			}
+		}
+	}
+	amounts
+}
+
+/// Number of change outputs to split the change into, so that none of them
+/// ends up below the dust threshold. Always at least one output, as change
+/// that can't be split is kept whole rather than dropped.
+pub fn change_outputs_above_dust(
+	change: u64,
+	num_change_outputs: usize,
+	dust_threshold: u64,
+) -> usize {
+	if dust_threshold == 0 {
+		return cmp::max(1, cmp::min(num_change_outputs as u64, change)) as usize;
+	}
+	let max_parts = change / dust_threshold;
+	cmp::max(1, cmp::min(num_change_outputs as u64, max_parts)) as usize
+}
+
+/// Looks up the outputs with the given commitments for coin control, without
+/// running any selection strategy. Each output must belong to one of the given
+/// accounts, be eligible to spend at the current height and not be reserved by
+/// a transaction not locked yet, otherwise an error naming the offending
+/// commitment is returned.
+pub fn select_coins_explicit<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	commits: &[pedersen::Commitment],
+	current_height: u64,
+	minimum_confirmations: u64,
+	min_coinbase_confirmations: u64,
+	parent_key_ids: &[Identifier],
+) -> Result<Vec<OutputData>, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	// look up the outputs of the accounts once, rather than once per commit,
+	// keeping the ones we can't spend to say why
+	let status = [
+		OutputStatus::Unconfirmed,
+		OutputStatus::Unspent,
+		OutputStatus::Locked,
+		OutputStatus::Spent,
+		OutputStatus::Reverted,
+	];
+	let mut by_commit: HashMap<String, OutputData> = HashMap::new();
+	for parent_key_id in parent_key_ids {
+		by_commit.extend(
+			wallet
+				.outputs_for_parent(parent_key_id, &status)
+				.filter_map(|out| out.commit.clone().map(|c| (c, out))),
+		);
+	}
+	let reservations = live_reservations(wallet, current_height);
+
+	let mut selected: Vec<OutputData> = vec![];
+	for commit in commits {
+		let commit = commit.as_ref().to_hex();
+		if selected
+			.iter()
+			.any(|out| out.commit.as_ref() == Some(&commit))
+		{
+			continue;
+		}
+		let out = by_commit.remove(&commit).ok_or_else(|| {
+			ErrorKind::RequiredInputUnavailable(format!("no output with commit {}", commit))
+		})?;
+		if out.is_frozen {
+			return Err(ErrorKind::RequiredInputUnavailable(format!(
+				"output {} is frozen",
+				commit
+			))
+			.into());
+		}
+		let confirmations =
+			required_confirmations(&out, minimum_confirmations, min_coinbase_confirmations);
+		if !out.eligible_to_spend(current_height, confirmations) {
+			return Err(ErrorKind::RequiredInputUnavailable(format!(
+				"output {} is {} and can't be spent with {} confirmations",
+				commit, out.status, confirmations
+			))
+			.into());
+		}
+		if reservations
+			.iter()
+			.any(|r| r.reserves(&out.key_id, &out.mmr_index))
+		{
+			return Err(ErrorKind::RequiredInputUnavailable(format!(
+				"output {} is reserved by a transaction not locked yet",
+				commit
+			))
+			.into());
+		}
+		selected.push(out);
+	}
+	Ok(selected)
+}
+
+/// Select spendable coins from a wallet.
+/// Eligible outputs are ordered according to the selection strategy:
+/// `UseAll` and `SmallestFirst` spend the smallest outputs first, while
+/// `LargestFirst` and `BiggestFewest` start from the largest. `UseAll` spends
+/// as many outputs as possible (up to max_outputs), the other strategies only
+/// as many as necessary. Outputs are only eligible as decided by
+/// `OutputData::eligible_to_spend`, so immature coinbase outputs are never
+/// selected, and neither are frozen outputs or outputs reserved by
+/// transactions not locked yet. Coinbase outputs need
+/// `min_coinbase_confirmations` when that's stricter. Any `required`
+/// outputs are always included, and the strategy only tops them up from the
+/// remaining eligible outputs when they don't cover the amount.
+/// Outputs aren't weighed by their value less the fee of spending them: inputs
+/// lower the weight of a transaction, so even the smallest output adds more
+/// than it costs.
+/// Outputs of equal value are ordered by key id and then mmr index, so the
+/// same set of outputs always results in the same selection.
+/// If a `selection_observer` is given, it is called once for every required
+/// and eligible output, in the order considered after sorting, along with
+/// whether the output ended up selected.
+/// TODO: Possibly move this into another trait to be owned by a wallet?
+
+pub fn select_coins<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	amount: u64,
+	current_height: u64,
+	minimum_confirmations: u64,
+	min_coinbase_confirmations: u64,
+	max_outputs: usize,
+	strict_max_outputs: bool,
+	selection_strategy: SelectionStrategy,
+	required: &[OutputData],
+	parent_key_id: &Identifier,
+	selection_observer: Option<&mut dyn FnMut(&OutputData, bool)>,
+) -> (usize, Vec<OutputData>)
+//    max_outputs_available, Outputs
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	select_coins_for_parents(
+		wallet,
+		amount,
+		current_height,
+		minimum_confirmations,
+		min_coinbase_confirmations,
+		max_outputs,
+		strict_max_outputs,
+		selection_strategy,
+		required,
+		std::slice::from_ref(parent_key_id),
+		selection_observer,
+	)
+}
+
+/// Select spendable coins as `select_coins` does, from the outputs of any of
+/// the given accounts rather than a single one
+pub fn select_coins_for_parents<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	amount: u64,
+	current_height: u64,
+	minimum_confirmations: u64,
+	min_coinbase_confirmations: u64,
+	max_outputs: usize,
+	strict_max_outputs: bool,
+	selection_strategy: SelectionStrategy,
+	required: &[OutputData],
+	parent_key_ids: &[Identifier],
+	selection_observer: Option<&mut dyn FnMut(&OutputData, bool)>,
+) -> (usize, Vec<OutputData>)
+//    max_outputs_available, Outputs
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let eligible = eligible_coins_for_parents(
+		wallet,
+		current_height,
+		minimum_confirmations,
+		min_coinbase_confirmations,
+		selection_strategy,
+		required,
+		parent_key_ids,
+	);
+	let (max_available, selected) = select_coins_from(
+		&StrategySelector,
+		&eligible,
+		amount,
+		max_outputs,
+		strict_max_outputs,
+		selection_strategy,
+		required,
+	);
+	if let Some(observer) = selection_observer {
+		observe_selection(required, &eligible, &selected, observer);
+	}
+	(max_available, selected)
+}
+
+/// Reports each required and eligible output to the observer, in order, along
+/// with whether it is part of the selection
+fn observe_selection(
+	required: &[OutputData],
+	eligible: &[OutputData],
+	selected: &[OutputData],
+	observer: &mut dyn FnMut(&OutputData, bool),
+) {
+	for out in required.iter().chain(eligible.iter()) {
+		let is_selected = selected
+			.iter()
+			.any(|s| s.key_id == out.key_id && s.mmr_index == out.mmr_index);
+		observer(out, is_selected);
+	}
+}
+
+/// Finds the outputs of the account eligible to spend, other than the
+/// `required` ones, sorted in the order the strategy wants to spend them.
+/// This is a snapshot of the wallet at the time of the call, which
+/// `select_coins_and_fee_from` can select from without the wallet.
+pub fn eligible_coins<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	current_height: u64,
+	minimum_confirmations: u64,
+	min_coinbase_confirmations: u64,
+	selection_strategy: SelectionStrategy,
+	required: &[OutputData],
+	parent_key_id: &Identifier,
+) -> Vec<OutputData>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	eligible_coins_for_parents(
+		wallet,
+		current_height,
+		minimum_confirmations,
+		min_coinbase_confirmations,
+		selection_strategy,
+		required,
+		std::slice::from_ref(parent_key_id),
+	)
+}
+
+/// Finds the eligible outputs as `eligible_coins` does, across all of the
+/// given accounts, sorted together as though they were a single account
+pub fn eligible_coins_for_parents<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	current_height: u64,
+	minimum_confirmations: u64,
+	min_coinbase_confirmations: u64,
+	selection_strategy: SelectionStrategy,
+	required: &[OutputData],
+	parent_key_ids: &[Identifier],
+) -> Vec<OutputData>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	// first find all eligible outputs based on number of confirmations, leaving
+	// out the ones the caller requires us to spend and those reserved by
+	// transactions not locked yet
+	let reservations = live_reservations(wallet, current_height);
+	let mut eligible: Vec<OutputData> = vec![];
+	for parent_key_id in parent_key_ids {
+		eligible.extend(
+			wallet
+				.outputs_for_parent(
+					parent_key_id,
+					&[OutputStatus::Unconfirmed, OutputStatus::Unspent],
+				)
+				.filter(|out| {
+					out.eligible_to_spend(
+						current_height,
+						required_confirmations(
+							out,
+							minimum_confirmations,
+							min_coinbase_confirmations,
+						),
+					) && !out.is_frozen && !reservations
+						.iter()
+						.any(|r| r.reserves(&out.key_id, &out.mmr_index))
+						&& !required
+							.iter()
+							.any(|r| r.key_id == out.key_id && r.mmr_index == out.mmr_index)
+				}),
+		);
+	}
+
+	// sort eligible outputs in the order the strategy wants to spend them
+	sort_for_strategy(&mut eligible, selection_strategy, |out| {
+		(out.value, out.height, out.key_id.clone(), out.mmr_index)
+	});
+	eligible
+}
+
+/// Reservations of transactions not locked yet that still apply at the given
+/// height
+pub fn live_reservations<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	current_height: u64,
+) -> Vec<OutputReservation>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	wallet
+		.reservation_iter()
+		.filter(|r| !r.is_expired(current_height))
+		.collect()
+}
+
+/// Reserves the inputs of a transaction that was built but isn't locked yet,
+/// so other transactions don't select them in the meantime. The reservation
+/// lasts until the slate's TTL, or `RESERVATION_TTL_BLOCKS` without one.
+pub fn reserve_inputs<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	slate: &Slate,
+	context: &Context,
+	current_height: u64,
+) -> Result<(), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let expiry_height = match slate.ttl_cutoff_height {
+		0 => current_height + RESERVATION_TTL_BLOCKS,
+		n => n,
+	};
+	let mut batch = wallet.batch(keychain_mask)?;
+	batch.save_reservation(&OutputReservation::new(slate.id, context, expiry_height))?;
+	batch.commit()?;
+	Ok(())
+}
+
+/// Releases the input reservations expired at the given height, so their
+/// inputs can be selected again, returning how many were released. Sends held
+/// back before locking, for instance while an external signer looks them over,
+/// only keep their inputs out of other sends until then.
+pub fn release_expired_reservations<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	height: u64,
+) -> Result<usize, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let expired: Vec<Uuid> = wallet
+		.reservation_iter()
+		.filter(|r| r.is_expired(height))
+		.map(|r| r.slate_id)
+		.collect();
+	if !expired.is_empty() {
+		let mut batch = wallet.batch(keychain_mask)?;
+		for slate_id in expired.iter() {
+			batch.delete_reservation(slate_id)?;
+		}
+		batch.commit()?;
+	}
+	Ok(expired.len())
+}
+
+/// The confirmations an output needs before it can be spent, the stricter of
+/// the two thresholds for coinbase outputs
+fn required_confirmations(
+	out: &OutputData,
+	minimum_confirmations: u64,
+	min_coinbase_confirmations: u64,
+) -> u64 {
+	if out.is_coinbase {
+		cmp::max(minimum_confirmations, min_coinbase_confirmations)
+	} else {
+		minimum_confirmations
+	}
+}
+
+/// Error for when the outputs at hand can't cover what is needed, noting how
+/// many outputs were eligible to spend and the largest of them
+fn not_enough_funds<I>(available: u64, needed: u64, eligible_values: I) -> Error
+where
+	I: Iterator<Item = u64>,
+{
+	let (num_outputs, max_output) =
+		eligible_values.fold((0, 0), |(n, max), value| (n + 1, cmp::max(max, value)));
+	ErrorKind::NotEnoughFunds {
+		available,
+		available_disp: amount_to_hr_string(available, false),
+		needed,
+		needed_disp: amount_to_hr_string(needed, false),
+		num_outputs,
+		max_output,
+		breakdown: BalanceBreakdown::default(),
+		breakdown_disp: String::new(),
+	}
+	.into()
+}
+
+/// Error for token outputs of the given type not covering what's needed
+fn not_enough_token_funds(token_type: &str, available: u64, needed: u64) -> Error {
+	ErrorKind::NotEnoughTokenFunds {
+		token_type: token_type.to_owned(),
+		available,
+		needed,
+		breakdown: BalanceBreakdown::default(),
+		breakdown_disp: String::new(),
+	}
+	.into()
+}
+
+/// Reports funds short of a token amount as short of that token
+fn token_funds_error(e: Error, token_type: &str) -> Error {
+	match e.kind() {
+		ErrorKind::NotEnoughFunds {
+			available, needed, ..
+		} => not_enough_token_funds(token_type, available, needed),
+		_ => e,
+	}
+}
+
+/// Adds how the rest of the balance is held up to a `NotEnoughFunds` error
+fn with_breakdown(e: Error, breakdown: BalanceBreakdown) -> Error {
+	match e.kind() {
+		ErrorKind::NotEnoughFunds {
+			available,
+			available_disp,
+			needed,
+			needed_disp,
+			num_outputs,
+			max_output,
+			..
+		} => ErrorKind::NotEnoughFunds {
+			available,
+			available_disp,
+			needed,
+			needed_disp,
+			num_outputs,
+			max_output,
+			breakdown,
+			breakdown_disp: breakdown_disp(&breakdown, |v| amount_to_hr_string(v, false)),
+		}
+		.into(),
+		_ => e,
+	}
+}
+
+/// Notes the parts of a balance coin selection can't spend yet, formatting
+/// amounts with `fmt`, or nothing if all of it is spendable
+fn breakdown_disp<F>(breakdown: &BalanceBreakdown, fmt: F) -> String
+where
+	F: Fn(u64) -> String,
+{
+	let held_up: Vec<String> = [
+		(breakdown.awaiting_confirmation, "awaiting confirmation"),
+		(breakdown.locked, "locked"),
+		(breakdown.immature_coinbase, "immature"),
+	]
+	.iter()
+	.filter(|(value, _)| *value > 0)
+	.map(|(value, reason)| format!("{} {}", fmt(*value), reason))
+	.collect();
+	match held_up.is_empty() {
+		true => String::new(),
+		false => format!(". {}", held_up.join(", ")),
+	}
+}
+
+/// Splits the balance of the account by whether coin selection can spend it at
+/// the given height, and if not why, so a lack of funds can be explained
+pub fn spendable_balance_breakdown<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	current_height: u64,
+	minimum_confirmations: u64,
+	min_coinbase_confirmations: u64,
+	parent_key_id: &Identifier,
+) -> BalanceBreakdown
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let reservations = live_reservations(wallet, current_height);
+	let mut breakdown = BalanceBreakdown::default();
+	let held = [
+		OutputStatus::Unconfirmed,
+		OutputStatus::Unspent,
+		OutputStatus::Locked,
+	];
+	for out in wallet.outputs_for_parent(parent_key_id, &held) {
+		if out.is_frozen {
+			continue;
+		}
+		let reserved = reservations
+			.iter()
+			.any(|r| r.reserves(&out.key_id, &out.mmr_index));
+		match out.status {
+			OutputStatus::Locked => breakdown.locked += out.value,
+			OutputStatus::Unspent | OutputStatus::Unconfirmed => {
+				let required =
+					required_confirmations(&out, minimum_confirmations, min_coinbase_confirmations);
+				if out.is_coinbase && out.status == OutputStatus::Unconfirmed {
+					// coinbase outputs that never made it into a block are ignored
+				} else if out.is_coinbase && out.lock_height > current_height {
+					breakdown.immature_coinbase += out.value;
+				} else if !out.eligible_to_spend(current_height, required) {
+					breakdown.awaiting_confirmation += out.value;
+				} else if reserved {
+					breakdown.locked += out.value;
+				} else {
+					breakdown.spendable += out.value;
+				}
+			}
+			OutputStatus::Spent | OutputStatus::Reverted => {}
+		}
+	}
+	breakdown
+}
+
+/// Splits the balance of a token as `spendable_balance_breakdown` does, token
+/// outputs waiting for their lock height counting as immature
+pub fn spendable_token_balance_breakdown<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	token_type: &str,
+	current_height: u64,
+	minimum_confirmations: u64,
+	parent_key_id: &Identifier,
+) -> BalanceBreakdown
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let reservations = live_reservations(wallet, current_height);
+	let mut breakdown = BalanceBreakdown::default();
+	let held = [
+		OutputStatus::Unconfirmed,
+		OutputStatus::Unspent,
+		OutputStatus::Locked,
+	];
+	for out in wallet.token_outputs_for(parent_key_id, token_type, &held) {
+		if out.is_frozen {
+			continue;
+		}
+		let reserved = reservations
+			.iter()
+			.any(|r| r.reserves_token(&out.key_id, &out.mmr_index));
+		match out.status {
+			OutputStatus::Locked => breakdown.locked += out.value,
+			OutputStatus::Unspent | OutputStatus::Unconfirmed => {
+				if out.lock_height > current_height {
+					breakdown.immature_coinbase += out.value;
+				} else if !out.eligible_to_spend(current_height, minimum_confirmations) {
+					breakdown.awaiting_confirmation += out.value;
+				} else if reserved {
+					breakdown.locked += out.value;
+				} else {
+					breakdown.spendable += out.value;
+				}
+			}
+			OutputStatus::Spent | OutputStatus::Reverted => {}
+		}
+	}
+	breakdown
+}
+
+/// Parameters a `CoinSelector` selects under
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelectionParams {
+	/// Most outputs to spend
+	pub max_outputs: usize,
+	/// Whether `max_outputs` is a hard limit, rather than one the selection may
+	/// go over when no smaller set of outputs covers the target
+	pub strict_max_outputs: bool,
+	/// Strategy the eligible outputs are sorted by
+	pub selection_strategy: SelectionStrategy,
+}
+
+/// Picks which eligible outputs a transaction spends. The eligible outputs are
+/// sorted in the order the selection strategy wants to spend them, and any
+/// outputs the caller requires are already taken out of both them and the
+/// target. While the fee settles, the selector is called again with a larger
+/// target each time the fee grows.
+pub trait CoinSelector {
+	/// Selects outputs covering the target, or `None` if there are none
+	fn select(
+		&self,
+		eligible: &[OutputData],
+		target: u64,
+		params: &SelectionParams,
+	) -> Option<Vec<OutputData>>;
+
+	/// Selects token outputs covering the target, or `None` if there are none
+	fn select_tokens(
+		&self,
+		eligible: &[TokenOutputData],
+		target: u64,
+		params: &SelectionParams,
+	) -> Option<Vec<TokenOutputData>>;
+}
+
+/// The default selector, spending outputs as the selection strategy says
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StrategySelector;
+
+impl CoinSelector for StrategySelector {
+	fn select(
+		&self,
+		eligible: &[OutputData],
+		target: u64,
+		params: &SelectionParams,
+	) -> Option<Vec<OutputData>> {
+		let values: Vec<u64> = eligible.iter().map(|out| out.value).collect();
+		let (range, selection_strategy) = strategy_window(
+			&values,
+			target,
+			params.max_outputs,
+			params.strict_max_outputs,
+			params.selection_strategy,
+		)?;
+		let extended = range.len() > params.max_outputs;
+		let outputs = select_from(target, selection_strategy, &eligible[range])?;
+		if extended {
+			debug!(
+				"Extending maximum number of outputs. {} outputs selected.",
+				outputs.len()
+			);
+		}
+		Some(outputs)
+	}
+
+	fn select_tokens(
+		&self,
+		eligible: &[TokenOutputData],
+		target: u64,
+		params: &SelectionParams,
+	) -> Option<Vec<TokenOutputData>> {
+		// max_outputs is always a soft limit for token outputs
+		let values: Vec<u64> = eligible.iter().map(|out| out.value).collect();
+		let (range, selection_strategy) = strategy_window(
+			&values,
+			target,
+			params.max_outputs,
+			false,
+			params.selection_strategy,
+		)?;
+		let extended = range.len() > params.max_outputs;
+		let outputs = select_token_from(target, selection_strategy, &eligible[range])?;
+		if extended {
+			debug!(
 				"Extending maximum number of outputs. {} outputs selected.",
 				outputs.len()
 			);
-			return (max_available, outputs);
+		}
+		Some(outputs)
+	}
+}
+
+/// Finds the range of the values, sorted in the order the strategy wants to
+/// spend them, to select from and the strategy to select with. This is the
+/// first window of `max_outputs` values covering the target, or when there is
+/// none, all the values with the strategy used for going over the limit,
+/// unless `max_outputs` is a hard limit.
+fn strategy_window(
+	values: &[u64],
+	target: u64,
+	max_outputs: usize,
+	strict_max_outputs: bool,
+	selection_strategy: SelectionStrategy,
+) -> Option<(Range<usize>, SelectionStrategy)> {
+	if values.len() <= max_outputs {
+		return Some((0..values.len(), selection_strategy));
+	}
+
+	// use a sliding window to identify potential sets of possible outputs to spend
+	// Case of amount > total amount of max_outputs(500):
+	// The limit exists because by default, we always select as many inputs as
+	// possible in a transaction, to reduce both the Output set and the fees.
+	// But that only makes sense up to a point, hence the limit to avoid being too
+	// greedy. But if max_outputs(500) is actually not enough to cover the whole
+	// amount, the wallet should allow going over it to satisfy what the user
+	// wants to send. So the wallet considers max_outputs more of a soft limit.
+	// The windows are contiguous ranges of the sorted values, so the total of
+	// each is the previous one's with one value leaving and one entering.
+	let mut window_total: u64 = values[..max_outputs].iter().sum();
+	for start in 0..=values.len() - max_outputs {
+		let end = start + max_outputs;
+		if start > 0 {
+			window_total = window_total - values[start - 1] + values[end - 1];
+		}
+		if window_total >= target {
+			return Some((start..end, selection_strategy));
+		}
+	}
+
+	// Not exist in any window of which total amount >= amount.
+	// Then take coins in strategy order up to the total amount of selected
+	// coins = the amount, unless max_outputs is a hard limit.
+	if strict_max_outputs {
+		debug!(
+			"No set of at most {} outputs covers the amount, not extending.",
+			max_outputs
+		);
+		return None;
+	}
+	Some((0..values.len(), extension_strategy(selection_strategy)))
+}
+
+/// Single random draw: spends eligible outputs in a random order, whatever
+/// their value, until the target is covered. The seed pins the draw.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SingleRandomDraw {
+	/// Seed of the draw
+	pub seed: u64,
+}
+
+impl SingleRandomDraw {
+	/// Indices of the values drawn, if they cover the target within the limits
+	fn draw(&self, values: &[u64], target: u64, params: &SelectionParams) -> Option<Vec<usize>> {
+		if values.iter().sum::<u64>() < target {
+			return None;
+		}
+		let indices = random_indices(target, values, self.seed);
+		if params.strict_max_outputs && indices.len() > params.max_outputs {
+			return None;
+		}
+		Some(indices)
+	}
+}
+
+impl CoinSelector for SingleRandomDraw {
+	fn select(
+		&self,
+		eligible: &[OutputData],
+		target: u64,
+		params: &SelectionParams,
+	) -> Option<Vec<OutputData>> {
+		let values: Vec<u64> = eligible.iter().map(|out| out.value).collect();
+		let indices = self.draw(&values, target, params)?;
+		Some(indices.into_iter().map(|i| eligible[i].clone()).collect())
+	}
+
+	fn select_tokens(
+		&self,
+		eligible: &[TokenOutputData],
+		target: u64,
+		params: &SelectionParams,
+	) -> Option<Vec<TokenOutputData>> {
+		let values: Vec<u64> = eligible.iter().map(|out| out.value).collect();
+		let indices = self.draw(&values, target, params)?;
+		Some(indices.into_iter().map(|i| eligible[i].clone()).collect())
+	}
+}
+
+/// Selects outputs covering the amount from the sorted eligible outputs with
+/// the given selector, always spending the required outputs. Returns the
+/// number of outputs available along with the selection.
+fn select_coins_from(
+	coin_selector: &dyn CoinSelector,
+	eligible: &[OutputData],
+	amount: u64,
+	max_outputs: usize,
+	strict_max_outputs: bool,
+	selection_strategy: SelectionStrategy,
+	required: &[OutputData],
+) -> (usize, Vec<OutputData>) {
+	let max_available = eligible.len() + required.len();
+
+	// a hard limit the required outputs already exceed can't be met at all
+	if strict_max_outputs && required.len() > max_outputs {
+		return (max_available, vec![]);
+	}
+
+	// required outputs are always spent, and we only top up from the other
+	// eligible outputs if they don't cover the amount
+	let mut selected = required.to_vec();
+	let required_total: u64 = required.iter().map(|out| out.value).sum();
+	if !required.is_empty() && required_total >= amount {
+		return (max_available, selected);
+	}
+	// nor can a hard limit the required outputs fill leave room to top up
+	if strict_max_outputs && !required.is_empty() && required.len() >= max_outputs {
+		return (max_available, selected);
+	}
+	let amount = amount - required_total;
+	let max_outputs = cmp::max(1, max_outputs.saturating_sub(required.len()));
+
+	let params = SelectionParams {
+		max_outputs,
+		strict_max_outputs,
+		selection_strategy,
+	};
+	if let Some(outputs) = coin_selector.select(eligible, amount, &params) {
+		selected.extend(outputs);
+		return (max_available, selected);
+	}
+
+	// we failed to find a suitable set of outputs to spend,
+	// so return the largest amount we can so we can provide guidance on what is
+	// possible
+	let mut eligible = eligible.to_vec();
+	sort_for_strategy(&mut eligible, SelectionStrategy::LargestFirst, |out| {
+		(out.value, out.height, out.key_id.clone(), out.mmr_index)
+	});
+	selected.extend(eligible.into_iter().take(max_outputs));
+	(max_available, selected)
+}
+
+/// Selects outputs to consolidate into a single output, regardless of any
+/// target amount. Up to `max_outputs` eligible outputs are taken, smallest
+/// first, using the same filtering as `select_coins`. Returns the selected
+/// outputs, the amount of the consolidated output and the fee.
+pub fn select_coins_for_consolidation<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	current_height: u64,
+	minimum_confirmations: u64,
+	max_outputs: usize,
+	fee_base: Option<u64>,
+	parent_key_id: &Identifier,
+) -> Result<(Vec<OutputData>, u64, u64), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let eligible = eligible_coins(
+		wallet,
+		current_height,
+		minimum_confirmations,
+		minimum_confirmations,
+		SelectionStrategy::SmallestFirst,
+		&[],
+		parent_key_id,
+	);
+	consolidation_from(eligible, max_outputs, fee_base)
+}
+
+/// Builds a transaction spending up to `max_outputs` of the smallest eligible
+/// outputs, optionally only those below `below_value`, back into a single
+/// output of our own, less the fee. Fails unless at least `min_outputs`
+/// outputs qualify.
+pub fn build_consolidation_tx<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain: &K,
+	keychain_mask: Option<&SecretKey>,
+	slate: &mut Slate,
+	current_height: u64,
+	minimum_confirmations: u64,
+	max_outputs: usize,
+	min_outputs: usize,
+	below_value: Option<u64>,
+	fee_base: Option<u64>,
+	parent_key_id: Identifier,
+	use_test_nonce: bool,
+) -> Result<Context, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let eligible: Vec<OutputData> = eligible_coins(
+		wallet,
+		current_height,
+		minimum_confirmations,
+		minimum_confirmations,
+		SelectionStrategy::SmallestFirst,
+		&[],
+		&parent_key_id,
+	)
+	.into_iter()
+	.filter(|out| below_value.map_or(true, |v| out.value < v))
+	.collect();
+
+	let num_outputs = cmp::min(eligible.len(), max_outputs);
+	if num_outputs < min_outputs {
+		return Err(ErrorKind::Consolidation(format!(
+			"only {} outputs qualify, at least {} needed",
+			num_outputs, min_outputs
+		))
+		.into());
+	}
+	let (coins, amount, fee) = consolidation_from(eligible, max_outputs, fee_base)?;
+
+	// the consolidated output is the change of a transaction sending nothing,
+	// so it's locked and logged like any other change
+	let (elems, change) = inputs_and_change(
+		&coins,
+		wallet,
+		keychain_mask,
+		0,
+		fee,
+		1,
+		0,
+		&ChangePlan::Even,
+		None,
+		true,
+	)?;
+
+	slate.amount = amount;
+	slate.fee = fee;
+	let (blinding, _) =
+		slate.add_transaction_elements(keychain, &ProofBuilder::new(keychain), elems)?;
+
+	// Create our own private context
+	let mut context = Context::new(
+		keychain.secp(),
+		blinding.secret_key(&keychain.secp()).unwrap(),
+		ZERO_KEY,
+		&parent_key_id,
+		use_test_nonce,
+		false,
+	);
+
+	context.fee = fee;
+	context.amount = amount;
+	context.selection_strategy = Some(SelectionStrategy::SmallestFirst);
+
+	// Store our private identifiers for each input
+	for input in coins {
+		context.add_input(&input.key_id, &input.mmr_index, input.value);
+		if let Some(ref commit) = input.commit {
+			context.add_input_commit(&input.key_id, commit);
+		}
+	}
+
+	// Store the consolidated output
+	for c in &change {
+		context.add_output(&c.key_id, &c.mmr_index, c.amount);
+	}
+
+	Ok(context)
+}
+
+/// Takes up to max_outputs of the sorted eligible outputs and works out the
+/// amount left to consolidate into one output after the fee
+fn consolidation_from(
+	eligible: Vec<OutputData>,
+	max_outputs: usize,
+	fee_base: Option<u64>,
+) -> Result<(Vec<OutputData>, u64, u64), Error> {
+	let coins: Vec<OutputData> = eligible.iter().take(max_outputs).cloned().collect();
+	let total: u64 = coins.iter().map(|c| c.value).sum();
+	let fee = tx_fee(coins.len(), 1, 1, 0, 0, 0, fee_base);
+	if total <= fee {
+		return Err(not_enough_funds(
+			total,
+			fee + 1,
+			eligible.iter().map(|o| o.value),
+		));
+	}
+	Ok((coins, total - fee, fee))
+}
+
+/// Builds a transaction spending our largest eligible outputs, as many as
+/// needed to cover `amount` and the fee, back into `num_outputs` outputs of our
+/// own of roughly equal value, or into the given `denominations`. With no
+/// amount the largest output is split on its own. If the slate has a token
+/// type its token outputs are split the same way, the fee being paid from base
+/// outputs.
+pub fn build_split_tx<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain: &K,
+	keychain_mask: Option<&SecretKey>,
+	slate: &mut Slate,
+	current_height: u64,
+	minimum_confirmations: u64,
+	max_outputs: usize,
+	num_outputs: usize,
+	amount: Option<u64>,
+	denominations: Option<Vec<u64>>,
+	fee_base: Option<u64>,
+	parent_key_id: Identifier,
+	use_test_nonce: bool,
+) -> Result<Context, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	if num_outputs < 2 && denominations.is_none() {
+		return Err(ErrorKind::SplitOutputs(format!(
+			"can't split into {} outputs, at least 2 needed",
+			num_outputs
+		))
+		.into());
+	}
+	let selection_strategy = SelectionStrategy::LargestFirst;
+
+	// the split outputs are the change of a transaction sending nothing, so
+	// they're locked and logged like any other change
+	let (mut token_elems, token_inputs, token_change) = match slate.token_type.clone() {
+		None => (vec![], vec![], vec![]),
+		Some(token_type) => {
+			if denominations.is_some() {
+				return Err(ErrorKind::SplitOutputs(
+					"denominations aren't supported for token outputs".to_owned(),
+				)
+				.into());
+			}
+			let wanted = amount.unwrap_or(1);
+			let (_, coins) = select_token_coins(
+				wallet,
+				wanted,
+				&token_type,
+				current_height,
+				minimum_confirmations,
+				max_outputs,
+				selection_strategy,
+				&parent_key_id,
+			);
+			let total: u64 = coins.iter().map(|c| c.value).sum();
+			if total < wanted || coins.is_empty() {
+				return Err(not_enough_token_funds(&token_type, total, wanted));
+			}
+			check_split_value(total, num_outputs)?;
+			let (elems, change) = token_inputs_and_change(
+				&coins,
+				wallet,
+				keychain_mask,
+				0,
+				0,
+				TokenKey::from_hex(&token_type)?,
+				num_outputs,
+				0,
+				None,
+				true,
+			)?;
+			slate.amount = total;
+			(elems, coins, change)
+		}
+	};
+
+	let mut selected = if slate.token_type.is_some() {
+		let params = SendParams {
+			minimum_confirmations,
+			selection: SelectionParams {
+				max_outputs,
+				strict_max_outputs: false,
+				selection_strategy,
+			},
+			recipient_outputs: 0,
+			fee_base,
+			..Default::default()
+		};
+		select_send_tx(
+			wallet,
+			keychain_mask,
+			0,
+			current_height,
+			&params,
+			&StrategySelector,
+			&parent_key_id,
+			&[],
+			None,
+			token_inputs.len(),
+			token_change.len(),
+			true,
+		)
+		.map_err(fee_funds_error)?
+	} else {
+		let change_plan = match denominations {
+			Some(d) => ChangePlan::Denominated(d),
+			None => ChangePlan::Balanced,
+		};
+		let (coins, total, fee) = split_coins_and_fee(
+			wallet,
+			amount.unwrap_or(0),
+			current_height,
+			minimum_confirmations,
+			max_outputs,
+			change_plan.max_change_outputs(num_outputs),
+			fee_base,
+			&parent_key_id,
+		)?;
+		check_split_value(total - fee, num_outputs)?;
+		let (elems, change) = inputs_and_change(
+			&coins,
+			wallet,
+			keychain_mask,
+			0,
+			fee,
+			num_outputs,
+			0,
+			&change_plan,
+			None,
+			true,
+		)?;
+		slate.amount = total - fee;
+		SelectionResult {
+			elems,
+			inputs: coins,
+			change,
+			amount: 0,
+			fee,
+			total,
+		}
+	};
+
+	let mut all_elems = vec![];
+	all_elems.append(&mut token_elems);
+	all_elems.append(&mut selected.elems);
+
+	slate.fee = selected.fee;
+	let (blinding, token_blinding) =
+		slate.add_transaction_elements(keychain, &ProofBuilder::new(keychain), all_elems)?;
+
+	// Create our own private context
+	let mut context = Context::new(
+		keychain.secp(),
+		blinding.secret_key(&keychain.secp()).unwrap(),
+		token_blinding.secret_key(&keychain.secp()).unwrap(),
+		&parent_key_id,
+		use_test_nonce,
+		false,
+	);
+
+	context.fee = selected.fee;
+	context.amount = slate.amount;
+	context.selection_strategy = Some(selection_strategy);
+
+	// Store our private identifiers for each input
+	for input in selected.inputs {
+		context.add_input(&input.key_id, &input.mmr_index, input.value);
+		if let Some(ref commit) = input.commit {
+			context.add_input_commit(&input.key_id, commit);
+		}
+	}
+	for input in token_inputs {
+		context.add_token_input(&input.key_id, &input.mmr_index, input.value);
+	}
+
+	// Store the split outputs, and any change of the fee
+	for c in &selected.change {
+		context.add_output(&c.key_id, &c.mmr_index, c.amount);
+	}
+	for c in &token_change {
+		context.add_token_output(&c.key_id, &c.mmr_index, c.amount);
+	}
+	let all_change: Vec<_> = selected
+		.change
+		.iter()
+		.chain(token_change.iter())
+		.cloned()
+		.collect();
+	cache_change_commits(wallet, keychain_mask, &mut context, &all_change)?;
+
+	Ok(context)
+}
+
+/// Selects the largest eligible outputs covering `amount` and the fee of
+/// splitting them into `num_outputs` outputs, returning them along with their
+/// total and the fee
+fn split_coins_and_fee<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	amount: u64,
+	current_height: u64,
+	minimum_confirmations: u64,
+	max_outputs: usize,
+	num_outputs: usize,
+	fee_base: Option<u64>,
+	parent_key_id: &Identifier,
+) -> Result<(Vec<OutputData>, u64, u64), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	// the fee only grows with the inputs selected, so select again until the
+	// inputs cover the fee they need
+	let mut fee = tx_fee(1, num_outputs, 1, 0, 0, 0, fee_base);
+	loop {
+		let (_, coins) = select_coins(
+			wallet,
+			amount + fee,
+			current_height,
+			minimum_confirmations,
+			minimum_confirmations,
+			max_outputs,
+			false,
+			SelectionStrategy::LargestFirst,
+			&[],
+			parent_key_id,
+			None,
+		);
+		let total: u64 = coins.iter().map(|c| c.value).sum();
+		let needed = tx_fee(cmp::max(coins.len(), 1), num_outputs, 1, 0, 0, 0, fee_base);
+		if needed <= fee {
+			if total < amount + needed {
+				return Err(not_enough_funds(
+					total,
+					amount + needed,
+					coins.iter().map(|c| c.value),
+				));
+			}
+			return Ok((coins, total, needed));
+		}
+		fee = needed;
+	}
+}
+
+/// Refuses splits that would leave less than one unit for each output
+fn check_split_value(value: u64, num_outputs: usize) -> Result<(), Error> {
+	if value < num_outputs as u64 {
+		return Err(ErrorKind::SplitOutputs(format!(
+			"{} isn't enough to split into {} outputs",
+			value, num_outputs
+		))
+		.into());
+	}
+	Ok(())
+}
+
+/// Selects token outputs of the given type to consolidate into a single token
+/// output. Up to `max_outputs` eligible outputs are taken, smallest first,
+/// using the same filtering as `select_token_coins`. Issue outputs spend like
+/// any other token output, so they're included too. The base fee is paid from
+/// base inputs, so the consolidated output holds the whole total of the
+/// selected outputs, which is returned along with them.
+pub fn select_token_coins_for_consolidation<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	token_type: &str,
+	current_height: u64,
+	minimum_confirmations: u64,
+	max_outputs: usize,
+	parent_key_id: &Identifier,
+) -> Result<(Vec<TokenOutputData>, u64), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let eligible = eligible_token_coins(
+		wallet,
+		token_type,
+		current_height,
+		minimum_confirmations,
+		SelectionStrategy::SmallestFirst,
+		parent_key_id,
+	);
+	let coins: Vec<TokenOutputData> = eligible.into_iter().take(max_outputs).collect();
+	let total: u64 = coins.iter().map(|c| c.value).sum();
+	if total == 0 {
+		return Err(not_enough_token_funds(token_type, 0, 1));
+	}
+	Ok((coins, total))
+}
+
+/// Sorts outputs in the order the strategy wants to spend them, given the
+/// value, height, key id and mmr index of each. Ties on value (or on height
+/// and value when spending the oldest first) are broken by key id and then
+/// mmr index, which keeps the order stable regardless of the order the
+/// wallet iterates its outputs.
+fn sort_for_strategy<O, F>(outputs: &mut [O], selection_strategy: SelectionStrategy, key: F)
+where
+	F: Fn(&O) -> (u64, u64, Identifier, Option<u64>),
+{
+	outputs.sort_by(|a, b| {
+		let (a_value, a_height, a_id, a_mmr_index) = key(a);
+		let (b_value, b_height, b_id, b_mmr_index) = key(b);
+		let by_value = match selection_strategy {
+			// random selection shuffles from the smallest first order, so
+			// the seed alone decides the outcome
+			SelectionStrategy::UseAll
+			| SelectionStrategy::SmallestFirst
+			| SelectionStrategy::Random { .. } => a_value.cmp(&b_value),
+			SelectionStrategy::LargestFirst | SelectionStrategy::BiggestFewest => {
+				b_value.cmp(&a_value)
+			}
+			SelectionStrategy::OldestFirst => {
+				a_height.cmp(&b_height).then_with(|| a_value.cmp(&b_value))
+			}
+		};
+		by_value
+			.then_with(|| a_id.cmp(&b_id))
+			.then_with(|| a_mmr_index.cmp(&b_mmr_index))
+	});
+}
+
+/// Strategy used when going over max_outputs; never spend everything
+fn extension_strategy(selection_strategy: SelectionStrategy) -> SelectionStrategy {
+	match selection_strategy {
+		SelectionStrategy::UseAll => SelectionStrategy::SmallestFirst,
+		s => s,
+	}
+}
+
+/// Select outputs covering the amount from outputs already sorted in the
+/// order the strategy wants to spend them
+fn select_from(
+	amount: u64,
+	selection_strategy: SelectionStrategy,
+	outputs: &[OutputData],
+) -> Option<Vec<OutputData>> {
+	let total = outputs.iter().fold(0, |acc, x| acc + x.value);
+	if total >= amount {
+		match selection_strategy {
+			SelectionStrategy::UseAll => Some(outputs.to_vec()),
+			SelectionStrategy::BiggestFewest => {
+				let values: Vec<u64> = outputs.iter().map(|out| out.value).collect();
+				Some(
+					fewest_indices(amount, &values)
+						.into_iter()
+						.map(|i| outputs[i].clone())
+						.collect(),
+				)
+			}
+			SelectionStrategy::SmallestFirst
+			| SelectionStrategy::LargestFirst
+			| SelectionStrategy::OldestFirst => {
+				let mut selected_amount = 0;
+				Some(
+					outputs
+						.iter()
+						.take_while(|out| {
+							let res = selected_amount < amount;
+							selected_amount += out.value;
+							res
+						})
+						.cloned()
+						.collect(),
+				)
+			}
+			SelectionStrategy::Random { seed } => {
+				let values: Vec<u64> = outputs.iter().map(|out| out.value).collect();
+				Some(
+					random_indices(amount, &values, seed)
+						.into_iter()
+						.map(|i| outputs[i].clone())
+						.collect(),
+				)
+			}
 		}
 	} else {
-		if let Some(outputs) = select_token_from(amount, select_all, eligible.clone()) {
-			return (max_available, outputs);
+		None
+	}
+}
+
+/// Given values whose sum covers the amount, returns the indices of values
+/// covering it, taken in an order shuffled by the seed until the amount is
+/// met. Any output may end up selected, larger ones included, rather than
+/// only those at one end of the value range.
+fn random_indices(amount: u64, values: &[u64], seed: u64) -> Vec<usize> {
+	let mut order: Vec<usize> = (0..values.len()).collect();
+	let mut state = seed;
+	// Fisher-Yates shuffle, driven by splitmix64 so a seed gives the same
+	// selection whatever the version of any rng crate
+	for i in (1..order.len()).rev() {
+		state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+		let mut z = state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		z ^= z >> 31;
+		order.swap(i, (z % (i as u64 + 1)) as usize);
+	}
+	let mut selected_amount = 0;
+	order
+		.into_iter()
+		.take_while(|i| {
+			let res = selected_amount < amount;
+			selected_amount += values[*i];
+			res
+		})
+		.collect()
+}
+
+/// Given values sorted by decreasing value whose sum covers the amount,
+/// returns the indices of the smallest number of values covering it. The
+/// largest values are taken first, and the final one is swapped for the
+/// smallest value that still covers what's left, to keep change low.
+fn fewest_indices(amount: u64, values: &[u64]) -> Vec<usize> {
+	let mut selected = vec![];
+	let mut selected_amount = 0;
+	for (i, v) in values.iter().enumerate() {
+		if selected_amount >= amount {
+			break;
+		}
+		selected.push(i);
+		selected_amount += v;
+	}
+	if let Some(last) = selected.pop() {
+		let needed = amount - (selected_amount - values[last]);
+		// values are sorted decreasing, so the last index covering what's
+		// needed is the smallest such value
+		let mut best = last;
+		for (i, v) in values.iter().enumerate().skip(last + 1) {
+			if *v < needed {
+				break;
+			}
+			best = i;
+		}
+		selected.push(best);
+	}
+	selected
+}
+
+/// Most steps `exact_indices` takes looking for fewer values before settling
+/// for the best match found
+const EXACT_MATCH_TRIES: usize = 100_000;
+
+/// Returns the indices, in order, of the fewest values adding up to exactly
+/// the amount, if any do. Values are tried largest first in a depth first
+/// search, which gives up looking for fewer after `EXACT_MATCH_TRIES` steps.
+fn exact_indices(amount: u64, values: &[u64]) -> Option<Vec<usize>> {
+	let mut order: Vec<usize> = (0..values.len()).collect();
+	order.sort_by_key(|i| cmp::Reverse(values[*i]));
+	// what the values from each position on add up to, to stop early
+	let mut remaining = vec![0; order.len() + 1];
+	for pos in (0..order.len()).rev() {
+		remaining[pos] = remaining[pos + 1] + values[order[pos]];
+	}
+
+	let mut best: Option<Vec<usize>> = None;
+	let mut included: Vec<usize> = vec![];
+	let mut sum = 0;
+	let mut pos = 0;
+	for _ in 0..EXACT_MATCH_TRIES {
+		let backtrack = if sum == amount {
+			best = Some(included.clone());
+			true
+		} else {
+			let no_fewer = best
+				.as_ref()
+				.map_or(false, |b| included.len() + 1 >= b.len());
+			pos == order.len() || sum + remaining[pos] < amount || no_fewer
+		};
+		if backtrack {
+			// leave out the last value included, and carry on after it
+			match included.pop() {
+				Some(last) => {
+					sum -= values[order[last]];
+					pos = last + 1;
+				}
+				None => break,
+			}
+		} else {
+			if sum + values[order[pos]] <= amount {
+				included.push(pos);
+				sum += values[order[pos]];
+			}
+			pos += 1;
+		}
+	}
+
+	best.map(|positions| {
+		let mut indices: Vec<usize> = positions.into_iter().map(|p| order[p]).collect();
+		indices.sort();
+		indices
+	})
+}
+
+/// Builds a token issue into the slate, returning the context along with the
+/// new token type it was issued under, which is also set on the slate
+pub fn build_issue_token_tx<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain: &K,
+	keychain_mask: Option<&SecretKey>,
+	slate: &mut Slate,
+	max_supply: Option<u64>,
+	current_height: u64,
+	minimum_confirmations: u64,
+	max_outputs: usize,
+	change_outputs: usize,
+	selection_strategy: SelectionStrategy,
+	parent_key_id: Identifier,
+	use_test_nonce: bool,
+) -> Result<(Context, TokenKey), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	// refused before any change key is derived
+	check_issue_amount(slate.amount, max_supply)?;
+	let params = SendParams {
+		minimum_confirmations,
+		selection: SelectionParams {
+			max_outputs,
+			strict_max_outputs: false,
+			selection_strategy,
+		},
+		change_outputs,
+		..Default::default()
+	};
+	let mut selected = select_send_tx(
+		wallet,
+		keychain_mask,
+		0,
+		current_height,
+		&params,
+		&StrategySelector,
+		&parent_key_id,
+		&[],
+		None,
+		0,
+		1,
+		true,
+	)?;
+
+	let (mut token_elems, (token_type, amount, key_id, mmr_index)) = token_issue_output(
+		wallet,
+		keychain_mask,
+		slate.amount,
+		max_supply,
+		use_test_nonce,
+	)?;
+
+	let mut all_elems = vec![];
+	all_elems.append(&mut token_elems);
+	all_elems.append(&mut selected.elems);
+
+	let fee = selected.fee;
+	slate.fee = fee;
+	let (blinding, token_blinding) =
+		slate.add_transaction_elements(keychain, &ProofBuilder::new(keychain), all_elems)?;
+
+	slate.token_type = Some(token_type.to_hex());
+	slate.construct_issue_token_kernel(keychain, amount, &key_id, use_test_nonce)?;
+
+	// Create our own private context
+	let mut context = Context::new(
+		keychain.secp(),
+		blinding.secret_key(&keychain.secp()).unwrap(),
+		token_blinding.secret_key(&keychain.secp()).unwrap(),
+		&parent_key_id,
+		use_test_nonce,
+		false,
+	);
+
+	context.fee = fee;
+	context.selection_strategy = Some(selection_strategy);
+
+	// Store our private identifiers for each input
+	for input in selected.inputs {
+		context.add_input(&input.key_id, &input.mmr_index, input.value);
+		if let Some(ref commit) = input.commit {
+			context.add_input_commit(&input.key_id, commit);
+		}
+	}
+
+	// Store change output(s) and cached commits
+	for change in &selected.change {
+		context.add_output(&change.key_id, &change.mmr_index, change.amount);
+	}
+
+	// Store change token output(s) and cached commits
+	context.add_token_output(&key_id, &mmr_index, amount);
+
+	Ok((context, token_type))
+}
+
+/// Checks the supply a token is issued with is neither zero nor over the
+/// given maximum supply
+pub fn check_issue_amount(amount: u64, max_supply: Option<u64>) -> Result<(), Error> {
+	if amount == 0 {
+		return Err(
+			ErrorKind::GenericError("Can't issue a token with no supply".to_owned()).into(),
+		);
+	}
+	match max_supply {
+		Some(max) if amount > max => Err(ErrorKind::GenericError(format!(
+			"Issued supply {} is over the maximum supply {}",
+			amount, max
+		))
+		.into()),
+		_ => Ok(()),
+	}
+}
+
+/// Builds the output of a token issue, under a new token type returned along
+/// with it. The issued amount is checked against `max_supply` first.
+pub fn token_issue_output<'a, T: ?Sized, C, K, B>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	amount: u64,
+	max_supply: Option<u64>,
+	use_test_rng: bool,
+) -> Result<
+	(
+		Vec<Box<build::Append<K, B>>>,
+		(TokenKey, u64, Identifier, Option<u64>),
+	),
+	Error,
+>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+	B: ProofBuild,
+{
+	check_issue_amount(amount, max_supply)?;
+	let mut parts = vec![];
+
+	let token_key = next_change_key(wallet, keychain_mask, None)?;
+	// test vectors need a reproducible token type, so it's derived from the
+	// key of the issued output rather than drawn at random
+	let token_type = match use_test_rng {
+		false => TokenKey::new_token_key(),
+		true => {
+			let hash = blake2b(32, &[], &token_key.to_bytes());
+			TokenKey::from_hex(&hash.as_bytes().to_vec().to_hex())?
 		}
+	};
+	parts.push(build::token_output(
+		amount,
+		token_type.clone(),
+		true,
+		token_key.clone(),
+	));
+
+	Ok((parts, (token_type, amount, token_key.clone(), None)))
+}
+
+/// Select spendable token outputs of the given type from a wallet, in the
+/// same manner as `select_coins`
+pub fn select_token_coins<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	amount: u64,
+	token_type: &str,
+	current_height: u64,
+	minimum_confirmations: u64,
+	max_outputs: usize,
+	selection_strategy: SelectionStrategy,
+	parent_key_id: &Identifier,
+) -> (usize, Vec<TokenOutputData>)
+//    max_outputs_available, Outputs
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let eligible = eligible_token_coins(
+		wallet,
+		token_type,
+		current_height,
+		minimum_confirmations,
+		selection_strategy,
+		parent_key_id,
+	);
+	select_token_coins_from(
+		&StrategySelector,
+		eligible,
+		amount,
+		max_outputs,
+		selection_strategy,
+	)
+}
+
+/// Finds the token outputs of the given type eligible to spend, sorted in the
+/// order the strategy wants to spend them
+fn eligible_token_coins<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	token_type: &str,
+	current_height: u64,
+	minimum_confirmations: u64,
+	selection_strategy: SelectionStrategy,
+	parent_key_id: &Identifier,
+) -> Vec<TokenOutputData>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	// first find all eligible outputs based on number of confirmations, leaving
+	// out those reserved by transactions not locked yet
+	let reservations = live_reservations(wallet, current_height);
+	let mut eligible = wallet
+		.token_outputs_for(
+			parent_key_id,
+			token_type,
+			&[OutputStatus::Unconfirmed, OutputStatus::Unspent],
+		)
+		.filter(|out| {
+			out.eligible_to_spend(current_height, minimum_confirmations)
+				&& !out.is_frozen
+				&& !reservations
+					.iter()
+					.any(|r| r.reserves_token(&out.key_id, &out.mmr_index))
+		})
+		.collect::<Vec<TokenOutputData>>();
+
+	// sort eligible outputs in the order the strategy wants to spend them
+	sort_for_strategy(&mut eligible, selection_strategy, |out| {
+		(out.value, out.height, out.key_id.clone(), out.mmr_index)
+	});
+	eligible
+}
+
+/// Selects token outputs covering the amount from the sorted eligible outputs
+/// with the given selector. Returns the number of outputs available along with
+/// the selection.
+fn select_token_coins_from(
+	coin_selector: &dyn CoinSelector,
+	mut eligible: Vec<TokenOutputData>,
+	amount: u64,
+	max_outputs: usize,
+	selection_strategy: SelectionStrategy,
+) -> (usize, Vec<TokenOutputData>) {
+	let max_available = eligible.len();
+
+	let params = SelectionParams {
+		max_outputs,
+		strict_max_outputs: false,
+		selection_strategy,
+	};
+	if let Some(outputs) = coin_selector.select_tokens(&eligible, amount, &params) {
+		return (max_available, outputs);
 	}
 
 	// we failed to find a suitable set of outputs to spend,
 	// so return the largest amount we can so we can provide guidance on what is
 	// possible
-	eligible.reverse();
+	sort_for_strategy(&mut eligible, SelectionStrategy::LargestFirst, |out| {
+		(out.value, out.height, out.key_id.clone(), out.mmr_index)
+	});
 	(
 		max_available,
 		eligible.iter().take(max_outputs).cloned().collect(),
 	)
 }
 
+/// Select token outputs covering the amount as `select_from` does, except
+/// that the fewest outputs adding up to exactly the amount are preferred when
+/// there are any. Token change would only split the balance further, so this
+/// goes before the strategy, other than a seeded random draw which is kept as
+/// drawn.
 fn select_token_from(
 	amount: u64,
-	select_all: bool,
-	outputs: Vec<TokenOutputData>,
+	selection_strategy: SelectionStrategy,
+	outputs: &[TokenOutputData],
 ) -> Option<Vec<TokenOutputData>> {
 	let total = outputs.iter().fold(0, |acc, x| acc + x.value);
 	if total >= amount {
-		if select_all {
-			return Some(outputs.iter().cloned().collect());
-		} else {
-			let mut selected_amount = 0;
-			return Some(
-				outputs
-					.iter()
-					.take_while(|out| {
-						let res = selected_amount < amount;
-						selected_amount += out.value;
-						res
-					})
-					.cloned()
+		let values: Vec<u64> = outputs.iter().map(|out| out.value).collect();
+		let exact = match selection_strategy {
+			SelectionStrategy::Random { .. } => None,
+			_ if amount == 0 => None,
+			_ => exact_indices(amount, &values),
+		};
+		if let Some(indices) = exact {
+			return Some(indices.into_iter().map(|i| outputs[i].clone()).collect());
+		}
+		match selection_strategy {
+			SelectionStrategy::UseAll => Some(outputs.to_vec()),
+			SelectionStrategy::BiggestFewest => Some(
+				fewest_indices(amount, &values)
+					.into_iter()
+					.map(|i| outputs[i].clone())
 					.collect(),
-			);
+			),
+			SelectionStrategy::SmallestFirst
+			| SelectionStrategy::LargestFirst
+			| SelectionStrategy::OldestFirst => {
+				let mut selected_amount = 0;
+				Some(
+					outputs
+						.iter()
+						.take_while(|out| {
+							let res = selected_amount < amount;
+							selected_amount += out.value;
+							res
+						})
+						.cloned()
+						.collect(),
+				)
+			}
+			SelectionStrategy::Random { seed } => Some(
+				random_indices(amount, &values, seed)
+					.into_iter()
+					.map(|i| outputs[i].clone())
+					.collect(),
+			),
 		}
 	} else {
 		None
@@ -1395,18 +4518,35 @@ fn select_token_from(
 /// with outputs from the stored context
 /// change outputs and tx log entry
 /// Remove the explicitly stored excess
+/// Every input of the context must be unspent in the wallet, and once the
+/// transaction is locked (`outputs_stored`) so must every output, otherwise
+/// the slate would come out unbalanced and an error is returned instead.
+/// The slate itself must still be the one the context was built for, see
+/// `check_slate_context`.
 pub fn repopulate_tx<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
 	keychain_mask: Option<&SecretKey>,
 	slate: &mut Slate,
 	context: &Context,
 	update_fee: bool,
+	outputs_stored: bool,
 ) -> Result<(), Error>
 where
 	T: WalletBackend<'a, C, K>,
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
 {
+	// a slate that carries our inputs has nothing to repopulate, adding them
+	// again would unbalance it
+	if should_include_inputs_in_sum(slate) {
+		return Err(ErrorKind::GenericError(format!(
+			"Slate version {} carries its inputs and can't be repopulated",
+			slate.version_info.version
+		))
+		.into());
+	}
+	check_slate_context(slate, context)?;
+
 	// restore the original amount, fee
 	slate.amount = context.amount;
 	if update_fee {
@@ -1422,43 +4562,84 @@ where
 	};
 	slate.add_participant_info(&keychain, key, &context.sec_nonce, None)?;
 
+	// index the wallet once rather than scanning it for every context entry
+	let outputs: HashMap<Identifier, OutputData> =
+		wallet.iter().map(|out| (out.key_id.clone(), out)).collect();
+	let token_outputs: HashMap<Identifier, TokenOutputData> = match slate.token_type {
+		Some(_) => wallet
+			.token_iter()
+			.map(|out| (out.key_id.clone(), out))
+			.collect(),
+		None => HashMap::new(),
+	};
+	let missing = |kind: &str, id: &Identifier| -> Error {
+		ErrorKind::ContextOutputMissing(format!("{} {:?}", kind, id)).into()
+	};
+	let mismatch = |kind: &str, id: &Identifier, reason: &str| -> Error {
+		ErrorKind::ContextOutputMismatch(format!("{} {:?} {}", kind, id, reason)).into()
+	};
+	// each key of the context stands for a single output, any key listed twice
+	// would add the same output to the transaction twice
+	let mut seen = HashSet::new();
+	let mut check_unique = |kind: &str, id: &Identifier| -> Result<(), Error> {
+		match seen.insert(id.clone()) {
+			true => Ok(()),
+			false => Err(mismatch(kind, id, "is listed more than once")),
+		}
+	};
+
 	let mut parts = vec![];
 	for (id, _, value) in &context.get_inputs() {
-		let input = wallet.iter().find(|out| out.key_id == *id);
-		if let Some(i) = input {
-			if i.is_coinbase {
-				parts.push(build::coinbase_input(*value, i.key_id.clone()));
-			} else {
-				parts.push(build::input(*value, i.key_id.clone()));
-			}
+		check_unique("input", id)?;
+		let i = outputs.get(id).ok_or_else(|| missing("input", id))?;
+		if i.status == OutputStatus::Spent {
+			return Err(mismatch("input", id, "has been spent"));
+		}
+		if i.is_coinbase {
+			parts.push(build::coinbase_input(*value, i.key_id.clone()));
+		} else {
+			parts.push(build::input(*value, i.key_id.clone()));
 		}
 	}
+	// our inputs must always be in the wallet, but outputs are only stored once
+	// the transaction is locked, so until then they're left out
 	for (id, _, value) in &context.get_outputs() {
-		let output = wallet.iter().find(|out| out.key_id == *id);
-		if let Some(i) = output {
-			parts.push(build::output(*value, i.key_id.clone()));
+		check_unique("output", id)?;
+		match outputs.get(id) {
+			Some(i) => parts.push(build::output(*value, i.key_id.clone())),
+			None if outputs_stored => return Err(missing("output", id)),
+			None => {}
 		}
 	}
-	for (id, _, value) in &context.get_token_inputs() {
-		let output = wallet.token_iter().find(|out| out.key_id == *id);
-		if let Some(i) = output {
+	// only token slates have token inputs and outputs in their context
+	if let Some(token_key) = slate.token_key()? {
+		for (id, _, value) in &context.get_token_inputs() {
+			check_unique("token input", id)?;
+			let i = token_outputs
+				.get(id)
+				.ok_or_else(|| missing("token input", id))?;
+			if i.status == OutputStatus::Spent {
+				return Err(mismatch("token input", id, "has been spent"));
+			}
 			parts.push(build::token_input(
 				*value,
-				TokenKey::from_hex(slate.token_type.clone().unwrap().as_str())?,
+				token_key.clone(),
 				i.is_token_issue,
 				i.key_id.clone(),
 			));
 		}
-	}
-	for (id, _, value) in &context.get_token_outputs() {
-		let output = wallet.token_iter().find(|out| out.key_id == *id);
-		if let Some(i) = output {
-			parts.push(build::token_output(
-				*value,
-				TokenKey::from_hex(slate.token_type.clone().unwrap().as_str())?,
-				false,
-				i.key_id.clone(),
-			));
+		for (id, _, value) in &context.get_token_outputs() {
+			check_unique("token output", id)?;
+			match token_outputs.get(id) {
+				Some(i) => parts.push(build::token_output(
+					*value,
+					token_key.clone(),
+					false,
+					i.key_id.clone(),
+				)),
+				None if outputs_stored => return Err(missing("token output", id)),
+				None => {}
+			}
 		}
 	}
 	let _ = slate.add_transaction_elements(&keychain, &ProofBuilder::new(&keychain), parts)?;
@@ -1466,3 +4647,1097 @@ where
 	slate.tx_or_err_mut()?.offset = slate.offset.clone();
 	Ok(())
 }
+
+/// Checks a slate handed back to us wasn't changed from what its context was
+/// built for, before we sign it again: its token type, its amount (which only
+/// the sender of a standard transaction fixes, and may be left out of a
+/// compact slate) and the addresses of its payment proof.
+pub fn check_slate_context(slate: &Slate, context: &Context) -> Result<(), Error> {
+	let changed = |field: &str| -> Error {
+		ErrorKind::SlateContextMismatch(format!("{} has been changed", field)).into()
+	};
+	// contexts from before the token type was recorded hold none to check
+	if context.token_type.is_some() && slate.token_type != context.token_type {
+		return Err(changed("token type"));
+	}
+	if !context.is_invoice && slate.amount != 0 && slate.amount != context.amount {
+		return Err(changed("amount"));
+	}
+	// contexts from before the addresses were recorded have nothing to check
+	if let (Some(sender), Some(receiver)) = (
+		context.payment_proof_sender_address,
+		context.payment_proof_receiver_address,
+	) {
+		let p = slate.payment_proof.as_ref().ok_or_else(|| -> Error {
+			ErrorKind::SlateContextMismatch("payment proof has been removed".to_owned()).into()
+		})?;
+		if p.sender_address != sender {
+			return Err(changed("payment proof sender address"));
+		}
+		if p.receiver_address != receiver {
+			return Err(changed("payment proof receiver address"));
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::grin_keychain::{ExtKeychain, ExtKeychainPath};
+	use crate::grin_util::secp;
+	use crate::slate_versions::CURRENT_SLATE_VERSION;
+
+	fn output(n: u32, value: u64) -> OutputData {
+		let key_id = ExtKeychainPath::new(1, n, 0, 0, 0).to_identifier();
+		OutputData {
+			root_key_id: ExtKeychainPath::new(0, 0, 0, 0, 0).to_identifier(),
+			key_id: key_id.clone(),
+			n_child: n,
+			commit: None,
+			mmr_index: None,
+			value,
+			status: OutputStatus::Unspent,
+			height: 1,
+			lock_height: 0,
+			is_coinbase: false,
+			is_frozen: false,
+			tx_log_entry: None,
+		}
+	}
+
+	fn outputs(values: &[u64]) -> Vec<OutputData> {
+		values
+			.iter()
+			.enumerate()
+			.map(|(i, v)| output(i as u32, *v))
+			.collect()
+	}
+
+	fn values(outputs: &[OutputData]) -> Vec<u64> {
+		outputs.iter().map(|o| o.value).collect()
+	}
+
+	fn token_output(out: OutputData) -> TokenOutputData {
+		TokenOutputData {
+			root_key_id: out.root_key_id,
+			key_id: out.key_id,
+			n_child: out.n_child,
+			commit: None,
+			token_type: String::new(),
+			mmr_index: None,
+			value: out.value,
+			status: OutputStatus::Unspent,
+			height: out.height,
+			lock_height: 0,
+			is_token_issue: false,
+			is_frozen: false,
+			tx_log_entry: None,
+		}
+	}
+
+	fn token_outputs(values: &[u64]) -> Vec<TokenOutputData> {
+		outputs(values).into_iter().map(token_output).collect()
+	}
+
+	#[test]
+	fn greedy_selection_takes_minimal_prefix() {
+		let available = [10, 10, 20];
+		// tokens take an exact match over the prefix when there is one
+		let cases: Vec<(u64, Option<Vec<u64>>, Option<Vec<u64>>)> = vec![
+			(0, Some(vec![]), Some(vec![])),
+			(1, Some(vec![10]), Some(vec![10])),
+			(10, Some(vec![10]), Some(vec![10])),
+			(15, Some(vec![10, 10]), Some(vec![10, 10])),
+			(20, Some(vec![10, 10]), Some(vec![20])),
+			(21, Some(vec![10, 10, 20]), Some(vec![10, 10, 20])),
+			(40, Some(vec![10, 10, 20]), Some(vec![10, 10, 20])),
+			(41, None, None),
+		];
+		for (amount, expected, token_expected) in cases {
+			let selected = select_from(
+				amount,
+				SelectionStrategy::SmallestFirst,
+				&outputs(&available),
+			);
+			assert_eq!(selected.map(|s| values(&s)), expected, "amount {}", amount);
+
+			let selected = select_token_from(
+				amount,
+				SelectionStrategy::SmallestFirst,
+				&token_outputs(&available),
+			);
+			let selected = selected.map(|s| s.iter().map(|o| o.value).collect::<Vec<u64>>());
+			assert_eq!(selected, token_expected, "token amount {}", amount);
+		}
+	}
+
+	#[test]
+	fn exact_token_match_needs_no_change() {
+		for strategy in &[
+			SelectionStrategy::UseAll,
+			SelectionStrategy::SmallestFirst,
+			SelectionStrategy::LargestFirst,
+			SelectionStrategy::BiggestFewest,
+		] {
+			let selected = select_token_from(3, *strategy, &token_outputs(&[1, 2, 3])).unwrap();
+			assert_eq!(
+				selected.iter().map(|o| o.value).collect::<Vec<u64>>(),
+				vec![3],
+				"{}",
+				strategy
+			);
+		}
+
+		// the fewest outputs matching are taken, in the order given
+		let selected = select_token_from(
+			9,
+			SelectionStrategy::SmallestFirst,
+			&token_outputs(&[1, 2, 3, 4, 5, 8]),
+		)
+		.unwrap();
+		assert_eq!(
+			selected.iter().map(|o| o.value).collect::<Vec<u64>>(),
+			vec![1, 8]
+		);
+		assert_eq!(exact_indices(9, &[1, 2, 3, 4, 5, 8]), Some(vec![0, 5]));
+		assert_eq!(exact_indices(7, &[5, 5, 4]), None);
+
+		// without an exact match the strategy selects as before
+		let selected =
+			select_token_from(4, SelectionStrategy::UseAll, &token_outputs(&[3, 3, 3])).unwrap();
+		assert_eq!(selected.len(), 3);
+	}
+
+	#[test]
+	fn largest_first_uses_fewer_inputs() {
+		let smallest = select_from(
+			100,
+			SelectionStrategy::SmallestFirst,
+			&outputs(&[10, 10, 20, 30, 60, 90]),
+		)
+		.unwrap();
+		let largest = select_from(
+			100,
+			SelectionStrategy::LargestFirst,
+			&outputs(&[90, 60, 30, 20, 10, 10]),
+		)
+		.unwrap();
+		assert_eq!(values(&smallest), vec![10, 10, 20, 30, 60]);
+		assert_eq!(values(&largest), vec![90, 60]);
+		assert!(largest.len() < smallest.len());
+	}
+
+	#[test]
+	fn random_is_reproducible_by_seed() {
+		let available = [10, 10, 20, 30, 60, 90];
+		let select = |seed| {
+			let selected = select_from(
+				100,
+				SelectionStrategy::Random { seed },
+				&outputs(&available),
+			)
+			.unwrap();
+			values(&selected)
+		};
+		assert_eq!(select(42), vec![60, 30, 10]);
+		assert_eq!(select(42), select(42));
+		// a different seed may reach for a larger output
+		assert_eq!(select(7), vec![10, 90]);
+
+		// tokens shuffle the same way
+		let selected = select_token_from(
+			100,
+			SelectionStrategy::Random { seed: 42 },
+			&token_outputs(&available),
+		)
+		.unwrap();
+		let selected: Vec<u64> = selected.iter().map(|o| o.value).collect();
+		assert_eq!(selected, vec![60, 30, 10]);
+
+		// not enough funds whatever the order
+		assert!(select_from(
+			500,
+			SelectionStrategy::Random { seed: 42 },
+			&outputs(&available)
+		)
+		.is_none());
+	}
+
+	#[test]
+	fn breakdown_notes_balance_held_up() {
+		let mut breakdown = BalanceBreakdown {
+			spendable: 5,
+			..Default::default()
+		};
+		assert_eq!(breakdown_disp(&breakdown, |v| v.to_string()), "");
+
+		breakdown.awaiting_confirmation = 20;
+		breakdown.immature_coinbase = 60;
+		assert_eq!(
+			breakdown_disp(&breakdown, |v| v.to_string()),
+			". 20 awaiting confirmation, 60 immature"
+		);
+	}
+
+	#[test]
+	fn biggest_fewest_limits_change() {
+		// two inputs are needed, the second should be the smallest covering 10
+		let selected = select_from(
+			100,
+			SelectionStrategy::BiggestFewest,
+			&outputs(&[90, 60, 30, 20, 10, 5]),
+		)
+		.unwrap();
+		assert_eq!(values(&selected), vec![90, 10]);
+
+		// a single output covers the amount
+		let selected = select_from(
+			25,
+			SelectionStrategy::BiggestFewest,
+			&outputs(&[90, 60, 30, 20, 10, 5]),
+		)
+		.unwrap();
+		assert_eq!(values(&selected), vec![30]);
+	}
+
+	#[test]
+	fn use_all_spends_everything() {
+		let selected =
+			select_from(15, SelectionStrategy::UseAll, &outputs(&[10, 10, 20, 30])).unwrap();
+		assert_eq!(selected.len(), 4);
+		assert!(select_from(100, SelectionStrategy::UseAll, &outputs(&[10, 20])).is_none());
+	}
+
+	#[test]
+	fn change_is_not_split_into_dust() {
+		// no threshold keeps the requested number of outputs
+		assert_eq!(change_outputs_above_dust(1_000, 4, 0), 4);
+		// enough change for every output
+		assert_eq!(change_outputs_above_dust(1_000, 4, 250), 4);
+		// only room for two outputs above the threshold
+		assert_eq!(change_outputs_above_dust(1_000, 4, 400), 2);
+		// change below the threshold is kept whole
+		assert_eq!(change_outputs_above_dust(100, 4, 400), 1);
+		// change is never dropped, even when no outputs were asked for
+		assert_eq!(change_outputs_above_dust(1_000, 0, 0), 1);
+	}
+
+	#[test]
+	fn split_change_smaller_than_outputs() {
+		let amounts = split_change(3, 5, ChangeDistribution::LastAbsorbs);
+		assert_eq!(amounts, vec![1, 1, 1]);
+		assert_eq!(amounts.iter().sum::<u64>(), 3);
+
+		// a single nanogrin can't be split at all
+		assert_eq!(split_change(1, 4, ChangeDistribution::LastAbsorbs), vec![1]);
+
+		let amounts = split_change(1_003, 4, ChangeDistribution::LastAbsorbs);
+		assert_eq!(amounts, vec![250, 250, 250, 253]);
+	}
+
+	#[test]
+	fn change_never_underflows() {
+		assert_eq!(change_from(100, 60, 40, [100].iter().cloned()).unwrap(), 0);
+		assert_eq!(change_from(100, 60, 10, [100].iter().cloned()).unwrap(), 30);
+
+		// inputs that don't cover the amount and fee
+		match change_from(100, 60, 50, [40, 60].iter().cloned()).map_err(|e| e.kind()) {
+			Err(ErrorKind::NotEnoughFunds {
+				available,
+				needed,
+				num_outputs,
+				..
+			}) => {
+				assert_eq!(available, 100);
+				assert_eq!(needed, 110);
+				assert_eq!(num_outputs, 2);
+			}
+			_ => panic!("expected not enough funds"),
+		}
+
+		// an amount and fee that can't even be added up
+		match change_from(100, u64::max_value(), 1, [100].iter().cloned()).map_err(|e| e.kind()) {
+			Err(ErrorKind::GenericError(_)) => {}
+			_ => panic!("expected the overflow to be reported"),
+		}
+		// as when adding the fee up front, before any inputs are selected
+		match with_fee(u64::max_value(), 1).map_err(|e| e.kind()) {
+			Err(ErrorKind::GenericError(_)) => {}
+			_ => panic!("expected the overflow to be reported"),
+		}
+	}
+
+	#[test]
+	fn balanced_change() {
+		let amounts = split_change(103, 4, ChangeDistribution::Balanced);
+		assert_eq!(amounts, vec![26, 26, 26, 25]);
+		assert_eq!(
+			plan_change(103, 4, 0, &ChangePlan::Balanced),
+			vec![26, 26, 26, 25]
+		);
+		// no remainder splits the same either way
+		assert_eq!(
+			split_change(100, 4, ChangeDistribution::Balanced),
+			split_change(100, 4, ChangeDistribution::LastAbsorbs)
+		);
+	}
+
+	#[test]
+	fn denominated_change() {
+		let plan = ChangePlan::Denominated(vec![1, 100, 10]);
+		assert_eq!(plan_change(1_234, 1, 0, &plan), vec![100, 10, 1, 1_123]);
+		// denominations that don't fit are skipped
+		assert_eq!(plan_change(50, 1, 0, &plan), vec![10, 1, 39]);
+		// exact fit needs no remainder output
+		assert_eq!(plan_change(111, 1, 0, &plan), vec![100, 10, 1]);
+		assert!(plan_change(1_234, 1, 0, &plan).len() <= plan.max_change_outputs(1));
+	}
+
+	#[test]
+	fn fee_converges_over_large_wallet() {
+		// a wallet of 50k small outputs, where the send needs more than
+		// max_outputs of them
+		let value = 1_000_003;
+		let eligible = outputs(&vec![value; 50_000]);
+		let amount = 1_000 * value;
+		let (coins, total, _, fee) = coins_and_fee_from(
+			&eligible,
+			amount,
+			500,
+			false,
+			1,
+			1,
+			SelectionStrategy::SmallestFirst,
+			&StrategySelector,
+			None,
+			0,
+			&ChangePlan::Even,
+			&[],
+			0,
+			0,
+			1,
+		)
+		.unwrap();
+		assert_eq!(fee, tx_fee(coins.len(), 2, 1, 0, 0, 0, None));
+		assert!(total >= amount + fee);
+		// and no more inputs than needed
+		assert!(total - value < amount + fee);
+	}
+
+	#[test]
+	fn inputs_in_sum_by_slate_version() {
+		let mut slate = Slate::blank(2, false);
+		assert_eq!(slate.version_info.version, CURRENT_SLATE_VERSION);
+		assert!(!should_include_inputs_in_sum(&slate));
+		for (version, included) in vec![(2, true), (3, true), (4, false), (5, false)] {
+			slate.version_info.version = version;
+			assert_eq!(
+				should_include_inputs_in_sum(&slate),
+				included,
+				"slate version {}",
+				version
+			);
+		}
+	}
+
+	#[test]
+	fn fee_pays_for_each_kernel() {
+		let eligible = outputs(&[1_000_000_000_000]);
+		let select = |num_kernels| {
+			coins_and_fee_from(
+				&eligible,
+				1_000_000_000,
+				500,
+				false,
+				1,
+				1,
+				SelectionStrategy::SmallestFirst,
+				&StrategySelector,
+				None,
+				0,
+				&ChangePlan::Even,
+				&[],
+				0,
+				0,
+				num_kernels,
+			)
+			.unwrap()
+		};
+		let (coins, _, _, fee) = select(1);
+		let (coins_2, _, _, fee_2) = select(2);
+		assert_eq!(coins.len(), 1);
+		assert_eq!(coins_2.len(), 1);
+		// one input, the recipient's output and the change
+		let kernel_fee = tx_fee(1, 2, 2, 0, 0, 0, None) - tx_fee(1, 2, 1, 0, 0, 0, None);
+		assert!(kernel_fee > 0);
+		assert_eq!(fee_2, fee + kernel_fee);
+	}
+
+	#[test]
+	fn context_without_token_type_checked() {
+		let secp = secp::Secp256k1::with_caps(secp::ContextFlag::Commit);
+		let sec_key = SecretKey::from_slice(&secp, &[2; 32]).unwrap();
+		let parent_key_id = ExtKeychainPath::new(2, 0, 0, 0, 0).to_identifier();
+		let mut context =
+			Context::new(&secp, sec_key.clone(), sec_key, &parent_key_id, true, false);
+		context.token_type = Some("token".to_owned());
+		context.amount = 300;
+		let mut slate = Slate::blank(2, false);
+		slate.amount = 300;
+		slate.token_type = Some("token".to_owned());
+		assert!(check_slate_context(&slate, &context).is_ok());
+
+		// a token send stored before the token type was recorded
+		let mut json = serde_json::to_value(&context).unwrap();
+		json.as_object_mut().unwrap().remove("token_type");
+		let loaded: Context = serde_json::from_value(json).unwrap();
+		assert_eq!(loaded.token_type, None);
+		assert!(check_slate_context(&slate, &loaded).is_ok());
+
+		// while a recorded one is still checked
+		slate.token_type = Some("other".to_owned());
+		match check_slate_context(&slate, &context) {
+			Err(e) => match e.kind() {
+				ErrorKind::SlateContextMismatch(s) => assert!(s.contains("token type")),
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Changed token type went unnoticed"),
+		}
+	}
+
+	#[test]
+	fn sender_balance_verified() {
+		let secp = secp::Secp256k1::with_caps(secp::ContextFlag::Commit);
+		let sec_key = SecretKey::from_slice(&secp, &[2; 32]).unwrap();
+		let parent_key_id = ExtKeychainPath::new(2, 0, 0, 0, 0).to_identifier();
+		let id = |n| ExtKeychainPath::new(3, 0, 0, 0, n).to_identifier();
+		let mut context =
+			Context::new(&secp, sec_key.clone(), sec_key, &parent_key_id, true, false);
+		context.add_input(&id(1), &None, 60);
+		context.add_input(&id(2), &None, 40);
+		context.add_output(&id(3), &None, 25);
+		context.fee = 15;
+		context.amount = 60;
+		assert!(verify_sender_balance(&context).is_ok());
+
+		// a fee recalculated without the change following it
+		let unbalanced = |context: &Context| match verify_sender_balance(context) {
+			Err(e) => match e.kind() {
+				ErrorKind::UnbalancedTransaction(_) => {}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Imbalance went unnoticed"),
+		};
+		context.fee = 16;
+		unbalanced(&context);
+		context.fee = 14;
+		unbalanced(&context);
+
+		// on token sends the vcash only pays the fee
+		context.token_type = Some("token".to_owned());
+		context.amount = 300;
+		context.add_input(&id(4), &None, 0);
+		context.add_token_input(&id(5), &None, 500);
+		context.add_token_output(&id(6), &None, 150);
+		context.token_fee = 50;
+		context.fee = 75;
+		assert!(verify_sender_balance(&context).is_ok());
+		context.token_fee = 0;
+		unbalanced(&context);
+	}
+
+	#[test]
+	fn fee_deducted_from_amount() {
+		let eligible = outputs(&[10, 20, 30, 40]);
+		let (coins, total, amount, fee) = coins_and_fee_within(
+			&eligible,
+			25,
+			500,
+			false,
+			1,
+			1,
+			SelectionStrategy::SmallestFirst,
+			&StrategySelector,
+			Some(1),
+			0,
+			&ChangePlan::Even,
+			&[],
+			0,
+			0,
+			1,
+		)
+		.unwrap();
+		assert_eq!(values(&coins), vec![10, 20]);
+		assert_eq!(fee, tx_fee(2, 2, 1, 0, 0, 0, Some(1)));
+		assert_eq!(amount + fee, 25);
+		assert_eq!(total - amount - fee, 5);
+
+		// a fee swallowing the whole amount is refused
+		let gross = tx_fee(1, 2, 1, 0, 0, 0, Some(1));
+		let res = coins_and_fee_within(
+			&outputs(&[1_000]),
+			gross,
+			500,
+			false,
+			1,
+			1,
+			SelectionStrategy::SmallestFirst,
+			&StrategySelector,
+			Some(1),
+			0,
+			&ChangePlan::Even,
+			&[],
+			0,
+			0,
+			1,
+		);
+		match res.map_err(|e| e.kind()) {
+			Err(ErrorKind::FeeExceedsAmount { fee, amount }) => {
+				assert_eq!(fee, gross);
+				assert_eq!(amount, gross);
+			}
+			_ => panic!("expected the fee to exceed the amount"),
+		}
+	}
+
+	#[test]
+	fn min_sendable_skips_outputs_below_fee() {
+		assert_eq!(min_sendable_from([5, 30, 12].iter().cloned(), 10), 2);
+		// an output only paying the fee sends nothing
+		assert_eq!(min_sendable_from([10, 40].iter().cloned(), 10), 30);
+		assert_eq!(min_sendable_from([3, 10].iter().cloned(), 10), 0);
+		assert_eq!(min_sendable_from(std::iter::empty(), 10), 0);
+		// with no fee the smallest output is sendable whole
+		assert_eq!(min_sendable_from([7, 3].iter().cloned(), 0), 3);
+	}
+
+	#[test]
+	fn fee_pays_for_the_change_built() {
+		let value = 1_000_000_000_000;
+		let amount = value / 2;
+		// a denomination that never fits budgets an output that isn't built
+		let plan = ChangePlan::Denominated(vec![1, 2 * value]);
+		let (coins, total, _, fee) = coins_and_fee_from(
+			&outputs(&[value]),
+			amount,
+			500,
+			false,
+			1,
+			1,
+			SelectionStrategy::SmallestFirst,
+			&StrategySelector,
+			None,
+			0,
+			&plan,
+			&[],
+			0,
+			0,
+			1,
+		)
+		.unwrap();
+		let change = plan_change(total - amount - fee, 1, 0, &plan);
+		assert_eq!(change.len(), 2);
+		assert_eq!(fee, tx_fee(coins.len(), 1 + change.len(), 1, 0, 0, 0, None));
+		assert!(fee < tx_fee(1, 1 + plan.max_change_outputs(1), 1, 0, 0, 0, None));
+
+		// as does a dust threshold leaving room for fewer change outputs
+		let dust_threshold = value / 5;
+		let (coins, total, _, fee) = coins_and_fee_from(
+			&outputs(&[value]),
+			amount,
+			500,
+			false,
+			1,
+			4,
+			SelectionStrategy::SmallestFirst,
+			&StrategySelector,
+			None,
+			dust_threshold,
+			&ChangePlan::Even,
+			&[],
+			0,
+			0,
+			1,
+		)
+		.unwrap();
+		let change = plan_change(total - amount - fee, 4, dust_threshold, &ChangePlan::Even);
+		assert_eq!(change.len(), 2);
+		assert_eq!(fee, tx_fee(coins.len(), 1 + change.len(), 1, 0, 0, 0, None));
+
+		// the same goes for a fee deducted from the amount
+		let (coins, total, amount, fee) = coins_and_fee_within(
+			&outputs(&[value]),
+			amount,
+			500,
+			false,
+			1,
+			4,
+			SelectionStrategy::SmallestFirst,
+			&StrategySelector,
+			None,
+			dust_threshold,
+			&ChangePlan::Even,
+			&[],
+			0,
+			0,
+			1,
+		)
+		.unwrap();
+		let change = plan_change(total - amount - fee, 4, dust_threshold, &ChangePlan::Even);
+		assert_eq!(change.len(), 2);
+		assert_eq!(fee, tx_fee(coins.len(), 1 + change.len(), 1, 0, 0, 0, None));
+	}
+
+	#[test]
+	fn fee_breakdown_adds_up() {
+		let breakdown =
+			FeeBreakdown::new(2, 3, 1, 1, 2, 1, Some(1), tx_fee(2, 3, 1, 1, 2, 1, Some(1)));
+		assert_eq!(breakdown.base, 0);
+		assert_eq!(breakdown.fee_base, 1);
+		assert_eq!(
+			breakdown.outputs,
+			3 * (tx_fee(0, 11, 1, 0, 0, 0, Some(1)) - tx_fee(0, 10, 1, 0, 0, 0, Some(1))) as i64
+		);
+
+		// a light transaction paying the minimum fee
+		let breakdown = FeeBreakdown::new(5, 1, 1, 0, 0, 0, None, tx_fee(5, 1, 1, 0, 0, 0, None));
+		assert_eq!(
+			breakdown.inputs + breakdown.outputs + breakdown.kernels + breakdown.base,
+			breakdown.total as i64
+		);
+
+		// change given to the fee as dust, and a fee deducted from the amount
+		let value = 1_000_000_000;
+		for (dust_threshold, includes_fee) in &[(0, false), (value, false), (0, true)] {
+			let select = match includes_fee {
+				true => coins_and_fee_within,
+				false => coins_and_fee_from,
+			};
+			let (coins, total, amount, fee) = select(
+				&outputs(&[value, value]),
+				value + value / 4,
+				500,
+				false,
+				1,
+				2,
+				SelectionStrategy::SmallestFirst,
+				&StrategySelector,
+				None,
+				*dust_threshold,
+				&ChangePlan::Even,
+				&[],
+				0,
+				0,
+				1,
+			)
+			.unwrap();
+			let breakdown = fee_breakdown(
+				&coins,
+				total,
+				amount,
+				fee,
+				1,
+				2,
+				*dust_threshold,
+				&ChangePlan::Even,
+				None,
+				0,
+				0,
+				1,
+			)
+			.unwrap();
+			assert_eq!(breakdown.total, fee);
+			assert_eq!(
+				breakdown.inputs
+					+ breakdown.outputs
+					+ breakdown.kernels
+					+ breakdown.token_inputs
+					+ breakdown.token_outputs
+					+ breakdown.token_kernels
+					+ breakdown.base,
+				fee as i64
+			);
+			// dust only shows up in the base of the fee
+			assert_eq!(breakdown.base != 0, *dust_threshold > 0);
+		}
+	}
+
+	#[test]
+	fn consolidation_takes_smallest_outputs() {
+		let mut eligible = outputs(&[500, 10, 20, 30, 40, 1_000_000_000]);
+		sort_for_strategy(&mut eligible, SelectionStrategy::SmallestFirst, |out| {
+			(out.value, out.height, out.key_id.clone(), out.mmr_index)
+		});
+		let (coins, amount, fee) = consolidation_from(eligible.clone(), 6, Some(1)).unwrap();
+		assert_eq!(coins.len(), 6);
+		assert_eq!(fee, tx_fee(6, 1, 1, 0, 0, 0, Some(1)));
+		assert_eq!(amount, 1_000_000_600 - fee);
+
+		let (coins, amount, fee) = consolidation_from(eligible.clone(), 3, Some(1)).unwrap();
+		assert_eq!(values(&coins), vec![10, 20, 30]);
+		assert_eq!(amount, 60 - fee);
+
+		// nothing to consolidate once the fee is paid
+		assert!(consolidation_from(eligible, 3, None).is_err());
+	}
+
+	#[test]
+	fn equal_values_select_deterministically() {
+		let key = |out: &OutputData| (out.value, out.height, out.key_id.clone(), out.mmr_index);
+		let ordered = outputs(&[10, 10, 10, 10, 10]);
+		let mut shuffled = ordered.clone();
+		shuffled.reverse();
+		shuffled.swap(1, 3);
+		for strategy in &[
+			SelectionStrategy::SmallestFirst,
+			SelectionStrategy::LargestFirst,
+			SelectionStrategy::BiggestFewest,
+			SelectionStrategy::Random { seed: 42 },
+		] {
+			let strategy = *strategy;
+			let mut a = ordered.clone();
+			let mut b = shuffled.clone();
+			sort_for_strategy(&mut a, strategy, key);
+			sort_for_strategy(&mut b, strategy, key);
+			let a: Vec<Identifier> = select_from(25, strategy, &a)
+				.unwrap()
+				.into_iter()
+				.map(|o| o.key_id)
+				.collect();
+			let b: Vec<Identifier> = select_from(25, strategy, &b)
+				.unwrap()
+				.into_iter()
+				.map(|o| o.key_id)
+				.collect();
+			assert_eq!(a.len(), 3);
+			assert_eq!(a, b);
+		}
+	}
+
+	#[test]
+	fn observer_sees_sorted_eligible_outputs() {
+		let required = vec![output(9, 5)];
+		let mut eligible = outputs(&[20, 60, 10, 30]);
+		sort_for_strategy(&mut eligible, SelectionStrategy::LargestFirst, |out| {
+			(out.value, out.height, out.key_id.clone(), out.mmr_index)
+		});
+		let (_, selected) = select_coins_from(
+			&StrategySelector,
+			&eligible,
+			80,
+			500,
+			false,
+			SelectionStrategy::LargestFirst,
+			&required,
+		);
+		let mut seen = vec![];
+		observe_selection(&required, &eligible, &selected, &mut |out, is_selected| {
+			seen.push((out.value, is_selected))
+		});
+		assert_eq!(
+			seen,
+			vec![(5, true), (60, true), (30, true), (20, false), (10, false)]
+		);
+	}
+
+	#[test]
+	fn oldest_first_spends_by_height() {
+		let heights = [4, 1, 3, 2];
+		let mut eligible: Vec<OutputData> = outputs(&[10, 20, 30, 40])
+			.into_iter()
+			.zip(heights.iter())
+			.map(|(out, height)| OutputData {
+				height: *height,
+				..out
+			})
+			.collect();
+		let key = |out: &OutputData| (out.value, out.height, out.key_id.clone(), out.mmr_index);
+		sort_for_strategy(&mut eligible, SelectionStrategy::OldestFirst, key);
+		let selected = select_from(50, SelectionStrategy::OldestFirst, &eligible).unwrap();
+		assert_eq!(values(&selected), vec![20, 40]);
+
+		// the value-based strategies don't look at the height
+		sort_for_strategy(&mut eligible, SelectionStrategy::SmallestFirst, key);
+		let selected = select_from(50, SelectionStrategy::SmallestFirst, &eligible).unwrap();
+		assert_eq!(values(&selected), vec![10, 20, 30]);
+	}
+
+	#[test]
+	fn strict_max_outputs_is_not_extended() {
+		let eligible = outputs(&[10, 10, 10, 10]);
+		let strategy = SelectionStrategy::SmallestFirst;
+		let (_, selected) =
+			select_coins_from(&StrategySelector, &eligible, 30, 2, false, strategy, &[]);
+		assert_eq!(values(&selected), vec![10, 10, 10]);
+		let (_, selected) =
+			select_coins_from(&StrategySelector, &eligible, 30, 2, true, strategy, &[]);
+		assert_eq!(values(&selected), vec![10, 10]);
+
+		// required outputs filling the hard limit aren't topped up, and ones
+		// going over it aren't spent at all
+		let required = outputs(&[5, 5]);
+		let (_, selected) = select_coins_from(
+			&StrategySelector,
+			&eligible,
+			30,
+			2,
+			false,
+			strategy,
+			&required,
+		);
+		assert_eq!(values(&selected), vec![5, 5, 10, 10]);
+		let (_, selected) = select_coins_from(
+			&StrategySelector,
+			&eligible,
+			30,
+			2,
+			true,
+			strategy,
+			&required,
+		);
+		assert_eq!(values(&selected), vec![5, 5]);
+		let (_, selected) = select_coins_from(
+			&StrategySelector,
+			&eligible,
+			5,
+			1,
+			true,
+			strategy,
+			&required,
+		);
+		assert!(selected.is_empty());
+	}
+
+	#[test]
+	fn fewer_inputs_preferred() {
+		let base = 1_000_000_000;
+		let select = |eligible: &[OutputData]| {
+			coins_and_fee_from(
+				eligible,
+				2 * base,
+				500,
+				false,
+				1,
+				1,
+				SelectionStrategy::SmallestFirst,
+				&StrategySelector,
+				None,
+				0,
+				&ChangePlan::Even,
+				&[],
+				0,
+				0,
+				1,
+			)
+		};
+		// the smallest outputs cover the amount and the fee by default
+		let eligible = outputs(&[base, base, base, 10 * base]);
+		let (coins, _, _, _) = select(&eligible).unwrap();
+		assert_eq!(values(&coins), vec![base, base, base]);
+
+		// while the one output covering it alone is spent when preferred
+		let (coins, total, amount, fee) =
+			single_covering_output(&eligible, 2 * base, select).unwrap();
+		assert_eq!(values(&coins), vec![10 * base]);
+		assert_eq!(total, 10 * base);
+		assert_eq!(fee, tx_fee(1, 2, 1, 0, 0, 0, None));
+		assert!(total > amount + fee);
+
+		// and there is none to swap in when no single output covers it
+		let eligible = outputs(&[base, base, base]);
+		assert!(single_covering_output(&eligible, 2 * base, select).is_none());
+	}
+
+	/// The selection as it was made before it worked over slices, cloning
+	/// every window it looked at
+	fn reference_select_from(
+		amount: u64,
+		selection_strategy: SelectionStrategy,
+		outputs: Vec<OutputData>,
+	) -> Option<Vec<OutputData>> {
+		let total = outputs.iter().fold(0, |acc, x| acc + x.value);
+		if total >= amount {
+			match selection_strategy {
+				SelectionStrategy::UseAll => Some(outputs.to_vec()),
+				SelectionStrategy::BiggestFewest => {
+					let values: Vec<u64> = outputs.iter().map(|out| out.value).collect();
+					Some(
+						fewest_indices(amount, &values)
+							.into_iter()
+							.map(|i| outputs[i].clone())
+							.collect(),
+					)
+				}
+				SelectionStrategy::SmallestFirst
+				| SelectionStrategy::LargestFirst
+				| SelectionStrategy::OldestFirst => {
+					let mut selected_amount = 0;
+					Some(
+						outputs
+							.iter()
+							.take_while(|out| {
+								let res = selected_amount < amount;
+								selected_amount += out.value;
+								res
+							})
+							.cloned()
+							.collect(),
+					)
+				}
+				SelectionStrategy::Random { seed } => {
+					let values: Vec<u64> = outputs.iter().map(|out| out.value).collect();
+					Some(
+						random_indices(amount, &values, seed)
+							.into_iter()
+							.map(|i| outputs[i].clone())
+							.collect(),
+					)
+				}
+			}
+		} else {
+			None
+		}
+	}
+
+	fn reference_select(
+		eligible: &[OutputData],
+		target: u64,
+		params: &SelectionParams,
+	) -> Option<Vec<OutputData>> {
+		let max_outputs = params.max_outputs;
+		let selection_strategy = params.selection_strategy;
+		if eligible.len() > max_outputs {
+			for window in eligible.windows(max_outputs) {
+				let windowed_eligibles = window.to_vec();
+				if let Some(outputs) =
+					reference_select_from(target, selection_strategy, windowed_eligibles)
+				{
+					return Some(outputs);
+				}
+			}
+			if params.strict_max_outputs {
+				return None;
+			}
+			reference_select_from(
+				target,
+				extension_strategy(selection_strategy),
+				eligible.to_vec(),
+			)
+		} else {
+			reference_select_from(target, selection_strategy, eligible.to_vec())
+		}
+	}
+
+	#[test]
+	fn strategy_selector_matches_window_scan() {
+		use rand::rngs::StdRng;
+		use rand::{Rng, SeedableRng};
+
+		let key = |out: &OutputData| (out.value, out.height, out.key_id.clone(), out.mmr_index);
+		let strategies = [
+			SelectionStrategy::SmallestFirst,
+			SelectionStrategy::LargestFirst,
+			SelectionStrategy::BiggestFewest,
+			SelectionStrategy::OldestFirst,
+			SelectionStrategy::UseAll,
+			SelectionStrategy::Random { seed: 7 },
+		];
+		let mut rng = StdRng::seed_from_u64(1);
+		for _ in 0..500 {
+			let len = rng.gen_range(0, 40);
+			let available: Vec<u64> = (0..len).map(|_| rng.gen_range(1, 100)).collect();
+			let total: u64 = available.iter().sum();
+			let target = rng.gen_range(0, total + 50);
+			let max_outputs = rng.gen_range(1, 12);
+			let strict_max_outputs = rng.gen();
+			let heights: Vec<u64> = (0..len).map(|_| rng.gen_range(1, 20)).collect();
+			for strategy in strategies.iter() {
+				let mut eligible: Vec<OutputData> = outputs(&available)
+					.into_iter()
+					.zip(heights.iter())
+					.map(|(out, height)| OutputData {
+						height: *height,
+						..out
+					})
+					.collect();
+				sort_for_strategy(&mut eligible, *strategy, key);
+				let params = SelectionParams {
+					max_outputs,
+					strict_max_outputs,
+					selection_strategy: *strategy,
+				};
+				let expected: Option<Vec<Identifier>> =
+					reference_select(&eligible, target, &params)
+						.map(|s| s.into_iter().map(|o| o.key_id).collect());
+				let selected: Option<Vec<Identifier>> = StrategySelector
+					.select(&eligible, target, &params)
+					.map(|s| s.into_iter().map(|o| o.key_id).collect());
+				assert_eq!(selected, expected, "{:?} of {:?}", params, available);
+
+				// token selection makes the same scan, max_outputs always soft, but
+				// may settle on an exact match instead
+				let params = SelectionParams {
+					strict_max_outputs: false,
+					..params
+				};
+				let expected: Option<Vec<Identifier>> =
+					reference_select(&eligible, target, &params)
+						.map(|s| s.into_iter().map(|o| o.key_id).collect());
+				let tokens: Vec<TokenOutputData> = eligible.into_iter().map(token_output).collect();
+				let selected = StrategySelector.select_tokens(&tokens, target, &params);
+				assert_eq!(selected.is_some(), expected.is_some());
+				if let Some(selected) = selected {
+					let total: u64 = selected.iter().map(|o| o.value).sum();
+					if total != target || target == 0 {
+						let selected: Vec<Identifier> =
+							selected.into_iter().map(|o| o.key_id).collect();
+						assert_eq!(Some(selected), expected, "{:?} of {:?}", params, available);
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn inputs_and_change_with_given_keys() {
+		type Parts = Vec<Box<build::Append<ExtKeychain, ProofBuilder<'static, ExtKeychain>>>>;
+		let select =
+			|amount: u64, keys: Vec<Identifier>| -> Result<(Parts, Vec<ChangeOutput>), Error> {
+				inputs_and_change_with_keys(
+					&outputs(&[10, 20]),
+					amount,
+					1,
+					2,
+					0,
+					&ChangePlan::Even,
+					keys,
+					true,
+				)
+			};
+		let keys: Vec<Identifier> = (1..3)
+			.map(|n| ExtKeychainPath::new(1, 100 + n, 0, 0, 0).to_identifier())
+			.collect();
+
+		// the change outputs take the given keys, in order
+		let (parts, change) = select(5, keys.clone()).unwrap();
+		assert_eq!(parts.len(), 4);
+		let change_keys: Vec<Identifier> = change.iter().map(|c| c.key_id.clone()).collect();
+		assert_eq!(change_keys, keys);
+		assert_eq!(change.iter().map(|c| c.amount).sum::<u64>(), 24);
+
+		// without change, the keys go unused
+		let (parts, change) = select(29, keys.clone()).unwrap();
+		assert_eq!(parts.len(), 2);
+		assert!(change.is_empty());
+
+		// one key is needed per change output
+		match select(5, keys[..1].to_vec()) {
+			Err(e) => match e.kind() {
+				ErrorKind::GenericError(_) => {}
+				k => panic!("Unexpected error: {}", k),
+			},
+			Ok(_) => panic!("Too few change keys should fail"),
+		}
+	}
+}
@@ -19,15 +19,23 @@ use std::io::Cursor;
 use uuid::Uuid;
 
 use crate::grin_core::consensus::valid_header_version;
+use crate::grin_core::core::transaction::TokenKey;
 use crate::grin_core::core::HeaderVersion;
+use crate::grin_core::libtx::{aggsig, tx_fee};
 use crate::grin_keychain::{BlindSum, BlindingFactor, Identifier, Keychain, SwitchCommitmentType};
-use crate::grin_util::from_hex;
-use crate::grin_util::secp::key::SecretKey;
+use crate::grin_util::secp::key::{SecretKey, ZERO_KEY};
 use crate::grin_util::secp::pedersen;
 use crate::grin_util::Mutex;
-use crate::internal::{selection, updater};
+use crate::grin_util::{from_hex, ToHex};
+use crate::internal::selection::{
+	self, CoinSelector, SelectionParams, SendParams, StrategySelector,
+};
+use crate::internal::updater;
 use crate::slate::Slate;
-use crate::types::{Context, NodeClient, StoredProofInfo, TxLogEntryType, WalletBackend};
+use crate::types::{
+	Context, FeeEstimate, LateLockArgs, NodeClient, Payout, PreviewInput, SelectionStrategy,
+	SlateTransport, StoredProofInfo, TxLogEntryType, TxPreview, WalletBackend,
+};
 use crate::util::OnionV3Address;
 use crate::{address, Error, ErrorKind};
 use ed25519_dalek::Keypair as DalekKeypair;
@@ -35,7 +43,7 @@ use ed25519_dalek::PublicKey as DalekPublicKey;
 use ed25519_dalek::SecretKey as DalekSecretKey;
 use ed25519_dalek::Signature as DalekSignature;
 
-use crate::types::TokenTxLogEntryType;
+use crate::types::{TokenTxLogEntry, TokenTxLogEntryType, TxLogEntry};
 // static for incrementing test UUIDs
 lazy_static! {
 	static ref SLATE_COUNTER: Mutex<u8> = Mutex::new(0);
@@ -97,10 +105,7 @@ pub fn estimate_send_tx<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
 	keychain_mask: Option<&SecretKey>,
 	amount: u64,
-	minimum_confirmations: u64,
-	max_outputs: usize,
-	num_change_outputs: usize,
-	selection_strategy_is_use_all: bool,
+	params: &SendParams,
 	parent_key_id: &Identifier,
 ) -> Result<
 	(
@@ -126,33 +131,349 @@ where
 	// according to plan
 	// This function is just a big helper to do all of that, in theory
 	// this process can be split up in any way
-	let (_coins, total, _amount, fee) = selection::select_coins_and_fee(
+	let (fee, total, _, _) =
+		selection::estimate_send(wallet, amount, current_height, params, parent_key_id)?;
+	Ok((total, fee))
+}
+
+/// Estimates the inputs, change and fee of a send transaction without deriving
+/// change keys, building a slate or touching the wallet batch. If the wallet
+/// can't cover the amount, the largest transaction it could build is reported
+/// instead of an error.
+pub fn estimate_fee<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	amount: u64,
+	token_type: Option<String>,
+	token_fee: u64,
+	params: &SendParams,
+	parent_key_id: &Identifier,
+) -> Result<FeeEstimate, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let current_height = wallet.w2n_client().get_chain_tip()?.0;
+	let minimum_confirmations = params.minimum_confirmations;
+	let min_coinbase_confirmations = params
+		.coinbase_min_confirmations
+		.unwrap_or(minimum_confirmations);
+	let max_outputs = params.selection.max_outputs;
+	let selection_strategy = params.selection.selection_strategy;
+	let mut estimate = FeeEstimate {
+		num_inputs: 0,
+		total: 0,
+		change: 0,
+		fee: 0,
+		token_num_inputs: 0,
+		token_total: 0,
+		token_change: 0,
+		sufficient_funds: true,
+	};
+
+	// for token sends the whole amount comes from token inputs, the base inputs
+	// only pay the fee
+	let (base_amount, base_change_outputs, token_inputs, token_outputs) = match token_type {
+		Some(t) => {
+			let coins = match selection::select_token_coins_and_fee(
+				wallet,
+				amount,
+				token_fee,
+				&t,
+				current_height,
+				minimum_confirmations,
+				max_outputs,
+				selection_strategy,
+				&StrategySelector,
+				parent_key_id,
+			) {
+				Ok((coins, _, _, _)) => coins,
+				Err(e) => match e.kind() {
+					ErrorKind::NotEnoughTokenFunds { .. } => {
+						selection::select_token_coins(
+							wallet,
+							amount + token_fee,
+							&t,
+							current_height,
+							minimum_confirmations,
+							max_outputs,
+							selection_strategy,
+							parent_key_id,
+						)
+						.1
+					}
+					_ => return Err(e),
+				},
+			};
+			estimate.token_num_inputs = coins.len();
+			estimate.token_total = coins.iter().map(|c| c.value).sum();
+			if estimate.token_total < amount + token_fee {
+				estimate.sufficient_funds = false;
+			}
+			estimate.token_change = estimate.token_total.saturating_sub(amount + token_fee);
+			let token_change_outputs = match estimate.token_change {
+				0 => 0,
+				c => selection::change_outputs_above_dust(
+					c,
+					params.change_outputs,
+					params.dust_threshold,
+				),
+			};
+			(
+				0,
+				params.fee_change_outputs,
+				coins.len(),
+				token_change_outputs + 1,
+			)
+		}
+		None => (amount, params.change_outputs, 0, 0),
+	};
+
+	// the estimate reports what the wallet could build when a limit is in the
+	// way, so only the selection itself is taken from the parameters
+	let base_params = SendParams {
+		change_outputs: base_change_outputs,
+		num_kernels: 1,
+		max_fee: None,
+		max_inputs_hard: None,
+		required_inputs: vec![],
+		send_all: false,
+		amount_includes_fee: false,
+		fixed_fee: None,
+		..params.clone()
+	};
+	match selection::select_coins_and_fee(
+		wallet,
+		base_amount,
+		current_height,
+		&base_params,
+		&StrategySelector,
+		&[],
+		std::slice::from_ref(parent_key_id),
+		token_inputs,
+		token_outputs,
+	) {
+		Ok((coins, total, amount, fee)) => {
+			estimate.num_inputs = coins.len();
+			estimate.total = total;
+			estimate.change = total - amount - fee;
+			estimate.fee = fee;
+		}
+		Err(e) => match e.kind() {
+			ErrorKind::NotEnoughFunds { .. } => {
+				// report the largest set of inputs we could spend, without change
+				let coins = selection::select_coins(
+					wallet,
+					u64::max_value(),
+					current_height,
+					minimum_confirmations,
+					min_coinbase_confirmations,
+					max_outputs,
+					params.selection.strict_max_outputs,
+					selection_strategy,
+					&[],
+					parent_key_id,
+					None,
+				)
+				.1;
+				let output_len = if base_amount == 0 {
+					0
+				} else {
+					params.recipient_outputs
+				};
+				let token_kernel_len = if token_outputs == 0 { 0 } else { 1 };
+				estimate.num_inputs = coins.len();
+				estimate.total = coins.iter().map(|c| c.value).sum();
+				estimate.fee = tx_fee(
+					coins.len(),
+					output_len,
+					1,
+					token_inputs,
+					token_outputs,
+					token_kernel_len,
+					params.fee_base,
+				);
+				estimate.sufficient_funds = false;
+			}
+			_ => return Err(e),
+		},
+	}
+
+	Ok(estimate)
+}
+
+/// Previews the exact inputs, change and fee of a send, running the same
+/// selection as `add_inputs_to_slate` without deriving change keys, building
+/// a slate or touching the wallet batch. Unlike `estimate_fee`, a wallet that
+/// can't cover the send fails with the error the send would.
+pub fn preview_tx<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	amount: u64,
+	token_type: Option<String>,
+	token_fee: u64,
+	params: &SendParams,
+	parent_key_id: &Identifier,
+) -> Result<TxPreview, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let current_height = wallet.w2n_client().get_chain_tip()?.0;
+	let minimum_confirmations = params.minimum_confirmations;
+	let token_type = match token_type {
+		Some(t) => t,
+		None => {
+			let (coins, amount, fee, change) = selection::preview_send(
+				wallet,
+				amount,
+				current_height,
+				params,
+				parent_key_id,
+				0,
+				0,
+			)?;
+			let inputs: Vec<PreviewInput> = coins.iter().map(|c| c.into()).collect();
+			let info = updater::retrieve_info(wallet, parent_key_id, minimum_confirmations)?;
+			return Ok(TxPreview {
+				amount,
+				balance_after: balance_after(info.total, &inputs, &change),
+				inputs,
+				change,
+				fee,
+				token_inputs: vec![],
+				token_change: vec![],
+				token_fee: 0,
+				token_balance_after: None,
+			});
+		}
+	};
+
+	// as in build_send_token_tx, the base inputs only pay the fee
+	let (token_coins, amount, token_fee, token_change) = selection::preview_send_token(
 		wallet,
 		amount,
+		token_fee,
+		&token_type,
 		current_height,
 		minimum_confirmations,
-		max_outputs,
-		num_change_outputs,
-		selection_strategy_is_use_all,
+		params.selection.max_outputs,
+		params.change_outputs,
+		params.selection.selection_strategy,
+		params.dust_threshold,
+		params.send_all,
 		parent_key_id,
-		0,
-		0,
 	)?;
-	Ok((total, fee))
+	let (coins, _, fee, change) = selection::preview_send(
+		wallet,
+		0,
+		current_height,
+		&params.fee_params(),
+		parent_key_id,
+		token_coins.len(),
+		token_change.len() + 1,
+	)
+	.map_err(selection::fee_funds_error)?;
+
+	let inputs: Vec<PreviewInput> = coins.iter().map(|c| c.into()).collect();
+	let token_inputs: Vec<PreviewInput> = token_coins.iter().map(|c| c.into()).collect();
+	let info = updater::retrieve_info(wallet, parent_key_id, minimum_confirmations)?;
+	let token_total = info
+		.token_infos
+		.iter()
+		.find(|t| t.token_type == token_type)
+		.map(|t| {
+			t.amount_awaiting_confirmation
+				+ t.amount_immature
+				+ t.amount_frozen
+				+ t.amount_reserved
+				+ t.amount_currently_spendable
+		})
+		.unwrap_or(0);
+	Ok(TxPreview {
+		amount,
+		balance_after: balance_after(info.total, &inputs, &change),
+		token_balance_after: Some(balance_after(token_total, &token_inputs, &token_change)),
+		inputs,
+		change,
+		fee,
+		token_inputs,
+		token_change,
+		token_fee,
+	})
+}
+
+/// Previews the inputs, change and fee of a token issuance, running the same
+/// checks and selection as `fill_tx_to_slate` without deriving keys or
+/// building a slate
+pub fn preview_issue_token_tx<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	amount: u64,
+	max_supply: Option<u64>,
+	minimum_confirmations: u64,
+	max_outputs: usize,
+	num_change_outputs: usize,
+	selection_strategy: SelectionStrategy,
+	parent_key_id: &Identifier,
+) -> Result<TxPreview, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	selection::check_issue_amount(amount, max_supply)?;
+	let current_height = wallet.w2n_client().get_chain_tip()?.0;
+	// as in build_issue_token_tx, paying the fee for the issued token output
+	let params = SendParams {
+		minimum_confirmations,
+		selection: SelectionParams {
+			max_outputs,
+			strict_max_outputs: false,
+			selection_strategy,
+		},
+		change_outputs: num_change_outputs,
+		..Default::default()
+	};
+	let (coins, _, fee, change) =
+		selection::preview_send(wallet, 0, current_height, &params, parent_key_id, 0, 1)?;
+
+	let inputs: Vec<PreviewInput> = coins.iter().map(|c| c.into()).collect();
+	let info = updater::retrieve_info(wallet, parent_key_id, minimum_confirmations)?;
+	Ok(TxPreview {
+		amount,
+		balance_after: balance_after(info.total, &inputs, &change),
+		inputs,
+		change,
+		fee,
+		token_inputs: vec![],
+		token_change: vec![],
+		token_fee: 0,
+		// a new token type only ever holds the issued amount
+		token_balance_after: Some(amount),
+	})
 }
 
+/// What a balance comes to once the given inputs are spent and change received
+fn balance_after(total: u64, inputs: &[PreviewInput], change: &[u64]) -> u64 {
+	let spent: u64 = inputs.iter().map(|i| i.value).sum();
+	total.saturating_sub(spent) + change.iter().sum::<u64>()
+}
+
+/// Builds a token issue into the slate and signs it, returning the context
+/// along with the new token type
 pub fn fill_tx_to_slate<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
 	keychain_mask: Option<&SecretKey>,
 	slate: &mut Slate,
+	max_supply: Option<u64>,
 	current_height: u64,
 	minimum_confirmations: u64,
 	max_outputs: usize,
 	num_change_outputs: usize,
-	selection_strategy_is_use_all: bool,
+	selection_strategy: SelectionStrategy,
 	parent_key_id: &Identifier,
 	use_test_rng: bool,
-) -> Result<Context, Error>
+) -> Result<(Context, TokenKey), Error>
 where
 	T: WalletBackend<'a, C, K>,
 	C: NodeClient + 'a,
@@ -160,16 +481,17 @@ where
 {
 	updater::refresh_outputs(wallet, keychain_mask, parent_key_id, false)?;
 
-	let mut context = selection::build_issue_token_tx(
+	let (mut context, token_type) = selection::build_issue_token_tx(
 		wallet,
 		&wallet.keychain(keychain_mask)?,
 		keychain_mask,
 		slate,
+		max_supply,
 		current_height,
 		minimum_confirmations,
 		max_outputs,
 		num_change_outputs,
-		selection_strategy_is_use_all,
+		selection_strategy,
 		parent_key_id.clone(),
 		use_test_rng,
 	)?;
@@ -177,22 +499,121 @@ where
 	let key_chain = wallet.keychain(keychain_mask)?;
 	slate.generate_offset(&key_chain, &mut context.sec_key, use_test_rng)?;
 
-	slate.finalize_token_parent_tx(&key_chain, &context.sec_key, true)?;
+	slate.finalize_token_parent_tx(&key_chain, &context.sec_key, true, use_test_rng)?;
+
+	Ok((context, token_type))
+}
+
+/// Builds a consolidation transaction into the slate and signs it, as we're
+/// its only party
+pub fn consolidate_to_slate<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	slate: &mut Slate,
+	current_height: u64,
+	minimum_confirmations: u64,
+	max_outputs: usize,
+	min_outputs: usize,
+	below_value: Option<u64>,
+	fee_base: Option<u64>,
+	parent_key_id: &Identifier,
+	use_test_rng: bool,
+) -> Result<Context, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	updater::refresh_outputs(wallet, keychain_mask, parent_key_id, false)?;
+
+	let mut context = selection::build_consolidation_tx(
+		wallet,
+		&wallet.keychain(keychain_mask)?,
+		keychain_mask,
+		slate,
+		current_height,
+		minimum_confirmations,
+		max_outputs,
+		min_outputs,
+		below_value,
+		fee_base,
+		parent_key_id.clone(),
+		use_test_rng,
+	)?;
+
+	let key_chain = wallet.keychain(keychain_mask)?;
+	slate.generate_offset(&key_chain, &mut context.sec_key, use_test_rng)?;
+
+	slate.finalize_token_parent_tx(&key_chain, &context.sec_key, true, use_test_rng)?;
 
 	Ok(context)
 }
 
-/// Add inputs to the slate (effectively becoming the sender)
-pub fn add_inputs_to_slate<'a, T: ?Sized, C, K>(
+/// Builds a transaction splitting our largest outputs into the slate and signs
+/// it, as we're its only party
+pub fn split_to_slate<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
 	keychain_mask: Option<&SecretKey>,
 	slate: &mut Slate,
 	current_height: u64,
 	minimum_confirmations: u64,
 	max_outputs: usize,
-	num_change_outputs: usize,
-	selection_strategy_is_use_all: bool,
+	num_outputs: usize,
+	amount: Option<u64>,
+	denominations: Option<Vec<u64>>,
+	fee_base: Option<u64>,
 	parent_key_id: &Identifier,
+	use_test_rng: bool,
+) -> Result<Context, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	updater::refresh_outputs(wallet, keychain_mask, parent_key_id, false)?;
+
+	let mut context = selection::build_split_tx(
+		wallet,
+		&wallet.keychain(keychain_mask)?,
+		keychain_mask,
+		slate,
+		current_height,
+		minimum_confirmations,
+		max_outputs,
+		num_outputs,
+		amount,
+		denominations,
+		fee_base,
+		parent_key_id.clone(),
+		use_test_rng,
+	)?;
+
+	let key_chain = wallet.keychain(keychain_mask)?;
+	slate.generate_offset(&key_chain, &mut context.sec_key, use_test_rng)?;
+
+	if slate.token_type.is_some() {
+		slate.sign_token_kernel(&key_chain, &context.token_sec_key, use_test_rng)?;
+	}
+	slate.finalize_token_parent_tx(&key_chain, &context.sec_key, true, use_test_rng)?;
+
+	Ok(context)
+}
+
+/// Add inputs to the slate (effectively becoming the sender), picked by the
+/// given coin selector. Base inputs may also be drawn from the accounts in
+/// `extra_parent_key_ids`, while the transaction stays with `parent_key_id`.
+pub fn add_inputs_to_slate<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	slate: &mut Slate,
+	current_height: u64,
+	params: &SendParams,
+	coin_selector: &dyn CoinSelector,
+	token_fee: u64,
+	verify_balance: bool,
+	parent_key_id: &Identifier,
+	extra_parent_key_ids: &[Identifier],
+	change_parent_key_id: Option<&Identifier>,
 	is_initiator: bool,
 	use_test_rng: bool,
 ) -> Result<Context, Error>
@@ -201,8 +622,11 @@ where
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
 {
-	// sender should always refresh outputs
+	// sender should always refresh outputs, of every account inputs may come from
 	updater::refresh_outputs(wallet, keychain_mask, parent_key_id, false)?;
+	for id in extra_parent_key_ids {
+		updater::refresh_outputs(wallet, keychain_mask, id, false)?;
+	}
 
 	// Sender selects outputs into a new slate and save our corresponding keys in
 	// a transaction context. The secret key in our transaction context will be
@@ -218,11 +642,12 @@ where
 			keychain_mask,
 			slate,
 			current_height,
-			minimum_confirmations,
-			max_outputs,
-			num_change_outputs,
-			selection_strategy_is_use_all,
+			params,
+			coin_selector,
+			token_fee,
+			verify_balance,
 			parent_key_id.clone(),
+			change_parent_key_id,
 			!is_initiator,
 			use_test_rng,
 		)?,
@@ -232,16 +657,20 @@ where
 			keychain_mask,
 			slate,
 			current_height,
-			minimum_confirmations,
-			max_outputs,
-			num_change_outputs,
-			selection_strategy_is_use_all,
+			params,
+			coin_selector,
+			verify_balance,
 			parent_key_id.clone(),
+			extra_parent_key_ids,
+			change_parent_key_id,
 			!is_initiator,
 			use_test_rng,
 		)?,
 	};
 
+	// keep our inputs out of other sends until this one is locked
+	selection::reserve_inputs(wallet, keychain_mask, slate, &context, current_height)?;
+
 	// Generate a kernel offset and subtract from our context's secret key. Store
 	// the offset in the slate's transaction kernel, and adds our public key
 	// information to the slate
@@ -268,14 +697,17 @@ where
 	Ok(context)
 }
 
-/// Add receiver output to the slate
-pub fn add_output_to_slate<'a, T: ?Sized, C, K>(
+/// Start a late locked transaction as the sender. Nothing is selected or
+/// locked yet: the fee is recorded in the slate and the selection parameters
+/// in the context, for inputs and change to be selected when the slate comes
+/// back to be finalized.
+pub fn create_late_lock_context<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
 	keychain_mask: Option<&SecretKey>,
 	slate: &mut Slate,
-	current_height: u64,
+	fee: u64,
+	late_lock_args: LateLockArgs,
 	parent_key_id: &Identifier,
-	is_initiator: bool,
 	use_test_rng: bool,
 ) -> Result<Context, Error>
 where
@@ -283,17 +715,178 @@ where
 	C: NodeClient + 'a,
 	K: Keychain + 'a,
 {
+	if !slate.is_compact() {
+		return Err(ErrorKind::LateLock("requires a compact slate".to_owned()).into());
+	}
 	let keychain = wallet.keychain(keychain_mask)?;
-	// create an output using the amount in the slate
-	let (_, mut context, tx, token_tx) = selection::build_recipient_output(
+	slate.fee = fee;
+
+	// with no inputs or change yet, our excess starts out as a random key that
+	// the offset makes up for once they're selected
+	let sec_key = match use_test_rng {
+		false => aggsig::create_secnonce(keychain.secp())?,
+		true => SecretKey::from_slice(keychain.secp(), &[2; 32])?,
+	};
+	let mut context = Context::new(
+		keychain.secp(),
+		sec_key,
+		ZERO_KEY,
+		parent_key_id,
+		use_test_rng,
+		false,
+	);
+	context.fee = fee;
+	context.amount = slate.amount;
+	context.late_lock_args = Some(late_lock_args);
+	if let Some(ref p) = slate.payment_proof {
+		context.set_payment_proof(p, selection::PAYMENT_PROOF_DERIVATION_INDEX);
+	}
+
+	// unlike add_inputs_to_slate, the initial key is left as the key we started
+	// with, before our part of the offset was taken off it
+	slate.fill_round_1(
+		&keychain,
+		&mut context.sec_key,
+		&context.token_sec_key,
+		&context.sec_nonce,
+		use_test_rng,
+	)?;
+
+	Ok(context)
+}
+
+/// Select the inputs and change of a late locked transaction once its slate
+/// is back to be finalized, around the fee already agreed with the recipient.
+/// The change outputs take over from the key the transaction was started with
+/// in the offset; the inputs are taken off the offset as for any compact slate.
+pub fn select_late_lock_inputs<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	slate: &mut Slate,
+	context: &mut Context,
+	late_lock_args: &LateLockArgs,
+) -> Result<(), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let current_height = wallet.w2n_client().get_chain_tip()?.0;
+	updater::refresh_outputs(wallet, keychain_mask, &context.parent_key_id, false)?;
+
+	// select on a scratch slate, only the inputs and change are kept
+	let keychain = wallet.keychain(keychain_mask)?;
+	let mut scratch = Slate::blank(2, false);
+	scratch.amount = context.amount;
+	let params = SendParams {
+		minimum_confirmations: late_lock_args.minimum_confirmations,
+		coinbase_min_confirmations: late_lock_args.coinbase_min_confirmations,
+		selection: SelectionParams {
+			max_outputs: late_lock_args.max_outputs,
+			strict_max_outputs: late_lock_args.strict_max_outputs,
+			selection_strategy: late_lock_args.selection_strategy,
+		},
+		recipient_outputs: late_lock_args.recipient_outputs,
+		change_outputs: late_lock_args.change_outputs,
+		max_inputs_hard: late_lock_args.max_inputs_hard,
+		dust_threshold: late_lock_args.dust_threshold,
+		change_plan: late_lock_args.change_plan.clone(),
+		fixed_fee: Some(context.fee),
+		..Default::default()
+	};
+	let selected = selection::build_send_tx(
 		wallet,
+		&keychain,
 		keychain_mask,
-		slate,
+		&mut scratch,
 		current_height,
-		parent_key_id.clone(),
-		is_initiator,
-		use_test_rng,
+		&params,
+		&StrategySelector,
+		false,
+		context.parent_key_id.clone(),
+		&[],
+		late_lock_args.change_parent_key_id.as_ref(),
+		false,
+		false,
+	)?;
+	context.input_ids = selected.input_ids;
+	context.input_commits = selected.input_commits;
+	context.output_ids = selected.output_ids;
+	context.changeless = selected.changeless;
+	context.selection_strategy = selected.selection_strategy;
+	context.change_parent_key_id = selected.change_parent_key_id;
+
+	let sum = context.get_outputs().iter().try_fold(
+		BlindSum::new()
+			.add_blinding_factor(slate.offset.clone())
+			.sub_blinding_factor(BlindingFactor::from_secret_key(
+				context.initial_sec_key.clone(),
+			)),
+		|acc, (id, _, value)| -> Result<BlindSum, Error> {
+			let k = keychain.derive_key(*value, id, SwitchCommitmentType::Regular)?;
+			Ok(acc.add_blinding_factor(BlindingFactor::from_secret_key(k)))
+		},
 	)?;
+	let new_offset = keychain.blind_sum(&sum)?;
+
+	slate.offset = new_offset.clone();
+	slate.tx_or_err_mut()?.offset = new_offset;
+
+	Ok(())
+}
+
+/// Add receiver output to the slate
+pub fn add_output_to_slate<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	slate: &mut Slate,
+	current_height: u64,
+	parent_key_id: &Identifier,
+	payouts: &[Payout],
+	is_initiator: bool,
+	transport: Option<SlateTransport>,
+	use_test_rng: bool,
+) -> Result<Context, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let keychain = wallet.keychain(keychain_mask)?;
+	let (mut context, tx, token_tx) = if payouts.is_empty() {
+		// create an output using the amount in the slate
+		let (_, context, tx, token_tx) = selection::build_recipient_output(
+			wallet,
+			keychain_mask,
+			slate,
+			current_height,
+			parent_key_id.clone(),
+			is_initiator,
+			None,
+			transport,
+			use_test_rng,
+		)?;
+		(context, tx, token_tx)
+	} else {
+		// or one output for each payout of a batch, all in the receiving account.
+		// The payouts are stored with the tx log entry once the excess is known.
+		let recipients = payouts
+			.iter()
+			.map(|p| (p.amount, parent_key_id.clone()))
+			.collect();
+		let (context, mut tx) = selection::build_recipient_outputs(
+			wallet,
+			keychain_mask,
+			slate,
+			current_height,
+			parent_key_id.clone(),
+			recipients,
+			transport,
+			use_test_rng,
+		)?;
+		tx.payouts = Some(payouts.to_vec());
+		(context, Some(tx), None)
+	};
 
 	// fill public keys
 	slate.fill_round_1(
@@ -365,7 +958,7 @@ where
 
 	let key_chain = wallet.keychain(keychain_mask)?;
 	if slate.token_type.is_some() {
-		slate.finalize_token_parent_tx(&key_chain, &context.sec_key, false)?;
+		slate.finalize_token_parent_tx(&key_chain, &context.sec_key, false, false)?;
 	}
 
 	// Final transaction can be built by anyone at this stage
@@ -400,19 +993,12 @@ where
 			TxLogEntryType::TxSent | TxLogEntryType::TxReceived | TxLogEntryType::TxReverted => {}
 			_ => return Err(ErrorKind::TransactionNotCancellable(tx_id_string).into()),
 		}
-		if tx.confirmed {
+		if tx.confirmed
+			|| kernel_on_chain(wallet, tx.kernel_excess, false, tx.kernel_lookup_min_height)?
+		{
 			return Err(ErrorKind::TransactionNotCancellable(tx_id_string).into());
 		}
-		// get outputs associated with tx
-		let res = updater::retrieve_outputs(
-			wallet,
-			keychain_mask,
-			false,
-			Some(tx.id),
-			Some(&parent_key_id),
-		)?;
-		let outputs = res.iter().map(|m| m.output.clone()).collect();
-		updater::cancel_tx_and_outputs(wallet, keychain_mask, tx, outputs, parent_key_id)?;
+		rollback_tx(wallet, keychain_mask, parent_key_id, tx, false)?;
 		return Ok(());
 	}
 
@@ -426,43 +1012,178 @@ where
 			| TokenTxLogEntryType::TokenTxReverted => {}
 			_ => return Err(ErrorKind::TransactionNotCancellable(tx_id_string).into()),
 		}
-		if tx.confirmed {
+		let min_height = tx.kernel_lookup_min_height;
+		if tx.confirmed
+			|| kernel_on_chain(wallet, tx.kernel_excess, false, min_height)?
+			|| kernel_on_chain(wallet, tx.token_kernel_excess, true, min_height)?
+		{
 			return Err(ErrorKind::TransactionNotCancellable(tx_id_string).into());
 		}
-		// get outputs associated with tx
-		let res = updater::retrieve_outputs(
-			wallet,
-			keychain_mask,
-			false,
-			Some(tx.id),
-			Some(&parent_key_id),
-		)?;
-		let outputs = res.iter().map(|m| m.output.clone()).collect();
-
-		// get token outputs associated with tx
-		let token_res = updater::retrieve_token_outputs(
-			wallet,
-			keychain_mask,
-			false,
-			Some(tx.id),
-			Some(&parent_key_id),
-		)?;
-		let token_outputs = token_res.iter().map(|m| m.output.clone()).collect();
-
-		updater::cancel_token_tx_and_outputs(
-			wallet,
-			keychain_mask,
-			tx,
-			outputs,
-			token_outputs,
-			parent_key_id,
-		)?;
+		rollback_token_tx(wallet, keychain_mask, parent_key_id, tx, false)?;
 		return Ok(());
 	}
 
+	// a transaction that was never locked only holds a reservation
+	if let Some(tx_slate_id) = tx_slate_id {
+		if wallet.get_reservation(&tx_slate_id)?.is_some() {
+			let mut batch = wallet.batch(keychain_mask)?;
+			batch.delete_reservation(&tx_slate_id)?;
+			batch.commit()?;
+			return Ok(());
+		}
+	}
+
 	return Err(ErrorKind::TransactionDoesntExist(tx_id_string).into());
 }
 
+/// Rollback every outstanding transaction whose TTL has expired at the given
+/// height, marking it as TTL cancelled. Transactions whose kernel already made
+/// it on chain are left alone, as are entries cancelled by an earlier run.
+/// Input reservations expired by then are released as well.
+pub fn cancel_expired_txs<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	parent_key_id: &Identifier,
+	height: u64,
+) -> Result<(), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let expired = |ttl_cutoff_height: Option<u64>| match ttl_cutoff_height {
+		Some(h) => height >= h,
+		None => false,
+	};
+
+	let txs = updater::retrieve_txs(wallet, None, None, Some(&parent_key_id), true)?;
+	for tx in txs {
+		if !expired(tx.ttl_cutoff_height)
+			|| kernel_on_chain(wallet, tx.kernel_excess, false, tx.kernel_lookup_min_height)?
+		{
+			continue;
+		}
+		rollback_tx(wallet, keychain_mask, parent_key_id, tx, true)?;
+	}
+
+	let token_txs = updater::retrieve_token_txs(wallet, None, None, Some(&parent_key_id), true)?;
+	for tx in token_txs {
+		let min_height = tx.kernel_lookup_min_height;
+		// token issues aren't cancellable
+		if tx.tx_type == TokenTxLogEntryType::TokenIssue
+			|| !expired(tx.ttl_cutoff_height)
+			|| kernel_on_chain(wallet, tx.kernel_excess, false, min_height)?
+			|| kernel_on_chain(wallet, tx.token_kernel_excess, true, min_height)?
+		{
+			continue;
+		}
+		rollback_token_tx(wallet, keychain_mask, parent_key_id, tx, true)?;
+	}
+
+	selection::release_expired_reservations(wallet, keychain_mask, height)?;
+	Ok(())
+}
+
+/// Unlock the inputs of a tx log entry, remove its unconfirmed outputs and
+/// mark it cancelled
+fn rollback_tx<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	parent_key_id: &Identifier,
+	tx: TxLogEntry,
+	ttl_expired: bool,
+) -> Result<(), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	// get outputs associated with tx
+	let res = updater::retrieve_outputs(
+		wallet,
+		keychain_mask,
+		false,
+		Some(tx.id),
+		Some(&parent_key_id),
+	)?;
+	let outputs = res.iter().map(|m| m.output.clone()).collect();
+	updater::cancel_tx_and_outputs(
+		wallet,
+		keychain_mask,
+		tx,
+		outputs,
+		parent_key_id,
+		ttl_expired,
+	)
+}
+
+/// Token counterpart of `rollback_tx`, where both base and token inputs may
+/// be locked
+fn rollback_token_tx<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	parent_key_id: &Identifier,
+	tx: TokenTxLogEntry,
+	ttl_expired: bool,
+) -> Result<(), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	// get outputs associated with tx
+	let res = updater::retrieve_outputs(
+		wallet,
+		keychain_mask,
+		false,
+		Some(tx.id),
+		Some(&parent_key_id),
+	)?;
+	let outputs = res.iter().map(|m| m.output.clone()).collect();
+
+	// get token outputs associated with tx
+	let token_res = updater::retrieve_token_outputs(
+		wallet,
+		keychain_mask,
+		false,
+		Some(tx.id),
+		Some(&parent_key_id),
+	)?;
+	let token_outputs = token_res.iter().map(|m| m.output.clone()).collect();
+
+	updater::cancel_token_tx_and_outputs(
+		wallet,
+		keychain_mask,
+		tx,
+		outputs,
+		token_outputs,
+		parent_key_id,
+		ttl_expired,
+	)
+}
+
+/// Whether a kernel recorded in a tx log entry has already made it on chain,
+/// in which case the transaction can no longer be cancelled. Token kernels are
+/// looked up among the token kernels.
+fn kernel_on_chain<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	excess: Option<pedersen::Commitment>,
+	is_token: bool,
+	min_height: Option<u64>,
+) -> Result<bool, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let client = wallet.w2n_client();
+	match excess {
+		Some(e) if is_token => Ok(client.get_token_kernel(&e, min_height, None)?.is_some()),
+		Some(e) => Ok(client.get_kernel(&e, min_height, None)?.is_some()),
+		None => Ok(false),
+	}
+}
+
 /// Update the stored transaction (this update needs to happen when the TX is finalised)
 pub fn update_stored_tx<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
@@ -503,31 +1224,9 @@ where
 			tx.token_kernel_excess = Some(slate.calc_excess(keychain.secp())?);
 		}
 
-		if let Some(ref p) = slate.clone().payment_proof {
-			let derivation_index = match context.payment_proof_derivation_index {
-				Some(i) => i,
-				None => 0,
-			};
-			let keychain = wallet.keychain(keychain_mask)?;
-			let parent_key_id = wallet.parent_key_id();
-			let excess = slate.calc_excess(keychain.secp())?;
-			let sender_key =
-				address::address_from_derivation_path(&keychain, &parent_key_id, derivation_index)?;
-			let sender_address = OnionV3Address::from_private(&sender_key.0)?;
-			let sig = create_payment_proof_signature(
-				slate.token_type.clone(),
-				slate.amount,
-				&excess,
-				p.sender_address,
-				sender_key,
-			)?;
-			tx.payment_proof = Some(StoredProofInfo {
-				receiver_address: p.receiver_address,
-				receiver_signature: p.receiver_signature,
-				sender_address_path: derivation_index,
-				sender_address: sender_address.to_ed25519()?,
-				sender_signature: Some(sig),
-			})
+		if let Some(p) = complete_payment_proof(wallet, keychain_mask, context, slate, is_invoiced)?
+		{
+			tx.payment_proof = Some(p);
 		}
 
 		wallet.store_tx(&format!("{}", tx.tx_slate_id.unwrap()), slate.tx_or_err()?)?;
@@ -561,31 +1260,9 @@ where
 			tx.kernel_excess = Some(slate.calc_excess(keychain.secp())?);
 		}
 
-		if let Some(ref p) = slate.clone().payment_proof {
-			let derivation_index = match context.payment_proof_derivation_index {
-				Some(i) => i,
-				None => 0,
-			};
-			let keychain = wallet.keychain(keychain_mask)?;
-			let parent_key_id = wallet.parent_key_id();
-			let excess = slate.calc_excess(keychain.secp())?;
-			let sender_key =
-				address::address_from_derivation_path(&keychain, &parent_key_id, derivation_index)?;
-			let sender_address = OnionV3Address::from_private(&sender_key.0)?;
-			let sig = create_payment_proof_signature(
-				slate.token_type.clone(),
-				slate.amount,
-				&excess,
-				p.sender_address,
-				sender_key,
-			)?;
-			tx.payment_proof = Some(StoredProofInfo {
-				receiver_address: p.receiver_address,
-				receiver_signature: p.receiver_signature,
-				sender_address_path: derivation_index,
-				sender_address: sender_address.to_ed25519()?,
-				sender_signature: Some(sig),
-			})
+		if let Some(p) = complete_payment_proof(wallet, keychain_mask, context, slate, is_invoiced)?
+		{
+			tx.payment_proof = Some(p);
 		}
 
 		wallet.store_tx(&format!("{}", tx.tx_slate_id.unwrap()), slate.tx_or_err()?)?;
@@ -597,6 +1274,93 @@ where
 	}
 }
 
+/// Fill in our signature of the slate's payment proof, if any, now that the
+/// kernel excess is final. On an invoice the roles are swapped: the payer
+/// already signed as the sender, and we sign as the recipient.
+fn complete_payment_proof<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	context: &Context,
+	slate: &Slate,
+	is_invoiced: bool,
+) -> Result<Option<StoredProofInfo>, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let p = match slate.payment_proof {
+		Some(ref p) => p,
+		None => return Ok(None),
+	};
+	let derivation_index = match context.payment_proof_derivation_index {
+		Some(i) => i,
+		None => 0,
+	};
+	let keychain = wallet.keychain(keychain_mask)?;
+	let parent_key_id = wallet.parent_key_id();
+	let excess = slate.calc_excess(keychain.secp())?;
+	let our_key =
+		address::address_from_derivation_path(&keychain, &parent_key_id, derivation_index)?;
+	let our_address = OnionV3Address::from_private(&our_key.0)?.to_ed25519()?;
+
+	if !is_invoiced {
+		let sig = create_payment_proof_signature(
+			slate.token_type.clone(),
+			slate.amount,
+			&excess,
+			p.sender_address,
+			our_key,
+		)?;
+		return Ok(Some(StoredProofInfo {
+			receiver_address: p.receiver_address,
+			receiver_signature: p.receiver_signature,
+			sender_address_path: derivation_index,
+			sender_address: our_address,
+			sender_signature: Some(sig),
+		}));
+	}
+
+	if p.receiver_address != our_address {
+		return Err(ErrorKind::PaymentProof(
+			"Recipient address of invoice payment proof doesn't belong to this wallet".to_owned(),
+		)
+		.into());
+	}
+	let sender_sig = match p.sender_signature {
+		Some(s) => s,
+		None => {
+			return Err(ErrorKind::PaymentProof(
+				"Invoice payment proof is missing the sender signature".to_owned(),
+			)
+			.into());
+		}
+	};
+	let msg = payment_proof_message(
+		slate.token_type.clone(),
+		slate.amount,
+		&excess,
+		p.sender_address,
+	)?;
+	if p.sender_address.verify(&msg, &sender_sig).is_err() {
+		return Err(ErrorKind::PaymentProof("Invalid sender signature".to_owned()).into());
+	}
+	let sig = create_payment_proof_signature(
+		slate.token_type.clone(),
+		slate.amount,
+		&excess,
+		p.sender_address,
+		our_key,
+	)?;
+	Ok(Some(StoredProofInfo {
+		receiver_address: our_address,
+		receiver_signature: Some(sig),
+		sender_address_path: derivation_index,
+		sender_address: p.sender_address,
+		sender_signature: Some(sender_sig),
+	}))
+}
+
 /// Update the transaction's offset by subtracting the inputs
 /// stored in the context
 pub fn sub_inputs_from_offset<'a, T: ?Sized, C, K>(
@@ -645,10 +1409,16 @@ pub fn payment_proof_message(
 	sender_address: DalekPublicKey,
 ) -> Result<Vec<u8>, Error> {
 	let mut msg = Vec::new();
-	if token_type.is_some() {
-		let token_type = token_type.unwrap();
-		let mut token_type_vec = from_hex(token_type.as_str()).unwrap();
-		msg.append(&mut token_type_vec);
+	// a token proof commits to the token key ahead of the token kernel
+	// excess, so it can't pass for a base coin send of the same amount
+	if let Some(t) = token_type {
+		let mut token_key = match from_hex(t.as_str()) {
+			Ok(k) if k.len() == 32 => k,
+			_ => {
+				return Err(ErrorKind::PaymentProof(format!("Invalid token type {}", t)).into());
+			}
+		};
+		msg.append(&mut token_key);
 	}
 	msg.write_u64::<BigEndian>(amount)?;
 	msg.append(&mut kernel_commitment.0.to_vec());
@@ -658,8 +1428,19 @@ pub fn payment_proof_message(
 
 pub fn _decode_payment_proof_message(
 	msg: &[u8],
-) -> Result<(u64, pedersen::Commitment, DalekPublicKey), Error> {
+) -> Result<(Option<String>, u64, pedersen::Commitment, DalekPublicKey), Error> {
 	let mut rdr = Cursor::new(msg);
+	// base proofs are exactly amount, excess and address long
+	let token_type = match msg.len() > 8 + 33 + 32 {
+		true => {
+			let mut token_key = [0u8; 32];
+			for i in 0..32 {
+				token_key[i] = rdr.read_u8()?;
+			}
+			Some(token_key.to_hex())
+		}
+		false => None,
+	};
 	let amount = rdr.read_u64::<BigEndian>()?;
 	let mut commit_bytes = [0u8; 33];
 	for i in 0..33 {
@@ -671,6 +1452,7 @@ pub fn _decode_payment_proof_message(
 	}
 
 	Ok((
+		token_type,
 		amount,
 		pedersen::Commitment::from_vec(commit_bytes.to_vec()),
 		DalekPublicKey::from_bytes(&sender_address_bytes).unwrap(),
@@ -876,16 +1658,45 @@ mod test {
 		};
 
 		let amount = 1_234_567_890_u64;
-		let msg = payment_proof_message(amount, &kernel_excess, address).unwrap();
+		let msg = payment_proof_message(None, amount, &kernel_excess, address).unwrap();
 		println!("payment proof message is (len {}): {:?}", msg.len(), msg);
 
 		let decoded = _decode_payment_proof_message(&msg).unwrap();
-		assert_eq!(decoded.0, amount);
-		assert_eq!(decoded.1, kernel_excess);
-		assert_eq!(decoded.2, address);
+		assert_eq!(decoded.0, None);
+		assert_eq!(decoded.1, amount);
+		assert_eq!(decoded.2, kernel_excess);
+		assert_eq!(decoded.3, address);
 
-		let sig = create_payment_proof_signature(amount, &kernel_excess, address, sec_key).unwrap();
+		let sig =
+			create_payment_proof_signature(None, amount, &kernel_excess, address, sec_key.clone())
+				.unwrap();
 
 		assert!(address.verify(&msg, &sig).is_ok());
+
+		// a token proof commits to the token type, and doesn't verify as a
+		// base coin proof of the same amount
+		let token_type =
+			Some("b2a2b12ba3d4c5e9f8d1e3b6c2a7f0e4d8c1b5a9e3f7d2c6b0a4e8f1d5c9b3a7".to_owned());
+		let token_msg =
+			payment_proof_message(token_type.clone(), amount, &kernel_excess, address).unwrap();
+		let decoded = _decode_payment_proof_message(&token_msg).unwrap();
+		assert_eq!(decoded.0, token_type);
+		assert_eq!(decoded.1, amount);
+
+		let token_sig = create_payment_proof_signature(
+			token_type.clone(),
+			amount,
+			&kernel_excess,
+			address,
+			sec_key,
+		)
+		.unwrap();
+		assert!(address.verify(&token_msg, &token_sig).is_ok());
+		assert!(address.verify(&msg, &token_sig).is_err());
+
+		assert!(
+			payment_proof_message(Some("00ff".to_owned()), amount, &kernel_excess, address)
+				.is_err()
+		);
 	}
 }
@@ -29,11 +29,12 @@ use crate::grin_util as util;
 use crate::grin_util::secp::key::SecretKey;
 use crate::grin_util::secp::pedersen;
 use crate::grin_util::static_secp_instance;
-use crate::internal::keys;
+use crate::internal::{keys, selection};
 use crate::types::{
-	NodeClient, OutputData, OutputStatus, TxLogEntry, TxLogEntryType, WalletBackend, WalletInfo,
+	ConfirmedBlock, NodeClient, OutputData, OutputStatus, TxLogEntry, TxLogEntryType,
+	WalletBackend, WalletInfo,
 };
-use crate::{BlockFees, CbData, OutputCommitMapping};
+use crate::{BlockFees, CbData, OutputCommitMapping, SlateActivity};
 
 use crate::types::{TokenOutputData, TokenTxLogEntry, TokenTxLogEntryType, WalletTokenInfo};
 use crate::TokenOutputCommitMapping;
@@ -234,6 +235,81 @@ where
 	Ok(txs)
 }
 
+/// Retrieve what each slate moved of both assets, or a particular slate,
+/// merging its entries in the tx log and the token tx log. See `SlateActivity`.
+pub fn retrieve_slate_activity<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	tx_slate_id: Option<Uuid>,
+	parent_key_id: Option<&Identifier>,
+) -> Result<Vec<SlateActivity>, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let txs = retrieve_txs(wallet, None, tx_slate_id, parent_key_id, false)?;
+	let token_txs = retrieve_token_txs(wallet, None, tx_slate_id, parent_key_id, false)?;
+
+	let mut activity: Vec<SlateActivity> = vec![];
+	let mut by_slate: HashMap<Uuid, usize> = HashMap::new();
+
+	for tx in txs {
+		let r = slate_record(&mut activity, &mut by_slate, tx.tx_slate_id, tx.creation_ts);
+		r.tx_log_ids.push(tx.id);
+		r.amount_credited += tx.amount_credited;
+		r.amount_debited += tx.amount_debited;
+		r.fee += tx.fee.unwrap_or(0);
+		r.creation_ts = r.creation_ts.min(tx.creation_ts);
+		r.confirmed &= tx.confirmed;
+	}
+	for tx in token_txs {
+		let r = slate_record(&mut activity, &mut by_slate, tx.tx_slate_id, tx.creation_ts);
+		r.token_tx_log_ids.push(tx.id);
+		r.token_type = Some(tx.token_type);
+		r.amount_credited += tx.amount_credited;
+		r.amount_debited += tx.amount_debited;
+		r.token_amount_credited += tx.token_amount_credited;
+		r.token_amount_debited += tx.token_amount_debited;
+		r.fee += tx.fee.unwrap_or(0);
+		r.token_fee += tx.token_fee.unwrap_or(0);
+		r.creation_ts = r.creation_ts.min(tx.creation_ts);
+		r.confirmed &= tx.confirmed;
+	}
+	activity.sort_by_key(|r| r.creation_ts);
+	Ok(activity)
+}
+
+/// The record of the slate, or a new one when the slate has none yet or the
+/// entry wasn't built from a slate
+fn slate_record<'b>(
+	activity: &'b mut Vec<SlateActivity>,
+	by_slate: &mut HashMap<Uuid, usize>,
+	tx_slate_id: Option<Uuid>,
+	creation_ts: chrono::DateTime<chrono::Utc>,
+) -> &'b mut SlateActivity {
+	if let Some(&i) = tx_slate_id.as_ref().and_then(|id| by_slate.get(id)) {
+		return &mut activity[i];
+	}
+	if let Some(id) = tx_slate_id {
+		by_slate.insert(id, activity.len());
+	}
+	activity.push(SlateActivity {
+		tx_slate_id,
+		tx_log_ids: vec![],
+		token_tx_log_ids: vec![],
+		token_type: None,
+		amount_credited: 0,
+		amount_debited: 0,
+		token_amount_credited: 0,
+		token_amount_debited: 0,
+		fee: 0,
+		token_fee: 0,
+		creation_ts,
+		confirmed: true,
+	});
+	activity.last_mut().unwrap()
+}
+
 /// Refreshes the outputs in a wallet with the latest information
 /// from a node
 pub fn refresh_outputs<'a, T: ?Sized, C, K>(
@@ -250,6 +326,183 @@ where
 	let height = wallet.w2n_client().get_chain_tip()?.0;
 	refresh_output_state(wallet, keychain_mask, height, parent_key_id, update_all)?;
 	refresh_token_output_state(wallet, keychain_mask, height, parent_key_id, update_all)?;
+	revert_orphaned_blocks(wallet, keychain_mask, parent_key_id)?;
+	record_confirmed_blocks(wallet, keychain_mask, parent_key_id, height)?;
+	Ok(())
+}
+
+/// Puts back to pending what the account saw confirmed in blocks a reorg has
+/// since orphaned: outputs still recorded at their height are unconfirmed
+/// again, inputs spent in them locked again and the transactions involved
+/// unconfirmed, for the kernel lookups to confirm them again on the new chain.
+/// Outputs the refresh found at a new height were already moved. Returns the
+/// number of orphaned blocks.
+pub fn revert_orphaned_blocks<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	parent_key_id: &Identifier,
+) -> Result<usize, Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let blocks: Vec<ConfirmedBlock> = wallet
+		.confirmed_block_iter()
+		.filter(|b| b.parent_key_id == *parent_key_id)
+		.collect();
+	let client = wallet.w2n_client().clone();
+	let mut orphaned = vec![];
+	for block in blocks {
+		if client.get_header_hash(block.height)?.as_ref() != Some(&block.hash) {
+			orphaned.push(block);
+		}
+	}
+	if orphaned.is_empty() {
+		return Ok(0);
+	}
+	warn!(
+		"Blocks at heights {:?} were orphaned by a reorg, reverting what they confirmed",
+		orphaned.iter().map(|b| b.height).collect::<Vec<_>>()
+	);
+
+	let heights: HashSet<u64> = orphaned.iter().map(|b| b.height).collect();
+	let mut tx_ids: HashSet<u32> = orphaned
+		.iter()
+		.flat_map(|b| b.tx_log_ids.iter().cloned())
+		.collect();
+
+	let outputs: Vec<OutputData> = wallet
+		.iter()
+		.filter(|o| {
+			o.root_key_id == *parent_key_id
+				&& o.status == OutputStatus::Unspent
+				&& heights.contains(&o.height)
+		})
+		.collect();
+	let token_outputs: Vec<TokenOutputData> = wallet
+		.token_iter()
+		.filter(|o| {
+			o.root_key_id == *parent_key_id
+				&& o.status == OutputStatus::Unspent
+				&& heights.contains(&o.height)
+		})
+		.collect();
+	// tx log ids are shared by the tx log and the token tx log
+	tx_ids.extend(outputs.iter().filter_map(|o| o.tx_log_entry));
+	tx_ids.extend(token_outputs.iter().filter_map(|o| o.tx_log_entry));
+
+	// inputs point to the transaction spending them
+	let spent_by = |status: &OutputStatus, tx_log_entry: &Option<u32>| {
+		*status == OutputStatus::Spent && tx_log_entry.map_or(false, |i| tx_ids.contains(&i))
+	};
+	let inputs: Vec<OutputData> = wallet
+		.iter()
+		.filter(|o| o.root_key_id == *parent_key_id && spent_by(&o.status, &o.tx_log_entry))
+		.collect();
+	let token_inputs: Vec<TokenOutputData> = wallet
+		.token_iter()
+		.filter(|o| o.root_key_id == *parent_key_id && spent_by(&o.status, &o.tx_log_entry))
+		.collect();
+	let txs: Vec<TxLogEntry> = wallet
+		.tx_log_iter()
+		.filter(|t| t.parent_key_id == *parent_key_id && t.confirmed && tx_ids.contains(&t.id))
+		.collect();
+	let token_txs: Vec<TokenTxLogEntry> = wallet
+		.token_tx_log_iter()
+		.filter(|t| t.parent_key_id == *parent_key_id && t.confirmed && tx_ids.contains(&t.id))
+		.collect();
+
+	let mut batch = wallet.batch(keychain_mask)?;
+	for mut output in outputs {
+		output.status = OutputStatus::Unconfirmed;
+		batch.save(output)?;
+	}
+	for mut output in token_outputs {
+		output.status = OutputStatus::Unconfirmed;
+		batch.save_token(output)?;
+	}
+	for mut output in inputs {
+		output.status = OutputStatus::Locked;
+		batch.save(output)?;
+	}
+	for mut output in token_inputs {
+		output.status = OutputStatus::Locked;
+		batch.save_token(output)?;
+	}
+	for mut tx in txs {
+		tx.confirmed = false;
+		tx.confirmation_ts = None;
+		batch.save_tx_log_entry(tx, parent_key_id)?;
+	}
+	for mut tx in token_txs {
+		tx.confirmed = false;
+		tx.confirmation_ts = None;
+		batch.save_token_tx_log_entry(tx, parent_key_id)?;
+	}
+	for block in orphaned.iter() {
+		batch.delete_confirmed_block(parent_key_id, block.height)?;
+	}
+	batch.commit()?;
+	Ok(orphaned.len())
+}
+
+/// Records the hash of the blocks the unspent outputs of the account were
+/// confirmed in, for a later refresh to notice a reorg orphaning them. Blocks
+/// past the cut-through horizon can't be reorged away and are forgotten.
+fn record_confirmed_blocks<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	parent_key_id: &Identifier,
+	height: u64,
+) -> Result<(), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let horizon = height.saturating_sub(global::cut_through_horizon() as u64);
+	let recorded: HashSet<u64> = wallet
+		.confirmed_block_iter()
+		.filter(|b| b.parent_key_id == *parent_key_id)
+		.map(|b| b.height)
+		.collect();
+	let mut heights: HashSet<u64> = wallet
+		.iter()
+		.filter(|o| o.root_key_id == *parent_key_id && o.status == OutputStatus::Unspent)
+		.map(|o| o.height)
+		.collect();
+	heights.extend(
+		wallet
+			.token_iter()
+			.filter(|o| o.root_key_id == *parent_key_id && o.status == OutputStatus::Unspent)
+			.map(|o| o.height),
+	);
+
+	let client = wallet.w2n_client().clone();
+	let mut blocks = vec![];
+	for h in heights {
+		if h == 0 || h <= horizon || recorded.contains(&h) {
+			continue;
+		}
+		if let Some(hash) = client.get_header_hash(h)? {
+			blocks.push(ConfirmedBlock {
+				parent_key_id: parent_key_id.clone(),
+				height: h,
+				hash,
+				tx_log_ids: vec![],
+			});
+		}
+	}
+
+	let mut batch = wallet.batch(keychain_mask)?;
+	for block in blocks {
+		batch.save_confirmed_block(&block)?;
+	}
+	for h in recorded.into_iter().filter(|h| *h <= horizon) {
+		batch.delete_confirmed_block(parent_key_id, h)?;
+	}
+	batch.commit()?;
 	Ok(())
 }
 
@@ -367,13 +620,15 @@ where
 	Ok(wallet_outputs)
 }
 
-/// Cancel transaction and associated outputs
+/// Cancel transaction and associated outputs. `ttl_expired` marks the entry as
+/// cancelled because its TTL expired rather than by the user
 pub fn cancel_tx_and_outputs<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
 	keychain_mask: Option<&SecretKey>,
 	mut tx: TxLogEntry,
 	outputs: Vec<OutputData>,
 	parent_key_id: &Identifier,
+	ttl_expired: bool,
 ) -> Result<(), Error>
 where
 	T: WalletBackend<'a, C, K>,
@@ -392,11 +647,15 @@ where
 			batch.save(o)?;
 		}
 	}
-	match tx.tx_type {
-		TxLogEntryType::TxSent => tx.tx_type = TxLogEntryType::TxSentCancelled,
-		TxLogEntryType::TxReceived | TxLogEntryType::TxReverted => {
+	match (tx.tx_type.clone(), ttl_expired) {
+		(TxLogEntryType::TxSent, false) => tx.tx_type = TxLogEntryType::TxSentCancelled,
+		(TxLogEntryType::TxSent, true) => tx.tx_type = TxLogEntryType::TxSentTtlCancelled,
+		(TxLogEntryType::TxReceived, false) | (TxLogEntryType::TxReverted, false) => {
 			tx.tx_type = TxLogEntryType::TxReceivedCancelled
 		}
+		(TxLogEntryType::TxReceived, true) | (TxLogEntryType::TxReverted, true) => {
+			tx.tx_type = TxLogEntryType::TxReceivedTtlCancelled
+		}
 		_ => {}
 	}
 	batch.save_tx_log_entry(tx, parent_key_id)?;
@@ -404,7 +663,8 @@ where
 	Ok(())
 }
 
-/// Cancel transaction and associated outputs
+/// Cancel token transaction and associated base and token outputs.
+/// `ttl_expired` marks the entry as cancelled because its TTL expired
 pub fn cancel_token_tx_and_outputs<'a, T: ?Sized, C, K>(
 	wallet: &mut T,
 	keychain_mask: Option<&SecretKey>,
@@ -412,6 +672,7 @@ pub fn cancel_token_tx_and_outputs<'a, T: ?Sized, C, K>(
 	outputs: Vec<OutputData>,
 	token_outputs: Vec<TokenOutputData>,
 	parent_key_id: &Identifier,
+	ttl_expired: bool,
 ) -> Result<(), Error>
 where
 	T: WalletBackend<'a, C, K>,
@@ -441,11 +702,21 @@ where
 			batch.save_token(o)?;
 		}
 	}
-	match tx.tx_type {
-		TokenTxLogEntryType::TokenTxSent => tx.tx_type = TokenTxLogEntryType::TokenTxSentCancelled,
-		TokenTxLogEntryType::TokenTxReceived | TokenTxLogEntryType::TokenTxReverted => {
+	match (tx.tx_type.clone(), ttl_expired) {
+		(TokenTxLogEntryType::TokenTxSent, false) => {
+			tx.tx_type = TokenTxLogEntryType::TokenTxSentCancelled
+		}
+		(TokenTxLogEntryType::TokenTxSent, true) => {
+			tx.tx_type = TokenTxLogEntryType::TokenTxSentTtlCancelled
+		}
+		(TokenTxLogEntryType::TokenTxReceived, false)
+		| (TokenTxLogEntryType::TokenTxReverted, false) => {
 			tx.tx_type = TokenTxLogEntryType::TokenTxReceivedCancelled
 		}
+		(TokenTxLogEntryType::TokenTxReceived, true)
+		| (TokenTxLogEntryType::TokenTxReverted, true) => {
+			tx.tx_type = TokenTxLogEntryType::TokenTxReceivedTtlCancelled
+		}
 		_ => {}
 	}
 	batch.save_token_tx_log_entry(tx, parent_key_id)?;
@@ -488,8 +759,12 @@ where
 			if let Ok(mut output) = batch.get(id, mmr_index) {
 				match api_outputs.get(&commit) {
 					Some(o) => {
-						// if this is a coinbase tx being confirmed, it's recordable in tx log
-						if output.is_coinbase && output.status == OutputStatus::Unconfirmed {
+						// if this is a coinbase tx being confirmed, it's recordable in tx log,
+						// unless a reorg orphaned the block it was confirmed in before
+						let new_coinbase = output.is_coinbase
+							&& output.status == OutputStatus::Unconfirmed
+							&& output.tx_log_entry.is_none();
+						if new_coinbase {
 							let log_id = batch.next_tx_log_id(parent_key_id)?;
 							let mut t = TxLogEntry::new(
 								parent_key_id.clone(),
@@ -516,7 +791,7 @@ where
 						// also mark the transaction in which this output is involved as confirmed
 						// note that one involved input/output confirmation SHOULD be enough
 						// to reliably confirm the tx
-						if !output.is_coinbase
+						if !new_coinbase
 							&& (output.status == OutputStatus::Unconfirmed
 								|| output.status == OutputStatus::Reverted)
 						{
@@ -665,6 +940,84 @@ where
 	Ok(())
 }
 
+/// Settles the outputs of a transaction whose kernel was found on chain at
+/// `height`: its locked inputs are spent and its unconfirmed change is
+/// unspent. Token transactions settle their token outputs as well. Tx log ids
+/// are per account, so only outputs of the account are touched.
+pub fn confirm_tx_outputs<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	keychain_mask: Option<&SecretKey>,
+	parent_key_id: &Identifier,
+	tx_id: u32,
+	height: u64,
+	is_token: bool,
+) -> Result<(), Error>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	// remember the block the kernel is in, should a reorg orphan it
+	let block = match wallet.w2n_client().get_header_hash(height)? {
+		Some(hash) => {
+			let mut block = match wallet.get_confirmed_block(parent_key_id, height)? {
+				Some(b) if b.hash == hash => b,
+				_ => ConfirmedBlock {
+					parent_key_id: parent_key_id.clone(),
+					height,
+					hash,
+					tx_log_ids: vec![],
+				},
+			};
+			if !block.tx_log_ids.contains(&tx_id) {
+				block.tx_log_ids.push(tx_id);
+			}
+			Some(block)
+		}
+		None => None,
+	};
+	let outputs: Vec<OutputData> = wallet
+		.iter()
+		.filter(|o| o.root_key_id == *parent_key_id && o.tx_log_entry == Some(tx_id))
+		.collect();
+	let token_outputs: Vec<TokenOutputData> = match is_token {
+		true => wallet
+			.token_iter()
+			.filter(|o| o.root_key_id == *parent_key_id && o.tx_log_entry == Some(tx_id))
+			.collect(),
+		false => vec![],
+	};
+
+	let mut batch = wallet.batch(keychain_mask)?;
+	for mut output in outputs {
+		match output.status {
+			OutputStatus::Locked => output.mark_spent(),
+			OutputStatus::Unconfirmed => {
+				output.height = height;
+				output.mark_unspent();
+			}
+			_ => continue,
+		}
+		batch.save(output)?;
+	}
+	for mut output in token_outputs {
+		match output.status {
+			OutputStatus::Locked => output.mark_spent(),
+			OutputStatus::Unconfirmed => {
+				output.height = height;
+				output.mark_unspent();
+			}
+			_ => continue,
+		}
+		batch.save_token(output)?;
+	}
+	if let Some(block) = block {
+		batch.save_confirmed_block(&block)?;
+	}
+	batch.commit()?;
+	Ok(())
+}
+
 /// Builds a single api query to retrieve the latest output data from the node.
 /// So we can refresh the local wallet outputs.
 fn refresh_output_state<'a, T: ?Sized, C, K>(
@@ -904,6 +1257,7 @@ where
 	K: Keychain + 'a,
 {
 	let current_height = wallet.last_confirmed_height()?;
+	let reservations = selection::live_reservations(wallet, current_height);
 	let outputs = wallet
 		.iter()
 		.filter(|out| out.root_key_id == *parent_key_id);
@@ -914,6 +1268,8 @@ where
 	let mut unconfirmed_total = 0;
 	let mut locked_total = 0;
 	let mut reverted_total = 0;
+	let mut frozen_total = 0;
+	let mut reserved_total = 0;
 
 	for out in outputs {
 		match out.status {
@@ -923,6 +1279,13 @@ where
 				} else if out.num_confirmations(current_height) < minimum_confirmations {
 					// Treat anything less than minimum confirmations as "unconfirmed".
 					unconfirmed_total += out.value;
+				} else if out.is_frozen {
+					frozen_total += out.value;
+				} else if reservations
+					.iter()
+					.any(|r| r.reserves(&out.key_id, &out.mmr_index))
+				{
+					reserved_total += out.value;
 				} else {
 					unspent_total += out.value;
 				}
@@ -945,6 +1308,41 @@ where
 		}
 	}
 
+	let token_infos =
+		retrieve_token_info(wallet, parent_key_id, current_height, minimum_confirmations);
+
+	Ok(WalletInfo {
+		last_confirmed_height: current_height,
+		minimum_confirmations,
+		total: unspent_total + unconfirmed_total + immature_total + frozen_total + reserved_total,
+		amount_awaiting_finalization: awaiting_finalization_total,
+		amount_awaiting_confirmation: unconfirmed_total,
+		amount_immature: immature_total,
+		amount_locked: locked_total,
+		amount_currently_spendable: unspent_total,
+		amount_reverted: reverted_total,
+		amount_frozen: frozen_total,
+		amount_reserved: reserved_total,
+		token_infos,
+	})
+}
+
+/// Retrieve summary info about the tokens in the wallet, one entry per token
+/// type ordered by token type. Outputs are classified against the given
+/// height and live reservations the same way coin selection sees them.
+/// caller should refresh first if desired
+pub fn retrieve_token_info<'a, T: ?Sized, C, K>(
+	wallet: &mut T,
+	parent_key_id: &Identifier,
+	current_height: u64,
+	minimum_confirmations: u64,
+) -> Vec<WalletTokenInfo>
+where
+	T: WalletBackend<'a, C, K>,
+	C: NodeClient + 'a,
+	K: Keychain + 'a,
+{
+	let reservations = selection::live_reservations(wallet, current_height);
 	let mut token_infos: HashMap<String, WalletTokenInfo> = HashMap::new();
 	let token_outputs = wallet
 		.token_iter()
@@ -956,15 +1354,31 @@ where
 				token_type: out.token_type.clone(),
 				amount_awaiting_finalization: 0,
 				amount_awaiting_confirmation: 0,
-				amount_locked: 0,
+				amount_immature: 0,
 				amount_currently_spendable: 0,
+				amount_locked: 0,
 				amount_reverted: 0,
+				amount_frozen: 0,
+				amount_reserved: 0,
+				num_outputs: 0,
 			});
+		if out.status != OutputStatus::Spent {
+			token_info.num_outputs += 1;
+		}
 		match out.status {
 			OutputStatus::Unspent => {
-				if out.num_confirmations(current_height) < minimum_confirmations {
+				if out.lock_height > current_height {
+					token_info.amount_immature += out.value;
+				} else if out.num_confirmations(current_height) < minimum_confirmations {
 					// Treat anything less than minimum confirmations as "unconfirmed".
 					token_info.amount_awaiting_confirmation += out.value;
+				} else if out.is_frozen {
+					token_info.amount_frozen += out.value;
+				} else if reservations
+					.iter()
+					.any(|r| r.reserves_token(&out.key_id, &out.mmr_index))
+				{
+					token_info.amount_reserved += out.value;
 				} else {
 					token_info.amount_currently_spendable += out.value;
 				}
@@ -984,23 +1398,9 @@ where
 		}
 	}
 
-	let mut tokens = vec![];
-	for token_info in token_infos.values() {
-		tokens.push(token_info.clone());
-	}
-
-	Ok(WalletInfo {
-		last_confirmed_height: current_height,
-		minimum_confirmations,
-		total: unspent_total + unconfirmed_total + immature_total,
-		amount_awaiting_finalization: awaiting_finalization_total,
-		amount_awaiting_confirmation: unconfirmed_total,
-		amount_immature: immature_total,
-		amount_locked: locked_total,
-		amount_currently_spendable: unspent_total,
-		amount_reverted: reverted_total,
-		token_infos: tokens,
-	})
+	let mut token_infos: Vec<WalletTokenInfo> = token_infos.into_iter().map(|(_, v)| v).collect();
+	token_infos.sort_by(|a, b| a.token_type.cmp(&b.token_type));
+	token_infos
 }
 
 /// Build a coinbase output and insert into wallet
@@ -1066,6 +1466,7 @@ where
 			height: height,
 			lock_height: lock_height,
 			is_coinbase: true,
+			is_frozen: false,
 			tx_log_entry: None,
 		})?;
 		batch.commit()?;
@@ -65,21 +65,36 @@ pub use crate::slate_versions::{
 pub use crate::slatepack::{
 	Slatepack, SlatepackAddress, SlatepackArmor, SlatepackBin, Slatepacker, SlatepackerArgs,
 };
-pub use api_impl::owner_updater::StatusMessage;
+pub use api_impl::owner_updater::{ScanProgress, StatusMessage};
 pub use api_impl::types::{
 	BlockFees, InitTxArgs, InitTxSendArgs, IssueInvoiceTxArgs, NodeHeightResult,
 	OutputCommitMapping, PaymentProof, VersionInfo,
 };
-pub use api_impl::types::{IssueTokenArgs, TokenOutputCommitMapping};
+pub use api_impl::types::{
+	ConsolidateArgs, IssueTokenArgs, SlateActivity, SplitOutputsArgs, TokenOutputCommitMapping,
+	TxExportArgs, TxExportColumn, TxExportFormat, TxNoteFilter, MAX_MEMO_LEN, MAX_TX_LABELS,
+	MAX_TX_LABEL_LEN, MAX_TX_NOTE_LEN,
+};
 pub use internal::scan::scan;
+pub use internal::selection::{
+	build_recipient_output, eligible_coins, eligible_coins_for_parents, estimate_send,
+	inputs_and_change_with_keys, min_sendable_amount, min_sendable_token_amount,
+	release_expired_reservations, select_coins_and_fee_detailed, select_coins_and_fee_from,
+	select_token_coins_for_consolidation, should_include_inputs_in_sum,
+	spendable_balance_breakdown, spendable_token_balance_breakdown, ChangeOutput, CoinSelector,
+	SelectionParams, SendParams, SingleRandomDraw, StrategySelector,
+};
 pub use internal::token_scan::token_scan;
 pub use slate_versions::ser as dalek_ser;
 pub use types::{
-	AcctPathMapping, BlockIdentifier, CbData, Context, NodeClient, NodeVersionInfo, OutputData,
-	OutputStatus, ScannedBlockInfo, StoredProofInfo, TxLogEntry, TxLogEntryType, TxWrapper,
-	WalletBackend, WalletInfo, WalletInitStatus, WalletInst, WalletLCProvider, WalletOutputBatch,
+	AcctPathMapping, BalanceBreakdown, BlockIdentifier, CbData, ChangeDistribution, ChangePlan,
+	ConfirmedBlock, Context, FeeBreakdown, FeeEstimate, LateLockArgs, NodeClient, NodeVersionInfo,
+	OutputData, OutputReservation, OutputStatus, Payout, PreviewInput, ScanCheckpoint,
+	ScannedBlockInfo, SelectionStrategy, SlateTransport, StoredProofInfo, TxLogEntry,
+	TxLogEntryType, TxPreview, TxWrapper, WalletBackend, WalletInfo, WalletInitStatus, WalletInst,
+	WalletLCProvider, WalletOutputBatch,
 };
-pub use types::{TokenOutputData, TokenTxLogEntry, TokenTxLogEntryType};
+pub use types::{TokenOutputData, TokenTxLogEntry, TokenTxLogEntryType, WalletTokenInfo};
 
 /// Helper for taking a lock on the wallet instance
 #[macro_export]
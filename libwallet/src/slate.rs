@@ -62,6 +62,9 @@ pub struct PaymentInfo {
 	pub receiver_address: DalekPublicKey,
 	/// Receiver signature
 	pub receiver_signature: Option<DalekSignature>,
+	/// Sender signature, only carried back to the recipient of an invoice,
+	/// who finalizes the transaction
+	pub sender_signature: Option<DalekSignature>,
 }
 
 /// Public data for each participant in the slate
@@ -364,11 +367,46 @@ impl Slate {
 		Ok(())
 	}
 
+	/// The token type of the slate as a token key, if it's a token slate. A
+	/// token type that isn't a valid key is refused here rather than failing
+	/// deep in building the transaction.
+	pub fn token_key(&self) -> Result<Option<TokenKey>, Error> {
+		let token_type = match &self.token_type {
+			Some(t) => t,
+			None => return Ok(None),
+		};
+		let invalid =
+			|| ErrorKind::InvalidTokenSlate(format!("invalid token type '{}'", token_type));
+		// a token key is a 32 byte hash
+		if token_type.len() != 64 {
+			return Err(invalid().into());
+		}
+		let token_key = TokenKey::from_hex(token_type.as_str()).map_err(|_| invalid())?;
+		Ok(Some(token_key))
+	}
+
+	/// Checks a token slate received from the sender is well formed before
+	/// adding to it: its token type must be a valid token key, and if it
+	/// carries the transaction, the transaction must have a token kernel.
+	pub fn check_token_type(&self) -> Result<Option<TokenKey>, Error> {
+		let token_key = self.token_key()?;
+		if token_key.is_some() {
+			if let Some(tx) = &self.tx {
+				if tx.token_kernels().is_empty() {
+					return Err(ErrorKind::InvalidTokenSlate(
+						"token transaction has no token kernel".to_owned(),
+					)
+					.into());
+				}
+			}
+		}
+		Ok(token_key)
+	}
+
 	/// Update the tx token kernel based on token kernel features derived from the current slate.
 	/// update the tx token kernel to reflect this during the tx building process.
 	pub fn update_token_kernel(&mut self) -> Result<(), Error> {
-		if self.token_type.is_some() {
-			let token_type = TokenKey::from_hex(self.token_type.clone().unwrap().as_str()).unwrap();
+		if let Some(token_type) = self.token_key()? {
 			self.tx = Some(
 				self.tx_or_err()?.clone().replace_token_kernel(
 					TokenTxKernel::with_features(self.token_kernel_features()?)
@@ -385,6 +423,7 @@ impl Slate {
 		keychain: &K,
 		amount: u64,
 		output_id: &Identifier,
+		use_test_rng: bool,
 	) -> Result<(), Error>
 	where
 		K: Keychain,
@@ -401,13 +440,14 @@ impl Slate {
 			let excess = secp.commit_sum(vec![out_commit], vec![value_commit])?;
 			let pubkey = excess.to_pubkey(&secp)?;
 			let msg = feature.token_kernel_sig_msg(token_type.clone())?;
+			let nonce = test_signing_nonce(secp, use_test_rng)?;
 			let sig = aggsig::sign_from_key_id(
 				&secp,
 				keychain,
 				&msg,
 				amount,
 				&output_id,
-				None,
+				nonce.as_ref(),
 				Some(&pubkey),
 			)?;
 
@@ -709,6 +749,31 @@ impl Slate {
 		Ok(())
 	}
 
+	/// Checks the fee set by the sender covers the minimum fee of the
+	/// transaction, before the recipient adds to it. A compact slate doesn't
+	/// carry the sender's inputs and change, so all the recipient can count on
+	/// is the kernel.
+	pub fn check_min_fee(&self) -> Result<(), Error> {
+		let min = if self.is_compact() {
+			tx_fee(0, 0, 1, 0, 0, 0, None)
+		} else {
+			let tx = self.tx_or_err()?;
+			tx_fee(
+				tx.inputs().len(),
+				tx.outputs().len(),
+				tx.kernels().len(),
+				tx.token_inputs().len(),
+				tx.token_outputs().len(),
+				tx.token_kernels().len(),
+				None,
+			)
+		};
+		if self.fee < min {
+			return Err(ErrorKind::FeeBelowMinimum { fee: self.fee, min }.into());
+		}
+		Ok(())
+	}
+
 	/// Checks the fees in the transaction in the given slate are valid
 	fn check_fees(&self) -> Result<(), Error> {
 		let tx = self.tx_or_err()?;
@@ -891,6 +956,7 @@ impl Slate {
 		keychain: &K,
 		sec_key: &SecretKey,
 		with_validate: bool,
+		use_test_rng: bool,
 	) -> Result<(), Error>
 	where
 		K: Keychain,
@@ -913,7 +979,8 @@ impl Slate {
 
 		let pubkey = final_excess.to_pubkey(&secp)?;
 
-		let sig = aggsig::sign_single(secp, &msg_to_sign, sec_key, None, Some(&pubkey))?;
+		let nonce = test_signing_nonce(secp, use_test_rng)?;
+		let sig = aggsig::sign_single(secp, &msg_to_sign, sec_key, nonce.as_ref(), Some(&pubkey))?;
 
 		// update the tx kernel to reflect the offset excess and sig
 		assert_eq!(final_tx.kernels().len(), 1);
@@ -934,6 +1001,40 @@ impl Slate {
 		Ok(())
 	}
 
+	/// Signs the token kernel of a transaction we're the only party to, with the
+	/// token excess of our own token inputs and outputs. The token kernel has no
+	/// offset, so the excess is simply the token outputs less the token inputs.
+	pub fn sign_token_kernel<K>(
+		&mut self,
+		keychain: &K,
+		token_sec_key: &SecretKey,
+		use_test_rng: bool,
+	) -> Result<(), Error>
+	where
+		K: Keychain,
+	{
+		let secp = keychain.secp();
+		let msg_to_sign = self.token_msg_to_sign()?;
+		let final_excess = self.calc_token_final_excess(secp)?;
+		let pubkey = final_excess.to_pubkey(&secp)?;
+
+		let nonce = test_signing_nonce(secp, use_test_rng)?;
+		let sig = aggsig::sign_single(
+			secp,
+			&msg_to_sign,
+			token_sec_key,
+			nonce.as_ref(),
+			Some(&pubkey),
+		)?;
+
+		let final_tx = self.tx_or_err_mut()?;
+		assert_eq!(final_tx.token_kernels().len(), 1);
+		final_tx.token_kernels_mut()[0].excess = final_excess;
+		final_tx.token_kernels_mut()[0].excess_sig = sig;
+		final_tx.token_kernels()[0].verify()?;
+		Ok(())
+	}
+
 	/// builds a final transaction after the aggregated sig exchange
 	fn finalize_token_transaction<K>(
 		&mut self,
@@ -970,6 +1071,18 @@ impl Slate {
 	}
 }
 
+/// The nonce to sign a kernel we're the only party to with, fixed for
+/// consistent test results and otherwise left to the signer to pick at random
+fn test_signing_nonce(
+	secp: &secp::Secp256k1,
+	use_test_rng: bool,
+) -> Result<Option<SecretKey>, Error> {
+	match use_test_rng {
+		false => Ok(None),
+		true => Ok(Some(SecretKey::from_slice(secp, &[3; 32])?)),
+	}
+}
+
 impl Serialize for Slate {
 	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
 	where
@@ -1241,14 +1354,17 @@ impl From<&PaymentInfo> for PaymentInfoV4 {
 			sender_address,
 			receiver_address,
 			receiver_signature,
+			sender_signature,
 		} = data;
 		let sender_address = *sender_address;
 		let receiver_address = *receiver_address;
 		let receiver_signature = *receiver_signature;
+		let sender_signature = *sender_signature;
 		PaymentInfoV4 {
 			saddr: sender_address,
 			raddr: receiver_address,
 			rsig: receiver_signature,
+			ssig: sender_signature,
 		}
 	}
 }
@@ -1645,14 +1761,17 @@ impl From<&PaymentInfoV4> for PaymentInfo {
 			saddr: sender_address,
 			raddr: receiver_address,
 			rsig: receiver_signature,
+			ssig: sender_signature,
 		} = data;
 		let sender_address = *sender_address;
 		let receiver_address = *receiver_address;
 		let receiver_signature = *receiver_signature;
+		let sender_signature = *sender_signature;
 		PaymentInfo {
 			sender_address,
 			receiver_address,
 			receiver_signature,
+			sender_signature,
 		}
 	}
 }
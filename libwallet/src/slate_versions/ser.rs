@@ -66,6 +66,37 @@ pub mod option_rangeproof_hex {
 	}
 }
 
+/// Serializes an Option<Vec<u8>> to and from hex
+pub mod option_bytes_hex {
+	use crate::grin_util::{from_hex, ToHex};
+	use serde::de::Error;
+	use serde::{Deserialize, Deserializer, Serializer};
+
+	///
+	pub fn serialize<S>(bytes: &Option<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		match bytes {
+			Some(b) => serializer.serialize_str(&b.to_hex()),
+			None => serializer.serialize_none(),
+		}
+	}
+
+	///
+	pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		Option::<String>::deserialize(deserializer).and_then(|res| match res {
+			Some(string) => from_hex(&string)
+				.map(Some)
+				.map_err(|err| Error::custom(err.to_string())),
+			None => Ok(None),
+		})
+	}
+}
+
 /// Serializes an OnionV3Address to and from hex
 pub mod option_ov3_serde {
 	use serde::de::Error;
@@ -52,6 +52,7 @@
 //! *  The `receiver_address` field is renamed to `raddr`
 //! *  The `receiver_signature` field is renamed to `rsig`
 //! * `rsig` may be omitted if it has not yet been filled out
+//! * `ssig` holds the sender signature of an invoice, and is omitted otherwise
 
 use crate::grin_core::core::transaction::{TokenKey, Transaction};
 use crate::grin_core::libtx::secp_ser;
@@ -252,6 +253,10 @@ pub struct PaymentInfoV4 {
 	#[serde(with = "ser::option_dalek_sig_serde")]
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub rsig: Option<DalekSignature>,
+	#[serde(default = "default_receiver_signature_none")]
+	#[serde(with = "ser::option_dalek_sig_serde")]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub ssig: Option<DalekSignature>,
 }
 
 fn default_receiver_signature_none() -> Option<DalekSignature> {
@@ -259,13 +259,17 @@ struct SlateOptStructs {
 	pub token_type: Option<TokenKey>,
 	/// token_coms, default none
 	pub token_coms: Option<Vec<TokenCommitsV4>>,
+	/// whether a proof sender signature follows the lock heights
+	pub has_ssig: bool,
 }
 
 impl<'a> Writeable for SlateOptStructsRef<'a> {
 	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), grin_ser::Error> {
 		// Status byte, bits determing which optional structs are serialized
-		// 0 0 0 0  0 1 1 1
-		//            t p c
+		// 0 0 0 1  1 1 1 1
+		//       s  k t p c
+		// s flags a proof sender signature, written after everything else so
+		// readers that don't know about it never see it
 		let mut status = 0u8;
 		if self.coms.is_some() {
 			status |= 0x01
@@ -279,6 +283,11 @@ impl<'a> Writeable for SlateOptStructsRef<'a> {
 		if self.token_coms.is_some() {
 			status |= 0x08
 		};
+		if let Some(p) = self.proof {
+			if p.ssig.is_some() {
+				status |= 0x10
+			}
+		};
 		writer.write_u8(status)?;
 		if let Some(c) = self.coms {
 			ComsWrapRef(&c).write(writer)?;
@@ -324,6 +333,7 @@ impl Readable for SlateOptStructs {
 			proof,
 			token_type,
 			token_coms,
+			has_ssig: status & 0x10 > 0,
 		})
 	}
 }
@@ -450,7 +460,14 @@ impl Readable for ProofWrap {
 			0 => None,
 			1 | _ => Some(DalekSignature::from_bytes(&reader.read_fixed_bytes(64)?).unwrap()),
 		};
-		Ok(ProofWrap(PaymentInfoV4 { saddr, raddr, rsig }))
+		// The sender signature isn't part of the proof struct on the wire,
+		// it's read from the end of the slate when flagged
+		Ok(ProofWrap(PaymentInfoV4 {
+			saddr,
+			raddr,
+			rsig,
+			ssig: None,
+		}))
 	}
 }
 
@@ -549,6 +566,11 @@ impl Writeable for SlateV4Bin {
 			};
 			writer.write_u64(lock_hgt)?;
 		}
+		// Sender signature of an invoice payment proof, flagged in the
+		// optional structs status byte
+		if let Some(s) = v4.proof.as_ref().and_then(|p| p.ssig) {
+			writer.write_fixed_bytes(&s.to_bytes().to_vec())?;
+		}
 		Ok(())
 	}
 }
@@ -565,7 +587,7 @@ impl Readable for SlateV4Bin {
 
 		let opts = SlateOptFields::read(reader)?;
 		let sigs = SigsWrap::read(reader)?.0;
-		let opt_structs = SlateOptStructs::read(reader)?;
+		let mut opt_structs = SlateOptStructs::read(reader)?;
 
 		let feat_args = if opts.feat == 2 {
 			Some(KernelFeaturesArgsV4 {
@@ -583,6 +605,14 @@ impl Readable for SlateV4Bin {
 			None
 		};
 
+		if opt_structs.has_ssig {
+			let ssig = DalekSignature::from_bytes(&reader.read_fixed_bytes(64)?).unwrap();
+			match opt_structs.proof.as_mut() {
+				Some(p) => p.ssig = Some(ssig),
+				None => return Err(grin_ser::Error::CorruptedData),
+			}
+		}
+
 		Ok(SlateV4Bin(SlateV4 {
 			ver,
 			id,
@@ -689,6 +719,7 @@ fn slate_v4_serialize_deserialize() {
 		raddr: d_pkey.clone(),
 		saddr: d_pkey.clone(),
 		rsig: None,
+		ssig: None,
 	});
 	v4.coms = None;
 	let v4_1 = v4.clone();
@@ -705,3 +736,53 @@ fn slate_v4_serialize_deserialize() {
 	assert_eq!(v4_1.sigs, v4_2.sigs);
 	assert_eq!(v4_1.proof, v4_2.proof);
 }
+
+#[test]
+fn slate_v4_bin_proof_sender_sig() {
+	use crate::grin_util::from_hex;
+	use crate::Slate;
+	let raw_pubkey_str = "d03c09e9c19bb74aa9ea44e0fe5ae237a9bf40bddf0941064a80913a4459c8bb";
+	let d_pkey = DalekPublicKey::from_bytes(&from_hex(raw_pubkey_str).unwrap()).unwrap();
+	let rsig = DalekSignature::from_bytes(&[1u8; 64]).unwrap();
+	let ssig = DalekSignature::from_bytes(&[2u8; 64]).unwrap();
+	let mut v4 = SlateV4::from(Slate::blank(2, true));
+	v4.coms = None;
+	v4.token_coms = None;
+	v4.proof = Some(PaymentInfoV4 {
+		saddr: d_pkey.clone(),
+		raddr: d_pkey.clone(),
+		rsig: Some(rsig),
+		ssig: None,
+	});
+
+	// Bytes as written before sender signatures existed: the proof struct is
+	// the two addresses, a presence byte and the receiver signature
+	let mut old_proof = vec![];
+	old_proof.extend_from_slice(d_pkey.as_bytes());
+	old_proof.extend_from_slice(d_pkey.as_bytes());
+	old_proof.push(1);
+	old_proof.extend_from_slice(&rsig.to_bytes());
+	let mut old = Vec::new();
+	grin_ser::serialize_default(&mut old, &SlateV4Bin(v4.clone())).unwrap();
+	assert!(old.ends_with(&old_proof));
+	let read: SlateV4Bin = grin_ser::deserialize_default(&mut &old[..]).unwrap();
+	assert_eq!(read.0.proof, v4.proof);
+
+	// With a sender signature the only changes are the status bit and the
+	// trailing signature, so an older reader still parses the rest
+	v4.proof.as_mut().unwrap().ssig = Some(ssig);
+	let mut new = Vec::new();
+	grin_ser::serialize_default(&mut new, &SlateV4Bin(v4.clone())).unwrap();
+	assert_eq!(new.len(), old.len() + 64);
+	assert!(new.ends_with(&ssig.to_bytes()));
+	let diffs: Vec<(u8, u8)> = old
+		.iter()
+		.zip(new.iter())
+		.filter(|(a, b)| a != b)
+		.map(|(a, b)| (*a, *b))
+		.collect();
+	assert_eq!(diffs.len(), 1);
+	assert_eq!(diffs[0].0 ^ diffs[0].1, 0x10);
+	let read: SlateV4Bin = grin_ser::deserialize_default(&mut &new[..]).unwrap();
+	assert_eq!(read.0.proof, v4.proof);
+}
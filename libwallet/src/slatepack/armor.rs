@@ -61,8 +61,9 @@ impl SlatepackArmor {
 		// Get the length of the header
 		let header_len = *&header_bytes.len() + 1;
 		// Skip the length of the header to read for the payload until the next period
-		let payload_bytes = &armor_bytes[header_len as usize..]
+		let payload_bytes = &armor_bytes
 			.iter()
+			.skip(header_len)
 			.take_while(|byte| **byte != b'.')
 			.cloned()
 			.collect::<Vec<u8>>();
@@ -70,8 +71,9 @@ impl SlatepackArmor {
 		let payload_len = *&payload_bytes.len();
 		// Get footer bytes and verify them
 		let consumed_bytes = header_len + payload_len + 1;
-		let footer_bytes = &armor_bytes[consumed_bytes as usize..]
+		let footer_bytes = &armor_bytes
 			.iter()
+			.skip(consumed_bytes)
 			.take_while(|byte| **byte != b'.')
 			.cloned()
 			.collect::<Vec<u8>>();
@@ -83,7 +85,14 @@ impl SlatepackArmor {
 			.cloned()
 			.collect::<Vec<u8>>();
 		// Decode payload from base58
-		let base_decode = bs58::decode(&clean_payload).into_vec().unwrap();
+		let base_decode = bs58::decode(&clean_payload)
+			.into_vec()
+			.map_err(|e| ErrorKind::InvalidSlatepackData(format!("Bad armor payload: {}", e)))?;
+		if base_decode.len() < 4 {
+			return Err(
+				ErrorKind::InvalidSlatepackData("Armor payload too short".to_string()).into(),
+			);
+		}
 		let error_code = &base_decode[0..4];
 		let slatepack_bytes = &base_decode[4..];
 		// Make sure the error check code is valid for the slate data
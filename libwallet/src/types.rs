@@ -19,13 +19,14 @@ use crate::config::{TorConfig, WalletConfig};
 use crate::error::{Error, ErrorKind};
 use crate::grin_core::core::hash::Hash;
 use crate::grin_core::core::{Output, TokenTxKernel, Transaction, TxKernel};
-use crate::grin_core::libtx::{aggsig, secp_ser};
+use crate::grin_core::libtx::{aggsig, secp_ser, tx_fee, DEFAULT_BASE_FEE};
 use crate::grin_core::{global, ser};
 use crate::grin_keychain::{Identifier, Keychain};
 use crate::grin_util::logger::LoggingConfig;
 use crate::grin_util::secp::key::{PublicKey, SecretKey};
 use crate::grin_util::secp::{self, pedersen, Secp256k1};
 use crate::grin_util::{ToHex, ZeroingString};
+use crate::slate::PaymentInfo;
 use crate::slate_versions::ser as dalek_ser;
 use chrono::prelude::*;
 use ed25519_dalek::PublicKey as DalekPublicKey;
@@ -169,6 +170,19 @@ where
 		id: &Identifier,
 	) -> Result<Option<String>, Error>;
 
+	/// return the commits for caching of several outputs at once, in the
+	/// order given, none for each if not allowed
+	fn calc_commits_for_cache(
+		&mut self,
+		keychain_mask: Option<&SecretKey>,
+		outputs: &[(u64, Identifier)],
+	) -> Result<Vec<Option<String>>, Error> {
+		outputs
+			.iter()
+			.map(|(amount, id)| self.calc_commit_for_cache(keychain_mask, *amount, id))
+			.collect()
+	}
+
 	/// Set parent key id by stored account name
 	fn set_parent_key_id_by_name(&mut self, label: &str) -> Result<(), Error>;
 
@@ -185,6 +199,41 @@ where
 	/// Iterate over all output data stored by the backend
 	fn token_iter<'a>(&'a self) -> Box<dyn Iterator<Item = TokenOutputData> + 'a>;
 
+	/// Iterate over the outputs of an account having any of the given statuses.
+	/// Backends able to index their outputs should override this, the default
+	/// filters `iter`.
+	fn outputs_for_parent<'a>(
+		&'a self,
+		parent_key_id: &Identifier,
+		status: &[OutputStatus],
+	) -> Box<dyn Iterator<Item = OutputData> + 'a> {
+		let parent_key_id = parent_key_id.clone();
+		let status = status.to_vec();
+		Box::new(
+			self.iter().filter(move |out| {
+				out.root_key_id == parent_key_id && status.contains(&out.status)
+			}),
+		)
+	}
+
+	/// Iterate over the token outputs of an account of the given token type
+	/// having any of the given statuses, as `outputs_for_parent` does
+	fn token_outputs_for<'a>(
+		&'a self,
+		parent_key_id: &Identifier,
+		token_type: &str,
+		status: &[OutputStatus],
+	) -> Box<dyn Iterator<Item = TokenOutputData> + 'a> {
+		let parent_key_id = parent_key_id.clone();
+		let token_type = token_type.to_owned();
+		let status = status.to_vec();
+		Box::new(self.token_iter().filter(move |out| {
+			out.root_key_id == parent_key_id
+				&& out.token_type == token_type
+				&& status.contains(&out.status)
+		}))
+	}
+
 	/// Get output data by id
 	fn get(&self, id: &Identifier, mmr_index: &Option<u64>) -> Result<OutputData, Error>;
 
@@ -211,6 +260,22 @@ where
 	/// Iterate over all output data stored by the backend
 	fn token_tx_log_iter<'a>(&'a self) -> Box<dyn Iterator<Item = TokenTxLogEntry> + 'a>;
 
+	/// Iterate over the reservations of transactions not locked yet
+	fn reservation_iter<'a>(&'a self) -> Box<dyn Iterator<Item = OutputReservation> + 'a>;
+
+	/// Get an (Optional) reservation by slate id
+	fn get_reservation(&self, slate_id: &Uuid) -> Result<Option<OutputReservation>, Error>;
+
+	/// Iterate over the blocks the wallet saw confirmations in
+	fn confirmed_block_iter<'a>(&'a self) -> Box<dyn Iterator<Item = ConfirmedBlock> + 'a>;
+
+	/// Get an (Optional) confirmed block of the account by height
+	fn get_confirmed_block(
+		&self,
+		parent_key_id: &Identifier,
+		height: u64,
+	) -> Result<Option<ConfirmedBlock>, Error>;
+
 	/// Iterate over all stored account paths
 	fn acct_path_iter<'a>(&'a self) -> Box<dyn Iterator<Item = AcctPathMapping> + 'a>;
 
@@ -246,6 +311,9 @@ where
 
 	/// Flag whether the wallet needs a full UTXO scan on next update attempt
 	fn init_status(&mut self) -> Result<WalletInitStatus, Error>;
+
+	/// Where a cancelled scan stopped, if one is waiting to be resumed
+	fn scan_checkpoint(&mut self) -> Result<Option<ScanCheckpoint>, Error>;
 }
 
 /// Batch trait to update the output data backend atomically. Trying to use a
@@ -301,6 +369,12 @@ where
 	/// Save flag indicating whether wallet needs a full UTXO scan
 	fn save_init_status(&mut self, value: WalletInitStatus) -> Result<(), Error>;
 
+	/// Save where a cancelled scan stopped, for the next scan to resume from
+	fn save_scan_checkpoint(&mut self, checkpoint: ScanCheckpoint) -> Result<(), Error>;
+
+	/// Delete the checkpoint of a cancelled scan, if any
+	fn delete_scan_checkpoint(&mut self) -> Result<(), Error>;
+
 	/// get next tx log entry for the parent
 	fn next_tx_log_id(&mut self, parent_key_id: &Identifier) -> Result<u32, Error>;
 
@@ -332,12 +406,35 @@ where
 	/// Save an output as locked in the backend
 	fn lock_token_output(&mut self, out: &mut TokenOutputData) -> Result<(), Error>;
 
+	/// Freeze the output or token output with the given commit, so it is never
+	/// picked by automatic coin selection
+	fn freeze_output(&mut self, commit: &str) -> Result<(), Error>;
+
+	/// Unfreeze the output or token output with the given commit
+	fn unfreeze_output(&mut self, commit: &str) -> Result<(), Error>;
+
 	/// Saves the private context associated with a slate id
 	fn save_private_context(&mut self, slate_id: &[u8], ctx: &Context) -> Result<(), Error>;
 
 	/// Delete the private context associated with the slate id
 	fn delete_private_context(&mut self, slate_id: &[u8]) -> Result<(), Error>;
 
+	/// Save the reservation of a transaction's inputs
+	fn save_reservation(&mut self, reservation: &OutputReservation) -> Result<(), Error>;
+
+	/// Delete the reservation held by the slate id, if any
+	fn delete_reservation(&mut self, slate_id: &Uuid) -> Result<(), Error>;
+
+	/// Save a block the wallet saw confirmations in
+	fn save_confirmed_block(&mut self, block: &ConfirmedBlock) -> Result<(), Error>;
+
+	/// Delete the confirmed block of the account at the given height, if any
+	fn delete_confirmed_block(
+		&mut self,
+		parent_key_id: &Identifier,
+		height: u64,
+	) -> Result<(), Error>;
+
 	/// Write the wallet data to backend file
 	fn commit(&self) -> Result<(), Error>;
 }
@@ -367,6 +464,10 @@ pub trait NodeClient: Send + Sync + Clone {
 	/// retrieves the current tip (height, hash) from the specified grin node
 	fn get_chain_tip(&self) -> Result<(u64, String), Error>;
 
+	/// Get the hash of the main chain block at the given height, if the chain
+	/// is that long
+	fn get_header_hash(&self, height: u64) -> Result<Option<String>, Error>;
+
 	/// Get a kernel and the height of the block it's included in. Returns
 	/// (tx_kernel, height, mmr_index)
 	fn get_kernel(
@@ -506,6 +607,9 @@ pub struct OutputData {
 	pub lock_height: u64,
 	/// Is this a coinbase output? Is it subject to coinbase locktime?
 	pub is_coinbase: bool,
+	/// Frozen outputs are never picked by automatic coin selection
+	#[serde(default)]
+	pub is_frozen: bool,
 	/// Optional corresponding internal entry in tx entry log
 	pub tx_log_entry: Option<u32>,
 }
@@ -548,7 +652,8 @@ impl OutputData {
 	}
 
 	/// Check if output is eligible to spend based on state and height and
-	/// confirmations
+	/// confirmations. Outputs whose lock_height hasn't been reached yet, such
+	/// as immature coinbase outputs, are never eligible.
 	pub fn eligible_to_spend(&self, current_height: u64, minimum_confirmations: u64) -> bool {
 		if [OutputStatus::Spent, OutputStatus::Locked].contains(&self.status)
 			|| self.status == OutputStatus::Unconfirmed && self.is_coinbase
@@ -622,6 +727,9 @@ pub struct TokenOutputData {
 	pub lock_height: u64,
 	/// Is this a coinbase output? Is it subject to coinbase locktime?
 	pub is_token_issue: bool,
+	/// Frozen outputs are never picked by automatic coin selection
+	#[serde(default)]
+	pub is_frozen: bool,
 	/// Optional corresponding internal entry in tx entry log
 	pub tx_log_entry: Option<u32>,
 }
@@ -736,6 +844,146 @@ impl fmt::Display for OutputStatus {
 	}
 }
 
+/// Strategy used to pick which eligible outputs are spent as inputs
+/// of a new transaction
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionStrategy {
+	/// Spend as many outputs as possible (up to `max_outputs`), smallest
+	/// first. Reduces the UTXO set at the cost of a larger change output
+	UseAll,
+	/// Spend the smallest outputs first, only as many as needed
+	SmallestFirst,
+	/// Spend the largest outputs first, only as many as needed
+	LargestFirst,
+	/// Spend as few outputs as possible, using the smallest output that
+	/// still covers what's left for the final input
+	BiggestFewest,
+	/// Spend the oldest outputs first, by the height they were confirmed at,
+	/// only as many as needed. Matches the first-in, first-out order of lots
+	OldestFirst,
+	/// Spend outputs in a shuffled order, only as many as needed, so the
+	/// inputs of a transaction say less about the rest of the wallet. The
+	/// same seed always shuffles the same outputs the same way
+	Random {
+		/// Seed of the shuffle
+		seed: u64,
+	},
+}
+
+impl From<bool> for SelectionStrategy {
+	/// Maps the legacy `selection_strategy_is_use_all` flag onto a strategy
+	fn from(use_all: bool) -> Self {
+		match use_all {
+			true => SelectionStrategy::UseAll,
+			false => SelectionStrategy::SmallestFirst,
+		}
+	}
+}
+
+impl fmt::Display for SelectionStrategy {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match *self {
+			SelectionStrategy::UseAll => write!(f, "all"),
+			SelectionStrategy::SmallestFirst => write!(f, "smallest"),
+			SelectionStrategy::LargestFirst => write!(f, "largest"),
+			SelectionStrategy::BiggestFewest => write!(f, "fewest"),
+			SelectionStrategy::OldestFirst => write!(f, "oldest"),
+			SelectionStrategy::Random { .. } => write!(f, "random"),
+		}
+	}
+}
+
+/// How the change of a transaction is split into change outputs
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum ChangePlan {
+	/// Split the change evenly over the requested number of change outputs
+	Even,
+	/// Split the change evenly like `Even`, but spread the remainder over the
+	/// first outputs so they all end up within one nanogrin of each other
+	Balanced,
+	/// Create one change output for each of the given denominations that fits,
+	/// largest first, with any remainder in a final output
+	Denominated(Vec<u64>),
+}
+
+impl ChangePlan {
+	/// The most change outputs this plan can create, used when calculating
+	/// the fee before the change amount is known
+	pub fn max_change_outputs(&self, num_change_outputs: usize) -> usize {
+		match self {
+			ChangePlan::Even | ChangePlan::Balanced => num_change_outputs,
+			ChangePlan::Denominated(denominations) => denominations.len() + 1,
+		}
+	}
+}
+
+impl Default for ChangePlan {
+	fn default() -> Self {
+		ChangePlan::Even
+	}
+}
+
+/// Where the remainder goes when change doesn't divide evenly over the change
+/// outputs
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeDistribution {
+	/// The last change output takes the whole remainder
+	LastAbsorbs,
+	/// The remainder is spread one nanogrin at a time over the first outputs
+	Balanced,
+}
+
+impl Default for ChangeDistribution {
+	fn default() -> Self {
+		ChangeDistribution::LastAbsorbs
+	}
+}
+
+/// A single payment of a batch payout, paying several recipients in one
+/// transaction
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Payout {
+	/// Amount paid to the recipient
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub amount: u64,
+	/// Who the payment is for, e.g. an address or a reference of the payer's
+	/// choosing. Only recorded, not interpreted by the wallet.
+	#[serde(default)]
+	pub recipient: Option<String>,
+}
+
+/// Selection parameters of a late locked transaction, kept in its context so
+/// inputs and change can be selected once the slate comes back to be finalized
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LateLockArgs {
+	/// Minimum confirmations required for an output to be spendable
+	pub minimum_confirmations: u64,
+	/// Stricter minimum confirmations for coinbase outputs, if any
+	#[serde(default)]
+	pub coinbase_min_confirmations: Option<u64>,
+	/// Max number of outputs to use as inputs
+	pub max_outputs: usize,
+	/// Whether `max_outputs` is a hard limit
+	#[serde(default)]
+	pub strict_max_outputs: bool,
+	/// Hard cap on the number of inputs, if any
+	#[serde(default)]
+	pub max_inputs_hard: Option<usize>,
+	/// Number of outputs paying the amount
+	pub recipient_outputs: usize,
+	/// Number of change outputs to generate
+	pub change_outputs: usize,
+	/// Strategy used to select the inputs
+	pub selection_strategy: SelectionStrategy,
+	/// Change below this amount is added to the fee rather than creating an output
+	pub dust_threshold: u64,
+	/// How the change is split into change outputs
+	pub change_plan: ChangePlan,
+	/// Parent to derive change outputs under, if not the active account
+	#[serde(default)]
+	pub change_parent_key_id: Option<Identifier>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 /// Holds the context for a single aggsig transaction
 pub struct Context {
@@ -758,6 +1006,15 @@ pub struct Context {
 	/// store my inputs
 	/// Id, mmr_index (if known), amount
 	pub input_ids: Vec<(Identifier, Option<u64>, u64)>,
+	/// cached commitments of my inputs, where known
+	/// Id, commit
+	#[serde(default)]
+	pub input_commits: Vec<(Identifier, String)>,
+	/// cached commitments of my (token) outputs, computed when they were
+	/// created so locking doesn't have to again
+	/// Id, commit
+	#[serde(default)]
+	pub output_commits: Vec<(Identifier, String)>,
 	/// store my token outputs + amounts between invocations
 	/// Id, mmr_index (if known), amount
 	pub token_output_ids: Vec<(Identifier, Option<u64>, u64)>,
@@ -776,6 +1033,38 @@ pub struct Context {
 	/// for invoice I2 Only, store the tx excess so we can
 	/// remove it from the slate on return
 	pub calculated_excess: Option<pedersen::Commitment>,
+	/// payouts of a batch payout, recorded in the tx log
+	#[serde(default)]
+	pub payouts: Vec<Payout>,
+	/// token-denominated fee of a token send, recorded in the tx log
+	#[serde(default)]
+	pub token_fee: u64,
+	/// selection parameters of a late locked transaction, whose inputs aren't
+	/// selected until it's finalized
+	#[serde(default)]
+	pub late_lock_args: Option<LateLockArgs>,
+	/// whether the transaction was built without any change outputs, which
+	/// may leak the sender's key
+	#[serde(default)]
+	pub changeless: bool,
+	/// strategy our inputs were selected with, recorded in the tx log
+	#[serde(default)]
+	pub selection_strategy: Option<SelectionStrategy>,
+	/// parent our change outputs were derived under, if not `parent_key_id`
+	#[serde(default)]
+	pub change_parent_key_id: Option<Identifier>,
+	/// memo to keep with the transaction in the tx log
+	#[serde(with = "dalek_ser::option_bytes_hex", default)]
+	pub memo: Option<Vec<u8>>,
+	/// token type a token transaction was built for
+	#[serde(default)]
+	pub token_type: Option<String>,
+	/// payment proof sender address, as the transaction was built with
+	#[serde(with = "dalek_ser::option_dalek_pubkey_serde", default)]
+	pub payment_proof_sender_address: Option<DalekPublicKey>,
+	/// payment proof receiver address, as the transaction was built with
+	#[serde(with = "dalek_ser::option_dalek_pubkey_serde", default)]
+	pub payment_proof_receiver_address: Option<DalekPublicKey>,
 }
 
 impl Context {
@@ -800,7 +1089,9 @@ impl Context {
 			initial_sec_key: sec_key.clone(),
 			initial_sec_nonce: sec_nonce.clone(),
 			input_ids: vec![],
+			input_commits: vec![],
 			output_ids: vec![],
+			output_commits: vec![],
 			amount: 0,
 			token_output_ids: vec![],
 			token_input_ids: vec![],
@@ -808,6 +1099,16 @@ impl Context {
 			payment_proof_derivation_index: None,
 			is_invoice,
 			calculated_excess: None,
+			payouts: vec![],
+			token_fee: 0,
+			late_lock_args: None,
+			changeless: false,
+			selection_strategy: None,
+			change_parent_key_id: None,
+			memo: None,
+			token_type: None,
+			payment_proof_sender_address: None,
+			payment_proof_receiver_address: None,
 		}
 	}
 }
@@ -836,6 +1137,50 @@ impl Context {
 		self.input_ids.clone()
 	}
 
+	/// Records the payment proof the transaction was built with, reserving
+	/// our proof address at the given derivation index
+	pub fn set_payment_proof(&mut self, proof: &PaymentInfo, derivation_index: u32) {
+		self.payment_proof_derivation_index = Some(derivation_index);
+		self.payment_proof_sender_address = Some(proof.sender_address);
+		self.payment_proof_receiver_address = Some(proof.receiver_address);
+	}
+
+	/// Records the cached commitment of one of my inputs
+	pub fn add_input_commit(&mut self, input_id: &Identifier, commit: &str) {
+		self.input_commits
+			.push((input_id.clone(), commit.to_owned()));
+	}
+
+	/// Records the cached commitment of one of my (token) outputs
+	pub fn add_output_commit(&mut self, output_id: &Identifier, commit: &str) {
+		self.output_commits
+			.push((output_id.clone(), commit.to_owned()));
+	}
+
+	/// Returns the cached commitment of one of my (token) outputs, if known
+	pub fn get_output_commit(&self, output_id: &Identifier) -> Option<String> {
+		self.output_commits
+			.iter()
+			.find(|(id, _)| id == output_id)
+			.map(|(_, c)| c.clone())
+	}
+
+	/// Returns all stored inputs along with their cached commitment, if any
+	/// Id, mmr_index (if known), amount, commit (if known)
+	pub fn get_input_commits(&self) -> Vec<(Identifier, Option<u64>, u64, Option<String>)> {
+		self.input_ids
+			.iter()
+			.map(|(id, mmr_index, amount)| {
+				let commit = self
+					.input_commits
+					.iter()
+					.find(|(c_id, _)| c_id == id)
+					.map(|(_, c)| c.clone());
+				(id.clone(), *mmr_index, *amount, commit)
+			})
+			.collect()
+	}
+
 	/// Tracks an output contributing to my excess value (if it needs to
 	/// be kept between invocations
 	pub fn add_token_output(
@@ -892,6 +1237,103 @@ impl ser::Readable for Context {
 	}
 }
 
+/// Inputs of a transaction that was built but isn't locked yet, kept out of
+/// coin selection until the transaction locks them, is cancelled or the
+/// reservation expires. The reservation record is what sets these outputs
+/// apart: their `OutputStatus` stays `Unspent` until they're locked, and
+/// summaries report them as reserved rather than spendable.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OutputReservation {
+	/// Slate id of the transaction holding the reservation
+	pub slate_id: Uuid,
+	/// Reserved inputs
+	/// Id, mmr_index (if known)
+	pub inputs: Vec<(Identifier, Option<u64>)>,
+	/// Reserved token inputs
+	/// Id, mmr_index (if known)
+	pub token_inputs: Vec<(Identifier, Option<u64>)>,
+	/// Height from which the reservation no longer applies
+	pub expiry_height: u64,
+}
+
+impl OutputReservation {
+	/// Reserve the inputs and token inputs of the given context
+	pub fn new(slate_id: Uuid, context: &Context, expiry_height: u64) -> OutputReservation {
+		let ids = |ids: Vec<(Identifier, Option<u64>, u64)>| -> Vec<(Identifier, Option<u64>)> {
+			ids.into_iter()
+				.map(|(id, mmr_index, _)| (id, mmr_index))
+				.collect()
+		};
+		OutputReservation {
+			slate_id,
+			inputs: ids(context.get_inputs()),
+			token_inputs: ids(context.get_token_inputs()),
+			expiry_height,
+		}
+	}
+
+	/// Whether the reservation no longer applies at the given height
+	pub fn is_expired(&self, height: u64) -> bool {
+		height >= self.expiry_height
+	}
+
+	/// Whether the given output is reserved
+	pub fn reserves(&self, key_id: &Identifier, mmr_index: &Option<u64>) -> bool {
+		self.inputs
+			.iter()
+			.any(|(id, i)| id == key_id && i == mmr_index)
+	}
+
+	/// Whether the given token output is reserved
+	pub fn reserves_token(&self, key_id: &Identifier, mmr_index: &Option<u64>) -> bool {
+		self.token_inputs
+			.iter()
+			.any(|(id, i)| id == key_id && i == mmr_index)
+	}
+}
+
+impl ser::Writeable for OutputReservation {
+	fn write<W: ser::Writer>(&self, writer: &mut W) -> Result<(), ser::Error> {
+		writer.write_bytes(&serde_json::to_vec(self).map_err(|_| ser::Error::CorruptedData)?)
+	}
+}
+
+impl ser::Readable for OutputReservation {
+	fn read<R: ser::Reader>(reader: &mut R) -> Result<OutputReservation, ser::Error> {
+		let data = reader.read_bytes_len_prefix()?;
+		serde_json::from_slice(&data[..]).map_err(|_| ser::Error::CorruptedData)
+	}
+}
+
+/// A block the wallet saw outputs or transactions of an account confirmed in,
+/// kept to notice the block being orphaned by a reorg. Outputs refer to it by
+/// their height, transactions confirmed by their kernel are listed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConfirmedBlock {
+	/// Account the confirmations belong to
+	pub parent_key_id: Identifier,
+	/// Height of the block
+	pub height: u64,
+	/// Hash of the block, hex encoded
+	pub hash: String,
+	/// Ids of the transactions and token transactions confirmed in the block
+	/// by their kernel
+	pub tx_log_ids: Vec<u32>,
+}
+
+impl ser::Writeable for ConfirmedBlock {
+	fn write<W: ser::Writer>(&self, writer: &mut W) -> Result<(), ser::Error> {
+		writer.write_bytes(&serde_json::to_vec(self).map_err(|_| ser::Error::CorruptedData)?)
+	}
+}
+
+impl ser::Readable for ConfirmedBlock {
+	fn read<R: ser::Reader>(reader: &mut R) -> Result<ConfirmedBlock, ser::Error> {
+		let data = reader.read_bytes_len_prefix()?;
+		serde_json::from_slice(&data[..]).map_err(|_| ser::Error::CorruptedData)
+	}
+}
+
 /// Block Identifier
 #[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord)]
 pub struct BlockIdentifier(pub Hash);
@@ -977,6 +1419,15 @@ pub struct WalletInfo {
 	/// amount previously confirmed, now reverted
 	#[serde(with = "secp_ser::string_or_u64")]
 	pub amount_reverted: u64,
+	/// amount in frozen outputs, excluded from the spendable amount
+	#[serde(with = "secp_ser::string_or_u64")]
+	#[serde(default)]
+	pub amount_frozen: u64,
+	/// amount in outputs reserved by transactions not locked yet, excluded
+	/// from the spendable amount
+	#[serde(with = "secp_ser::string_or_u64")]
+	#[serde(default)]
+	pub amount_reserved: u64,
 	/// token info
 	pub token_infos: Vec<WalletTokenInfo>,
 }
@@ -993,6 +1444,10 @@ pub struct WalletTokenInfo {
 	/// amount awaiting confirmation
 	#[serde(with = "secp_ser::string_or_u64")]
 	pub amount_awaiting_confirmation: u64,
+	/// amount in outputs waiting for their lock height
+	#[serde(with = "secp_ser::string_or_u64")]
+	#[serde(default)]
+	pub amount_immature: u64,
 	/// amount currently spendable
 	#[serde(with = "secp_ser::string_or_u64")]
 	pub amount_currently_spendable: u64,
@@ -1002,6 +1457,214 @@ pub struct WalletTokenInfo {
 	/// amount previously confirmed, now reverted
 	#[serde(with = "secp_ser::string_or_u64")]
 	pub amount_reverted: u64,
+	/// amount in frozen outputs, excluded from the spendable amount
+	#[serde(with = "secp_ser::string_or_u64")]
+	#[serde(default)]
+	pub amount_frozen: u64,
+	/// amount in outputs reserved by transactions not locked yet, excluded
+	/// from the spendable amount
+	#[serde(with = "secp_ser::string_or_u64")]
+	#[serde(default)]
+	pub amount_reserved: u64,
+	/// number of outputs making up the amounts above
+	#[serde(default)]
+	pub num_outputs: usize,
+}
+
+/// Estimate of the inputs, change and fee a send transaction would use,
+/// without building or locking anything
+#[derive(Serialize, Eq, PartialEq, Deserialize, Debug, Clone)]
+pub struct FeeEstimate {
+	/// number of inputs that would be selected
+	pub num_inputs: usize,
+	/// total value of the selected inputs
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub total: u64,
+	/// amount returned as change
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub change: u64,
+	/// transaction fee
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub fee: u64,
+	/// number of token inputs that would be selected, for token sends
+	pub token_num_inputs: usize,
+	/// total value of the selected token inputs, for token sends
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub token_total: u64,
+	/// token amount returned as change, for token sends
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub token_change: u64,
+	/// whether the wallet can cover the amount and fee. If not, the estimate
+	/// describes the largest transaction the wallet could build
+	pub sufficient_funds: bool,
+}
+
+/// How the balance of an account splits by whether coin selection can spend
+/// it. Frozen, spent and reverted outputs are left out.
+#[derive(Serialize, Eq, PartialEq, Deserialize, Debug, Clone, Copy, Default)]
+pub struct BalanceBreakdown {
+	/// amount coin selection can spend now
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub spendable: u64,
+	/// amount short of the confirmations required, or not confirmed yet
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub awaiting_confirmation: u64,
+	/// amount locked by previous transactions, or reserved by sends not
+	/// locked yet
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub locked: u64,
+	/// amount in coinbase outputs not mature yet, or for tokens in outputs
+	/// waiting for their lock height
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub immature_coinbase: u64,
+}
+
+/// How the fee of a transaction adds up from its elements. Each component is
+/// the fee the elements of one kind contribute, which is negative for inputs
+/// as spending an output lowers the weight of a transaction. The components
+/// always add up to `total`, the fee actually paid.
+#[derive(Serialize, Eq, PartialEq, Deserialize, Debug, Clone, Copy, Default)]
+pub struct FeeBreakdown {
+	/// fee per unit of weight
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub fee_base: u64,
+	/// contribution of the inputs
+	pub inputs: i64,
+	/// contribution of the outputs, recipient and change
+	pub outputs: i64,
+	/// contribution of the kernels
+	pub kernels: i64,
+	/// contribution of the token inputs
+	pub token_inputs: i64,
+	/// contribution of the token outputs
+	pub token_outputs: i64,
+	/// contribution of the token kernels
+	pub token_kernels: i64,
+	/// what the elements don't account for: raising a light transaction to
+	/// the minimum fee, or change too small to keep given to the fee
+	pub base: i64,
+	/// the fee paid
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub total: u64,
+}
+
+impl FeeBreakdown {
+	/// Break down `fee`, paid by a transaction with the given numbers of
+	/// elements. The fee of each kind of element is measured with `tx_fee`
+	/// itself, so the components weigh elements exactly as the fee does.
+	pub fn new(
+		num_inputs: usize,
+		num_outputs: usize,
+		num_kernels: usize,
+		num_token_inputs: usize,
+		num_token_outputs: usize,
+		num_token_kernels: usize,
+		fee_base: Option<u64>,
+		fee: u64,
+	) -> FeeBreakdown {
+		let counts = [
+			num_inputs,
+			num_outputs,
+			num_kernels,
+			num_token_inputs,
+			num_token_outputs,
+			num_token_kernels,
+		];
+		let components: Vec<i64> = counts
+			.iter()
+			.enumerate()
+			.map(|(i, n)| *n as i64 * element_fee(i, fee_base))
+			.collect();
+		FeeBreakdown {
+			fee_base: fee_base.unwrap_or(DEFAULT_BASE_FEE),
+			inputs: components[0],
+			outputs: components[1],
+			kernels: components[2],
+			token_inputs: components[3],
+			token_outputs: components[4],
+			token_kernels: components[5],
+			base: fee as i64 - components.iter().sum::<i64>(),
+			total: fee,
+		}
+	}
+}
+
+/// Fee one more element of the given kind adds, in the order `tx_fee` takes
+/// the counts. It's measured against a transaction heavy enough to be above
+/// the minimum fee, where every element counts in full.
+fn element_fee(kind: usize, fee_base: Option<u64>) -> i64 {
+	let fee = |counts: &[usize; 6]| {
+		tx_fee(
+			counts[0], counts[1], counts[2], counts[3], counts[4], counts[5], fee_base,
+		) as i64
+	};
+	let reference = [0, 10, 1, 0, 0, 0];
+	let mut counts = reference;
+	counts[kind] += 1;
+	fee(&counts) - fee(&reference)
+}
+
+/// An output a previewed transaction would spend
+#[derive(Serialize, Eq, PartialEq, Deserialize, Debug, Clone)]
+pub struct PreviewInput {
+	/// commitment of the output, if known
+	pub commit: Option<String>,
+	/// value of the output
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub value: u64,
+	/// height the output was created at
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub height: u64,
+}
+
+/// Preview of the exact transaction a send or token issuance would build,
+/// without deriving keys, building a slate or locking anything
+#[derive(Serialize, Eq, PartialEq, Deserialize, Debug, Clone)]
+pub struct TxPreview {
+	/// amount the recipient would receive, in the token for token sends
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub amount: u64,
+	/// outputs that would be spent
+	pub inputs: Vec<PreviewInput>,
+	/// values of the change outputs that would be created
+	pub change: Vec<u64>,
+	/// transaction fee
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub fee: u64,
+	/// token outputs that would be spent, for token sends
+	pub token_inputs: Vec<PreviewInput>,
+	/// values of the token change outputs that would be created, for token sends
+	pub token_change: Vec<u64>,
+	/// fee paid in the token itself, for token sends
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub token_fee: u64,
+	/// wallet total once the transaction confirms, change included
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub balance_after: u64,
+	/// total of the token once the transaction confirms, for token sends and
+	/// issuance
+	#[serde(with = "secp_ser::opt_string_or_u64")]
+	pub token_balance_after: Option<u64>,
+}
+
+impl From<&OutputData> for PreviewInput {
+	fn from(out: &OutputData) -> Self {
+		PreviewInput {
+			commit: out.commit.clone(),
+			value: out.value,
+			height: out.height,
+		}
+	}
+}
+
+impl From<&TokenOutputData> for PreviewInput {
+	fn from(out: &TokenOutputData) -> Self {
+		PreviewInput {
+			commit: out.commit.clone(),
+			value: out.value,
+			height: out.height,
+		}
+	}
 }
 
 /// Types of transactions that can be contained within a TXLog entry
@@ -1019,6 +1682,10 @@ pub enum TxLogEntryType {
 	TxSentCancelled,
 	/// Received transaction that was reverted on-chain
 	TxReverted,
+	/// Received transaction rolled back automatically once its TTL expired
+	TxReceivedTtlCancelled,
+	/// Sent transaction rolled back automatically once its TTL expired
+	TxSentTtlCancelled,
 }
 
 impl fmt::Display for TxLogEntryType {
@@ -1030,6 +1697,29 @@ impl fmt::Display for TxLogEntryType {
 			TxLogEntryType::TxReceivedCancelled => write!(f, "Received Tx\n- Cancelled"),
 			TxLogEntryType::TxSentCancelled => write!(f, "Sent Tx\n- Cancelled"),
 			TxLogEntryType::TxReverted => write!(f, "Received Tx\n- Reverted"),
+			TxLogEntryType::TxReceivedTtlCancelled => write!(f, "Received Tx\n- TTL Cancelled"),
+			TxLogEntryType::TxSentTtlCancelled => write!(f, "Sent Tx\n- TTL Cancelled"),
+		}
+	}
+}
+
+/// How a slate was exchanged with the other party of a transaction
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SlateTransport {
+	/// A slate file, as written before slatepacks
+	File,
+	/// Sent to or received from a listener, over http or TOR
+	Http,
+	/// A slatepack message, pasted or in a file
+	Slatepack,
+}
+
+impl fmt::Display for SlateTransport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match *self {
+			SlateTransport::File => write!(f, "file"),
+			SlateTransport::Http => write!(f, "http"),
+			SlateTransport::Slatepack => write!(f, "slatepack"),
 		}
 	}
 }
@@ -1090,6 +1780,32 @@ pub struct TxLogEntry {
 	/// Track the time it took for a transaction to get reverted
 	#[serde(with = "option_duration_as_secs", default)]
 	pub reverted_after: Option<Duration>,
+	/// Amounts paid to each recipient, if this was a batch payout
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub payouts: Option<Vec<Payout>>,
+	/// Strategy the inputs were selected with, if we spent any
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub selection_strategy: Option<SelectionStrategy>,
+	/// Memo the sender kept with the transaction, such as an invoice id. Only
+	/// stored locally, it isn't part of the transaction itself
+	#[serde(with = "dalek_ser::option_bytes_hex", default)]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub memo: Option<Vec<u8>>,
+	/// Slatepack address of the other party, when known from the payment proof
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub counterparty_address: Option<String>,
+	/// How the slate was exchanged, when known
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub transport: Option<SlateTransport>,
+	/// Version of the slate the transaction was built from
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub slate_version: Option<u16>,
+	/// Free-form note the owner kept with the transaction, such as "payroll June"
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub note: Option<String>,
+	/// Labels the owner tagged the transaction with
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub labels: Vec<String>,
 }
 
 impl ser::Writeable for TxLogEntry {
@@ -1127,6 +1843,14 @@ impl TxLogEntry {
 			kernel_lookup_min_height: None,
 			payment_proof: None,
 			reverted_after: None,
+			payouts: None,
+			selection_strategy: None,
+			memo: None,
+			counterparty_address: None,
+			transport: None,
+			slate_version: None,
+			note: None,
+			labels: vec![],
 		}
 	}
 
@@ -1159,6 +1883,10 @@ pub enum TokenTxLogEntryType {
 	TokenTxSentCancelled,
 	/// Received token transaction that was reverted on-chain
 	TokenTxReverted,
+	/// Received token transaction rolled back automatically once its TTL expired
+	TokenTxReceivedTtlCancelled,
+	/// Sent token transaction rolled back automatically once its TTL expired
+	TokenTxSentTtlCancelled,
 }
 
 impl fmt::Display for TokenTxLogEntryType {
@@ -1172,6 +1900,12 @@ impl fmt::Display for TokenTxLogEntryType {
 			}
 			TokenTxLogEntryType::TokenTxSentCancelled => write!(f, "Sent Token Tx\n- Cancelled"),
 			TokenTxLogEntryType::TokenTxReverted => write!(f, "Received Token Tx\n- Reverted"),
+			TokenTxLogEntryType::TokenTxReceivedTtlCancelled => {
+				write!(f, "Received Token Tx\n- TTL Cancelled")
+			}
+			TokenTxLogEntryType::TokenTxSentTtlCancelled => {
+				write!(f, "Sent Token Tx\n- TTL Cancelled")
+			}
 		}
 	}
 }
@@ -1224,6 +1958,10 @@ pub struct TokenTxLogEntry {
 	/// Fee
 	#[serde(with = "secp_ser::opt_string_or_u64")]
 	pub fee: Option<u64>,
+	/// Token-denominated fee paid from the token inputs, if any
+	#[serde(with = "secp_ser::opt_string_or_u64")]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub token_fee: Option<u64>,
 	/// Cutoff block height
 	#[serde(with = "secp_ser::opt_string_or_u64")]
 	#[serde(default)]
@@ -1248,6 +1986,24 @@ pub struct TokenTxLogEntry {
 	/// Track the time it took for a transaction to get reverted
 	#[serde(with = "option_duration_as_secs", default)]
 	pub reverted_after: Option<Duration>,
+	/// Strategy the inputs were selected with, if we spent any
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub selection_strategy: Option<SelectionStrategy>,
+	/// Slatepack address of the other party, when known from the payment proof
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub counterparty_address: Option<String>,
+	/// How the slate was exchanged, when known
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub transport: Option<SlateTransport>,
+	/// Version of the slate the transaction was built from
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub slate_version: Option<u16>,
+	/// Free-form note the owner kept with the transaction, such as "payroll June"
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub note: Option<String>,
+	/// Labels the owner tagged the transaction with
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub labels: Vec<String>,
 }
 
 impl ser::Writeable for TokenTxLogEntry {
@@ -1284,6 +2040,7 @@ impl TokenTxLogEntry {
 			token_amount_credited: 0,
 			token_amount_debited: 0,
 			fee: None,
+			token_fee: None,
 			ttl_cutoff_height: None,
 			stored_tx: None,
 			kernel_excess: None,
@@ -1291,6 +2048,12 @@ impl TokenTxLogEntry {
 			kernel_lookup_min_height: None,
 			payment_proof: None,
 			reverted_after: None,
+			selection_strategy: None,
+			counterparty_address: None,
+			transport: None,
+			slate_version: None,
+			note: None,
+			labels: vec![],
 		}
 	}
 
@@ -1318,7 +2081,8 @@ pub struct StoredProofInfo {
 	#[serde(with = "dalek_ser::option_dalek_sig_serde")]
 	/// receiver signature
 	pub receiver_signature: Option<DalekSignature>,
-	/// sender address derivation path index
+	/// derivation path index of this wallet's address, the recipient's
+	/// on an invoice
 	pub sender_address_path: u32,
 	/// sender address
 	#[serde(with = "dalek_ser::dalek_pubkey_serde")]
@@ -1348,6 +2112,10 @@ pub struct AcctPathMapping {
 	pub label: String,
 	/// Corresponding parent BIP32 derivation path
 	pub path: Identifier,
+	/// Whether change outputs are derived on the account's dedicated change
+	/// branch rather than alongside received outputs
+	#[serde(default)]
+	pub change_branch: bool,
 }
 
 impl ser::Writeable for AcctPathMapping {
@@ -1396,6 +2164,32 @@ impl ser::Readable for ScannedBlockInfo {
 	}
 }
 
+/// Where a cancelled scan stopped. The next scan picks up from the indices
+/// after the ones given, a scan of the token outputs only being started once
+/// all outputs have been checked.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScanCheckpoint {
+	/// Height the scan was started from
+	pub start_height: u64,
+	/// Highest output PMMR index checked, if any were
+	pub last_pmmr_index: Option<u64>,
+	/// Highest token output PMMR index checked, if any were
+	pub last_token_pmmr_index: Option<u64>,
+}
+
+impl ser::Writeable for ScanCheckpoint {
+	fn write<W: ser::Writer>(&self, writer: &mut W) -> Result<(), ser::Error> {
+		writer.write_bytes(&serde_json::to_vec(self).map_err(|_| ser::Error::CorruptedData)?)
+	}
+}
+
+impl ser::Readable for ScanCheckpoint {
+	fn read<R: ser::Reader>(reader: &mut R) -> Result<ScanCheckpoint, ser::Error> {
+		let data = reader.read_bytes_len_prefix()?;
+		serde_json::from_slice(&data[..]).map_err(|_| ser::Error::CorruptedData)
+	}
+}
+
 /// Wrapper for reward output and kernel used when building a coinbase for a mining node.
 /// Note: Not serializable, must be converted to necesssary "versioned" representation
 /// before serializing to json to ensure compatibility with mining node.
@@ -1470,6 +2264,7 @@ pub mod option_duration_as_secs {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::grin_keychain::ExtKeychainPath;
 	use serde_json::Value;
 
 	#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
@@ -1511,4 +2306,90 @@ mod tests {
 		let none2 = serde_json::from_str::<TestSer>("{}").unwrap();
 		assert_eq!(none, none2);
 	}
+
+	#[test]
+	fn immature_coinbase_not_eligible() {
+		let id = ExtKeychainPath::new(3, 0, 0, 0, 1).to_identifier();
+		let coinbase = OutputData {
+			root_key_id: ExtKeychainPath::new(2, 0, 0, 0, 0).to_identifier(),
+			key_id: id,
+			n_child: 1,
+			commit: None,
+			mmr_index: None,
+			value: 60,
+			status: OutputStatus::Unspent,
+			height: 5,
+			lock_height: 8,
+			is_coinbase: true,
+			is_frozen: false,
+			tx_log_entry: None,
+		};
+		assert!(!coinbase.eligible_to_spend(7, 1));
+		assert!(!coinbase.eligible_to_spend(7, 0));
+		assert!(coinbase.eligible_to_spend(8, 1));
+	}
+
+	#[test]
+	fn context_input_commits() {
+		let secp = Secp256k1::with_caps(secp::ContextFlag::Commit);
+		let sec_key = SecretKey::from_slice(&secp, &[2; 32]).unwrap();
+		let parent_key_id = ExtKeychainPath::new(2, 0, 0, 0, 0).to_identifier();
+		let id1 = ExtKeychainPath::new(3, 0, 0, 0, 1).to_identifier();
+		let id2 = ExtKeychainPath::new(3, 0, 0, 0, 2).to_identifier();
+		let mut context =
+			Context::new(&secp, sec_key.clone(), sec_key, &parent_key_id, true, false);
+		context.add_input(&id1, &Some(3), 10);
+		context.add_input_commit(&id1, "08ab");
+		context.add_input(&id2, &None, 20);
+		assert_eq!(
+			context.get_input_commits(),
+			vec![
+				(id1, Some(3), 10, Some("08ab".to_owned())),
+				(id2, None, 20, None),
+			]
+		);
+	}
+
+	#[test]
+	fn context_output_commits() {
+		let secp = Secp256k1::with_caps(secp::ContextFlag::Commit);
+		let sec_key = SecretKey::from_slice(&secp, &[2; 32]).unwrap();
+		let parent_key_id = ExtKeychainPath::new(2, 0, 0, 0, 0).to_identifier();
+		let id1 = ExtKeychainPath::new(3, 0, 0, 0, 1).to_identifier();
+		let id2 = ExtKeychainPath::new(3, 0, 0, 0, 2).to_identifier();
+		let mut context =
+			Context::new(&secp, sec_key.clone(), sec_key, &parent_key_id, true, false);
+		context.add_output(&id1, &None, 10);
+		context.add_output_commit(&id1, "09cd");
+		context.add_token_output(&id2, &None, 20);
+		assert_eq!(context.get_output_commit(&id1), Some("09cd".to_owned()));
+		assert_eq!(context.get_output_commit(&id2), None);
+
+		// contexts saved before commits were cached still load
+		let mut json = serde_json::to_value(&context).unwrap();
+		json.as_object_mut().unwrap().remove("output_commits");
+		let loaded: Context = serde_json::from_value(json).unwrap();
+		assert!(loaded.output_commits.is_empty());
+	}
+	#[test]
+	fn tx_log_entry_exchange_info() {
+		let parent_key_id = ExtKeychainPath::new(2, 0, 0, 0, 0).to_identifier();
+		let mut t = TxLogEntry::new(parent_key_id, TxLogEntryType::TxSent, 1);
+
+		// entries stored before the exchange was recorded still load
+		let json = serde_json::to_value(&t).unwrap();
+		assert!(json.get("transport").is_none());
+		let loaded: TxLogEntry = serde_json::from_value(json).unwrap();
+		assert_eq!(loaded.counterparty_address, None);
+		assert_eq!(loaded.transport, None);
+		assert_eq!(loaded.slate_version, None);
+
+		t.transport = Some(SlateTransport::Slatepack);
+		t.slate_version = Some(4);
+		let json = serde_json::to_value(&t).unwrap();
+		assert_eq!(json["transport"], "Slatepack");
+		let loaded: TxLogEntry = serde_json::from_value(json).unwrap();
+		assert_eq!(loaded.transport, Some(SlateTransport::Slatepack));
+		assert_eq!(loaded.slate_version, Some(4));
+	}
 }
@@ -100,6 +100,12 @@ pub fn start_updater_thread(rx: Receiver<StatusMessage>) -> Result<(), Error> {
 					}
 					StatusMessage::ScanningComplete(s) => cli_message_inline!("{}", s),
 					StatusMessage::UpdateWarning(s) => cli_message_inline!("{}", s),
+					StatusMessage::ScanProgress(p) => cli_message_inline!(
+						"Scanning - {}% complete, {} outputs and {} token outputs found - Please Wait",
+						p.percentage_complete,
+						p.outputs_found,
+						p.token_outputs_found
+					),
 				}
 			}
 		});
@@ -738,6 +738,20 @@ pub fn parse_issue_invoice_args(
 		None => "default",
 	};
 
+	let payment_proof_address = match args.is_present("request_payment_proof") {
+		true => match SlatepackAddress::try_from(dest) {
+			Ok(a) => Some(a),
+			Err(e) => {
+				let msg = format!(
+					"Payer Slatepack address must be provided (-d) if payment proof is requested: {:?}",
+					e
+				);
+				return Err(ParseError::ArgumentError(msg));
+			}
+		},
+		false => None,
+	};
+
 	let outfile = parse_optional(args, "outfile")?;
 
 	Ok(command::IssueInvoiceArgs {
@@ -748,6 +762,7 @@ pub fn parse_issue_invoice_args(
 			token_type: None,
 			amount,
 			target_slate_version,
+			payment_proof_sender_address: payment_proof_address,
 		},
 		outfile,
 	})
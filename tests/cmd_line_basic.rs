@@ -238,7 +238,7 @@ fn command_line_test_impl(test_dir: &str) -> Result<(), grin_wallet_controller::
 		None,
 		|api, m| {
 			api.set_active_account(m, "mining")?;
-			let (refreshed, txs) = api.retrieve_txs(m, true, None, None)?;
+			let (refreshed, txs) = api.retrieve_txs(m, true, None, None, None)?;
 			assert!(refreshed);
 			assert_eq!(txs.len(), bh as usize);
 			for t in txs {
@@ -357,7 +357,7 @@ fn command_line_test_impl(test_dir: &str) -> Result<(), grin_wallet_controller::
 		None,
 		|api, m| {
 			api.set_active_account(m, "mining")?;
-			let (refreshed, txs) = api.retrieve_txs(m, true, None, None)?;
+			let (refreshed, txs) = api.retrieve_txs(m, true, None, None, None)?;
 			assert!(refreshed);
 			assert_eq!(txs.len(), bh as usize);
 			Ok(())
@@ -430,7 +430,7 @@ fn command_line_test_impl(test_dir: &str) -> Result<(), grin_wallet_controller::
 		None,
 		|api, m| {
 			api.set_active_account(m, "mining")?;
-			let (refreshed, txs) = api.retrieve_txs(m, true, None, None)?;
+			let (refreshed, txs) = api.retrieve_txs(m, true, None, None, None)?;
 			assert!(refreshed);
 			assert_eq!(txs.len(), bh as usize + 1);
 			Ok(())
@@ -555,7 +555,7 @@ fn command_line_test_impl(test_dir: &str) -> Result<(), grin_wallet_controller::
 		None,
 		|api, m| {
 			api.set_active_account(m, "default")?;
-			let (_, txs) = api.retrieve_txs(m, true, None, None)?;
+			let (_, txs) = api.retrieve_txs(m, true, None, None, None)?;
 			let some_tx_id = txs[0].tx_slate_id.clone();
 			assert!(some_tx_id.is_some());
 			tx_id = some_tx_id.unwrap().to_hyphenated().to_string().clone();